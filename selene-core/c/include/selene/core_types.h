@@ -26,29 +26,6 @@ typedef struct SeleneErrorModelAPIVersion {
 
 typedef void *SeleneErrorModelInstance;
 
-/**
- * An instance is provided to `selene_runtime_get_next_operations`, which must
- * pass that back to any function it calls in it's provided
- * [ErrorModelSetResultInterface].
- */
-typedef void *SeleneErrorModelSetResultInstance;
-
-/**
- * A plugin's implementation of `selene_runtime_get_next_operations` is provided
- * a pointer to a `ErrorModelSetResultInterface` as well as a
- * [ErrorModelSetResultInstance]. It should call the functions
- * within to populate a batch. All such calls must pass the instance as the
- * first parameter.
- */
-typedef struct SeleneErrorModelSetResultInterface {
-  void (*set_bool_result_fn)(SeleneErrorModelSetResultInstance,
-                             uint64_t,
-                             bool);
-  void (*set_u64_result_fn)(SeleneErrorModelSetResultInstance,
-                            uint64_t,
-                            uint64_t);
-} SeleneErrorModelSetResultInterface;
-
 typedef struct SeleneSimulatorAPIVersion {
   /**
    * Reserved for future use, must be 0.
@@ -130,6 +107,9 @@ typedef struct SeleneRuntimeGetOperationInterface {
   void (*set_batch_time_fn)(SeleneRuntimeGetOperationInstance,
                             uint64_t,
                             uint64_t);
+  void (*measure_reset_fn)(SeleneRuntimeGetOperationInstance,
+                           uint64_t,
+                           uint64_t);
 } SeleneRuntimeGetOperationInterface;
 
 typedef void *SeleneRuntimeExtractOperationInstance;
@@ -140,4 +120,167 @@ typedef struct SeleneRuntimeExtractOperationInterface {
                      struct SeleneRuntimeGetOperationInterface);
 } SeleneRuntimeExtractOperationInterface;
 
+/**
+ * An instance is provided to `selene_runtime_get_next_operations`, which must
+ * pass that back to any function it calls in it's provided
+ * [ErrorModelSetResultInterface].
+ */
+typedef void *SeleneErrorModelSetResultInstance;
+
+/**
+ * A plugin's implementation of `selene_runtime_get_next_operations` is provided
+ * a pointer to a `ErrorModelSetResultInterface` as well as a
+ * [ErrorModelSetResultInstance]. It should call the functions
+ * within to populate a batch. All such calls must pass the instance as the
+ * first parameter.
+ *
+ * `report_operations_fn` optionally reports operations the error model
+ * applied in addition to those of the batch, such as Pauli errors, so that
+ * event hooks can observe them. It is given the number of the batch's
+ * operations applied before them, and the operations as a batch to be
+ * extracted, as batches are passed to `selene_error_model_handle_operations`,
+ * whose timing is ignored. It may be called any number of times, in the order
+ * in which the operations were applied.
+ */
+typedef struct SeleneErrorModelSetResultInterface {
+  void (*set_bool_result_fn)(SeleneErrorModelSetResultInstance,
+                             uint64_t,
+                             bool);
+  void (*set_u64_result_fn)(SeleneErrorModelSetResultInstance,
+                            uint64_t,
+                            uint64_t);
+  void (*report_operations_fn)(SeleneErrorModelSetResultInstance,
+                               uint64_t,
+                               SeleneRuntimeExtractOperationInstance,
+                               const struct SeleneRuntimeExtractOperationInterface*);
+} SeleneErrorModelSetResultInterface;
+
+typedef struct SeleneEventHookAPIVersion {
+  /**
+   * Reserved for future use, must be 0.
+   */
+  uint8_t reserved;
+  /**
+   * Major version of the API.
+   */
+  uint8_t major;
+  /**
+   * Minor version of the API.
+   */
+  uint8_t minor;
+  /**
+   * Patch version of the API.
+   */
+  uint8_t patch;
+} SeleneEventHookAPIVersion;
+
+typedef void *SeleneEventHookInstance;
+
+/**
+ * The kind of an [EventHookOperation], corresponding to the variants of
+ * [Operation].
+ */
+enum SeleneEventHookOperationKind {
+  SeleneEventHookOperationKind_QFree = 0,
+  SeleneEventHookOperationKind_QAlloc = 1,
+  SeleneEventHookOperationKind_RXY = 2,
+  SeleneEventHookOperationKind_RZZ = 3,
+  SeleneEventHookOperationKind_RZ = 4,
+  SeleneEventHookOperationKind_Reset = 5,
+  SeleneEventHookOperationKind_MeasureRequest = 6,
+  SeleneEventHookOperationKind_MeasureLeakedRequest = 7,
+  SeleneEventHookOperationKind_MeasureResetRequest = 8,
+  SeleneEventHookOperationKind_FutureRead = 9,
+  SeleneEventHookOperationKind_BatchStart = 10,
+  SeleneEventHookOperationKind_GlobalBarrier = 11,
+  SeleneEventHookOperationKind_LocalBarrier = 12,
+  SeleneEventHookOperationKind_Custom = 13,
+  SeleneEventHookOperationKind_BeginCondition = 14,
+  SeleneEventHookOperationKind_EndCondition = 15,
+};
+typedef uint32_t SeleneEventHookOperationKind;
+
+/**
+ * An [Operation] as passed to `selene_event_hook_on_user_call`. The fields
+ * used depend on the kind of the operation:
+ *
+ * - QFree, QAlloc, Reset and the measurement requests act on one qubit.
+ * - RXY acts on one qubit with the angles theta and phi, RZ on one qubit with
+ *   the angle theta, and RZZ on two qubits with the angle theta.
+ * - FutureRead reads the result in `values[0]`.
+ * - BatchStart starts at `values[0]` and lasts for `values[1]` nanoseconds.
+ * - GlobalBarrier sleeps for `values[0]` nanoseconds, as does LocalBarrier,
+ *   which acts on any number of qubits.
+ * - Custom has the tag `values[0]` and the given data.
+ * - BeginCondition conditions on the result `values[0]` being `values[1]`.
+ *
+ * Unused fields are zero or null. The pointers are only valid for the
+ * duration of the call.
+ */
+typedef struct SeleneEventHookOperation {
+  SeleneEventHookOperationKind kind;
+  const uint64_t *qubits;
+  uint64_t qubits_length;
+  double angles[2];
+  uint64_t values[2];
+  const uint8_t *data;
+  uint64_t data_length;
+} SeleneEventHookOperation;
+
 typedef int32_t SeleneErrno;
+
+/**
+ * The category of an error, with the code used to report it.
+ */
+enum SeleneErrorKind {
+  /**
+   * A null instance was passed to the C interface.
+   */
+  SeleneErrorKind_NullInstance = 100000,
+  /**
+   * An error that does not fall into any other category.
+   */
+  SeleneErrorKind_Internal = 100001,
+  /**
+   * An operation referred to a qubit outside of the range available.
+   */
+  SeleneErrorKind_QubitOutOfBounds = 100002,
+  /**
+   * A component could not allocate the memory it requires.
+   */
+  SeleneErrorKind_OutOfMemory = 100003,
+  /**
+   * A postselection was requested for an outcome with zero probability.
+   */
+  SeleneErrorKind_PostselectionImpossible = 100004,
+  /**
+   * An argument to an operation or plugin was invalid.
+   */
+  SeleneErrorKind_InvalidArgument = 100005,
+  /**
+   * An operation is not supported by the plugin it was sent to.
+   */
+  SeleneErrorKind_Unsupported = 100006,
+  /**
+   * Writing to the result stream failed.
+   */
+  SeleneErrorKind_Output = 100007,
+  /**
+   * A plugin hosted in a separate process exited unexpectedly.
+   */
+  SeleneErrorKind_PluginCrashed = 100008,
+  /**
+   * A shot exceeded one of the limits configured for it, such as the
+   * number of operations it may issue. Unlike the other kinds, this ends
+   * only the shot in which it is raised. The code is reserved, and must not
+   * be used by user programs.
+   */
+  SeleneErrorKind_ShotLimitExceeded = 999,
+  /**
+   * A shot failed with an error that the configured error policy confines
+   * to the shot, which is described in the message. The code is reserved,
+   * and must not be used by user programs.
+   */
+  SeleneErrorKind_ShotFailed = 998,
+};
+typedef uint32_t SeleneErrorKind;
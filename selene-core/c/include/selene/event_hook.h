@@ -0,0 +1,136 @@
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+#include "selene/core_types.h"
+
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * The API version comprises four unsigned 8-bit integers:
+ *     - reserved: 8 bits (must be 0)
+ *     - major: 8 bits
+ *     - minor: 8 bits
+ *     - patch: 8 bits
+ *
+ * Selene maintains its own API version for event hooks
+ * and is updated upon changes to the API depending on how
+ * breaking the changes are. Selene is also responsible for
+ * validating the API version of the plugin against its own
+ * version.
+ */
+uint64_t selene_event_hook_get_api_version(void);
+
+/**
+ * Initialise an instance of the event hook with the maximum number of
+ * qubits and the user-provided arguments, in an argc, argv format. The
+ * `instance` pointer is set by this function to hold all of the state of
+ * the instance, and is passed to each of the other functions.
+ */
+SeleneErrno selene_event_hook_init(SeleneEventHookInstance *instance,
+                                   uint64_t n_qubits,
+                                   uint32_t argc,
+                                   const char *const *argv);
+
+/**
+ * This function is called when Selene is exiting, and it is responsible for
+ * cleaning up any resources that the event hook has allocated, such as
+ * flushing any logs it has written.
+ */
+SeleneErrno selene_event_hook_exit(SeleneEventHookInstance instance);
+
+/**
+ * This function is called at the start of a shot. The seed is provided for
+ * any randomness the event hook uses, such as sampling.
+ */
+SeleneErrno selene_event_hook_shot_start(SeleneEventHookInstance instance,
+                                         uint64_t shot_id,
+                                         uint64_t seed);
+
+/**
+ * This function is called at the end of a shot, after the metrics of the
+ * event hook have been requested.
+ */
+SeleneErrno selene_event_hook_shot_end(SeleneEventHookInstance instance);
+
+/**
+ * This function is called with each operation issued by the user program,
+ * once the runtime has accepted it. Returning an error fails the
+ * operation.
+ */
+SeleneErrno selene_event_hook_on_user_call(SeleneEventHookInstance instance,
+                                           const SeleneEventHookOperation *operation);
+
+/**
+ * This function is called with each batch of operations emitted by the
+ * runtime, before it is passed to the error model. The batch is extracted
+ * as in `selene_error_model_handle_operations`.
+ */
+SeleneErrno selene_event_hook_on_runtime_batch(SeleneEventHookInstance instance,
+                                               SeleneRuntimeExtractOperationInstance extract_ops_instance,
+                                               const SeleneRuntimeExtractOperationInterface *extract_ops_interface);
+
+/**
+ * This function is called with each operation the error model reports
+ * having injected while handling a batch, such as a Pauli error, and
+ * the number of the batch's operations applied before it. It is
+ * extracted as a batch holding that operation alone.
+ */
+SeleneErrno selene_event_hook_on_error_model_op(SeleneEventHookInstance instance,
+                                                uint64_t position,
+                                                SeleneRuntimeExtractOperationInstance extract_ops_instance,
+                                                const SeleneRuntimeExtractOperationInterface *extract_ops_interface);
+
+/**
+ * This function is called with each boolean result set by the error
+ * model, before it is passed to the runtime.
+ */
+SeleneErrno selene_event_hook_on_bool_result(SeleneEventHookInstance instance,
+                                             uint64_t result_id,
+                                             bool value);
+
+/**
+ * This function is called with each u64 result set by the error model,
+ * before it is passed to the runtime.
+ */
+SeleneErrno selene_event_hook_on_u64_result(SeleneEventHookInstance instance,
+                                            uint64_t result_id,
+                                            uint64_t value);
+
+/**
+ * This function is called to get a metric from the event hook at the end of
+ * each shot, as `selene_error_model_get_metrics` is for error models.
+ */
+SeleneErrno selene_event_hook_get_metrics(SeleneEventHookInstance instance,
+                                          uint8_t nth_metric,
+                                          char *tag_ptr,
+                                          uint8_t *datatype_ptr,
+                                          uint64_t *data_ptr);
+
+/**
+ * Get the message describing the latest error returned by the event hook
+ * in the calling thread, as a null terminated string, or null if there
+ * has been no error. The string remains valid until the next error.
+ */
+const char *selene_event_hook_last_error_message(void);
+
+/**
+ * Get the optional features supported by the event hook, as the bits of a
+ * `selene_core::capabilities::Capabilities` set.
+ */
+uint64_t selene_event_hook_get_capabilities(void);
+
+/**
+ * Get a description of the event hook as a nul-terminated JSON string, in
+ * the form of a `selene_core::metadata::PluginMetadata`. The string is
+ * owned by the plugin and lives as long as it is loaded.
+ */
+const char *selene_event_hook_get_metadata(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
@@ -23,10 +23,10 @@ extern "C" {
  * validating the API version of the plugin against its own
  * version.
  *
- * The plans for this validation are a work-in-progress, but
- * currently selene will reject any plugin that has a different
- * major or minor version than the current Selene version, or with
- * a reserved field that is not 0.
+ * Selene rejects plugins with a different major version, a newer
+ * minor version, or a minor version older than it supports, as well
+ * as plugins with a reserved field that is not 0. Plugins of older
+ * supported minor versions are adapted to.
  */
 uint64_t selene_runtime_get_api_version(void);
 
@@ -107,6 +107,27 @@ SeleneErrno selene_runtime_custom_call(RuntimeInstance instance,
                                        size_t data_len,
                                        uint64_t *result);
 
+/**
+ * This function is called when the user program opens a conditional block.
+ * Quantum operations issued until the matching call to
+ * `selene_runtime_end_condition` should only be applied if the result with
+ * the given ID equals `value`. The runtime need not wait for the result to be
+ * available: it may keep the conditional operations queued and emit them
+ * once the result has been set, taking any classical latency into account.
+ *
+ * Runtimes that do not support conditional operations should return a
+ * non-zero error code.
+ */
+SeleneErrno selene_runtime_begin_condition(RuntimeInstance instance,
+                                           uint64_t result_id,
+                                           bool value);
+
+/**
+ * This function is called when the user program closes the innermost
+ * conditional block opened with `selene_runtime_begin_condition`.
+ */
+SeleneErrno selene_runtime_end_condition(RuntimeInstance instance);
+
 /**
  * This function is called to get the next operations from the runtime. The
  * runtime should use the [RuntimeGetOperationInterface] callbacks along with
@@ -228,6 +249,16 @@ int32_t selene_runtime_measure_leaked(RuntimeInstance instance,
                                       uint64_t qubit_id,
                                       uint64_t *result);
 
+/**
+ * Instruct the runtime to measure a qubit and reset it to the |0> state as a
+ * single fused primitive, writing a reference ID to the result to the `result`
+ * pointer. Runtimes that do not export this function receive a measurement
+ * followed by a reset instead.
+ */
+int32_t selene_runtime_measure_reset(RuntimeInstance instance,
+                                     uint64_t qubit_id,
+                                     uint64_t *result);
+
 /**
  * Instruct the runtime to reset a qubit to the |0> state with the given ID.
  * Note that it is up to the runtime whether or not this reset is applied immediately.
@@ -290,6 +321,43 @@ SeleneErrno selene_runtime_increment_future_refcount(RuntimeInstance instance,
 SeleneErrno selene_runtime_decrement_future_refcount(RuntimeInstance instance,
                                                      uint64_t future_ref);
 
+/**
+ * Get the message describing the latest error returned by the runtime
+ * in the calling thread, as a null terminated string, or null if there
+ * has been no error. The string remains valid until the next error.
+ *
+ * Selene calls this after an entry point returns an error, and reports
+ * the message along with the error. Plugins that do not provide this
+ * entry point should write any details of their errors to stderr.
+ */
+const char *selene_runtime_last_error_message(void);
+
+/**
+ * Get the optional features supported by the runtime, as the bits of a
+ * `selene_core::capabilities::Capabilities` set. Selene only uses a
+ * declared capability if the entry points it relies on are present.
+ */
+uint64_t selene_runtime_get_capabilities(void);
+
+/**
+ * Get the optional features supported by the runtime instance, writing
+ * the bits of a `selene_core::capabilities::Capabilities` set to
+ * `bits_ptr`. Returns 0 if they were written, or 1 if they are those
+ * reported by `selene_runtime_get_capabilities`. Selene only uses a
+ * declared capability if the entry points it relies on are present.
+ */
+SeleneErrno selene_runtime_get_instance_capabilities(RuntimeInstance instance,
+                                                     uint64_t *bits_ptr);
+
+/**
+ * Get a description of the runtime as a nul-terminated JSON string, in
+ * the form of a `selene_core::metadata::PluginMetadata`: its name and
+ * version, the API version, its capabilities and the arguments it
+ * accepts. The string is owned by the plugin and lives as long as it
+ * is loaded.
+ */
+const char *selene_runtime_get_metadata(void);
+
 #ifdef __cplusplus
 }  // extern "C"
 #endif  // __cplusplus
@@ -23,10 +23,10 @@ extern "C" {
  * validating the API version of the plugin against its own
  * version.
  *
- * The plans for this validation are a work-in-progress, but
- * currently selene will reject any plugin that has a different
- * major or minor version than the current Selene version, or with
- * a reserved field that is not 0.
+ * Selene rejects plugins with a different major version, a newer
+ * minor version, or a minor version older than it supports, as well
+ * as plugins with a reserved field that is not 0. Plugins of older
+ * supported minor versions are adapted to.
  */
 uint64_t selene_simulator_get_api_version(void);
 
@@ -174,6 +174,33 @@ int32_t selene_simulator_dump_state(SeleneSimulatorInstance instance,
                                     const uint64_t *qubits,
                                     uint64_t n_qubits);
 
+/**
+ * Get the message describing the latest error returned by the simulator
+ * in the calling thread, as a null terminated string, or null if there
+ * has been no error. The string remains valid until the next error.
+ *
+ * Selene calls this after an entry point returns an error, and reports
+ * the message along with the error. Plugins that do not provide this
+ * entry point should write any details of their errors to stderr.
+ */
+const char *selene_simulator_last_error_message(void);
+
+/**
+ * Get the optional features supported by the simulator, as the bits of a
+ * `selene_core::capabilities::Capabilities` set. Selene only uses a
+ * declared capability if the entry points it relies on are present.
+ */
+uint64_t selene_simulator_get_capabilities(void);
+
+/**
+ * Get a description of the simulator as a nul-terminated JSON string, in
+ * the form of a `selene_core::metadata::PluginMetadata`: its name and
+ * version, the API version, its capabilities and the arguments it
+ * accepts. The string is owned by the plugin and lives as long as it
+ * is loaded.
+ */
+const char *selene_simulator_get_metadata(void);
+
 #ifdef __cplusplus
 }  // extern "C"
 #endif  // __cplusplus
@@ -207,6 +207,21 @@ impl ErrorModelInterface for ExampleErrorModel {
                     };
                     results.set_u64_result(result_id, measurement);
                 }
+                Operation::MeasureReset {
+                    qubit_id,
+                    result_id,
+                } => {
+                    // A fused measurement and reset. We treat it as its two halves,
+                    // but a hardware-specific model could give it its own noise.
+                    let measurement = if self.leakage_map[qubit_id as usize] {
+                        true
+                    } else {
+                        self.simulator.measure(qubit_id)?
+                    };
+                    self.leakage_map[qubit_id as usize] = false;
+                    self.simulator.reset(qubit_id)?;
+                    results.set_bool_result(result_id, measurement);
+                }
                 Operation::Reset { qubit_id } => {
                     // A reset has been requested.
                     self.leakage_map[qubit_id as usize] = false; // Reset leakage state
@@ -0,0 +1,101 @@
+use anyhow::{Result, bail};
+use selene_core::event_hook::{
+    EventHookInterface, EventHookInterfaceFactory, Operation, ResultValue,
+};
+use selene_core::export_event_hook_plugin;
+use selene_core::runtime::{self, BatchOperation};
+use selene_core::utils::MetricValue;
+use std::sync::Arc;
+
+/// Counts the operations issued by the user program, the batches emitted by
+/// the runtime and the operations and results of the error model, reporting
+/// them as metrics at the end of each shot.
+///
+/// Given `--max-two-qubit-gates=N`, it also acts as a validator, failing any
+/// shot that issues more than N two-qubit gates.
+#[derive(Default)]
+pub struct OperationCounter {
+    max_two_qubit_gates: Option<u64>,
+    user_calls: u64,
+    two_qubit_gates: u64,
+    runtime_batches: u64,
+    runtime_operations: u64,
+    error_model_operations: u64,
+    results: u64,
+}
+
+impl EventHookInterface for OperationCounter {
+    fn shot_start(&mut self, _shot_id: u64, _seed: u64) -> Result<()> {
+        *self = Self {
+            max_two_qubit_gates: self.max_two_qubit_gates,
+            ..Self::default()
+        };
+        Ok(())
+    }
+    fn on_user_call(&mut self, operation: &Operation) -> Result<()> {
+        self.user_calls += 1;
+        if let Operation::RZZ(q0, q1, _) = operation {
+            self.two_qubit_gates += 1;
+            if let Some(max) = self.max_two_qubit_gates {
+                if self.two_qubit_gates > max {
+                    bail!("RZZ({q0}, {q1}) exceeds the limit of {max} two-qubit gates");
+                }
+            }
+        }
+        Ok(())
+    }
+    fn on_runtime_batch(&mut self, batch: &BatchOperation) -> Result<()> {
+        self.runtime_batches += 1;
+        self.runtime_operations += batch.iter_ops().count() as u64;
+        Ok(())
+    }
+    fn on_error_model_op(&mut self, _position: u64, _operation: &runtime::Operation) -> Result<()> {
+        self.error_model_operations += 1;
+        Ok(())
+    }
+    fn on_result(&mut self, _result_id: u64, _value: ResultValue) -> Result<()> {
+        self.results += 1;
+        Ok(())
+    }
+    fn get_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        let (tag, value) = match nth_metric {
+            0 => ("user_calls", self.user_calls),
+            1 => ("two_qubit_gates", self.two_qubit_gates),
+            2 => ("runtime_batches", self.runtime_batches),
+            3 => ("runtime_operations", self.runtime_operations),
+            4 => ("error_model_operations", self.error_model_operations),
+            5 => ("results", self.results),
+            _ => return Ok(None),
+        };
+        Ok(Some((tag.to_string(), MetricValue::U64(value))))
+    }
+}
+
+#[derive(Default)]
+pub struct OperationCounterFactory;
+
+impl EventHookInterfaceFactory for OperationCounterFactory {
+    type Interface = OperationCounter;
+
+    fn init(
+        self: Arc<Self>,
+        _n_qubits: u64,
+        args: &[impl AsRef<str>],
+    ) -> Result<Box<Self::Interface>> {
+        let mut counter = OperationCounter::default();
+        // The first argument is the program name.
+        for arg in args.iter().skip(1) {
+            match arg.as_ref().strip_prefix("--max-two-qubit-gates=") {
+                Some(max) => counter.max_two_qubit_gates = Some(max.parse()?),
+                None => bail!("Unknown argument '{}'", arg.as_ref()),
+            }
+        }
+        Ok(Box::new(counter))
+    }
+
+    fn capabilities(&self) -> selene_core::capabilities::Capabilities {
+        selene_core::capabilities::Capabilities::METRICS
+    }
+}
+
+export_event_hook_plugin!(crate::OperationCounterFactory);
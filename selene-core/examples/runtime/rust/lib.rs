@@ -125,6 +125,11 @@ impl RuntimeInterface for ExampleRuntime {
                             last_op_using_qubits = i;
                         }
                     }
+                    Operation::MeasureReset { qubit_id, .. } => {
+                        if qubits.contains(qubit_id) {
+                            last_op_using_qubits = i;
+                        }
+                    }
                     Operation::Reset { qubit_id, .. } => {
                         if qubits.contains(qubit_id) {
                             last_op_using_qubits = i;
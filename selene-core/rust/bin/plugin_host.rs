@@ -0,0 +1,41 @@
+//! Host a simulator or error model plugin out of process.
+//!
+//! Usage: `selene-plugin-host <ADDRESS>`
+//!
+//! Launched by selene rather than by hand: the host connects to the given
+//! loopback address, presents the token in `SELENE_PLUGIN_HOST_TOKEN`, and
+//! serves the plugin that selene asks it to load, as described in
+//! [selene_core::host].
+use std::io::{BufReader, Write};
+use std::net::TcpStream;
+use std::process::ExitCode;
+
+use anyhow::{Context, Result, bail};
+use selene_core::host::{TOKEN_ENV, server};
+
+fn run(address: &str) -> Result<()> {
+    let Ok(token) = std::env::var(TOKEN_ENV) else {
+        bail!("{TOKEN_ENV} is not set");
+    };
+    let mut stream = TcpStream::connect(address)
+        .with_context(|| format!("Failed to connect to selene at {address}"))?;
+    stream.set_nodelay(true)?;
+    writeln!(stream, "{token}")?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    server::serve(&mut reader, &mut stream)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [address] = args.as_slice() else {
+        eprintln!("Usage: selene-plugin-host <ADDRESS>");
+        return ExitCode::FAILURE;
+    };
+    match run(address) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("selene-plugin-host: {e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,46 @@
+//! Print the metadata of selene plugins as JSON.
+//!
+//! Usage: `selene-plugin-info <PLUGIN>...`
+//!
+//! Prints a JSON array with an entry for each plugin exported by the given
+//! libraries, as described in [selene_core::metadata].
+use std::io::Write;
+use std::process::ExitCode;
+
+use anyhow::Result;
+use selene_core::metadata::{PluginMetadata, load_metadata};
+
+const USAGE: &str = "Usage: selene-plugin-info <PLUGIN>...
+
+Prints the metadata of the runtime, error model and simulator plugins
+exported by each plugin library as a JSON array.";
+
+fn run(paths: &[String]) -> Result<()> {
+    let mut metadata: Vec<PluginMetadata> = Vec::new();
+    for path in paths {
+        metadata.extend(load_metadata(path)?);
+    }
+    let mut stdout = std::io::stdout().lock();
+    serde_json::to_writer_pretty(&mut stdout, &metadata)?;
+    writeln!(stdout)?;
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let paths: Vec<String> = std::env::args().skip(1).collect();
+    if paths.is_empty() || paths.iter().any(|arg| arg == "-h" || arg == "--help") {
+        eprintln!("{USAGE}");
+        return if paths.is_empty() {
+            ExitCode::FAILURE
+        } else {
+            ExitCode::SUCCESS
+        };
+    }
+    match run(&paths) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
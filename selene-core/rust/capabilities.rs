@@ -0,0 +1,125 @@
+//! Optional features supported by plugins.
+//!
+//! Plugins declare the optional features they support through the
+//! `selene_*_get_capabilities` entry point, returning the bits of a
+//! [Capabilities] set. The `export_*_plugin!` macros implement it from the
+//! factory's `capabilities` method. For plugins built against an earlier
+//! version of the API, which lack the entry point, the capabilities are
+//! inferred from the optional entry points they provide.
+//!
+//! A declared capability is only active if the entry points it relies on are
+//! present, so a plugin cannot claim a feature it does not implement.
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitOrAssign};
+
+/// A set of optional plugin features.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[repr(transparent)]
+pub struct Capabilities(u64);
+
+impl Capabilities {
+    /// The simulator supports postselection of measurement outcomes.
+    pub const POSTSELECT: Self = Self(1 << 0);
+    /// The simulator can dump its state, or the error model passes dumps
+    /// through to its simulator.
+    pub const DUMP_STATE: Self = Self(1 << 1);
+    /// The runtime handles custom runtime calls and may emit custom
+    /// operations.
+    pub const CUSTOM_OPS: Self = Self(1 << 2);
+    /// The runtime groups several operations into a batch rather than
+    /// forwarding each operation as it is requested.
+    pub const BATCHING: Self = Self(1 << 3);
+    /// The plugin reports metrics.
+    pub const METRICS: Self = Self(1 << 4);
+    /// The runtime supports operations conditioned on measurement results.
+    pub const CONDITIONS: Self = Self(1 << 5);
+    /// The runtime emits fused measure-and-reset operations.
+    pub const MEASURE_RESET: Self = Self(1 << 6);
+
+    const NAMES: [(Self, &'static str); 7] = [
+        (Self::POSTSELECT, "postselect"),
+        (Self::DUMP_STATE, "dump_state"),
+        (Self::CUSTOM_OPS, "custom_ops"),
+        (Self::BATCHING, "batching"),
+        (Self::METRICS, "metrics"),
+        (Self::CONDITIONS, "conditions"),
+        (Self::MEASURE_RESET, "measure_reset"),
+    ];
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn bits(self) -> u64 {
+        self.0
+    }
+
+    /// The capabilities with the given bits, ignoring any that are not known
+    /// to this version of selene.
+    pub const fn from_bits_truncate(bits: u64) -> Self {
+        let mut known = 0;
+        let mut i = 0;
+        while i < Self::NAMES.len() {
+            known |= Self::NAMES[i].0.0;
+            i += 1;
+        }
+        Self(bits & known)
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub const fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// The capabilities present if the given condition holds, e.g. because an
+    /// optional entry point is provided.
+    pub const fn when(self, condition: bool) -> Self {
+        if condition { self } else { Self::empty() }
+    }
+
+    /// The names of the capabilities in the set.
+    pub fn names(self) -> Vec<&'static str> {
+        Self::NAMES
+            .iter()
+            .filter(|(capability, _)| self.contains(*capability))
+            .map(|(_, name)| *name)
+            .collect()
+    }
+}
+
+impl BitOr for Capabilities {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        self.union(rhs)
+    }
+}
+
+impl BitOrAssign for Capabilities {
+    fn bitor_assign(&mut self, rhs: Self) {
+        *self = self.union(rhs);
+    }
+}
+
+impl BitAnd for Capabilities {
+    type Output = Self;
+    fn bitand(self, rhs: Self) -> Self {
+        self.intersection(rhs)
+    }
+}
+
+impl fmt::Display for Capabilities {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.names().join(", "))
+    }
+}
@@ -0,0 +1,581 @@
+//! Reading of the result stream written by [OutputStream](crate::encoder::OutputStream).
+//!
+//! Each message in the stream consists of a little-endian `u64` time cursor,
+//! a string tag and a sequence of values. Every value is prefixed by a `u16`
+//! type code and a `u16` length, which is zero for a single value and
+//! otherwise the number of array elements (or bytes, for strings). A message
+//! is terminated by a zero type code and length, and the stream by a time
+//! cursor of `u64::MAX`.
+//!
+//! [OutputStreamReader] reads messages incrementally from any [Read]
+//! implementation, so it may be used on a stream that is still being written.
+//! [Message::kind] interprets a message according to the tag conventions used
+//! by selene: shot boundaries, user results, exits, metrics and instruction
+//! logs.
+use std::io::Read;
+
+use thiserror::Error;
+
+use crate::encoder::{StreamWritable, StreamWritableSingle};
+use crate::utils::MetricValue;
+
+#[cfg(test)]
+mod tests;
+
+const END_REPR: u16 = 0;
+const U64_REPR: u16 = <u64 as StreamWritableSingle>::TYPE_REPR;
+const F64_REPR: u16 = <f64 as StreamWritableSingle>::TYPE_REPR;
+const STR_REPR: u16 = <&str as StreamWritable>::TYPE_REPR;
+const BOOL_REPR: u16 = <bool as StreamWritableSingle>::TYPE_REPR;
+const I64_REPR: u16 = <i64 as StreamWritableSingle>::TYPE_REPR;
+const BYTE_REPR: u16 = <u8 as StreamWritableSingle>::TYPE_REPR;
+const END_OF_STREAM: u64 = u64::MAX;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+/// Exit codes at or above this value indicate a panic, after which no
+/// further shots are run. Lower codes end only the current shot.
+pub const PANIC_EXIT_CODE: u64 = 1000;
+
+#[derive(Debug, Error)]
+pub enum OutputStreamReadError {
+    #[error("IO Error: {0}")]
+    IoError(std::io::Error),
+    #[error("Unexpected end of stream")]
+    UnexpectedEndOfStream,
+    #[error("Expected a string tag at the start of a message, got type {0} with length {1}")]
+    MissingTagError(u16, u16),
+    #[error("Unexpected type {0} with length {1}")]
+    UnexpectedTypeError(u16, u16),
+    #[error("String is not valid UTF-8: {0}")]
+    InvalidStringError(std::string::FromUtf8Error),
+    #[error("Malformed '{tag}' message: {reason}")]
+    MalformedMessageError { tag: String, reason: String },
+    #[error("{0}")]
+    ShotBoundaryError(String),
+}
+
+/// The framing of a result stream, as written with selene's compressed
+/// output options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamCompression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+/// Detect the framing of a result stream from its first bytes. An
+/// uncompressed stream begins with a time cursor, which is zero for the first
+/// message, so it cannot be confused with the gzip or zstd magic bytes.
+pub fn detect_compression(prefix: &[u8]) -> StreamCompression {
+    if prefix.starts_with(&ZSTD_MAGIC) {
+        StreamCompression::Zstd
+    } else if prefix.starts_with(&GZIP_MAGIC) {
+        StreamCompression::Gzip
+    } else {
+        StreamCompression::None
+    }
+}
+
+/// A single value, or array of values, within a message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    U64(u64),
+    I64(i64),
+    F64(f64),
+    Bool(bool),
+    Byte(u8),
+    Str(String),
+    U64Array(Vec<u64>),
+    I64Array(Vec<i64>),
+    F64Array(Vec<f64>),
+    BoolArray(Vec<bool>),
+    Bytes(Vec<u8>),
+}
+
+impl Value {
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Self::U64(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Str(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// A tagged message read from the result stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub time_cursor: u64,
+    pub tag: String,
+    pub values: Vec<Value>,
+}
+
+/// The interpretation of a [Message] according to its tag.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MessageKind<'a> {
+    /// `SELENE:SHOT_START`, carrying the shot id.
+    ShotStart(u64),
+    /// `SELENE:SHOT_END`, carrying the shot id.
+    ShotEnd(u64),
+    /// `EXIT:INT:<message>`, carrying an exit code. See [PANIC_EXIT_CODE].
+    Exit { message: &'a str, code: u64 },
+    /// `USER:STATE:<name>`, carrying the path of a state dump.
+    UserState { name: &'a str, path: &'a str },
+    /// `USER:<type>:<name>`, carrying a single value.
+    UserResult { name: &'a str, value: &'a Value },
+    /// `METRICS:<type>:[<category>:]<name>`, carrying a single value.
+    Metric {
+        category: Option<&'a str>,
+        name: &'a str,
+        value: MetricValue,
+    },
+    /// `INSTRUCTIONLOG`, carrying the instructions issued during the shot.
+    InstructionLog(Vec<Instruction>),
+    /// A message with a tag not covered by the above.
+    Other,
+}
+
+impl MessageKind<'_> {
+    pub fn is_panic(&self) -> bool {
+        matches!(self, Self::Exit { code, .. } if *code >= PANIC_EXIT_CODE)
+    }
+}
+
+impl Message {
+    fn malformed(&self, reason: impl Into<String>) -> OutputStreamReadError {
+        OutputStreamReadError::MalformedMessageError {
+            tag: self.tag.clone(),
+            reason: reason.into(),
+        }
+    }
+
+    fn single_value(&self) -> Result<&Value, OutputStreamReadError> {
+        match self.values.as_slice() {
+            [value] => Ok(value),
+            values => Err(self.malformed(format!("expected a single value, got {}", values.len()))),
+        }
+    }
+
+    fn single_u64(&self) -> Result<u64, OutputStreamReadError> {
+        self.single_value()?
+            .as_u64()
+            .ok_or_else(|| self.malformed("expected an unsigned integer"))
+    }
+
+    /// Interpret the message according to its tag.
+    pub fn kind(&self) -> Result<MessageKind<'_>, OutputStreamReadError> {
+        let tag = self.tag.as_str();
+        if tag == "SELENE:SHOT_START" {
+            return Ok(MessageKind::ShotStart(self.single_u64()?));
+        }
+        if tag == "SELENE:SHOT_END" {
+            return Ok(MessageKind::ShotEnd(self.single_u64()?));
+        }
+        if tag == "INSTRUCTIONLOG" {
+            return Ok(MessageKind::InstructionLog(self.instructions()?));
+        }
+        if let Some(rest) = tag.strip_prefix("EXIT:") {
+            let message = rest.strip_prefix("INT:").unwrap_or(rest);
+            let code = match self.values.first() {
+                Some(value) => value
+                    .as_u64()
+                    .ok_or_else(|| self.malformed("expected an unsigned exit code"))?,
+                None => return Err(self.malformed("missing exit code")),
+            };
+            return Ok(MessageKind::Exit { message, code });
+        }
+        if let Some(name) = tag.strip_prefix("USER:STATE:") {
+            let path = self
+                .single_value()?
+                .as_str()
+                .ok_or_else(|| self.malformed("expected a state file path"))?;
+            return Ok(MessageKind::UserState { name, path });
+        }
+        if let Some(rest) = tag.strip_prefix("USER:") {
+            let name = rest.split_once(':').map_or(rest, |(_, name)| name);
+            let value = self.single_value()?;
+            return Ok(MessageKind::UserResult { name, value });
+        }
+        if let Some(rest) = tag.strip_prefix("METRICS:") {
+            let rest = rest.split_once(':').map_or(rest, |(_, rest)| rest);
+            let (category, name) = match rest.split_once(':') {
+                Some((category, name)) => (Some(category), name),
+                None => (None, rest),
+            };
+            let value = match self.single_value()? {
+                Value::U64(value) => MetricValue::U64(*value),
+                Value::I64(value) => MetricValue::I64(*value),
+                Value::F64(value) => MetricValue::F64(*value),
+                Value::Bool(value) => MetricValue::Bool(*value),
+                _ => return Err(self.malformed("expected a numeric or boolean metric")),
+            };
+            return Ok(MessageKind::Metric {
+                category,
+                name,
+                value,
+            });
+        }
+        Ok(MessageKind::Other)
+    }
+
+    fn instructions(&self) -> Result<Vec<Instruction>, OutputStreamReadError> {
+        let mut cursor = InstructionCursor {
+            message: self,
+            values: self.values.iter(),
+        };
+        let mut instructions = Vec::new();
+        while let Some(source) = cursor.next_u64_or_end()? {
+            let source = match source {
+                0 => InstructionSource::UserProgram,
+                1 => InstructionSource::RuntimeOptimiser,
+                2 => InstructionSource::ErrorModel,
+                other => return Err(self.malformed(format!("unknown source {other}"))),
+            };
+            let operation = match cursor.u64()? {
+                0 => LoggedOperation::BatchStart(cursor.u64()?, cursor.u64()?),
+                1 => LoggedOperation::QAlloc(cursor.u64()?),
+                2 => LoggedOperation::QFree(cursor.u64()?),
+                3 => LoggedOperation::Reset(cursor.u64()?),
+                4 => LoggedOperation::MeasureRequest(cursor.u64()?),
+                5 => LoggedOperation::FutureRead(cursor.u64()?),
+                6 => LoggedOperation::RXY(cursor.u64()?, cursor.f64()?, cursor.f64()?),
+                7 => LoggedOperation::RZ(cursor.u64()?, cursor.f64()?),
+                8 => LoggedOperation::RZZ(cursor.u64()?, cursor.u64()?, cursor.f64()?),
+                9 => LoggedOperation::Custom(cursor.u64()?, cursor.bytes()?),
+                10 => {
+                    let n_qubits = cursor.u64()?;
+                    let qubits = (0..n_qubits)
+                        .map(|_| cursor.u64())
+                        .collect::<Result<_, _>>()?;
+                    LoggedOperation::LocalBarrier(qubits, cursor.u64()?)
+                }
+                11 => LoggedOperation::GlobalBarrier(cursor.u64()?),
+                12 => LoggedOperation::MeasureLeakedRequest(cursor.u64()?),
+                13 => LoggedOperation::BeginCondition(cursor.u64()?, cursor.u64()? != 0),
+                14 => LoggedOperation::EndCondition,
+                15 => LoggedOperation::MeasureResetRequest(cursor.u64()?),
+                16 => LoggedOperation::BoolResult(cursor.u64()?, cursor.u64()? != 0),
+                17 => LoggedOperation::U64Result(cursor.u64()?, cursor.u64()?),
+                other => return Err(self.malformed(format!("unknown operation {other}"))),
+            };
+            instructions.push(Instruction { source, operation });
+        }
+        Ok(instructions)
+    }
+}
+
+/// The component responsible for an [Instruction] in the instruction log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstructionSource {
+    UserProgram,
+    RuntimeOptimiser,
+    ErrorModel,
+}
+
+/// An operation recorded in the instruction log, mirroring the operations
+/// reported to selene's event hooks.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoggedOperation {
+    BatchStart(u64, u64),
+    QAlloc(u64),
+    QFree(u64),
+    Reset(u64),
+    MeasureRequest(u64),
+    FutureRead(u64),
+    RXY(u64, f64, f64),
+    RZ(u64, f64),
+    RZZ(u64, u64, f64),
+    Custom(u64, Vec<u8>),
+    LocalBarrier(Vec<u64>, u64),
+    GlobalBarrier(u64),
+    MeasureLeakedRequest(u64),
+    BeginCondition(u64, bool),
+    EndCondition,
+    MeasureResetRequest(u64),
+    /// A boolean result set by the error model, with its result id.
+    BoolResult(u64, bool),
+    /// An integer result set by the error model, with its result id.
+    U64Result(u64, u64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Instruction {
+    pub source: InstructionSource,
+    pub operation: LoggedOperation,
+}
+
+struct InstructionCursor<'a> {
+    message: &'a Message,
+    values: std::slice::Iter<'a, Value>,
+}
+
+impl InstructionCursor<'_> {
+    fn next_u64_or_end(&mut self) -> Result<Option<u64>, OutputStreamReadError> {
+        match self.values.next() {
+            None => Ok(None),
+            Some(Value::U64(value)) => Ok(Some(*value)),
+            Some(other) => Err(self
+                .message
+                .malformed(format!("expected an unsigned integer, got {other:?}"))),
+        }
+    }
+
+    fn u64(&mut self) -> Result<u64, OutputStreamReadError> {
+        self.next_u64_or_end()?
+            .ok_or_else(|| self.message.malformed("truncated instruction"))
+    }
+
+    fn f64(&mut self) -> Result<f64, OutputStreamReadError> {
+        match self.values.next() {
+            Some(Value::F64(value)) => Ok(*value),
+            other => Err(self
+                .message
+                .malformed(format!("expected a float, got {other:?}"))),
+        }
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>, OutputStreamReadError> {
+        match self.values.next() {
+            Some(Value::Bytes(value)) => Ok(value.clone()),
+            Some(Value::Byte(value)) => Ok(vec![*value]),
+            other => Err(self
+                .message
+                .malformed(format!("expected bytes, got {other:?}"))),
+        }
+    }
+}
+
+/// The messages of a single shot, excluding the shot start and end markers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shot {
+    pub shot_id: u64,
+    pub messages: Vec<Message>,
+}
+
+/// Reads [Message]s from a result stream.
+///
+/// Reads are made in small pieces, so wrapping unbuffered sources such as
+/// files or sockets in a [std::io::BufReader] is recommended.
+pub struct OutputStreamReader<R> {
+    reader: R,
+    bytes_read: u64,
+    finished: bool,
+}
+
+impl<R: Read> OutputStreamReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            bytes_read: 0,
+            finished: false,
+        }
+    }
+
+    /// The number of bytes consumed from the underlying reader so far.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// Whether the end of stream marker has been read (or reading has
+    /// failed), after which no further messages will be returned.
+    pub fn is_finished(&self) -> bool {
+        self.finished
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// The underlying reader. Reading from it directly will corrupt the
+    /// messages read afterwards.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Result<Vec<u8>, OutputStreamReadError> {
+        let mut buffer = vec![0u8; length];
+        self.reader
+            .read_exact(&mut buffer)
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::UnexpectedEof => OutputStreamReadError::UnexpectedEndOfStream,
+                _ => OutputStreamReadError::IoError(e),
+            })?;
+        self.bytes_read += length as u64;
+        Ok(buffer)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N], OutputStreamReadError> {
+        let bytes = self.read_bytes(N)?;
+        Ok(bytes.try_into().expect("read_bytes returns N bytes"))
+    }
+
+    fn read_header(&mut self) -> Result<(u16, u16), OutputStreamReadError> {
+        let datatype = u16::from_le_bytes(self.read_array()?);
+        let size = u16::from_le_bytes(self.read_array()?);
+        Ok((datatype, size))
+    }
+
+    fn read_string(&mut self, size: u16) -> Result<String, OutputStreamReadError> {
+        String::from_utf8(self.read_bytes(size as usize)?)
+            .map_err(OutputStreamReadError::InvalidStringError)
+    }
+
+    fn read_words<T>(
+        &mut self,
+        size: u16,
+        convert: impl Fn([u8; 8]) -> T,
+    ) -> Result<Vec<T>, OutputStreamReadError> {
+        let bytes = self.read_bytes(size as usize * 8)?;
+        Ok(bytes
+            .chunks_exact(8)
+            .map(|chunk| convert(chunk.try_into().expect("chunks are 8 bytes")))
+            .collect())
+    }
+
+    fn read_value(&mut self, datatype: u16, size: u16) -> Result<Value, OutputStreamReadError> {
+        Ok(match (datatype, size) {
+            (U64_REPR, 0) => Value::U64(u64::from_le_bytes(self.read_array()?)),
+            (U64_REPR, n) => Value::U64Array(self.read_words(n, u64::from_le_bytes)?),
+            (I64_REPR, 0) => Value::I64(i64::from_le_bytes(self.read_array()?)),
+            (I64_REPR, n) => Value::I64Array(self.read_words(n, i64::from_le_bytes)?),
+            (F64_REPR, 0) => Value::F64(f64::from_le_bytes(self.read_array()?)),
+            (F64_REPR, n) => Value::F64Array(self.read_words(n, f64::from_le_bytes)?),
+            (BOOL_REPR, 0) => Value::Bool(self.read_array::<1>()?[0] != 0),
+            (BOOL_REPR, n) => Value::BoolArray(
+                self.read_bytes(n as usize)?
+                    .into_iter()
+                    .map(|byte| byte != 0)
+                    .collect(),
+            ),
+            (BYTE_REPR, 0) => Value::Byte(self.read_array::<1>()?[0]),
+            (BYTE_REPR, n) => Value::Bytes(self.read_bytes(n as usize)?),
+            (STR_REPR, n) => Value::Str(self.read_string(n)?),
+            (datatype, size) => {
+                return Err(OutputStreamReadError::UnexpectedTypeError(datatype, size));
+            }
+        })
+    }
+
+    /// Read the next message, returning `None` once the end of stream marker
+    /// has been reached.
+    pub fn next_message(&mut self) -> Result<Option<Message>, OutputStreamReadError> {
+        if self.finished {
+            return Ok(None);
+        }
+        let result = self.read_message();
+        if !matches!(result, Ok(Some(_))) {
+            self.finished = true;
+        }
+        result
+    }
+
+    fn read_message(&mut self) -> Result<Option<Message>, OutputStreamReadError> {
+        let time_cursor = u64::from_le_bytes(self.read_array()?);
+        if time_cursor == END_OF_STREAM {
+            return Ok(None);
+        }
+        let tag = match self.read_header()? {
+            (STR_REPR, size) => self.read_string(size)?,
+            (datatype, size) => {
+                return Err(OutputStreamReadError::MissingTagError(datatype, size));
+            }
+        };
+        let mut values = Vec::new();
+        loop {
+            match self.read_header()? {
+                (END_REPR, 0) => break,
+                (datatype, size) => values.push(self.read_value(datatype, size)?),
+            }
+        }
+        Ok(Some(Message {
+            time_cursor,
+            tag,
+            values,
+        }))
+    }
+
+    /// Read all messages belonging to the next shot, validating that they are
+    /// enclosed by matching shot start and end markers. Returns `None` once
+    /// the end of stream marker has been reached.
+    pub fn next_shot(&mut self) -> Result<Option<Shot>, OutputStreamReadError> {
+        let boundary_error = |reason: String| OutputStreamReadError::ShotBoundaryError(reason);
+        let Some(first) = self.next_message()? else {
+            return Ok(None);
+        };
+        let shot_id = match first.kind()? {
+            MessageKind::ShotStart(shot_id) => shot_id,
+            _ => {
+                return Err(boundary_error(format!(
+                    "Unexpected message '{}' before shot start",
+                    first.tag
+                )));
+            }
+        };
+        let mut messages = Vec::new();
+        loop {
+            let Some(message) = self.next_message()? else {
+                return Err(boundary_error(format!("Shot {shot_id} ended unexpectedly")));
+            };
+            match message.kind()? {
+                MessageKind::ShotStart(other) => {
+                    return Err(boundary_error(format!(
+                        "Received shot start for shot {other} while shot {shot_id} is in progress"
+                    )));
+                }
+                MessageKind::ShotEnd(other) if other != shot_id => {
+                    return Err(boundary_error(format!(
+                        "Received shot end for shot {other} while shot {shot_id} is in progress"
+                    )));
+                }
+                MessageKind::ShotEnd(_) => break,
+                _ => messages.push(message),
+            }
+        }
+        Ok(Some(Shot { shot_id, messages }))
+    }
+}
+
+#[cfg(feature = "compression")]
+impl OutputStreamReader<Box<dyn Read>> {
+    /// Create a reader for a result stream that may be compressed, detecting
+    /// the framing from the first bytes of the stream.
+    pub fn open(mut reader: impl Read + 'static) -> Result<Self, OutputStreamReadError> {
+        let mut prefix = [0u8; ZSTD_MAGIC.len()];
+        let mut filled = 0;
+        while filled < prefix.len() {
+            match reader.read(&mut prefix[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(OutputStreamReadError::IoError(e)),
+            }
+        }
+        let prefix = prefix[..filled].to_vec();
+        let compression = detect_compression(&prefix);
+        let reader = std::io::Cursor::new(prefix).chain(reader);
+        let reader: Box<dyn Read> = match compression {
+            StreamCompression::None => Box::new(reader),
+            StreamCompression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(reader)),
+            StreamCompression::Zstd => Box::new(
+                zstd::stream::read::Decoder::new(reader).map_err(OutputStreamReadError::IoError)?,
+            ),
+        };
+        Ok(Self::new(reader))
+    }
+}
+
+impl<R: Read> Iterator for OutputStreamReader<R> {
+    type Item = Result<Message, OutputStreamReadError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_message().transpose()
+    }
+}
@@ -0,0 +1,289 @@
+use super::*;
+use crate::encoder::{MessageWriter, OutputStream, OutputStreamError};
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+
+// A writer that can be inspected after the OutputStream owning it is dropped.
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn encode(write_messages: impl FnOnce(&mut OutputStream)) -> Vec<u8> {
+    let buffer = SharedBuffer::default();
+    let mut stream = OutputStream::new(Box::new(buffer.clone()));
+    write_messages(&mut stream);
+    // Dropping the stream writes the end of stream marker.
+    drop(stream);
+    buffer.0.take()
+}
+
+fn print<T: StreamWritable>(stream: &mut OutputStream, time_cursor: u64, tag: &str, value: T) {
+    stream.begin_message(time_cursor).unwrap();
+    stream.write(tag).unwrap();
+    stream.write(value).unwrap();
+    stream.end_message().unwrap();
+}
+
+#[test]
+fn round_trip_values() {
+    let bytes = encode(|stream| {
+        stream.begin_message(42).unwrap();
+        stream.write("USER:MIXED:values").unwrap();
+        stream.write(7u64).unwrap();
+        stream.write(-7i64).unwrap();
+        stream.write(1.5f64).unwrap();
+        stream.write(true).unwrap();
+        stream.write(200u8).unwrap();
+        stream.write("hello").unwrap();
+        stream.write("").unwrap();
+        stream.write(&[1u64, 2, 3][..]).unwrap();
+        stream.write(&[-1i64, 0, 1][..]).unwrap();
+        stream.write(&[0.25f64, -0.5][..]).unwrap();
+        stream.write(&[true, false, true][..]).unwrap();
+        stream.write(&[1u8, 2, 255][..]).unwrap();
+        stream.end_message().unwrap();
+    });
+    let mut reader = OutputStreamReader::new(bytes.as_slice());
+    let message = reader.next_message().unwrap().unwrap();
+    assert_eq!(message.time_cursor, 42);
+    assert_eq!(message.tag, "USER:MIXED:values");
+    assert_eq!(
+        message.values,
+        vec![
+            Value::U64(7),
+            Value::I64(-7),
+            Value::F64(1.5),
+            Value::Bool(true),
+            Value::Byte(200),
+            Value::Str("hello".to_string()),
+            Value::Str(String::new()),
+            Value::U64Array(vec![1, 2, 3]),
+            Value::I64Array(vec![-1, 0, 1]),
+            Value::F64Array(vec![0.25, -0.5]),
+            Value::BoolArray(vec![true, false, true]),
+            Value::Bytes(vec![1, 2, 255]),
+        ]
+    );
+    assert!(reader.next_message().unwrap().is_none());
+    assert!(reader.is_finished());
+    assert_eq!(reader.bytes_read(), bytes.len() as u64);
+}
+
+#[test]
+fn shots_and_message_kinds() {
+    let bytes = encode(|stream| {
+        for shot_id in 0..2u64 {
+            print(stream, 0, "SELENE:SHOT_START", shot_id);
+            print(stream, 10, "USER:BOOL:c0", shot_id == 1);
+            print(stream, 10, "USER:STATE:psi", "/tmp/state.bin");
+            print(stream, 20, "METRICS:INT:user_program:qalloc_count", 2u64);
+            print(stream, 20, "METRICS:FLT:fidelity", 0.5f64);
+            print(stream, 30, "EXIT:INT:out of qubits", 3u64);
+            stream.begin_message(30).unwrap();
+            stream.write("INSTRUCTIONLOG").unwrap();
+            for word in [0u64, 1, 4] {
+                stream.write(word).unwrap();
+            }
+            for word in [1u64, 6, 4] {
+                stream.write(word).unwrap();
+            }
+            stream.write(0.5f64).unwrap();
+            stream.write(-0.25f64).unwrap();
+            for word in [0u64, 10, 2, 4, 5, 100, 0, 13, 3, 1, 0, 14, 2, 16, 3, 1] {
+                stream.write(word).unwrap();
+            }
+            stream.end_message().unwrap();
+            print(stream, 40, "SELENE:SHOT_END", shot_id);
+        }
+    });
+    let mut reader = OutputStreamReader::new(bytes.as_slice());
+    for expected_shot_id in 0..2u64 {
+        let shot = reader.next_shot().unwrap().unwrap();
+        assert_eq!(shot.shot_id, expected_shot_id);
+        let kinds: Vec<MessageKind> = shot.messages.iter().map(|m| m.kind().unwrap()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                MessageKind::UserResult {
+                    name: "c0",
+                    value: &Value::Bool(expected_shot_id == 1)
+                },
+                MessageKind::UserState {
+                    name: "psi",
+                    path: "/tmp/state.bin"
+                },
+                MessageKind::Metric {
+                    category: Some("user_program"),
+                    name: "qalloc_count",
+                    value: MetricValue::U64(2)
+                },
+                MessageKind::Metric {
+                    category: None,
+                    name: "fidelity",
+                    value: MetricValue::F64(0.5)
+                },
+                MessageKind::Exit {
+                    message: "out of qubits",
+                    code: 3
+                },
+                MessageKind::InstructionLog(vec![
+                    Instruction {
+                        source: InstructionSource::UserProgram,
+                        operation: LoggedOperation::QAlloc(4),
+                    },
+                    Instruction {
+                        source: InstructionSource::RuntimeOptimiser,
+                        operation: LoggedOperation::RXY(4, 0.5, -0.25),
+                    },
+                    Instruction {
+                        source: InstructionSource::UserProgram,
+                        operation: LoggedOperation::LocalBarrier(vec![4, 5], 100),
+                    },
+                    Instruction {
+                        source: InstructionSource::UserProgram,
+                        operation: LoggedOperation::BeginCondition(3, true),
+                    },
+                    Instruction {
+                        source: InstructionSource::UserProgram,
+                        operation: LoggedOperation::EndCondition,
+                    },
+                    Instruction {
+                        source: InstructionSource::ErrorModel,
+                        operation: LoggedOperation::BoolResult(3, true),
+                    },
+                ]),
+            ]
+        );
+        assert!(!kinds[4].is_panic());
+    }
+    assert!(reader.next_shot().unwrap().is_none());
+}
+
+#[test]
+fn truncated_stream() {
+    let bytes = encode(|stream| print(stream, 0, "SELENE:SHOT_START", 0u64));
+    // Remove the end of stream marker and part of the final message.
+    let truncated = &bytes[..bytes.len() - 10];
+    let mut reader = OutputStreamReader::new(truncated);
+    assert!(matches!(
+        reader.next(),
+        Some(Err(OutputStreamReadError::UnexpectedEndOfStream))
+    ));
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn mismatched_shot_end() {
+    let bytes = encode(|stream| {
+        print(stream, 0, "SELENE:SHOT_START", 0u64);
+        print(stream, 0, "SELENE:SHOT_END", 1u64);
+    });
+    let mut reader = OutputStreamReader::new(bytes.as_slice());
+    assert!(matches!(
+        reader.next_shot(),
+        Err(OutputStreamReadError::ShotBoundaryError(_))
+    ));
+}
+
+#[test]
+fn detects_compression() {
+    let bytes = encode(|stream| print(stream, 0, "SELENE:SHOT_START", 0u64));
+    assert_eq!(detect_compression(&bytes), StreamCompression::None);
+    assert_eq!(
+        detect_compression(&[0x1f, 0x8b, 8, 0]),
+        StreamCompression::Gzip
+    );
+    assert_eq!(
+        detect_compression(&[0x28, 0xb5, 0x2f, 0xfd]),
+        StreamCompression::Zstd
+    );
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn round_trip_compressed() {
+    let bytes = encode(|stream| {
+        for shot_id in 0..3u64 {
+            print(stream, 0, "SELENE:SHOT_START", shot_id);
+            print(stream, 5, "USER:INT:x", shot_id);
+            print(stream, 9, "SELENE:SHOT_END", shot_id);
+        }
+    });
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(&bytes).unwrap();
+    let gzip = gzip.finish().unwrap();
+    let zstd = zstd::stream::encode_all(bytes.as_slice(), 0).unwrap();
+    for encoded in [bytes.clone(), gzip, zstd] {
+        let reader = OutputStreamReader::open(std::io::Cursor::new(encoded)).unwrap();
+        let messages: Vec<Message> = reader.map(|m| m.unwrap()).collect();
+        assert_eq!(messages.len(), 9);
+        assert_eq!(messages[4].values, vec![Value::U64(1)]);
+    }
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn concatenated_gzip_members() {
+    // A gzip stream may hold several members, for example when compressed
+    // output is appended to a file, and all of them are decoded.
+    let bytes = encode(|stream| {
+        for shot_id in 0..3u64 {
+            print(stream, 0, "SELENE:SHOT_START", shot_id);
+            print(stream, 5, "USER:INT:x", shot_id);
+            print(stream, 9, "SELENE:SHOT_END", shot_id);
+        }
+    });
+    let mut gzip = Vec::new();
+    for chunk in bytes.chunks(bytes.len() / 3 + 1) {
+        let mut member = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        member.write_all(chunk).unwrap();
+        gzip.extend(member.finish().unwrap());
+    }
+    let reader = OutputStreamReader::open(std::io::Cursor::new(gzip)).unwrap();
+    let messages: Vec<Message> = reader.map(|m| m.unwrap()).collect();
+    assert_eq!(messages.len(), 9);
+    assert_eq!(messages[7].values, vec![Value::U64(2)]);
+}
+
+#[test]
+fn message_writer_receives_decoded_messages() {
+    // The messages passed to a MessageWriter match those decoded from the
+    // binary encoding of the same calls.
+    struct Collect(Rc<RefCell<Vec<Message>>>);
+    impl MessageWriter for Collect {
+        fn write_message(&mut self, message: Message) -> Result<(), OutputStreamError> {
+            self.0.borrow_mut().push(message);
+            Ok(())
+        }
+        fn end_of_stream(&mut self) -> Result<(), OutputStreamError> {
+            Ok(())
+        }
+        fn flush(&mut self) -> Result<(), OutputStreamError> {
+            Ok(())
+        }
+    }
+    let write_messages = |stream: &mut OutputStream| {
+        print(stream, 0, "SELENE:SHOT_START", 0u64);
+        print(stream, 3, "USER:FLOATARR:xs", &[0.5f64, 1.5][..]);
+        print(stream, 5, "USER:STATE:psi", "/tmp/state.bin");
+        print(stream, 9, "SELENE:SHOT_END", 0u64);
+    };
+    let collected = Rc::new(RefCell::new(Vec::new()));
+    let mut stream = OutputStream::with_message_writer(Box::new(Collect(collected.clone())));
+    write_messages(&mut stream);
+    drop(stream);
+    let decoded: Vec<Message> = OutputStreamReader::new(encode(write_messages).as_slice())
+        .map(|m| m.unwrap())
+        .collect();
+    assert_eq!(collected.take(), decoded);
+}
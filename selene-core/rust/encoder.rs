@@ -1,6 +1,8 @@
 use std::io::Write;
 use thiserror::Error;
 
+use crate::decoder::{Message, Value};
+
 #[derive(Debug, Error)]
 pub enum OutputStreamError {
     #[error("IO Error: {0}")]
@@ -33,6 +35,8 @@ pub trait StreamWritable {
     }
     fn get_length(&self) -> Result<u16, OutputStreamError>;
     fn write_impl(&self, stream: &mut dyn Write) -> Result<(), OutputStreamError>;
+    /// The typed value, as passed to a [MessageWriter].
+    fn to_value(&self) -> Value;
 }
 impl StreamWritable for &str {
     const TYPE_REPR: u16 = 3;
@@ -52,10 +56,15 @@ impl StreamWritable for &str {
             .write_all(self.as_bytes())
             .map_err(OutputStreamError::IoError)
     }
+    fn to_value(&self) -> Value {
+        Value::Str(self.to_string())
+    }
 }
-pub trait StreamWritableSingle {
+pub trait StreamWritableSingle: Sized {
     const TYPE_REPR: u16;
     fn write_impl(&self, stream: &mut dyn Write) -> Result<(), OutputStreamError>;
+    fn to_value(&self) -> Value;
+    fn to_array_value(items: &[Self]) -> Value;
 }
 impl<T: StreamWritableSingle> StreamWritable for T {
     const TYPE_REPR: u16 = T::TYPE_REPR;
@@ -67,6 +76,9 @@ impl<T: StreamWritableSingle> StreamWritable for T {
     fn write_impl(&self, stream: &mut dyn Write) -> Result<(), OutputStreamError> {
         self.write_impl(stream)
     }
+    fn to_value(&self) -> Value {
+        StreamWritableSingle::to_value(self)
+    }
 }
 impl<T: StreamWritableSingle> StreamWritable for &[T] {
     const TYPE_REPR: u16 = T::TYPE_REPR;
@@ -85,6 +97,9 @@ impl<T: StreamWritableSingle> StreamWritable for &[T] {
         }
         Ok(())
     }
+    fn to_value(&self) -> Value {
+        T::to_array_value(self)
+    }
 }
 impl StreamWritableSingle for bool {
     const TYPE_REPR: u16 = 4;
@@ -94,6 +109,12 @@ impl StreamWritableSingle for bool {
             .write_all(&[byte])
             .map_err(OutputStreamError::IoError)
     }
+    fn to_value(&self) -> Value {
+        Value::Bool(*self)
+    }
+    fn to_array_value(items: &[Self]) -> Value {
+        Value::BoolArray(items.to_vec())
+    }
 }
 impl StreamWritableSingle for u64 {
     const TYPE_REPR: u16 = 1;
@@ -102,6 +123,12 @@ impl StreamWritableSingle for u64 {
             .write_all(&self.to_le_bytes())
             .map_err(OutputStreamError::IoError)
     }
+    fn to_value(&self) -> Value {
+        Value::U64(*self)
+    }
+    fn to_array_value(items: &[Self]) -> Value {
+        Value::U64Array(items.to_vec())
+    }
 }
 impl StreamWritableSingle for i64 {
     const TYPE_REPR: u16 = 5;
@@ -110,6 +137,12 @@ impl StreamWritableSingle for i64 {
             .write_all(&self.to_le_bytes())
             .map_err(OutputStreamError::IoError)
     }
+    fn to_value(&self) -> Value {
+        Value::I64(*self)
+    }
+    fn to_array_value(items: &[Self]) -> Value {
+        Value::I64Array(items.to_vec())
+    }
 }
 impl StreamWritableSingle for f64 {
     const TYPE_REPR: u16 = 2;
@@ -118,6 +151,12 @@ impl StreamWritableSingle for f64 {
             .write_all(&self.to_le_bytes())
             .map_err(OutputStreamError::IoError)
     }
+    fn to_value(&self) -> Value {
+        Value::F64(*self)
+    }
+    fn to_array_value(items: &[Self]) -> Value {
+        Value::F64Array(items.to_vec())
+    }
 }
 impl StreamWritableSingle for u8 {
     const TYPE_REPR: u16 = 9116;
@@ -126,37 +165,148 @@ impl StreamWritableSingle for u8 {
             .write_all(&self.to_le_bytes())
             .map_err(OutputStreamError::IoError)
     }
+    fn to_value(&self) -> Value {
+        Value::Byte(*self)
+    }
+    fn to_array_value(items: &[Self]) -> Value {
+        Value::Bytes(items.to_vec())
+    }
 }
+/// A destination for result stream messages in a format other than the
+/// binary encoding. Rather than bytes, it receives each message complete with
+/// its typed values once the message has ended.
+pub trait MessageWriter {
+    fn write_message(&mut self, message: Message) -> Result<(), OutputStreamError>;
+    /// Called when the stream is closed. This may be called more than once.
+    fn end_of_stream(&mut self) -> Result<(), OutputStreamError>;
+    fn flush(&mut self) -> Result<(), OutputStreamError>;
+}
+
+enum Backend {
+    Binary(Box<dyn Write>),
+    Structured {
+        writer: Box<dyn MessageWriter>,
+        // The message being built: its time cursor, its tag once written,
+        // and the values written after the tag.
+        pending: Option<(u64, Option<String>, Vec<Value>)>,
+    },
+}
+
+/// The result stream. By default messages are written in the binary encoding
+/// understood by [crate::decoder], but they may instead be passed to a
+/// [MessageWriter] with [OutputStream::with_message_writer]. Either way, the
+/// same calls are made to write results, metrics, instruction logs and so on.
 pub struct OutputStream {
-    writer: Box<dyn Write>,
+    backend: Backend,
 }
 impl OutputStream {
     pub fn new(writer: Box<dyn Write>) -> Self {
-        OutputStream { writer }
+        OutputStream {
+            backend: Backend::Binary(writer),
+        }
+    }
+    pub fn with_message_writer(writer: Box<dyn MessageWriter>) -> Self {
+        OutputStream {
+            backend: Backend::Structured {
+                writer,
+                pending: None,
+            },
+        }
     }
     fn write_impl(&mut self, value: &[u8]) -> Result<(), OutputStreamError> {
-        match self.writer.write_all(value) {
-            Ok(_) => Ok(()),
-            Err(e) => Err(OutputStreamError::IoError(e)),
+        match &mut self.backend {
+            Backend::Binary(writer) => writer.write_all(value).map_err(OutputStreamError::IoError),
+            Backend::Structured { .. } => Err(OutputStreamError::OtherError(
+                "Raw bytes cannot be written to a structured output stream".to_string(),
+            )),
         }
     }
     pub fn flush(&mut self) -> Result<(), OutputStreamError> {
-        self.writer.flush().map_err(OutputStreamError::IoError)
+        match &mut self.backend {
+            Backend::Binary(writer) => writer.flush().map_err(OutputStreamError::IoError),
+            Backend::Structured { writer, .. } => writer.flush(),
+        }
     }
 
     pub fn begin_message(&mut self, time_cursor: u64) -> Result<(), OutputStreamError> {
-        self.write_impl(&time_cursor.to_le_bytes())
+        match &mut self.backend {
+            Backend::Binary(_) => self.write_impl(&time_cursor.to_le_bytes()),
+            Backend::Structured { pending, .. } => {
+                *pending = Some((time_cursor, None, Vec::new()));
+                Ok(())
+            }
+        }
     }
     pub fn end_message(&mut self) -> Result<(), OutputStreamError> {
-        self.write_impl(&0u16.to_le_bytes())?;
-        self.write_impl(&0u16.to_le_bytes())
+        match &mut self.backend {
+            Backend::Binary(_) => {
+                self.write_impl(&0u16.to_le_bytes())?;
+                self.write_impl(&0u16.to_le_bytes())
+            }
+            Backend::Structured { writer, pending } => match pending.take() {
+                Some((time_cursor, Some(tag), values)) => writer.write_message(Message {
+                    time_cursor,
+                    tag,
+                    values,
+                }),
+                _ => Err(OutputStreamError::OtherError(
+                    "Message ended without a tag".to_string(),
+                )),
+            },
+        }
     }
     pub fn end_of_stream(&mut self) -> Result<(), OutputStreamError> {
-        self.write_impl(&u64::MAX.to_le_bytes())
+        match &mut self.backend {
+            Backend::Binary(_) => self.write_impl(&u64::MAX.to_le_bytes()),
+            Backend::Structured { writer, .. } => writer.end_of_stream(),
+        }
+    }
+    /// Write a complete message, e.g. one previously captured by a
+    /// [MessageWriter] or decoded from another stream.
+    pub fn write_message(&mut self, message: &Message) -> Result<(), OutputStreamError> {
+        self.begin_message(message.time_cursor)?;
+        self.write(message.tag.as_str())?;
+        for value in &message.values {
+            match value {
+                Value::U64(v) => self.write(*v)?,
+                Value::I64(v) => self.write(*v)?,
+                Value::F64(v) => self.write(*v)?,
+                Value::Bool(v) => self.write(*v)?,
+                Value::Byte(v) => self.write(*v)?,
+                Value::Str(v) => self.write(v.as_str())?,
+                Value::U64Array(v) => self.write(v.as_slice())?,
+                Value::I64Array(v) => self.write(v.as_slice())?,
+                Value::F64Array(v) => self.write(v.as_slice())?,
+                Value::BoolArray(v) => self.write(v.as_slice())?,
+                Value::Bytes(v) => self.write(v.as_slice())?,
+            }
+        }
+        self.end_message()
     }
     // Single element writers
     pub fn write<T: StreamWritable>(&mut self, value: T) -> Result<(), OutputStreamError> {
-        value.write(&mut self.writer)
+        match &mut self.backend {
+            Backend::Binary(writer) => value.write(writer),
+            Backend::Structured { pending, .. } => {
+                let Some((_, tag, values)) = pending else {
+                    return Err(OutputStreamError::OtherError(
+                        "Value written outside of a message".to_string(),
+                    ));
+                };
+                // Apply the same validation as the binary encoding.
+                value.get_length()?;
+                match (tag.is_none(), value.to_value()) {
+                    (true, Value::Str(s)) => *tag = Some(s),
+                    (true, _) => {
+                        return Err(OutputStreamError::OtherError(
+                            "The first value of a message must be its tag".to_string(),
+                        ));
+                    }
+                    (false, v) => values.push(v),
+                }
+                Ok(())
+            }
+        }
     }
 }
 impl Drop for OutputStream {
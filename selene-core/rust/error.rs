@@ -0,0 +1,162 @@
+//! Categories of error reported across the FFI boundary.
+//!
+//! Failures are reported to frontends through the `error_code` of the result
+//! structs returned by selene's C interface, and by plugins through the
+//! [Errno](crate::runtime::plugin::Errno) returned from their entry points. In
+//! both cases the code identifies an [ErrorKind], so that callers can tell
+//! apart, for example, a program addressing a qubit that doesn't exist from a
+//! simulator running out of memory.
+//!
+//! Errors are given a kind by constructing them with [ErrorKind::error]. Any
+//! error without a kind is reported as [ErrorKind::Internal]. Plugins built
+//! with the `export_*_plugin!` macros return the code of the kind from their
+//! entry points, and `-1` for errors without a kind.
+//!
+//! Frontends end the run on codes of 1000 and above, and only the current shot
+//! on lower codes. All kinds end the run except for
+//! [ErrorKind::ShotLimitExceeded] and [ErrorKind::ShotFailed], which are
+//! confined to the shot that raised them.
+//!
+//! The codes of those two kinds, 999 and 998, lie in the range that user
+//! programs use for panics ending only the current shot, and are reserved for
+//! selene. Frontends identify exits with these codes as coming from selene,
+//! so user programs must not panic with them.
+use std::fmt;
+
+use crate::encoder::OutputStreamError;
+
+/// The category of an error, with the code used to report it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum ErrorKind {
+    /// A null instance was passed to the C interface.
+    NullInstance = 100000,
+    /// An error that does not fall into any other category.
+    Internal = 100001,
+    /// An operation referred to a qubit outside of the range available.
+    QubitOutOfBounds = 100002,
+    /// A component could not allocate the memory it requires.
+    OutOfMemory = 100003,
+    /// A postselection was requested for an outcome with zero probability.
+    PostselectionImpossible = 100004,
+    /// An argument to an operation or plugin was invalid.
+    InvalidArgument = 100005,
+    /// An operation is not supported by the plugin it was sent to.
+    Unsupported = 100006,
+    /// Writing to the result stream failed.
+    Output = 100007,
+    /// A plugin hosted in a separate process exited unexpectedly.
+    PluginCrashed = 100008,
+    /// A shot exceeded one of the limits configured for it, such as the
+    /// number of operations it may issue. Unlike the other kinds, this ends
+    /// only the shot in which it is raised. The code is reserved, and must not
+    /// be used by user programs.
+    ShotLimitExceeded = 999,
+    /// A shot failed with an error that the configured error policy confines
+    /// to the shot, which is described in the message. The code is reserved,
+    /// and must not be used by user programs.
+    ShotFailed = 998,
+}
+
+impl ErrorKind {
+    const ALL: [ErrorKind; 11] = [
+        ErrorKind::NullInstance,
+        ErrorKind::Internal,
+        ErrorKind::QubitOutOfBounds,
+        ErrorKind::OutOfMemory,
+        ErrorKind::PostselectionImpossible,
+        ErrorKind::InvalidArgument,
+        ErrorKind::Unsupported,
+        ErrorKind::Output,
+        ErrorKind::PluginCrashed,
+        ErrorKind::ShotLimitExceeded,
+        ErrorKind::ShotFailed,
+    ];
+
+    pub fn code(self) -> u32 {
+        self as u32
+    }
+
+    pub fn from_code(code: u32) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.code() == code)
+    }
+
+    /// The errno for a plugin to return for an error of this kind.
+    pub fn errno(self) -> i32 {
+        match self {
+            ErrorKind::Internal => -1,
+            kind => kind.code() as i32,
+        }
+    }
+
+    /// The kind of error indicated by a nonzero errno returned by a plugin.
+    /// Errnos that are not the code of a kind are treated as internal errors.
+    pub fn from_errno(errno: i32) -> Self {
+        u32::try_from(errno)
+            .ok()
+            .and_then(Self::from_code)
+            .unwrap_or(ErrorKind::Internal)
+    }
+
+    /// Create an error of this kind with the given message.
+    pub fn error(self, message: impl fmt::Display) -> anyhow::Error {
+        SeleneError {
+            kind: self,
+            message: message.to_string(),
+        }
+        .into()
+    }
+
+    /// The kind of the given error. This is the kind of the innermost error in
+    /// its chain that was given one, or [ErrorKind::Output] for failures to
+    /// write the result stream.
+    pub fn of(error: &anyhow::Error) -> Self {
+        error
+            .chain()
+            .rev()
+            .find_map(|cause| {
+                if let Some(error) = cause.downcast_ref::<SeleneError>() {
+                    Some(error.kind)
+                } else if cause.is::<OutputStreamError>() {
+                    Some(ErrorKind::Output)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(ErrorKind::Internal)
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            ErrorKind::NullInstance => "null instance",
+            ErrorKind::Internal => "internal error",
+            ErrorKind::QubitOutOfBounds => "qubit out of bounds",
+            ErrorKind::OutOfMemory => "out of memory",
+            ErrorKind::PostselectionImpossible => "postselection impossible",
+            ErrorKind::InvalidArgument => "invalid argument",
+            ErrorKind::Unsupported => "unsupported operation",
+            ErrorKind::Output => "output error",
+            ErrorKind::PluginCrashed => "plugin crashed",
+            ErrorKind::ShotLimitExceeded => "shot limit exceeded",
+            ErrorKind::ShotFailed => "shot failed",
+        };
+        f.write_str(description)
+    }
+}
+
+/// An error with a kind, created by [ErrorKind::error].
+#[derive(Debug)]
+pub struct SeleneError {
+    pub kind: ErrorKind,
+    pub message: String,
+}
+
+impl fmt::Display for SeleneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for SeleneError {}
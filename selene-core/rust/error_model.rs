@@ -1,5 +1,7 @@
+use crate::capabilities::Capabilities;
 use crate::utils::MetricValue;
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use std::ffi::OsStr;
 use std::sync;
 
@@ -7,26 +9,44 @@ pub mod helper;
 pub mod interface;
 pub mod plugin;
 pub mod version;
-use crate::runtime::BatchOperation;
+use crate::runtime::{BatchOperation, Operation};
 use delegate::delegate;
 pub use interface::{ErrorModelInterface, ErrorModelInterfaceFactory};
 pub use version::ErrorModelAPIVersion;
 
-#[derive(Default)]
+#[derive(Default, Debug, Serialize, Deserialize)]
 pub struct BoolResult {
     pub result_id: u64,
     pub value: bool,
 }
-#[derive(Default)]
+#[derive(Default, Debug, Serialize, Deserialize)]
 pub struct U64Result {
     pub result_id: u64,
     pub value: u64,
 }
 
-#[derive(Default)]
+/// An operation the error model applied in addition to those of a batch,
+/// such as a Pauli error.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InjectedOperation {
+    /// The number of the batch's operations applied before this one: 0 if it
+    /// was applied before the first of them, and the length of the batch if
+    /// it was applied after the last.
+    pub position: u64,
+    pub operation: Operation,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
 pub struct BatchResult {
     pub bool_results: Vec<BoolResult>,
     pub u64_results: Vec<U64Result>,
+    /// Operations the error model applied in addition to those of the batch,
+    /// such as Pauli errors, in the order it applied them, along with their
+    /// positions among the operations of the batch. Reporting them is
+    /// optional, and only serves to let event hooks observe the noisy
+    /// trajectory of a shot.
+    #[serde(default)]
+    pub injected_operations: Vec<InjectedOperation>,
 }
 impl BatchResult {
     pub fn set_bool_result(&mut self, result_id: u64, value: bool) {
@@ -35,9 +55,17 @@ impl BatchResult {
     pub fn set_u64_result(&mut self, result_id: u64, value: u64) {
         self.u64_results.push(U64Result { result_id, value });
     }
+    /// Report an operation applied after the first `position` operations of
+    /// the batch.
+    pub fn report_operation(&mut self, position: u64, operation: Operation) {
+        self.injected_operations.push(InjectedOperation {
+            position,
+            operation,
+        });
+    }
 }
 
-pub struct ErrorModel(Box<dyn ErrorModelInterface>);
+pub struct ErrorModel(Box<dyn ErrorModelInterface>, Capabilities);
 
 impl ErrorModel {
     pub fn new(
@@ -47,12 +75,58 @@ impl ErrorModel {
         simulator_path: &impl AsRef<OsStr>,
         simulator_args: &[impl AsRef<str>],
     ) -> Result<Self> {
-        Ok(Self(factory.init(
+        let capabilities = factory.capabilities();
+        let error_model =
+            factory.init(n_qubits, error_model_args, simulator_path, simulator_args)?;
+        Ok(Self(error_model, capabilities))
+    }
+
+    /// Loads the error model plugin at the given path, or the registered
+    /// error model it refers to (see [crate::registry]). Paths with the
+    /// `isolated:` prefix are hosted out of process, along with the error
+    /// model's simulator (see [crate::host]).
+    pub fn load_from_file(
+        plugin_path: &impl AsRef<OsStr>,
+        n_qubits: u64,
+        error_model_args: &[impl AsRef<str>],
+        simulator_path: &impl AsRef<OsStr>,
+        simulator_args: &[impl AsRef<str>],
+    ) -> Result<Self> {
+        if let Some(name) = crate::registry::builtin_name(plugin_path.as_ref()) {
+            return crate::registry::new_error_model(
+                name,
+                n_qubits,
+                error_model_args,
+                simulator_path.as_ref(),
+                simulator_args,
+            );
+        }
+        if let Some(plugin_path) = crate::host::isolated_plugin(plugin_path.as_ref()) {
+            let factory = crate::host::RemoteErrorModelFactory::launch(plugin_path)?;
+            return Self::new(
+                sync::Arc::new(factory),
+                n_qubits,
+                error_model_args,
+                simulator_path,
+                simulator_args,
+            );
+        }
+        let plugin = plugin::ErrorModelPluginInterface::new_from_file(plugin_path)?;
+        Self::new(
+            plugin,
             n_qubits,
             error_model_args,
             simulator_path,
             simulator_args,
-        )?))
+        )
+    }
+}
+
+impl ErrorModel {
+    /// The optional features supported by the error model. The capabilities
+    /// of its simulator are reported separately.
+    pub fn capabilities(&self) -> Capabilities {
+        self.1
     }
 }
 
@@ -78,6 +152,7 @@ impl ErrorModelInterface for ErrorModel {
             fn dump_simulator_state(&mut self, file: &std::path::Path, qubits: &[u64]) -> Result<()>;
             fn get_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>>;
             fn get_simulator_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>>;
+            fn simulator_capabilities(&mut self) -> Result<Option<Capabilities>>;
         }
     }
 }
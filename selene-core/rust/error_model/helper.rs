@@ -9,8 +9,9 @@ use super::{
         Errno, ErrorModelInstance, ErrorModelSetResultInstance, ErrorModelSetResultInterface,
     },
 };
+use crate::runtime::BatchOperation;
 use crate::runtime::plugin::{
-    BatchBuilder, RuntimeExtractOperationInstance, RuntimeExtractOperationInterface,
+    BatchBuilder, BatchExtractor, RuntimeExtractOperationInstance, RuntimeExtractOperationInterface,
 };
 use crate::utils::{convert_cargs_to_strings, result_of_errno_to_errno, result_to_errno};
 use std::{ffi, mem, sync::Arc};
@@ -148,10 +149,13 @@ impl<F: ErrorModelInterfaceFactory> Helper<F> {
                 let (builder_instance, builder_interface) = batch_builder.runtime_get_operation();
                 let RuntimeExtractOperationInterface { extract_fn, .. } = &*extract_ops_interface;
                 extract_fn(extract_ops_instance, builder_instance, builder_interface);
-                let results = error_model.handle_operations(batch_builder.finish())?;
+                let batch = batch_builder.finish();
+                let (start, duration) = (batch.start(), batch.duration());
+                let results = error_model.handle_operations(batch)?;
                 let ErrorModelSetResultInterface {
                     set_bool_result_fn,
                     set_u64_result_fn,
+                    report_operations_fn,
                     ..
                 } = &*result_interface;
                 for bool_result in results.bool_results {
@@ -160,6 +164,24 @@ impl<F: ErrorModelInterfaceFactory> Helper<F> {
                 for u64_result in results.u64_results {
                     set_u64_result_fn(result_instance, u64_result.result_id, u64_result.value);
                 }
+                // Operations injected at the same position are reported together.
+                for injected in results
+                    .injected_operations
+                    .chunk_by(|a, b| a.position == b.position)
+                {
+                    let operations = injected.iter().map(|i| i.operation.clone()).collect();
+                    let mut batch_extractor = BatchExtractor::from_batch_operation(
+                        BatchOperation::new(operations, start, duration),
+                    );
+                    let (extractor_instance, extractor_interface) =
+                        batch_extractor.runtime_batch_extraction();
+                    report_operations_fn(
+                        result_instance,
+                        injected[0].position,
+                        extractor_instance,
+                        &raw const extractor_interface,
+                    );
+                }
                 anyhow::Ok(())
             }),
         )
@@ -202,6 +224,22 @@ impl<F: ErrorModelInterfaceFactory> Helper<F> {
             }),
         )
     }
+
+    pub unsafe fn get_simulator_capabilities(
+        instance: ErrorModelInstance,
+        bits_ptr: *mut u64,
+    ) -> Errno {
+        result_of_errno_to_errno(
+            "Failed to get the simulator capabilities",
+            Self::with_error_model_instance(instance, |error_model| {
+                let Some(capabilities) = error_model.simulator_capabilities()? else {
+                    return anyhow::Ok(1);
+                };
+                unsafe { *bits_ptr = capabilities.bits() };
+                Ok(0)
+            }),
+        )
+    }
 }
 
 #[macro_export]
@@ -217,8 +255,16 @@ impl<F: ErrorModelInterfaceFactory> Helper<F> {
 /// A crate can only export a single runtime plugin (although it may export other
 /// plugins of other types). See the `selene-ideal-error-model-plugin` python package
 /// for a fully worked example.
+///
+/// The plugin describes itself in its metadata under the name given after the
+/// factory type, which should be the name it is registered under when linked
+/// into selene (see [crate::registry]), or under its package name if no name
+/// is given.
 macro_rules! export_error_model_plugin {
     ($factory_type:ty) => {
+        $crate::export_error_model_plugin!($factory_type, env!("CARGO_PKG_NAME"));
+    };
+    ($factory_type:ty, $name:expr) => {
         mod _plugin {
             use selene_core::{
                 error_model::{
@@ -257,10 +303,10 @@ macro_rules! export_error_model_plugin {
             /// validating the API version of the plugin against its own
             /// version.
             ///
-            /// The plans for this validation are a work-in-progress, but
-            /// currently selene will reject any plugin that has a different
-            /// major or minor version than the current Selene version, or with
-            /// a reserved field that is not 0.
+            /// Selene rejects plugins with a different major version, a newer
+            /// minor version, or a minor version older than it supports, as well
+            /// as plugins with a reserved field that is not 0. Plugins of older
+            /// supported minor versions are adapted to.
             #[unsafe(no_mangle)]
             pub unsafe extern "C" fn selene_error_model_get_api_version() -> u64 {
                 // The API version is defined in the version module
@@ -468,6 +514,65 @@ macro_rules! export_error_model_plugin {
             ) -> Errno {
                 Helper::get_metric(instance, nth_metric, tag_ptr, datatype_ptr, data_ptr)
             }
+
+            /// Get the optional features supported by the simulator that the error
+            /// model instance has loaded, writing the bits of a
+            /// `selene_core::capabilities::Capabilities` set to `bits_ptr`. Returns 0
+            /// if they were written, or 1 if the error model does not report them, in
+            /// which case Selene reads them from the simulator plugin itself.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_error_model_get_simulator_capabilities(
+                instance: ErrorModelInstance,
+                bits_ptr: *mut u64,
+            ) -> Errno {
+                Helper::get_simulator_capabilities(instance, bits_ptr)
+            }
+
+            /// Get the message describing the latest error returned by the error model
+            /// in the calling thread, as a null terminated string, or null if there
+            /// has been no error. The string remains valid until the next error.
+            ///
+            /// Selene calls this after an entry point returns an error, and reports
+            /// the message along with the error. Plugins that do not provide this
+            /// entry point should write any details of their errors to stderr.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_error_model_last_error_message() -> *const c_char {
+                selene_core::utils::last_error_message_ptr()
+            }
+
+            /// Get the optional features supported by the error model, as the bits of a
+            /// `selene_core::capabilities::Capabilities` set. Selene only uses a
+            /// declared capability if the entry points it relies on are present.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_error_model_get_capabilities() -> u64 {
+                <$factory_type as Default>::default().capabilities().bits()
+            }
+
+            /// Get a description of the error model as a nul-terminated JSON string, in
+            /// the form of a `selene_core::metadata::PluginMetadata`: its name and
+            /// version, the API version, its capabilities and the arguments it
+            /// accepts. The string is owned by the plugin and lives as long as it
+            /// is loaded.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_error_model_get_metadata() -> *const c_char {
+                static METADATA: std::sync::OnceLock<std::ffi::CString> =
+                    std::sync::OnceLock::new();
+                METADATA
+                    .get_or_init(|| {
+                        let factory = <$factory_type as Default>::default();
+                        selene_core::metadata::PluginMetadata::new(
+                            selene_core::metadata::PluginKind::ErrorModel,
+                            $name,
+                            env!("CARGO_PKG_NAME"),
+                            env!("CARGO_PKG_VERSION"),
+                            CURRENT_API_VERSION,
+                            factory.capabilities(),
+                            factory.arguments(),
+                        )
+                        .to_c_string()
+                    })
+                    .as_ptr()
+            }
         }
     };
 }
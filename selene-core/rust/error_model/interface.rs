@@ -2,7 +2,9 @@ use anyhow::{Result, bail};
 use std::ffi::OsStr;
 use std::sync::Arc;
 
+use crate::capabilities::Capabilities;
 use crate::error_model::BatchResult;
+use crate::metadata::ArgumentMetadata;
 use crate::runtime::BatchOperation;
 use crate::utils::MetricValue;
 
@@ -71,6 +73,13 @@ pub trait ErrorModelInterface {
     /// - Precision loss due to numerical errors
     /// - Measures of connectivity between qubits
     fn get_simulator_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>>;
+    /// The optional features supported by the simulator the error model has
+    /// loaded, so that selene need not load the simulator again to find them.
+    /// `None` if the error model does not report them, in which case they are
+    /// read from the simulator plugin itself.
+    fn simulator_capabilities(&mut self) -> Result<Option<Capabilities>> {
+        Ok(None)
+    }
 }
 
 pub trait ErrorModelInterfaceFactory {
@@ -83,4 +92,18 @@ pub trait ErrorModelInterfaceFactory {
         simulator_plugin: &impl AsRef<OsStr>,
         simulator_args: &[impl AsRef<str>],
     ) -> Result<Box<Self::Interface>>;
+
+    /// The optional features supported by the interfaces this factory
+    /// creates. See [Capabilities].
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::empty()
+    }
+
+    /// The arguments accepted by [Self::init], reported in the plugin's
+    /// metadata so that they can be checked before a run. `None` if they are
+    /// not described. See `ArgumentMetadata::from_clap` for factories that
+    /// parse their arguments with clap.
+    fn arguments(&self) -> Option<Vec<ArgumentMetadata>> {
+        None
+    }
 }
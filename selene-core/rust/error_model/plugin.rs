@@ -2,19 +2,32 @@ use super::{
     BatchResult, BoolResult, ErrorModelAPIVersion, ErrorModelInterface, ErrorModelInterfaceFactory,
     U64Result,
 };
+use crate::capabilities::Capabilities;
 use crate::runtime::BatchOperation;
-use crate::utils::{MetricValue, check_errno, read_raw_metric, with_strings_to_cargs};
+use crate::runtime::plugin::{
+    BatchBuilder, BatchExtractor, RuntimeExtractOperationInstance, RuntimeExtractOperationInterface,
+};
+use crate::utils::{
+    MetricValue, check_plugin_errno, has_entry_point, plugin_capabilities, plugin_error,
+    read_raw_metric, with_strings_to_cargs,
+};
 use anyhow::{Result, anyhow, bail};
 use libloading;
 use std::ffi::OsStr;
 use std::marker::PhantomData;
 use std::{ffi, sync::Arc};
 
+#[cfg(test)]
+mod tests;
+
 pub type ErrorModelInstance = *mut ffi::c_void;
 pub type Errno = i32;
 
 /// Controls an error model plugin according to the C interface provided in the selene-core wheel.
 ///
+/// Failures are reported as in [RuntimePluginInterface](crate::runtime::plugin::RuntimePluginInterface),
+/// with messages provided through the optional `selene_error_model_last_error_message` entry point.
+///
 /// This interface allows implementations of behaviour to be written and distributed independently
 /// of selene. Users should be cautious about the plugins they use, as it is possible that mistakes
 /// or malicious code could be present in the plugin, and as with all external libraries, due
@@ -23,6 +36,7 @@ pub type Errno = i32;
 pub struct ErrorModelPluginInterface {
     lib: libloading::Library,
     version: ErrorModelAPIVersion,
+    capabilities: Capabilities,
     #[borrows(lib)]
     #[covariant]
     init_fn: libloading::Symbol<
@@ -117,8 +131,32 @@ pub struct ErrorModelPluginInterface {
             ) -> Errno,
         >,
     >,
+
+    #[borrows(lib)]
+    #[covariant]
+    get_simulator_capabilities_fn: Option<
+        libloading::Symbol<
+            'this,
+            unsafe extern "C" fn(handle: ErrorModelInstance, out_bits: *mut u64) -> Errno,
+        >,
+    >,
+
+    #[borrows(lib)]
+    #[covariant]
+    last_error_message_fn:
+        Option<libloading::Symbol<'this, unsafe extern "C" fn() -> *const ffi::c_char>>,
 }
 impl ErrorModelPluginInterface {
+    /// Checks the errno returned by a call to the plugin, reporting the
+    /// plugin's own message for the error if it provides one.
+    fn check_errno(&self, errno: Errno, mk_err: impl FnOnce() -> anyhow::Error) -> Result<()> {
+        check_plugin_errno(
+            errno,
+            self.borrow_last_error_message_fn().as_deref(),
+            mk_err,
+        )
+    }
+
     pub fn new_from_file(plugin_file: impl AsRef<OsStr>) -> Result<Arc<Self>> {
         let lib = unsafe { libloading::Library::new(plugin_file.as_ref()) }.map_err(|e| {
             anyhow!(
@@ -140,10 +178,22 @@ impl ErrorModelPluginInterface {
             }
         };
         version.validate()?;
+        let inferred = Capabilities::DUMP_STATE.when(has_entry_point(
+            &lib,
+            b"selene_error_model_dump_simulator_state",
+        )) | Capabilities::METRICS
+            .when(has_entry_point(&lib, b"selene_error_model_get_metrics"));
+        let capabilities = plugin_capabilities(
+            &lib,
+            b"selene_error_model_get_capabilities",
+            inferred,
+            inferred,
+        );
 
         let result = ErrorModelPluginInterfaceTryBuilder {
             lib,
             version,
+            capabilities,
             init_fn_builder: |lib| unsafe { lib.get(b"selene_error_model_init") },
             exit_fn_builder: |lib| unsafe { Ok(lib.get(b"selene_error_model_exit").ok()) },
             shot_start_fn_builder: |lib| unsafe { lib.get(b"selene_error_model_shot_start") },
@@ -160,6 +210,14 @@ impl ErrorModelPluginInterface {
             get_simulator_metrics_fn_builder: |lib| unsafe {
                 Ok(lib.get(b"selene_error_model_get_simulator_metrics").ok())
             },
+            get_simulator_capabilities_fn_builder: |lib| unsafe {
+                Ok(lib
+                    .get(b"selene_error_model_get_simulator_capabilities")
+                    .ok())
+            },
+            last_error_message_fn_builder: |lib| unsafe {
+                Ok(lib.get(b"selene_error_model_last_error_message").ok())
+            },
         }
         .try_build()?;
         Ok(Arc::new(result))
@@ -169,6 +227,10 @@ impl ErrorModelPluginInterface {
 impl ErrorModelInterfaceFactory for ErrorModelPluginInterface {
     type Interface = ErrorModelPlugin;
 
+    fn capabilities(&self) -> Capabilities {
+        *self.borrow_capabilities()
+    }
+
     fn init(
         self: Arc<Self>,
         n_qubits: u64,
@@ -185,7 +247,7 @@ impl ErrorModelInterfaceFactory for ErrorModelPluginInterface {
                 with_strings_to_cargs(
                     simulator_args,
                     |simulator_argc, simulator_argv| -> Result<()> {
-                        check_errno(
+                        self.check_errno(
                             unsafe {
                                 self.borrow_init_fn()(
                                     &mut instance,
@@ -215,14 +277,64 @@ pub struct ErrorModelPlugin {
     instance: ErrorModelInstance,
 }
 
+impl ErrorModelPlugin {
+    fn call_handle_operations(
+        &mut self,
+        batch_instance: RuntimeExtractOperationInstance,
+        batch_interface: *const RuntimeExtractOperationInterface,
+    ) -> Result<BatchResult> {
+        let mut result_builder = BatchResultBuilder::default();
+        let (result_instance, result_interface) = result_builder.error_model_set_result();
+        self.interface.check_errno(
+            unsafe {
+                self.interface.borrow_handle_operations_fn()(
+                    self.instance,
+                    batch_instance,
+                    batch_interface,
+                    result_instance,
+                    &raw const result_interface,
+                )
+            },
+            || anyhow!("ErrorModelPlugin: handle_operations failed"),
+        )?;
+        Ok(result_builder.finish())
+    }
+
+    /// Handles operations for error models that predate the fused
+    /// MeasureReset operation, passing it as a measurement and a reset.
+    fn handle_operations_legacy(&mut self, operations: BatchOperation) -> Result<BatchResult> {
+        with_legacy_batch(operations, |batch_instance, batch_interface| {
+            self.call_handle_operations(batch_instance, batch_interface)
+        })
+    }
+}
+
+/// Provides a batch to `handle` in the form expected by error models that
+/// predate the fused MeasureReset operation: with each MeasureReset split into
+/// a measurement and a reset, and an extraction function that takes a
+/// [LegacyRuntimeGetOperationInterface](crate::runtime::plugin::LegacyRuntimeGetOperationInterface).
+fn with_legacy_batch<T>(
+    operations: BatchOperation,
+    handle: impl FnOnce(RuntimeExtractOperationInstance, *const RuntimeExtractOperationInterface) -> T,
+) -> T {
+    let mut batch_extractor =
+        BatchExtractor::from_batch_operation(operations.split_measure_reset());
+    let (batch_instance, batch_interface) = batch_extractor.legacy_runtime_batch_extraction();
+    // The legacy interface has the same layout as the current one, differing
+    // only in the signature of the extraction function.
+    let batch_interface = &raw const batch_interface as *const RuntimeExtractOperationInterface;
+    handle(batch_instance, batch_interface)
+}
+
 impl ErrorModelInterface for ErrorModelPlugin {
     fn exit(&mut self) -> Result<()> {
         let Some(exit_fn) = self.interface.borrow_exit_fn() else {
             return Ok(());
         };
-        check_errno(unsafe { exit_fn(self.instance) }, || {
-            anyhow!("ErrorModelPlugin: exit failed")
-        })
+        self.interface
+            .check_errno(unsafe { exit_fn(self.instance) }, || {
+                anyhow!("ErrorModelPlugin: exit failed")
+            })
     }
     fn shot_start(
         &mut self,
@@ -230,7 +342,7 @@ impl ErrorModelInterface for ErrorModelPlugin {
         error_model_seed: u64,
         simulator_seed: u64,
     ) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe {
                 self.interface.borrow_shot_start_fn()(
                     self.instance,
@@ -243,30 +355,19 @@ impl ErrorModelInterface for ErrorModelPlugin {
         )
     }
     fn shot_end(&mut self) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe { self.interface.borrow_shot_end_fn()(self.instance) },
             || anyhow!("ErrorModelPlugin: shot_end failed"),
         )
     }
     fn handle_operations(&mut self, operations: BatchOperation) -> Result<BatchResult> {
+        if !self.interface.borrow_version().has_measure_reset() {
+            return self.handle_operations_legacy(operations);
+        }
         let mut batch_extractor =
             crate::runtime::plugin::BatchExtractor::from_batch_operation(operations);
         let (batch_instance, batch_interface) = batch_extractor.runtime_batch_extraction();
-        let mut result_builder = BatchResultBuilder::default();
-        let (result_instance, result_interface) = result_builder.error_model_set_result();
-        check_errno(
-            unsafe {
-                self.interface.borrow_handle_operations_fn()(
-                    self.instance,
-                    batch_instance,
-                    &raw const batch_interface,
-                    result_instance,
-                    &raw const result_interface,
-                )
-            },
-            || anyhow!("ErrorModelPlugin: handle_operations failed"),
-        )?;
-        Ok(result_builder.finish())
+        self.call_handle_operations(batch_instance, &raw const batch_interface)
     }
     fn dump_simulator_state(&mut self, file: &std::path::Path, qubits: &[u64]) -> Result<()> {
         let Some(dump_fn) = self.interface.borrow_dump_simulator_state_fn() else {
@@ -276,7 +377,7 @@ impl ErrorModelInterface for ErrorModelPlugin {
             anyhow!("ErrorModelPlugin: dump_simulator_state failed, invalid filename")
         })?;
         let safe_filename = ffi::CString::new(filename).unwrap();
-        check_errno(
+        self.interface.check_errno(
             unsafe {
                 dump_fn(
                     self.instance,
@@ -305,6 +406,23 @@ impl ErrorModelInterface for ErrorModelPlugin {
             get_simulator_metrics_fn(self.instance, nth_metric, tag, data_type, data)
         })
     }
+    fn simulator_capabilities(&mut self) -> Result<Option<Capabilities>> {
+        let Some(get_simulator_capabilities_fn) =
+            self.interface.borrow_get_simulator_capabilities_fn()
+        else {
+            return Ok(None);
+        };
+        let mut bits = 0;
+        match unsafe { get_simulator_capabilities_fn(self.instance, &mut bits) } {
+            0 => Ok(Some(Capabilities::from_bits_truncate(bits))),
+            1 => Ok(None),
+            errno => Err(plugin_error(
+                errno,
+                self.interface.borrow_last_error_message_fn().as_deref(),
+                || anyhow!("ErrorModelPlugin: get_simulator_capabilities failed"),
+            )),
+        }
+    }
 }
 
 #[derive(Default)]
@@ -333,6 +451,23 @@ impl BatchResultBuilder {
             .u64_results
             .push(U64Result { result_id, value })
     }
+    unsafe extern "C" fn report_operations(
+        interface: ErrorModelSetResultInstance,
+        position: u64,
+        extract_ops_instance: RuntimeExtractOperationInstance,
+        extract_ops_interface: *const RuntimeExtractOperationInterface,
+    ) {
+        let result = interface as *mut BatchResult;
+        let mut batch_builder = BatchBuilder::default();
+        let (builder_instance, builder_interface) = batch_builder.runtime_get_operation();
+        let RuntimeExtractOperationInterface { extract_fn, .. } =
+            unsafe { &*extract_ops_interface };
+        unsafe { extract_fn(extract_ops_instance, builder_instance, builder_interface) };
+        let result = unsafe { &mut *result };
+        for operation in batch_builder.finish() {
+            result.report_operation(position, operation);
+        }
+    }
 
     /// The plugin calls this to obtain an instance and an interface.
     /// The lifetime parameter of the interface ensures that it cannot outlive the `Vec`
@@ -347,6 +482,7 @@ impl BatchResultBuilder {
         let interface = ErrorModelSetResultInterface {
             set_bool_result_fn: Self::set_bool_result,
             set_u64_result_fn: Self::set_u64_result,
+            report_operations_fn: Self::report_operations,
             _marker: PhantomData,
         };
         (instance, interface)
@@ -370,8 +506,22 @@ pub type ErrorModelSetResultInstance = *mut ffi::c_void;
 /// [ErrorModelSetResultInstance]. It should call the functions
 /// within to populate a batch. All such calls must pass the instance as the
 /// first parameter.
+///
+/// `report_operations_fn` optionally reports operations the error model
+/// applied in addition to those of the batch, such as Pauli errors, so that
+/// event hooks can observe them. It is given the number of the batch's
+/// operations applied before them, and the operations as a batch to be
+/// extracted, as batches are passed to `selene_error_model_handle_operations`,
+/// whose timing is ignored. It may be called any number of times, in the order
+/// in which the operations were applied.
 pub struct ErrorModelSetResultInterface<'a> {
     pub set_bool_result_fn: unsafe extern "C" fn(ErrorModelSetResultInstance, u64, bool),
     pub set_u64_result_fn: unsafe extern "C" fn(ErrorModelSetResultInstance, u64, u64),
+    pub report_operations_fn: unsafe extern "C" fn(
+        ErrorModelSetResultInstance,
+        u64,
+        RuntimeExtractOperationInstance,
+        *const RuntimeExtractOperationInterface,
+    ),
     _marker: PhantomData<&'a ()>,
 }
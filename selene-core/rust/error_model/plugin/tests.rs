@@ -0,0 +1,203 @@
+use super::*;
+use crate::runtime::Operation;
+use crate::runtime::plugin::{
+    LegacyRuntimeExtractOperationInterface, LegacyRuntimeGetOperationInterface,
+    RuntimeGetOperationInstance,
+};
+
+type Log = Vec<String>;
+
+unsafe fn log<'a>(instance: RuntimeGetOperationInstance) -> &'a mut Log {
+    unsafe { &mut *(instance as *mut Log) }
+}
+
+unsafe extern "C" fn rzz(instance: RuntimeGetOperationInstance, q1: u64, q2: u64, theta: f64) {
+    unsafe { log(instance) }.push(format!("rzz {q1} {q2} {theta}"));
+}
+unsafe extern "C" fn rxy(instance: RuntimeGetOperationInstance, q: u64, theta: f64, phi: f64) {
+    unsafe { log(instance) }.push(format!("rxy {q} {theta} {phi}"));
+}
+unsafe extern "C" fn rz(instance: RuntimeGetOperationInstance, q: u64, theta: f64) {
+    unsafe { log(instance) }.push(format!("rz {q} {theta}"));
+}
+unsafe extern "C" fn measure(instance: RuntimeGetOperationInstance, q: u64, r: u64) {
+    unsafe { log(instance) }.push(format!("measure {q} {r}"));
+}
+unsafe extern "C" fn measure_leaked(instance: RuntimeGetOperationInstance, q: u64, r: u64) {
+    unsafe { log(instance) }.push(format!("measure_leaked {q} {r}"));
+}
+unsafe extern "C" fn reset(instance: RuntimeGetOperationInstance, q: u64) {
+    unsafe { log(instance) }.push(format!("reset {q}"));
+}
+unsafe extern "C" fn custom(
+    instance: RuntimeGetOperationInstance,
+    tag: usize,
+    data: *const ffi::c_void,
+    length: usize,
+) {
+    let data = unsafe { std::slice::from_raw_parts(data as *const u8, length) };
+    unsafe { log(instance) }.push(format!("custom {tag} {data:?}"));
+}
+unsafe extern "C" fn set_batch_time(
+    instance: RuntimeGetOperationInstance,
+    start: u64,
+    duration: u64,
+) {
+    unsafe { log(instance) }.push(format!("batch {start} {duration}"));
+}
+
+/// Extracts a batch as an error model built against an API version before
+/// measure_reset_fn was added would: reading the extraction function from
+/// the interface it was given, and passing it the get-operation interface of
+/// that version.
+unsafe fn extract_as_legacy_plugin(
+    batch_instance: RuntimeExtractOperationInstance,
+    batch_interface: *const RuntimeExtractOperationInterface,
+) -> Log {
+    let batch_interface = batch_interface as *const LegacyRuntimeExtractOperationInterface;
+    let LegacyRuntimeExtractOperationInterface { extract_fn, .. } = unsafe { &*batch_interface };
+    let interface = LegacyRuntimeGetOperationInterface {
+        rzz_fn: rzz,
+        rxy_fn: rxy,
+        rz_fn: rz,
+        measure_fn: measure,
+        measure_leaked_fn: measure_leaked,
+        reset_fn: reset,
+        custom_fn: custom,
+        set_batch_time_fn: set_batch_time,
+    };
+    let mut log = Log::new();
+    unsafe {
+        extract_fn(
+            batch_instance,
+            &raw mut log as RuntimeGetOperationInstance,
+            interface,
+        )
+    };
+    log
+}
+
+fn batch(ops: Vec<Operation>) -> BatchOperation {
+    BatchOperation::new(ops, 10.into(), 5.into())
+}
+
+#[test]
+fn split_measure_reset() {
+    let split = batch(vec![
+        Operation::RZGate {
+            qubit_id: 0,
+            theta: 0.5,
+        },
+        Operation::MeasureReset {
+            qubit_id: 1,
+            result_id: 7,
+        },
+        Operation::Reset { qubit_id: 2 },
+    ])
+    .split_measure_reset();
+    assert_eq!(split.start(), 10.into());
+    assert_eq!(split.duration(), 5.into());
+    assert_eq!(
+        split.iter_ops().cloned().collect::<Vec<_>>(),
+        vec![
+            Operation::RZGate {
+                qubit_id: 0,
+                theta: 0.5,
+            },
+            Operation::Measure {
+                qubit_id: 1,
+                result_id: 7,
+            },
+            Operation::Reset { qubit_id: 1 },
+            Operation::Reset { qubit_id: 2 },
+        ]
+    );
+}
+
+#[test]
+fn legacy_plugins_receive_split_operations() {
+    let operations = batch(vec![
+        Operation::RXYGate {
+            qubit_id: 0,
+            theta: 0.25,
+            phi: -0.5,
+        },
+        Operation::RZZGate {
+            qubit_id_1: 0,
+            qubit_id_2: 1,
+            theta: 1.5,
+        },
+        Operation::MeasureReset {
+            qubit_id: 0,
+            result_id: 3,
+        },
+        Operation::MeasureLeaked {
+            qubit_id: 1,
+            result_id: 4,
+        },
+        Operation::Custom {
+            custom_tag: 9,
+            data: Box::new([1, 2, 3]),
+        },
+        Operation::RZGate {
+            qubit_id: 1,
+            theta: 2.0,
+        },
+    ]);
+    let log = with_legacy_batch(operations, |batch_instance, batch_interface| unsafe {
+        extract_as_legacy_plugin(batch_instance, batch_interface)
+    });
+    assert_eq!(
+        log,
+        vec![
+            "batch 10 5",
+            "rxy 0 0.25 -0.5",
+            "rzz 0 1 1.5",
+            "measure 0 3",
+            "reset 0",
+            "measure_leaked 1 4",
+            "custom 9 [1, 2, 3]",
+            "rz 1 2",
+        ]
+    );
+}
+
+#[test]
+fn legacy_plugins_receive_empty_batches() {
+    let log = with_legacy_batch(batch(vec![]), |batch_instance, batch_interface| unsafe {
+        extract_as_legacy_plugin(batch_instance, batch_interface)
+    });
+    assert_eq!(log, vec!["batch 10 5"]);
+}
+
+#[test]
+fn reported_operations_keep_their_positions() {
+    let rz = |qubit_id| Operation::RZGate {
+        qubit_id,
+        theta: 1.0,
+    };
+    let mut builder = BatchResultBuilder::default();
+    let (result_instance, result_interface) = builder.error_model_set_result();
+    // As the plugin tooling reports them, operations injected at the same
+    // position together.
+    for (position, operations) in [(0, vec![rz(0), rz(1)]), (2, vec![rz(2)])] {
+        let batch = BatchOperation::new(operations, Default::default(), Default::default());
+        let mut batch_extractor = BatchExtractor::from_batch_operation(batch);
+        let (batch_instance, batch_interface) = batch_extractor.runtime_batch_extraction();
+        unsafe {
+            (result_interface.report_operations_fn)(
+                result_instance,
+                position,
+                batch_instance,
+                &raw const batch_interface,
+            )
+        };
+    }
+    let injected: Vec<(u64, Operation)> = builder
+        .finish()
+        .injected_operations
+        .into_iter()
+        .map(|injected| (injected.position, injected.operation))
+        .collect();
+    assert_eq!(injected, vec![(0, rz(0)), (0, rz(1)), (2, rz(2))]);
+}
@@ -23,6 +23,11 @@ impl From<u64> for ErrorModelAPIVersion {
         }
     }
 }
+impl std::fmt::Display for ErrorModelAPIVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
 impl From<ErrorModelAPIVersion> for u64 {
     fn from(value: ErrorModelAPIVersion) -> u64 {
         ((value.reserved as u64) << 24)
@@ -35,7 +40,7 @@ impl From<ErrorModelAPIVersion> for u64 {
 pub const CURRENT_API_VERSION: ErrorModelAPIVersion = ErrorModelAPIVersion {
     reserved: 0,
     major: 0,
-    minor: 2,
+    minor: 4,
     patch: 0,
 };
 
@@ -43,15 +48,29 @@ pub const CURRENT_API_VERSION: ErrorModelAPIVersion = ErrorModelAPIVersion {
 // 0.1.0: Initial version.
 // 0.2.0: Replaced set_measurement_result with set_bool_result and set_u64_result in
 //   ErrorModelSetResultInterface
+// 0.3.0: RuntimeGetOperationInterface, passed to the batch extraction function, gained
+//   measure_reset_fn for the fused MeasureReset operation
+// 0.3.1: Introduced the optional get_capabilities entry point
+// 0.4.0: ErrorModelSetResultInterface gained report_operations_fn, for reporting operations
+//   injected by the error model to event hooks
+
+/// The oldest minor version of the current major version that selene can still load.
+/// Error models from 0.2 pass a RuntimeGetOperationInterface without measure_reset_fn
+/// to the batch extraction function, so are given batches with MeasureReset split into
+/// its measurement and reset (see [ErrorModelAPIVersion::has_measure_reset]).
+pub const MIN_SUPPORTED_MINOR: u8 = 2;
 
 impl ErrorModelAPIVersion {
+    pub fn minor(&self) -> u8 {
+        self.minor
+    }
+
+    /// Whether the plugin's batch extraction interface includes measure_reset_fn.
+    pub fn has_measure_reset(&self) -> bool {
+        self.minor >= 3
+    }
+
     pub fn validate(&self) -> Result<()> {
-        // Note: this is a naive check at the moment, as we have not introduced a breaking
-        // change since versioning was introduced. This logic should evolve as and when
-        // changes occur.
-        //
-        // As such, this is mostly a sketch of what the logic may look like.
-        //
         // Reserved must be 0. We may want to attribute meaning to this one day.
         if self.reserved != 0 {
             return Err(anyhow!(
@@ -67,13 +86,21 @@ impl ErrorModelAPIVersion {
                 self.major
             ));
         }
-        // If the minor version is different, the plugin might be incompatible. This function
-        // should be updated to reflect the actual changes in the API. For now we reject.
-        if self.minor != CURRENT_API_VERSION.minor {
+        // Plugins built for a newer minor version may rely on behaviour that selene does
+        // not provide. Plugins built for an older minor version are supported back to
+        // MIN_SUPPORTED_MINOR, with selene adapting to the differences in the API.
+        if self.minor > CURRENT_API_VERSION.minor {
+            return Err(anyhow!(
+                "Error model API minor version {} is newer than Selene's Error model API minor version ({}). Upgrade selene to use this plugin.",
+                self.minor,
+                CURRENT_API_VERSION.minor
+            ));
+        }
+        if self.minor < MIN_SUPPORTED_MINOR {
             return Err(anyhow!(
-                "API minor version must be the same as Selene's API minor version ({}), got {}",
-                CURRENT_API_VERSION.minor,
-                self.minor
+                "Error model API minor version {} is no longer supported. Selene supports error model plugins from minor version {}.",
+                self.minor,
+                MIN_SUPPORTED_MINOR
             ));
         }
         // The patch version might be bumped for changes that are optional and additive.
@@ -0,0 +1,117 @@
+//! Event hooks observe the operations of a run, for profiling, validation or
+//! custom logging, without taking part in it.
+//!
+//! Event hook plugins are loaded from shared libraries exporting the C
+//! interface described by [plugin::EventHookPluginInterface], which Rust
+//! crates can export with [crate::export_event_hook_plugin!]. Any number of
+//! them can be loaded alongside the runtime, error model and simulator.
+pub mod helper;
+pub mod interface;
+pub mod plugin;
+pub mod version;
+
+use std::ffi::OsStr;
+use std::sync::Arc;
+
+pub use interface::{EventHookInterface, EventHookInterfaceFactory};
+pub use version::EventHookAPIVersion;
+
+use crate::capabilities::Capabilities;
+use crate::error::ErrorKind;
+use crate::runtime::{self, BatchOperation};
+use crate::utils::MetricValue;
+use anyhow::Result;
+use delegate::delegate;
+use serde::{Deserialize, Serialize};
+
+#[cfg(test)]
+mod tests;
+
+/// An operation issued by the user program.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Operation {
+    QFree(u64),
+    QAlloc(u64),
+    RXY(u64, f64, f64),
+    RZZ(u64, u64, f64),
+    RZ(u64, f64),
+    Reset(u64),
+    MeasureRequest(u64),
+    MeasureLeakedRequest(u64),
+    MeasureResetRequest(u64),
+    FutureRead(u64),
+    BatchStart(u64, u64),
+    GlobalBarrier(u64),
+    LocalBarrier(Vec<u64>, u64),
+    Custom(u64, Vec<u8>),
+    BeginCondition(u64, bool),
+    EndCondition,
+}
+
+/// The value of a result set by the error model, as passed to
+/// [EventHookInterface::on_result].
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ResultValue {
+    Bool(bool),
+    U64(u64),
+}
+
+/// An instance of an event hook plugin, ready to observe a run.
+///
+/// `EventHook`'s impl [EventHookInterface] delegates to a wrapped `dyn
+/// EventHookInterface`, usually an external plugin as defined by
+/// [plugin::EventHookPluginInterface].
+pub struct EventHook(Box<dyn EventHookInterface>, Capabilities);
+
+impl EventHook {
+    /// Constructs a new EventHook from an [EventHookInterfaceFactory].
+    pub fn new(
+        factory: Arc<impl EventHookInterfaceFactory + 'static>,
+        n_qubits: u64,
+        args: &[impl AsRef<str>],
+    ) -> Result<Self> {
+        let capabilities = factory.capabilities();
+        Ok(Self(factory.init(n_qubits, args)?, capabilities))
+    }
+
+    /// Loads the event hook plugin at the given path, or the registered event
+    /// hook it refers to (see [crate::registry]). Event hooks cannot be hosted
+    /// out of process.
+    pub fn load_from_file(
+        plugin_path: &impl AsRef<OsStr>,
+        n_qubits: u64,
+        args: &[impl AsRef<str>],
+    ) -> Result<Self> {
+        if let Some(name) = crate::registry::builtin_name(plugin_path.as_ref()) {
+            return crate::registry::new_event_hook(name, n_qubits, args);
+        }
+        if crate::host::isolated_plugin(plugin_path.as_ref()).is_some() {
+            return Err(
+                ErrorKind::InvalidArgument.error("Event hooks cannot be hosted out of process")
+            );
+        }
+        let plugin = plugin::EventHookPluginInterface::new_from_file(plugin_path)?;
+        Self::new(plugin, n_qubits, args)
+    }
+
+    /// The optional features supported by the event hook.
+    pub fn capabilities(&self) -> Capabilities {
+        self.1
+    }
+}
+
+impl EventHookInterface for EventHook {
+    delegate! {
+        to self.0 {
+            fn exit(&mut self) -> Result<()>;
+            fn shot_start(&mut self, shot_id: u64, seed: u64) -> Result<()>;
+            fn shot_end(&mut self) -> Result<()>;
+            fn on_user_call(&mut self, operation: &Operation) -> Result<()>;
+            fn on_runtime_batch(&mut self, batch: &BatchOperation) -> Result<()>;
+            fn on_error_model_op(&mut self, position: u64, operation: &runtime::Operation) -> Result<()>;
+            fn on_result(&mut self, result_id: u64, value: ResultValue) -> Result<()>;
+            fn get_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>>;
+        }
+    }
+}
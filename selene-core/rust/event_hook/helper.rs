@@ -0,0 +1,402 @@
+//! Defines the [crate::export_event_hook_plugin!] and helpers for implementing
+//! event hook plugins as rust crates.
+use super::{
+    EventHookInterface, ResultValue,
+    interface::EventHookInterfaceFactory,
+    plugin::{Errno, EventHookInstance, EventHookOperation},
+};
+use crate::runtime::plugin::{
+    BatchBuilder, RuntimeExtractOperationInstance, RuntimeExtractOperationInterface,
+};
+use crate::utils::{convert_cargs_to_strings, result_of_errno_to_errno, result_to_errno};
+use std::{ffi, mem, sync::Arc};
+
+#[derive(Default)]
+/// A helper struct used by [crate::export_event_hook_plugin!] to implement the event hook
+/// plugin entry points defined by [super::plugin::EventHookPluginInterface] using an
+/// implementation of [EventHookInterfaceFactory].
+pub struct Helper<F>(Arc<F>);
+
+impl<F: EventHookInterfaceFactory> Helper<F> {
+    fn into_event_hook_instance(h: Box<F::Interface>) -> EventHookInstance {
+        Box::into_raw(h) as EventHookInstance
+    }
+
+    unsafe fn from_event_hook_instance(instance: EventHookInstance) -> Box<F::Interface> {
+        assert!(!instance.is_null());
+        unsafe { Box::from_raw(instance as *mut F::Interface) }
+    }
+
+    fn with_event_hook_instance<T>(
+        instance: EventHookInstance,
+        mut go: impl FnMut(&mut F::Interface) -> T,
+    ) -> T {
+        let mut h = unsafe { Self::from_event_hook_instance(instance) };
+        let t = go(&mut h);
+        mem::forget(h);
+        t
+    }
+
+    fn factory(&self) -> Arc<F> {
+        self.0.clone()
+    }
+
+    pub unsafe fn init(
+        &self,
+        instance: *mut EventHookInstance,
+        n_qubits: u64,
+        argc: u32,
+        argv: *const *const ffi::c_char,
+    ) -> Errno {
+        if instance.is_null() {
+            eprintln!("cannot initialize event hook plugin: provided instance is null");
+            return -1;
+        }
+
+        let args: Vec<String> = {
+            let mut v = vec!["lib".to_string()];
+            v.extend(unsafe { convert_cargs_to_strings(argc, argv) });
+            v
+        };
+
+        result_to_errno(
+            "Failed to initialize the event hook plugin",
+            self.factory()
+                .init(n_qubits, args.as_ref())
+                .map(|event_hook| unsafe {
+                    *instance = Self::into_event_hook_instance(event_hook);
+                }),
+        )
+    }
+
+    pub unsafe fn exit(instance: EventHookInstance) -> Errno {
+        result_to_errno(
+            "Failed to exit the event hook plugin",
+            Self::with_event_hook_instance(instance, |h| h.exit()),
+        )
+    }
+
+    pub unsafe fn shot_start(instance: EventHookInstance, shot_id: u64, seed: u64) -> Errno {
+        result_to_errno(
+            format!("Failed to start shot {shot_id}"),
+            Self::with_event_hook_instance(instance, |h| h.shot_start(shot_id, seed)),
+        )
+    }
+
+    pub unsafe fn shot_end(instance: EventHookInstance) -> Errno {
+        result_to_errno(
+            "Failed to end the current shot",
+            Self::with_event_hook_instance(instance, |h| h.shot_end()),
+        )
+    }
+
+    pub unsafe fn on_user_call(
+        instance: EventHookInstance,
+        operation: *const EventHookOperation,
+    ) -> Errno {
+        result_to_errno(
+            "Failed to handle a user call",
+            Self::with_event_hook_instance(instance, |h| {
+                let operation = unsafe { (*operation).to_operation() }?;
+                h.on_user_call(&operation)
+            }),
+        )
+    }
+
+    pub unsafe fn on_runtime_batch(
+        instance: EventHookInstance,
+        extract_ops_instance: RuntimeExtractOperationInstance,
+        extract_ops_interface: *const RuntimeExtractOperationInterface,
+    ) -> Errno {
+        result_to_errno(
+            "Failed to handle a runtime batch",
+            Self::with_event_hook_instance(instance, |h| unsafe {
+                let mut batch_builder = BatchBuilder::default();
+                let (builder_instance, builder_interface) = batch_builder.runtime_get_operation();
+                let RuntimeExtractOperationInterface { extract_fn, .. } = &*extract_ops_interface;
+                extract_fn(extract_ops_instance, builder_instance, builder_interface);
+                h.on_runtime_batch(&batch_builder.finish())
+            }),
+        )
+    }
+
+    pub unsafe fn on_error_model_op(
+        instance: EventHookInstance,
+        position: u64,
+        extract_ops_instance: RuntimeExtractOperationInstance,
+        extract_ops_interface: *const RuntimeExtractOperationInterface,
+    ) -> Errno {
+        result_to_errno(
+            "Failed to handle an error model operation",
+            Self::with_event_hook_instance(instance, |h| unsafe {
+                let mut batch_builder = BatchBuilder::default();
+                let (builder_instance, builder_interface) = batch_builder.runtime_get_operation();
+                let RuntimeExtractOperationInterface { extract_fn, .. } = &*extract_ops_interface;
+                extract_fn(extract_ops_instance, builder_instance, builder_interface);
+                for operation in batch_builder.finish() {
+                    h.on_error_model_op(position, &operation)?;
+                }
+                anyhow::Ok(())
+            }),
+        )
+    }
+
+    pub unsafe fn on_result(
+        instance: EventHookInstance,
+        result_id: u64,
+        value: ResultValue,
+    ) -> Errno {
+        result_to_errno(
+            format!("Failed to handle result {result_id}"),
+            Self::with_event_hook_instance(instance, |h| h.on_result(result_id, value)),
+        )
+    }
+
+    pub unsafe fn get_metric(
+        instance: EventHookInstance,
+        nth_metric: u8,
+        tag_ptr: *mut ffi::c_char,
+        datatype_ptr: *mut u8,
+        data_ptr: *mut u64,
+    ) -> Errno {
+        result_of_errno_to_errno(
+            "Failed to get metric",
+            Self::with_event_hook_instance(instance, |h| {
+                let Some((tag, metric)) = h.get_metric(nth_metric)? else {
+                    return anyhow::Ok(1);
+                };
+                unsafe { metric.write_raw(tag, tag_ptr, datatype_ptr, data_ptr) }
+                Ok(0)
+            }),
+        )
+    }
+}
+
+#[macro_export]
+/// A macro to export an event hook plugin from a crate
+///
+/// Expands to a module named _plugin with `pub extern "C" unsafe` definitions
+/// of all entry points specified in the documentation of
+/// [super::plugin::EventHookPluginInterface].
+///
+/// Those functions are all implemented using the `$factory_type`'s implementation
+/// of [EventHookInterfaceFactory].
+///
+/// A crate can only export a single event hook plugin (although it may export
+/// other plugins of other types).
+///
+/// The plugin describes itself in its metadata under the name given after the
+/// factory type, which should be the name it is registered under when linked
+/// into selene (see [crate::registry]), or under its package name if no name
+/// is given.
+macro_rules! export_event_hook_plugin {
+    ($factory_type:ty) => {
+        $crate::export_event_hook_plugin!($factory_type, env!("CARGO_PKG_NAME"));
+    };
+    ($factory_type:ty, $name:expr) => {
+        mod _plugin {
+            use selene_core::{
+                event_hook::{
+                    EventHookInterfaceFactory, ResultValue,
+                    plugin::{Errno, EventHookInstance, EventHookOperation},
+                    version::CURRENT_API_VERSION,
+                },
+                runtime::plugin::{
+                    RuntimeExtractOperationInstance, RuntimeExtractOperationInterface,
+                },
+            };
+
+            use std::ffi::c_char;
+
+            /// cbindgen:ignore
+            type Helper = selene_core::event_hook::helper::Helper<$factory_type>;
+
+            // Enforce that $factory_type implements the EventHookInterfaceFactory trait
+            const _: fn() = || {
+                fn _assert_impl<T: EventHookInterfaceFactory>() {}
+                _assert_impl::<$factory_type>();
+            };
+
+            /// The API version comprises four unsigned 8-bit integers:
+            ///     - reserved: 8 bits (must be 0)
+            ///     - major: 8 bits
+            ///     - minor: 8 bits
+            ///     - patch: 8 bits
+            ///
+            /// Selene maintains its own API version for event hooks
+            /// and is updated upon changes to the API depending on how
+            /// breaking the changes are. Selene is also responsible for
+            /// validating the API version of the plugin against its own
+            /// version.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_event_hook_get_api_version() -> u64 {
+                CURRENT_API_VERSION.into()
+            }
+
+            /// Initialise an instance of the event hook with the maximum number of
+            /// qubits and the user-provided arguments, in an argc, argv format. The
+            /// `instance` pointer is set by this function to hold all of the state of
+            /// the instance, and is passed to each of the other functions.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_event_hook_init(
+                instance: *mut EventHookInstance,
+                n_qubits: u64,
+                argc: u32,
+                argv: *const *const c_char,
+            ) -> Errno {
+                use std::cell::OnceCell;
+                use std::sync::Mutex;
+                static FACTORY: Mutex<OnceCell<Helper>> = Mutex::new(OnceCell::new());
+                FACTORY
+                    .lock()
+                    .unwrap()
+                    .get_or_init(|| Helper::default())
+                    .init(instance, n_qubits, argc, argv)
+            }
+
+            /// This function is called when Selene is exiting, and it is responsible for
+            /// cleaning up any resources that the event hook has allocated, such as
+            /// flushing any logs it has written.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_event_hook_exit(instance: EventHookInstance) -> Errno {
+                Helper::exit(instance)
+            }
+
+            /// This function is called at the start of a shot. The seed is provided for
+            /// any randomness the event hook uses, such as sampling.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_event_hook_shot_start(
+                instance: EventHookInstance,
+                shot_id: u64,
+                seed: u64,
+            ) -> Errno {
+                Helper::shot_start(instance, shot_id, seed)
+            }
+
+            /// This function is called at the end of a shot, after the metrics of the
+            /// event hook have been requested.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_event_hook_shot_end(
+                instance: EventHookInstance,
+            ) -> Errno {
+                Helper::shot_end(instance)
+            }
+
+            /// This function is called with each operation issued by the user program,
+            /// once the runtime has accepted it. Returning an error fails the
+            /// operation.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_event_hook_on_user_call(
+                instance: EventHookInstance,
+                operation: *const EventHookOperation,
+            ) -> Errno {
+                Helper::on_user_call(instance, operation)
+            }
+
+            /// This function is called with each batch of operations emitted by the
+            /// runtime, before it is passed to the error model. The batch is extracted
+            /// as in `selene_error_model_handle_operations`.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_event_hook_on_runtime_batch(
+                instance: EventHookInstance,
+                extract_ops_instance: RuntimeExtractOperationInstance,
+                extract_ops_interface: *const RuntimeExtractOperationInterface,
+            ) -> Errno {
+                Helper::on_runtime_batch(instance, extract_ops_instance, extract_ops_interface)
+            }
+
+            /// This function is called with each operation the error model reports
+            /// having injected while handling a batch, such as a Pauli error, and
+            /// the number of the batch's operations applied before it. It is
+            /// extracted as a batch holding that operation alone.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_event_hook_on_error_model_op(
+                instance: EventHookInstance,
+                position: u64,
+                extract_ops_instance: RuntimeExtractOperationInstance,
+                extract_ops_interface: *const RuntimeExtractOperationInterface,
+            ) -> Errno {
+                Helper::on_error_model_op(
+                    instance,
+                    position,
+                    extract_ops_instance,
+                    extract_ops_interface,
+                )
+            }
+
+            /// This function is called with each boolean result set by the error
+            /// model, before it is passed to the runtime.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_event_hook_on_bool_result(
+                instance: EventHookInstance,
+                result_id: u64,
+                value: bool,
+            ) -> Errno {
+                Helper::on_result(instance, result_id, ResultValue::Bool(value))
+            }
+
+            /// This function is called with each u64 result set by the error model,
+            /// before it is passed to the runtime.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_event_hook_on_u64_result(
+                instance: EventHookInstance,
+                result_id: u64,
+                value: u64,
+            ) -> Errno {
+                Helper::on_result(instance, result_id, ResultValue::U64(value))
+            }
+
+            /// This function is called to get a metric from the event hook at the end of
+            /// each shot, as `selene_error_model_get_metrics` is for error models.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_event_hook_get_metrics(
+                instance: EventHookInstance,
+                nth_metric: u8,
+                tag_ptr: *mut c_char,
+                datatype_ptr: *mut u8,
+                data_ptr: *mut u64,
+            ) -> Errno {
+                Helper::get_metric(instance, nth_metric, tag_ptr, datatype_ptr, data_ptr)
+            }
+
+            /// Get the message describing the latest error returned by the event hook
+            /// in the calling thread, as a null terminated string, or null if there
+            /// has been no error. The string remains valid until the next error.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_event_hook_last_error_message() -> *const c_char {
+                selene_core::utils::last_error_message_ptr()
+            }
+
+            /// Get the optional features supported by the event hook, as the bits of a
+            /// `selene_core::capabilities::Capabilities` set.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_event_hook_get_capabilities() -> u64 {
+                <$factory_type as Default>::default().capabilities().bits()
+            }
+
+            /// Get a description of the event hook as a nul-terminated JSON string, in
+            /// the form of a `selene_core::metadata::PluginMetadata`. The string is
+            /// owned by the plugin and lives as long as it is loaded.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_event_hook_get_metadata() -> *const c_char {
+                static METADATA: std::sync::OnceLock<std::ffi::CString> =
+                    std::sync::OnceLock::new();
+                METADATA
+                    .get_or_init(|| {
+                        let factory = <$factory_type as Default>::default();
+                        selene_core::metadata::PluginMetadata::new(
+                            selene_core::metadata::PluginKind::EventHook,
+                            $name,
+                            env!("CARGO_PKG_NAME"),
+                            env!("CARGO_PKG_VERSION"),
+                            CURRENT_API_VERSION,
+                            factory.capabilities(),
+                            factory.arguments(),
+                        )
+                        .to_c_string()
+                    })
+                    .as_ptr()
+            }
+        }
+    };
+}
@@ -0,0 +1,91 @@
+use anyhow::Result;
+use std::sync::Arc;
+
+use super::{Operation, ResultValue};
+use crate::capabilities::Capabilities;
+use crate::metadata::ArgumentMetadata;
+use crate::runtime::{self, BatchOperation};
+use crate::utils::MetricValue;
+
+/// Instances of event hook plugins implement this interface.
+///
+/// Event hooks observe a run without taking part in it: they are told of each
+/// operation issued by the user program, of each batch emitted by the runtime
+/// and of what the error model made of it, and may report metrics at the end
+/// of each shot. Every function has
+/// a default that does nothing, so hooks only implement the events they need.
+///
+/// An error returned from any function fails the operation that triggered it,
+/// which lets hooks act as validators.
+///
+/// `cdylib` crates can export the event hook plugin C interface via
+/// [crate::export_event_hook_plugin!]
+pub trait EventHookInterface {
+    /// Signals that the instance of the event hook should cleanup, e.g. by
+    /// flushing any logs it has written.
+    fn exit(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// Called at the start of each shot, with a seed derived from the shot id
+    /// for any randomness the hook uses.
+    fn shot_start(&mut self, _shot_id: u64, _seed: u64) -> Result<()> {
+        Ok(())
+    }
+    /// Called at the end of each shot, after its metrics have been requested.
+    fn shot_end(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// Called with each operation issued by the user program, once the
+    /// runtime has accepted it.
+    fn on_user_call(&mut self, _operation: &Operation) -> Result<()> {
+        Ok(())
+    }
+    /// Called with each batch of operations emitted by the runtime, before it
+    /// is passed to the error model.
+    fn on_runtime_batch(&mut self, _batch: &BatchOperation) -> Result<()> {
+        Ok(())
+    }
+    /// Called with each operation the error model reports having injected
+    /// while handling a batch, such as a Pauli error, in the order it applied
+    /// them. The position is the number of the batch's operations applied
+    /// before it. Error models that do not report their operations never
+    /// trigger this.
+    fn on_error_model_op(&mut self, _position: u64, _operation: &runtime::Operation) -> Result<()> {
+        Ok(())
+    }
+    /// Called with each result the error model sets while handling a batch,
+    /// before it is passed to the runtime.
+    fn on_result(&mut self, _result_id: u64, _value: ResultValue) -> Result<()> {
+        Ok(())
+    }
+    /// Request metrics from the event hook, as for
+    /// [ErrorModelInterface::get_metric](crate::error_model::ErrorModelInterface::get_metric).
+    /// Metrics are requested at the end of each shot.
+    fn get_metric(&mut self, _nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        Ok(None)
+    }
+}
+
+pub trait EventHookInterfaceFactory {
+    type Interface: EventHookInterface;
+
+    fn init(
+        self: Arc<Self>,
+        n_qubits: u64,
+        args: &[impl AsRef<str>],
+    ) -> Result<Box<Self::Interface>>;
+
+    /// The optional features supported by the interfaces this factory
+    /// creates. See [Capabilities].
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::empty()
+    }
+
+    /// The arguments accepted by [Self::init], reported in the plugin's
+    /// metadata so that they can be checked before a run. `None` if they are
+    /// not described. See `ArgumentMetadata::from_clap` for factories that
+    /// parse their arguments with clap.
+    fn arguments(&self) -> Option<Vec<ArgumentMetadata>> {
+        None
+    }
+}
@@ -0,0 +1,544 @@
+use super::{
+    EventHookAPIVersion, EventHookInterface, EventHookInterfaceFactory, Operation, ResultValue,
+};
+use crate::capabilities::Capabilities;
+use crate::runtime::plugin::{
+    BatchExtractor, RuntimeExtractOperationInstance, RuntimeExtractOperationInterface,
+};
+use crate::runtime::{self, BatchOperation};
+use crate::utils::{
+    MetricValue, check_plugin_errno, has_entry_point, plugin_capabilities, read_raw_metric,
+    with_strings_to_cargs,
+};
+use anyhow::{Result, anyhow, bail};
+use libloading;
+use std::ffi::OsStr;
+use std::{ffi, sync::Arc};
+
+pub type EventHookInstance = *mut ffi::c_void;
+pub type Errno = i32;
+
+/// Controls an event hook plugin according to the C interface provided in the
+/// selene-core wheel. The plugin must export:
+///
+/// - `uint64_t selene_event_hook_get_api_version()`
+/// - `int selene_event_hook_init(void** handle_out, uint64_t n_qubits, uint32_t argc,
+///   const char** argv)`
+/// - `int selene_event_hook_shot_start(void* handle, uint64_t shot_id, uint64_t seed)`
+/// - `int selene_event_hook_shot_end(void* handle)`
+///
+/// and may export:
+///
+/// - `int selene_event_hook_exit(void* handle)`
+/// - `int selene_event_hook_on_user_call(void* handle, const SeleneEventHookOperation* op)`,
+///   called with each operation issued by the user program.
+/// - `int selene_event_hook_on_runtime_batch(void* handle, ...)`, called with each batch
+///   emitted by the runtime, which is extracted as in `selene_error_model_handle_operations`.
+/// - `int selene_event_hook_on_error_model_op(void* handle, uint64_t position, ...)`, called
+///   with each operation reported by the error model and the number of the batch's operations
+///   applied before it, extracted as a batch holding that operation alone.
+/// - `int selene_event_hook_on_bool_result(void* handle, uint64_t result_id, bool value)` and
+///   `int selene_event_hook_on_u64_result(void* handle, uint64_t result_id, uint64_t value)`,
+///   called with each result set by the error model.
+/// - `int selene_event_hook_get_metrics(...)`, as for error models.
+///
+/// along with the `last_error_message`, `get_capabilities` and `get_metadata`
+/// entry points common to all plugins. Events with no entry point are not
+/// passed to the plugin. Each function returns 0 on success, and any other
+/// value fails the operation that triggered it.
+#[ouroboros::self_referencing]
+pub struct EventHookPluginInterface {
+    lib: libloading::Library,
+    version: EventHookAPIVersion,
+    capabilities: Capabilities,
+    #[borrows(lib)]
+    #[covariant]
+    init_fn: libloading::Symbol<
+        'this,
+        unsafe extern "C" fn(
+            handle: *mut EventHookInstance,
+            n_qubits: u64,
+            argc: u32,
+            argv: *const *const ffi::c_char,
+        ) -> Errno,
+    >,
+
+    #[borrows(lib)]
+    #[covariant]
+    exit_fn:
+        Option<libloading::Symbol<'this, unsafe extern "C" fn(handle: EventHookInstance) -> Errno>>,
+
+    #[borrows(lib)]
+    #[covariant]
+    shot_start_fn: libloading::Symbol<
+        'this,
+        unsafe extern "C" fn(handle: EventHookInstance, shot_id: u64, seed: u64) -> Errno,
+    >,
+
+    #[borrows(lib)]
+    #[covariant]
+    shot_end_fn:
+        libloading::Symbol<'this, unsafe extern "C" fn(handle: EventHookInstance) -> Errno>,
+
+    #[borrows(lib)]
+    #[covariant]
+    on_user_call_fn: Option<
+        libloading::Symbol<
+            'this,
+            unsafe extern "C" fn(
+                handle: EventHookInstance,
+                operation: *const EventHookOperation,
+            ) -> Errno,
+        >,
+    >,
+
+    #[borrows(lib)]
+    #[covariant]
+    on_runtime_batch_fn: Option<
+        libloading::Symbol<
+            'this,
+            unsafe extern "C" fn(
+                handle: EventHookInstance,
+                batch_instance: RuntimeExtractOperationInstance,
+                batch_interface: *const RuntimeExtractOperationInterface,
+            ) -> Errno,
+        >,
+    >,
+
+    #[borrows(lib)]
+    #[covariant]
+    on_error_model_op_fn: Option<
+        libloading::Symbol<
+            'this,
+            unsafe extern "C" fn(
+                handle: EventHookInstance,
+                position: u64,
+                batch_instance: RuntimeExtractOperationInstance,
+                batch_interface: *const RuntimeExtractOperationInterface,
+            ) -> Errno,
+        >,
+    >,
+
+    #[borrows(lib)]
+    #[covariant]
+    on_bool_result_fn: Option<
+        libloading::Symbol<
+            'this,
+            unsafe extern "C" fn(handle: EventHookInstance, result_id: u64, value: bool) -> Errno,
+        >,
+    >,
+
+    #[borrows(lib)]
+    #[covariant]
+    on_u64_result_fn: Option<
+        libloading::Symbol<
+            'this,
+            unsafe extern "C" fn(handle: EventHookInstance, result_id: u64, value: u64) -> Errno,
+        >,
+    >,
+
+    #[borrows(lib)]
+    #[covariant]
+    get_metrics_fn: Option<
+        libloading::Symbol<
+            'this,
+            unsafe extern "C" fn(
+                handle: EventHookInstance,
+                nth_metric: u8,
+                out_tag_str: *mut ffi::c_char,
+                out_datatype: *mut u8,
+                out_data: *mut u64,
+            ) -> Errno,
+        >,
+    >,
+
+    #[borrows(lib)]
+    #[covariant]
+    last_error_message_fn:
+        Option<libloading::Symbol<'this, unsafe extern "C" fn() -> *const ffi::c_char>>,
+}
+
+impl EventHookPluginInterface {
+    /// Checks the errno returned by a call to the plugin, reporting the
+    /// plugin's own message for the error if it provides one.
+    fn check_errno(&self, errno: Errno, mk_err: impl FnOnce() -> anyhow::Error) -> Result<()> {
+        check_plugin_errno(
+            errno,
+            self.borrow_last_error_message_fn().as_deref(),
+            mk_err,
+        )
+    }
+
+    pub fn new_from_file(plugin_file: impl AsRef<OsStr>) -> Result<Arc<Self>> {
+        let lib = unsafe { libloading::Library::new(plugin_file.as_ref()) }.map_err(|e| {
+            anyhow!(
+                "Failed to load event hook plugin: {}. Error: {}",
+                plugin_file.as_ref().to_string_lossy(),
+                e
+            )
+        })?;
+        let version: EventHookAPIVersion = unsafe {
+            if let Ok(func) =
+                lib.get::<unsafe extern "C" fn() -> u64>(b"selene_event_hook_get_api_version")
+            {
+                func().into()
+            } else {
+                bail!(
+                    "Failed to load version from event hook at '{}'. The plugin is not compatible with this version of selene.",
+                    plugin_file.as_ref().to_string_lossy(),
+                );
+            }
+        };
+        version.validate()?;
+        let inferred =
+            Capabilities::METRICS.when(has_entry_point(&lib, b"selene_event_hook_get_metrics"));
+        let capabilities = plugin_capabilities(
+            &lib,
+            b"selene_event_hook_get_capabilities",
+            inferred,
+            inferred,
+        );
+
+        let result = EventHookPluginInterfaceTryBuilder {
+            lib,
+            version,
+            capabilities,
+            init_fn_builder: |lib| unsafe { lib.get(b"selene_event_hook_init") },
+            exit_fn_builder: |lib| unsafe { Ok(lib.get(b"selene_event_hook_exit").ok()) },
+            shot_start_fn_builder: |lib| unsafe { lib.get(b"selene_event_hook_shot_start") },
+            shot_end_fn_builder: |lib| unsafe { lib.get(b"selene_event_hook_shot_end") },
+            on_user_call_fn_builder: |lib| unsafe {
+                Ok(lib.get(b"selene_event_hook_on_user_call").ok())
+            },
+            on_runtime_batch_fn_builder: |lib| unsafe {
+                Ok(lib.get(b"selene_event_hook_on_runtime_batch").ok())
+            },
+            on_error_model_op_fn_builder: |lib| unsafe {
+                Ok(lib.get(b"selene_event_hook_on_error_model_op").ok())
+            },
+            on_bool_result_fn_builder: |lib| unsafe {
+                Ok(lib.get(b"selene_event_hook_on_bool_result").ok())
+            },
+            on_u64_result_fn_builder: |lib| unsafe {
+                Ok(lib.get(b"selene_event_hook_on_u64_result").ok())
+            },
+            get_metrics_fn_builder: |lib| unsafe {
+                Ok(lib.get(b"selene_event_hook_get_metrics").ok())
+            },
+            last_error_message_fn_builder: |lib| unsafe {
+                Ok(lib.get(b"selene_event_hook_last_error_message").ok())
+            },
+        }
+        .try_build()?;
+        Ok(Arc::new(result))
+    }
+}
+
+impl EventHookInterfaceFactory for EventHookPluginInterface {
+    type Interface = EventHookPlugin;
+
+    fn capabilities(&self) -> Capabilities {
+        *self.borrow_capabilities()
+    }
+
+    fn init(
+        self: Arc<Self>,
+        n_qubits: u64,
+        args: &[impl AsRef<str>],
+    ) -> Result<Box<Self::Interface>> {
+        let mut instance = std::ptr::null_mut();
+        with_strings_to_cargs(args, |argc, argv| {
+            self.check_errno(
+                unsafe { self.borrow_init_fn()(&mut instance, n_qubits, argc, argv) },
+                || anyhow!("EventHookPluginInterface: init failed"),
+            )
+        })?;
+        Ok(Box::new(EventHookPlugin {
+            interface: self.clone(),
+            instance,
+        }))
+    }
+}
+
+pub struct EventHookPlugin {
+    interface: Arc<EventHookPluginInterface>,
+    instance: EventHookInstance,
+}
+
+impl EventHookInterface for EventHookPlugin {
+    fn exit(&mut self) -> Result<()> {
+        let Some(exit_fn) = self.interface.borrow_exit_fn() else {
+            return Ok(());
+        };
+        self.interface
+            .check_errno(unsafe { exit_fn(self.instance) }, || {
+                anyhow!("EventHookPlugin: exit failed")
+            })
+    }
+    fn shot_start(&mut self, shot_id: u64, seed: u64) -> Result<()> {
+        self.interface.check_errno(
+            unsafe { self.interface.borrow_shot_start_fn()(self.instance, shot_id, seed) },
+            || anyhow!("EventHookPlugin: shot_start failed"),
+        )
+    }
+    fn shot_end(&mut self) -> Result<()> {
+        self.interface.check_errno(
+            unsafe { self.interface.borrow_shot_end_fn()(self.instance) },
+            || anyhow!("EventHookPlugin: shot_end failed"),
+        )
+    }
+    fn on_user_call(&mut self, operation: &Operation) -> Result<()> {
+        let Some(on_user_call_fn) = self.interface.borrow_on_user_call_fn() else {
+            return Ok(());
+        };
+        let errno = EventHookOperation::with_operation(operation, |raw| unsafe {
+            on_user_call_fn(self.instance, raw)
+        });
+        self.interface
+            .check_errno(errno, || anyhow!("EventHookPlugin: on_user_call failed"))
+    }
+    fn on_runtime_batch(&mut self, batch: &BatchOperation) -> Result<()> {
+        let Some(on_runtime_batch_fn) = self.interface.borrow_on_runtime_batch_fn() else {
+            return Ok(());
+        };
+        let mut batch_extractor = BatchExtractor::from_batch_operation(batch.clone());
+        let (batch_instance, batch_interface) = batch_extractor.runtime_batch_extraction();
+        self.interface.check_errno(
+            unsafe {
+                on_runtime_batch_fn(self.instance, batch_instance, &raw const batch_interface)
+            },
+            || anyhow!("EventHookPlugin: on_runtime_batch failed"),
+        )
+    }
+    fn on_error_model_op(&mut self, position: u64, operation: &runtime::Operation) -> Result<()> {
+        let Some(on_error_model_op_fn) = self.interface.borrow_on_error_model_op_fn() else {
+            return Ok(());
+        };
+        let batch = BatchOperation::new(vec![operation.clone()], 0.into(), 0.into());
+        let mut batch_extractor = BatchExtractor::from_batch_operation(batch);
+        let (batch_instance, batch_interface) = batch_extractor.runtime_batch_extraction();
+        self.interface.check_errno(
+            unsafe {
+                on_error_model_op_fn(
+                    self.instance,
+                    position,
+                    batch_instance,
+                    &raw const batch_interface,
+                )
+            },
+            || anyhow!("EventHookPlugin: on_error_model_op failed"),
+        )
+    }
+    fn on_result(&mut self, result_id: u64, value: ResultValue) -> Result<()> {
+        let errno = match value {
+            ResultValue::Bool(value) => match self.interface.borrow_on_bool_result_fn() {
+                Some(on_bool_result_fn) => unsafe {
+                    on_bool_result_fn(self.instance, result_id, value)
+                },
+                None => return Ok(()),
+            },
+            ResultValue::U64(value) => match self.interface.borrow_on_u64_result_fn() {
+                Some(on_u64_result_fn) => unsafe {
+                    on_u64_result_fn(self.instance, result_id, value)
+                },
+                None => return Ok(()),
+            },
+        };
+        self.interface
+            .check_errno(errno, || anyhow!("EventHookPlugin: on_result failed"))
+    }
+    fn get_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        let Some(get_metrics_fn) = self.interface.borrow_get_metrics_fn() else {
+            return Ok(None);
+        };
+        read_raw_metric(|tag, data_type, data| unsafe {
+            get_metrics_fn(self.instance, nth_metric, tag, data_type, data)
+        })
+    }
+}
+
+/// The kind of an [EventHookOperation], corresponding to the variants of
+/// [Operation].
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventHookOperationKind {
+    QFree = 0,
+    QAlloc = 1,
+    RXY = 2,
+    RZZ = 3,
+    RZ = 4,
+    Reset = 5,
+    MeasureRequest = 6,
+    MeasureLeakedRequest = 7,
+    MeasureResetRequest = 8,
+    FutureRead = 9,
+    BatchStart = 10,
+    GlobalBarrier = 11,
+    LocalBarrier = 12,
+    Custom = 13,
+    BeginCondition = 14,
+    EndCondition = 15,
+}
+
+/// An [Operation] as passed to `selene_event_hook_on_user_call`. The fields
+/// used depend on the kind of the operation:
+///
+/// - QFree, QAlloc, Reset and the measurement requests act on one qubit.
+/// - RXY acts on one qubit with the angles theta and phi, RZ on one qubit with
+///   the angle theta, and RZZ on two qubits with the angle theta.
+/// - FutureRead reads the result in `values[0]`.
+/// - BatchStart starts at `values[0]` and lasts for `values[1]` nanoseconds.
+/// - GlobalBarrier sleeps for `values[0]` nanoseconds, as does LocalBarrier,
+///   which acts on any number of qubits.
+/// - Custom has the tag `values[0]` and the given data.
+/// - BeginCondition conditions on the result `values[0]` being `values[1]`.
+///
+/// Unused fields are zero or null. The pointers are only valid for the
+/// duration of the call.
+#[repr(C)]
+#[derive(Debug)]
+pub struct EventHookOperation {
+    pub kind: EventHookOperationKind,
+    pub qubits: *const u64,
+    pub qubits_length: u64,
+    pub angles: [f64; 2],
+    pub values: [u64; 2],
+    pub data: *const u8,
+    pub data_length: u64,
+}
+
+impl EventHookOperation {
+    /// Call `go` with the C representation of the given operation.
+    pub fn with_operation<T>(operation: &Operation, go: impl FnOnce(&Self) -> T) -> T {
+        use EventHookOperationKind as Kind;
+        let pair;
+        let (kind, qubits, angles, values, data): (_, &[u64], _, _, &[u8]) = match operation {
+            Operation::QFree(q) => (Kind::QFree, std::slice::from_ref(q), [0.0; 2], [0; 2], &[]),
+            Operation::QAlloc(q) => (Kind::QAlloc, std::slice::from_ref(q), [0.0; 2], [0; 2], &[]),
+            Operation::RXY(q, theta, phi) => (
+                Kind::RXY,
+                std::slice::from_ref(q),
+                [*theta, *phi],
+                [0; 2],
+                &[],
+            ),
+            Operation::RZZ(q0, q1, theta) => {
+                pair = [*q0, *q1];
+                (Kind::RZZ, &pair, [*theta, 0.0], [0; 2], &[])
+            }
+            Operation::RZ(q, theta) => (
+                Kind::RZ,
+                std::slice::from_ref(q),
+                [*theta, 0.0],
+                [0; 2],
+                &[],
+            ),
+            Operation::Reset(q) => (Kind::Reset, std::slice::from_ref(q), [0.0; 2], [0; 2], &[]),
+            Operation::MeasureRequest(q) => (
+                Kind::MeasureRequest,
+                std::slice::from_ref(q),
+                [0.0; 2],
+                [0; 2],
+                &[],
+            ),
+            Operation::MeasureLeakedRequest(q) => (
+                Kind::MeasureLeakedRequest,
+                std::slice::from_ref(q),
+                [0.0; 2],
+                [0; 2],
+                &[],
+            ),
+            Operation::MeasureResetRequest(q) => (
+                Kind::MeasureResetRequest,
+                std::slice::from_ref(q),
+                [0.0; 2],
+                [0; 2],
+                &[],
+            ),
+            Operation::FutureRead(result_id) => {
+                (Kind::FutureRead, &[], [0.0; 2], [*result_id, 0], &[])
+            }
+            Operation::BatchStart(start, duration) => {
+                (Kind::BatchStart, &[], [0.0; 2], [*start, *duration], &[])
+            }
+            Operation::GlobalBarrier(sleep_time) => {
+                (Kind::GlobalBarrier, &[], [0.0; 2], [*sleep_time, 0], &[])
+            }
+            Operation::LocalBarrier(qubits, sleep_time) => {
+                (Kind::LocalBarrier, qubits, [0.0; 2], [*sleep_time, 0], &[])
+            }
+            Operation::Custom(tag, data) => (Kind::Custom, &[], [0.0; 2], [*tag, 0], data),
+            Operation::BeginCondition(result_id, value) => (
+                Kind::BeginCondition,
+                &[],
+                [0.0; 2],
+                [*result_id, *value as u64],
+                &[],
+            ),
+            Operation::EndCondition => (Kind::EndCondition, &[], [0.0; 2], [0; 2], &[]),
+        };
+        go(&Self {
+            kind,
+            qubits: if qubits.is_empty() {
+                std::ptr::null()
+            } else {
+                qubits.as_ptr()
+            },
+            qubits_length: qubits.len() as u64,
+            angles,
+            values,
+            data: if data.is_empty() {
+                std::ptr::null()
+            } else {
+                data.as_ptr()
+            },
+            data_length: data.len() as u64,
+        })
+    }
+
+    /// The operation that this represents.
+    ///
+    /// # Safety
+    /// `qubits` and `data` must point to at least `qubits_length` and
+    /// `data_length` elements respectively, unless those lengths are zero.
+    pub unsafe fn to_operation(&self) -> Result<Operation> {
+        use EventHookOperationKind as Kind;
+        let qubits: &[u64] = match self.qubits_length {
+            0 => &[],
+            n => unsafe { std::slice::from_raw_parts(self.qubits, n as usize) },
+        };
+        let data: &[u8] = match self.data_length {
+            0 => &[],
+            n => unsafe { std::slice::from_raw_parts(self.data, n as usize) },
+        };
+        let qubit = |n: usize| {
+            qubits.get(n).copied().ok_or_else(|| {
+                anyhow!(
+                    "{:?} operation is missing qubit {n}, having {} qubits",
+                    self.kind,
+                    qubits.len()
+                )
+            })
+        };
+        let [theta, phi] = self.angles;
+        let [value0, value1] = self.values;
+        Ok(match self.kind {
+            Kind::QFree => Operation::QFree(qubit(0)?),
+            Kind::QAlloc => Operation::QAlloc(qubit(0)?),
+            Kind::RXY => Operation::RXY(qubit(0)?, theta, phi),
+            Kind::RZZ => Operation::RZZ(qubit(0)?, qubit(1)?, theta),
+            Kind::RZ => Operation::RZ(qubit(0)?, theta),
+            Kind::Reset => Operation::Reset(qubit(0)?),
+            Kind::MeasureRequest => Operation::MeasureRequest(qubit(0)?),
+            Kind::MeasureLeakedRequest => Operation::MeasureLeakedRequest(qubit(0)?),
+            Kind::MeasureResetRequest => Operation::MeasureResetRequest(qubit(0)?),
+            Kind::FutureRead => Operation::FutureRead(value0),
+            Kind::BatchStart => Operation::BatchStart(value0, value1),
+            Kind::GlobalBarrier => Operation::GlobalBarrier(value0),
+            Kind::LocalBarrier => Operation::LocalBarrier(qubits.to_vec(), value0),
+            Kind::Custom => Operation::Custom(value0, data.to_vec()),
+            Kind::BeginCondition => Operation::BeginCondition(value0, value1 != 0),
+            Kind::EndCondition => Operation::EndCondition,
+        })
+    }
+}
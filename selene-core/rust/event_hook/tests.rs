@@ -0,0 +1,59 @@
+use super::Operation;
+use super::plugin::{EventHookOperation, EventHookOperationKind};
+
+fn round_trip(operation: Operation) -> Operation {
+    EventHookOperation::with_operation(&operation, |raw| unsafe { raw.to_operation() }.unwrap())
+}
+
+#[test]
+fn operations_survive_the_c_representation() {
+    let operations = [
+        Operation::QFree(3),
+        Operation::QAlloc(4),
+        Operation::RXY(1, 0.25, -0.5),
+        Operation::RZZ(1, 2, 0.75),
+        Operation::RZ(0, 1.5),
+        Operation::Reset(5),
+        Operation::MeasureRequest(6),
+        Operation::MeasureLeakedRequest(7),
+        Operation::MeasureResetRequest(8),
+        Operation::FutureRead(9),
+        Operation::BatchStart(100, 20),
+        Operation::GlobalBarrier(30),
+        Operation::LocalBarrier(vec![0, 2, 4], 40),
+        Operation::LocalBarrier(vec![], 0),
+        Operation::Custom(11, vec![1, 2, 3]),
+        Operation::Custom(12, vec![]),
+        Operation::BeginCondition(13, true),
+        Operation::BeginCondition(14, false),
+        Operation::EndCondition,
+    ];
+    for operation in operations {
+        assert_eq!(round_trip(operation.clone()), operation);
+    }
+}
+
+#[test]
+fn unused_fields_are_empty() {
+    EventHookOperation::with_operation(&Operation::FutureRead(9), |raw| {
+        assert_eq!(raw.kind, EventHookOperationKind::FutureRead);
+        assert!(raw.qubits.is_null());
+        assert_eq!(raw.qubits_length, 0);
+        assert!(raw.data.is_null());
+        assert_eq!(raw.values, [9, 0]);
+    });
+}
+
+#[test]
+fn missing_qubits_are_rejected() {
+    let raw = EventHookOperation {
+        kind: EventHookOperationKind::RZZ,
+        qubits: [1u64].as_ptr(),
+        qubits_length: 1,
+        angles: [0.5, 0.0],
+        values: [0; 2],
+        data: std::ptr::null(),
+        data_length: 0,
+    };
+    assert!(unsafe { raw.to_operation() }.is_err());
+}
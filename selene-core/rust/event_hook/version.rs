@@ -0,0 +1,92 @@
+use anyhow::{Result, anyhow};
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct EventHookAPIVersion {
+    /// Reserved for future use, must be 0.
+    reserved: u8,
+    /// Major version of the API.
+    major: u8,
+    /// Minor version of the API.
+    minor: u8,
+    /// Patch version of the API.
+    patch: u8,
+}
+
+impl From<u64> for EventHookAPIVersion {
+    fn from(value: u64) -> Self {
+        Self {
+            reserved: ((value >> 24) & 255) as u8,
+            major: ((value >> 16) & 255) as u8,
+            minor: ((value >> 8) & 255) as u8,
+            patch: (value & 255) as u8,
+        }
+    }
+}
+impl std::fmt::Display for EventHookAPIVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+impl From<EventHookAPIVersion> for u64 {
+    fn from(value: EventHookAPIVersion) -> u64 {
+        ((value.reserved as u64) << 24)
+            | ((value.major as u64) << 16)
+            | ((value.minor as u64) << 8)
+            | (value.patch as u64)
+    }
+}
+
+pub const CURRENT_API_VERSION: EventHookAPIVersion = EventHookAPIVersion {
+    reserved: 0,
+    major: 0,
+    minor: 1,
+    patch: 1,
+};
+
+// Changelog:
+// 0.1.0: Initial version.
+// 0.1.1: Introduced the optional on_error_model_op, on_bool_result and on_u64_result entry
+//   points
+
+/// The oldest minor version of the current major version that selene can still load.
+pub const MIN_SUPPORTED_MINOR: u8 = 1;
+
+impl EventHookAPIVersion {
+    pub fn minor(&self) -> u8 {
+        self.minor
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        // Reserved must be 0. We may want to attribute meaning to this one day.
+        if self.reserved != 0 {
+            return Err(anyhow!(
+                "API version reserved field must be 0, got {}",
+                self.reserved
+            ));
+        }
+        // If the major version is different, the plugin is almost definitely incompatible.
+        if self.major != CURRENT_API_VERSION.major {
+            return Err(anyhow!(
+                "API major version must be the same as Selene's API major version ({}), got {}",
+                CURRENT_API_VERSION.major,
+                self.major
+            ));
+        }
+        if self.minor > CURRENT_API_VERSION.minor {
+            return Err(anyhow!(
+                "Event hook API minor version {} is newer than Selene's event hook API minor version ({}). Upgrade selene to use this plugin.",
+                self.minor,
+                CURRENT_API_VERSION.minor
+            ));
+        }
+        if self.minor < MIN_SUPPORTED_MINOR {
+            return Err(anyhow!(
+                "Event hook API minor version {} is no longer supported. Selene supports event hook plugins from minor version {}.",
+                self.minor,
+                MIN_SUPPORTED_MINOR
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+//! Hosting of plugins in a separate process.
+//!
+//! Plugins are normally loaded into the emulator's process, so a plugin that
+//! crashes, such as a simulator hitting a segfault in native code, takes the
+//! whole run down with it, and its output is lost. Simulators and error models
+//! can instead be hosted by the `selene-plugin-host` binary, which loads the
+//! plugin and serves its interface over a loopback connection (see
+//! [protocol]). A crash of the host is then reported as an error of kind
+//! [ErrorKind::PluginCrashed](crate::error::ErrorKind::PluginCrashed), naming
+//! the shot in which it occurred, and the emulator carries on to report it.
+//!
+//! A plugin is hosted out of process when its path is given with the
+//! `isolated:` prefix (see [isolated_path]). The host binary is found through
+//! the `SELENE_PLUGIN_HOST` environment variable, next to the current
+//! executable, or on the `PATH`, in that order.
+//!
+//! Every operation is a round trip to the host, so hosting a plugin out of
+//! process is considerably slower than loading it, and is best reserved for
+//! plugins that are untrusted or liable to crash.
+pub mod protocol;
+pub mod proxy;
+pub mod server;
+
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+pub use proxy::{RemoteErrorModelFactory, RemoteSimulatorFactory};
+
+/// The prefix of plugin paths that refer to plugins to host out of process.
+pub const ISOLATED_PREFIX: &str = "isolated:";
+
+/// The environment variable giving the path of the host binary.
+pub const HOST_ENV: &str = "SELENE_PLUGIN_HOST";
+
+/// The environment variable through which the host is given the token it
+/// must present when connecting.
+pub const TOKEN_ENV: &str = "SELENE_PLUGIN_HOST_TOKEN";
+
+const HOST_BINARY: &str = "selene-plugin-host";
+
+/// The path that refers to the plugin at the given path, hosted out of
+/// process.
+pub fn isolated_path(path: impl AsRef<OsStr>) -> OsString {
+    let mut isolated = OsString::from(ISOLATED_PREFIX);
+    isolated.push(path);
+    isolated
+}
+
+/// The path of the plugin to host out of process that a plugin path refers
+/// to, if any.
+pub fn isolated_plugin(path: &OsStr) -> Option<&Path> {
+    path.to_str()?.strip_prefix(ISOLATED_PREFIX).map(Path::new)
+}
+
+/// The host binary to launch.
+fn host_executable() -> PathBuf {
+    if let Some(path) = std::env::var_os(HOST_ENV) {
+        return PathBuf::from(path);
+    }
+    let sibling = std::env::current_exe()
+        .ok()
+        .and_then(|exe| {
+            Some(
+                exe.parent()?
+                    .join(HOST_BINARY)
+                    .with_extension(std::env::consts::EXE_EXTENSION),
+            )
+        })
+        .filter(|path| path.is_file());
+    sibling.unwrap_or_else(|| PathBuf::from(HOST_BINARY))
+}
@@ -0,0 +1,216 @@
+//! The messages exchanged with a plugin host.
+//!
+//! The emulator listens on a loopback port and launches the host, passing it
+//! the address as its only argument. The host connects, and sends the token
+//! it was given in [TOKEN_ENV](super::TOKEN_ENV) on a line of its own, so that
+//! no other process can take its place. From then on, the emulator sends a
+//! [Request] and the host answers with a [Response], each encoded with
+//! bincode and preceded by its length as a little-endian `u64`. The encoding
+//! is binary rather than textual so that angles and metrics, including NaNs
+//! and infinities, reach the other side bit for bit.
+//!
+//! The first request is always [Request::Load], followed by one of the init
+//! requests, after which the requests mirror the methods of the hosted
+//! plugin's interface. The host exits after answering [Request::Exit], or
+//! when the connection is closed.
+use std::io::{ErrorKind as IoErrorKind, Read, Write};
+use std::path::PathBuf;
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+
+use crate::capabilities::Capabilities;
+use crate::error::ErrorKind;
+use crate::error_model::BatchResult;
+use crate::metadata::PluginKind;
+use crate::runtime::BatchOperation;
+use crate::utils::MetricValue;
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum Request {
+    /// Load the plugin library, replying with its capabilities.
+    Load {
+        kind: PluginKind,
+        path: PathBuf,
+    },
+    InitSimulator {
+        n_qubits: u64,
+        args: Vec<String>,
+    },
+    InitErrorModel {
+        n_qubits: u64,
+        args: Vec<String>,
+        simulator_path: PathBuf,
+        simulator_args: Vec<String>,
+    },
+    /// Start a shot. The simulator seed is only used by error models.
+    ShotStart {
+        shot_id: u64,
+        seed: u64,
+        simulator_seed: u64,
+    },
+    ShotEnd,
+    Rxy {
+        qubit: u64,
+        theta: f64,
+        phi: f64,
+    },
+    Rzz {
+        qubit1: u64,
+        qubit2: u64,
+        theta: f64,
+    },
+    Rz {
+        qubit: u64,
+        theta: f64,
+    },
+    Measure {
+        qubit: u64,
+    },
+    Postselect {
+        qubit: u64,
+        target_value: bool,
+    },
+    Reset {
+        qubit: u64,
+    },
+    /// Dump the state of the simulator, or of the error model's simulator.
+    DumpState {
+        file: PathBuf,
+        qubits: Vec<u64>,
+    },
+    HandleOperations(BatchOperation),
+    GetMetric {
+        nth_metric: u8,
+    },
+    GetSimulatorMetric {
+        nth_metric: u8,
+    },
+    /// Get the capabilities of the error model's simulator, if it reports
+    /// them.
+    GetSimulatorCapabilities,
+    Exit,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum Reply {
+    Unit,
+    Bool(bool),
+    Capabilities(u64),
+    SimulatorCapabilities(Option<u64>),
+    Metric(Option<(String, MetricValue)>),
+    Results(BatchResult),
+}
+
+/// The answer to a request: a reply, or the code of the [ErrorKind] of the
+/// error raised by the plugin along with its message.
+#[derive(Serialize, Deserialize, Debug)]
+pub enum Response {
+    Ok(Reply),
+    Err { code: u32, message: String },
+}
+
+impl Response {
+    pub fn from_result(result: Result<Reply>) -> Self {
+        match result {
+            Ok(reply) => Self::Ok(reply),
+            Err(e) => Self::Err {
+                code: ErrorKind::of(&e).code(),
+                message: format!("{e:#}"),
+            },
+        }
+    }
+
+    pub fn into_result(self) -> Result<Reply> {
+        match self {
+            Self::Ok(reply) => Ok(reply),
+            Self::Err { code, message } => Err(ErrorKind::from_code(code)
+                .unwrap_or(ErrorKind::Internal)
+                .error(message)),
+        }
+    }
+}
+
+fn unexpected(reply: Reply) -> anyhow::Error {
+    anyhow!("Unexpected reply from plugin host: {reply:?}")
+}
+
+impl Reply {
+    pub fn unit(self) -> Result<()> {
+        match self {
+            Self::Unit => Ok(()),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    pub fn bool(self) -> Result<bool> {
+        match self {
+            Self::Bool(value) => Ok(value),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    pub fn capabilities(self) -> Result<Capabilities> {
+        match self {
+            Self::Capabilities(bits) => Ok(Capabilities::from_bits_truncate(bits)),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    pub fn simulator_capabilities(self) -> Result<Option<Capabilities>> {
+        match self {
+            Self::SimulatorCapabilities(bits) => Ok(bits.map(Capabilities::from_bits_truncate)),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    pub fn metric(self) -> Result<Option<(String, MetricValue)>> {
+        match self {
+            Self::Metric(metric) => Ok(metric),
+            reply => Err(unexpected(reply)),
+        }
+    }
+
+    pub fn results(self) -> Result<BatchResult> {
+        match self {
+            Self::Results(results) => Ok(results),
+            reply => Err(unexpected(reply)),
+        }
+    }
+}
+
+/// Write a message, preceded by its length.
+pub fn send(writer: &mut impl Write, message: &impl Serialize) -> Result<()> {
+    let body = bincode::serialize(message)?;
+    writer.write_all(&(body.len() as u64).to_le_bytes())?;
+    writer.write_all(&body)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read a message, or `None` if the connection has been closed between
+/// messages.
+pub fn receive<T: DeserializeOwned>(reader: &mut impl Read) -> Result<Option<T>> {
+    let mut length = [0u8; 8];
+    let mut filled = 0;
+    while filled < length.len() {
+        match reader.read(&mut length[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => bail!("Connection to plugin host closed mid-message"),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == IoErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    let mut body = vec![0u8; u64::from_le_bytes(length) as usize];
+    reader.read_exact(&mut body).map_err(|e| match e.kind() {
+        IoErrorKind::UnexpectedEof => anyhow!("Connection to plugin host closed mid-message"),
+        _ => e.into(),
+    })?;
+    bincode::deserialize(&body)
+        .map(Some)
+        .context("Invalid plugin host message")
+}
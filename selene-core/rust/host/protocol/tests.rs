@@ -0,0 +1,214 @@
+use super::*;
+use crate::error_model::{BoolResult, InjectedOperation};
+use crate::runtime::Operation;
+use std::io::Cursor;
+
+/// Angles that do not survive a round trip through decimal text.
+const ANGLES: [f64; 7] = [
+    f64::NAN,
+    f64::INFINITY,
+    f64::NEG_INFINITY,
+    -0.0,
+    f64::MIN_POSITIVE / 3.0,
+    0.1 + 0.2,
+    std::f64::consts::PI,
+];
+
+/// A NaN with a payload other than that of [f64::NAN].
+fn signalling_nan() -> f64 {
+    f64::from_bits(0x7ff0_0000_dead_beef)
+}
+
+fn angles() -> impl Iterator<Item = f64> {
+    ANGLES.into_iter().chain([signalling_nan()])
+}
+
+fn roundtrip<T: Serialize + DeserializeOwned>(message: &T) -> T {
+    let mut buffer = Vec::new();
+    send(&mut buffer, message).unwrap();
+    let mut reader = Cursor::new(buffer);
+    let received = receive(&mut reader).unwrap().unwrap();
+    assert!(receive::<T>(&mut reader).unwrap().is_none());
+    received
+}
+
+#[test]
+fn angles_are_sent_exactly() {
+    for angle in angles() {
+        let Request::Rxy { qubit, theta, phi } = roundtrip(&Request::Rxy {
+            qubit: 7,
+            theta: angle,
+            phi: -angle,
+        }) else {
+            panic!("expected an rxy request");
+        };
+        assert_eq!(qubit, 7);
+        assert_eq!(theta.to_bits(), angle.to_bits());
+        assert_eq!(phi.to_bits(), (-angle).to_bits());
+    }
+}
+
+#[test]
+fn batches_are_sent_exactly() {
+    let ops: Vec<Operation> = angles()
+        .map(|theta| Operation::RZGate { qubit_id: 1, theta })
+        .collect();
+    let batch = BatchOperation::new(ops.clone(), Default::default(), Default::default());
+    let Request::HandleOperations(received) = roundtrip(&Request::HandleOperations(batch)) else {
+        panic!("expected a batch");
+    };
+    let received: Vec<Operation> = received.into_iter().collect();
+    assert_eq!(received.len(), ops.len());
+    for (sent, received) in ops.iter().zip(&received) {
+        let (
+            Operation::RZGate { theta: sent, .. },
+            Operation::RZGate {
+                qubit_id: 1,
+                theta: received,
+            },
+        ) = (sent, received)
+        else {
+            panic!("expected rz gates, got {received:?}");
+        };
+        assert_eq!(sent.to_bits(), received.to_bits());
+    }
+
+    let results = BatchResult {
+        bool_results: vec![BoolResult {
+            result_id: 4,
+            value: true,
+        }],
+        u64_results: vec![],
+        injected_operations: vec![InjectedOperation {
+            position: 3,
+            operation: Operation::RXYGate {
+                qubit_id: 0,
+                theta: signalling_nan(),
+                phi: -0.0,
+            },
+        }],
+    };
+    let Response::Ok(Reply::Results(received)) = roundtrip(&Response::Ok(Reply::Results(results)))
+    else {
+        panic!("expected results");
+    };
+    assert_eq!(received.bool_results.len(), 1);
+    assert_eq!(received.bool_results[0].result_id, 4);
+    assert!(received.bool_results[0].value);
+    let [
+        InjectedOperation {
+            position: 3,
+            operation: Operation::RXYGate { theta, phi, .. },
+        },
+    ] = received.injected_operations.as_slice()
+    else {
+        panic!("expected the injected rxy gate");
+    };
+    assert_eq!(theta.to_bits(), signalling_nan().to_bits());
+    assert_eq!(phi.to_bits(), (-0.0f64).to_bits());
+}
+
+#[test]
+fn metrics_are_sent_exactly() {
+    for value in angles() {
+        let reply = Reply::Metric(Some(("fidelity".to_string(), MetricValue::F64(value))));
+        let received = roundtrip(&Response::Ok(reply)).into_result().unwrap();
+        let Some((tag, MetricValue::F64(received))) = received.metric().unwrap() else {
+            panic!("expected an f64 metric");
+        };
+        assert_eq!(tag, "fidelity");
+        assert_eq!(received.to_bits(), value.to_bits());
+    }
+}
+
+#[test]
+fn messages_are_read_in_turn() {
+    let mut buffer = Vec::new();
+    send(&mut buffer, &Request::Measure { qubit: 3 }).unwrap();
+    send(&mut buffer, &Request::Exit).unwrap();
+    let mut reader = Cursor::new(buffer);
+    assert!(matches!(
+        receive(&mut reader).unwrap(),
+        Some(Request::Measure { qubit: 3 })
+    ));
+    assert!(matches!(receive(&mut reader).unwrap(), Some(Request::Exit)));
+    assert!(receive::<Request>(&mut reader).unwrap().is_none());
+}
+
+#[test]
+fn truncated_messages_are_rejected() {
+    let mut buffer = Vec::new();
+    send(
+        &mut buffer,
+        &Request::Rz {
+            qubit: 0,
+            theta: 1.0,
+        },
+    )
+    .unwrap();
+    // Cut off within the length, and within the message itself.
+    for cut in [3, buffer.len() - 1] {
+        let error = receive::<Request>(&mut Cursor::new(&buffer[..cut])).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Connection to plugin host closed mid-message"
+        );
+    }
+}
+
+#[test]
+fn invalid_messages_are_rejected() {
+    let mut buffer = Vec::new();
+    send(&mut buffer, &u32::MAX).unwrap();
+    let error = receive::<Request>(&mut Cursor::new(buffer)).unwrap_err();
+    assert_eq!(error.to_string(), "Invalid plugin host message");
+}
+
+#[test]
+fn errors_keep_their_kind() {
+    let response = Response::from_result(Err(ErrorKind::ShotFailed.error("postselection failed")));
+    let error = roundtrip(&response).into_result().unwrap_err();
+    assert_eq!(ErrorKind::of(&error), ErrorKind::ShotFailed);
+    assert_eq!(error.to_string(), "postselection failed");
+
+    // Errors without a kind, or with one unknown to this version, are
+    // internal errors.
+    let response = Response::from_result(Err(anyhow!("plugin failed")));
+    let error = roundtrip(&response).into_result().unwrap_err();
+    assert_eq!(ErrorKind::of(&error), ErrorKind::Internal);
+    let response = Response::Err {
+        code: 123456,
+        message: "from the future".to_string(),
+    };
+    let error = response.into_result().unwrap_err();
+    assert_eq!(ErrorKind::of(&error), ErrorKind::Internal);
+    assert_eq!(error.to_string(), "from the future");
+}
+
+#[test]
+fn replies_are_checked() {
+    assert!(Reply::Unit.unit().is_ok());
+    assert!(Reply::Bool(true).bool().unwrap());
+    assert_eq!(
+        Reply::Capabilities(Capabilities::POSTSELECT.bits())
+            .capabilities()
+            .unwrap(),
+        Capabilities::POSTSELECT
+    );
+    assert_eq!(
+        Reply::SimulatorCapabilities(None)
+            .simulator_capabilities()
+            .unwrap(),
+        None
+    );
+    let error = Reply::Bool(false).unit().unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Unexpected reply from plugin host: Bool(false)"
+    );
+    assert!(Reply::Unit.bool().is_err());
+    assert!(Reply::Unit.capabilities().is_err());
+    assert!(Reply::Unit.simulator_capabilities().is_err());
+    assert!(Reply::Unit.metric().is_err());
+    assert!(Reply::Unit.results().is_err());
+}
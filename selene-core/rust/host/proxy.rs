@@ -0,0 +1,430 @@
+//! The emulator's side of an out-of-process plugin: proxies implementing the
+//! plugin interfaces by forwarding each call to a plugin host.
+use std::collections::hash_map::RandomState;
+use std::ffi::OsStr;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{BufRead, BufReader, ErrorKind as IoErrorKind};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result, anyhow, bail};
+
+use super::protocol::{Reply, Request, Response, receive, send};
+use crate::capabilities::Capabilities;
+use crate::error::ErrorKind;
+use crate::error_model::{BatchResult, ErrorModelInterface, ErrorModelInterfaceFactory};
+use crate::metadata::PluginKind;
+use crate::runtime::BatchOperation;
+use crate::simulator::{SimulatorInterface, SimulatorInterfaceFactory};
+use crate::utils::MetricValue;
+
+/// How long to wait for a launched host to connect.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to wait for a host that has closed its connection to exit, so
+/// that its exit status can be reported.
+const EXIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A connection to a plugin host, which is killed when the connection is
+/// dropped.
+pub struct HostConnection {
+    kind: PluginKind,
+    plugin: PathBuf,
+    child: Child,
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    /// The shot in progress, for reporting crashes.
+    shot_id: Option<u64>,
+    /// The description of the crash of the host, once it has crashed.
+    crash: Option<String>,
+}
+
+impl HostConnection {
+    /// Launch a host for the plugin at the given path, and load the plugin,
+    /// returning the connection and the plugin's capabilities.
+    pub fn launch(kind: PluginKind, plugin: &Path) -> Result<(Self, Capabilities)> {
+        let listener = TcpListener::bind(("127.0.0.1", 0))?;
+        let address = listener.local_addr()?;
+        let token = RandomState::new().build_hasher().finish().to_string();
+        let executable = super::host_executable();
+        let mut child = Command::new(&executable)
+            .arg(address.to_string())
+            .env(super::TOKEN_ENV, &token)
+            .stdin(Stdio::null())
+            .spawn()
+            .with_context(|| {
+                format!(
+                    "Failed to launch the plugin host '{}'. Set {} to the path of the selene-plugin-host binary.",
+                    executable.display(),
+                    super::HOST_ENV
+                )
+            })?;
+        let stream = match accept(&listener, &mut child, &token) {
+            Ok(stream) => stream,
+            Err(e) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(e);
+            }
+        };
+        let mut connection = Self {
+            kind,
+            plugin: plugin.to_path_buf(),
+            child,
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            shot_id: None,
+            crash: None,
+        };
+        let capabilities = connection
+            .request(&Request::Load {
+                kind,
+                path: plugin.to_path_buf(),
+            })?
+            .capabilities()?;
+        Ok((connection, capabilities))
+    }
+
+    /// Send a request to the host and wait for its response.
+    pub fn request(&mut self, request: &Request) -> Result<Reply> {
+        if let Some(crash) = &self.crash {
+            return Err(ErrorKind::PluginCrashed.error(crash.clone()));
+        }
+        let response =
+            send(&mut self.writer, request).and_then(|_| receive::<Response>(&mut self.reader));
+        match response {
+            Ok(Some(response)) => response.into_result(),
+            Ok(None) => Err(self.crashed(None)),
+            Err(e) => Err(self.crashed(Some(e))),
+        }
+    }
+
+    /// Record that the host has gone away, returning the error to report.
+    fn crashed(&mut self, cause: Option<anyhow::Error>) -> anyhow::Error {
+        let deadline = Instant::now() + EXIT_TIMEOUT;
+        let status = loop {
+            match self.child.try_wait() {
+                Ok(Some(status)) => break Some(status),
+                Ok(None) if Instant::now() < deadline => {
+                    std::thread::sleep(Duration::from_millis(10))
+                }
+                _ => {
+                    let _ = self.child.kill();
+                    break self.child.wait().ok();
+                }
+            }
+        };
+        let mut crash = format!(
+            "The {} plugin '{}' exited unexpectedly",
+            self.kind,
+            self.plugin.display()
+        );
+        if let Some(shot_id) = self.shot_id {
+            crash.push_str(&format!(" during shot {shot_id}"));
+        }
+        match (status, cause) {
+            (Some(status), _) => crash.push_str(&format!(" ({status})")),
+            (None, Some(cause)) => crash.push_str(&format!(" ({cause:#})")),
+            (None, None) => {}
+        }
+        self.crash = Some(crash.clone());
+        ErrorKind::PluginCrashed.error(crash)
+    }
+
+    fn shot_start(&mut self, shot_id: u64, seed: u64, simulator_seed: u64) -> Result<()> {
+        self.shot_id = Some(shot_id);
+        self.request(&Request::ShotStart {
+            shot_id,
+            seed,
+            simulator_seed,
+        })?
+        .unit()
+    }
+
+    fn shot_end(&mut self) -> Result<()> {
+        // The crash has already been reported within the shot, which can
+        // still be brought to an end.
+        if self.crash.is_some() {
+            return Ok(());
+        }
+        self.request(&Request::ShotEnd)?.unit()?;
+        self.shot_id = None;
+        Ok(())
+    }
+
+    fn exit(&mut self) -> Result<()> {
+        // A host that has crashed has nothing left to clean up.
+        if self.crash.is_some() {
+            return Ok(());
+        }
+        self.request(&Request::Exit)?.unit()
+    }
+}
+
+impl Drop for HostConnection {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Accept the connection from the launched host, checking its token.
+fn accept(listener: &TcpListener, child: &mut Child, token: &str) -> Result<TcpStream> {
+    listener.set_nonblocking(true)?;
+    let deadline = Instant::now() + CONNECT_TIMEOUT;
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                stream.set_nonblocking(false)?;
+                stream.set_read_timeout(Some(CONNECT_TIMEOUT))?;
+                let mut presented = String::new();
+                BufReader::new(&stream).read_line(&mut presented)?;
+                if presented.trim_end() == token {
+                    stream.set_read_timeout(None)?;
+                    stream.set_nodelay(true)?;
+                    return Ok(stream);
+                }
+            }
+            Err(e) if e.kind() == IoErrorKind::WouldBlock => {
+                if let Some(status) = child.try_wait()? {
+                    bail!("The plugin host exited before connecting ({status})");
+                }
+                if Instant::now() > deadline {
+                    bail!("Timed out waiting for the plugin host to connect");
+                }
+                std::thread::sleep(Duration::from_millis(1));
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+/// Takes the connection made to load a plugin, for the first instance created
+/// from a factory, or launches a new host for later ones.
+fn take_connection(
+    connection: &Mutex<Option<HostConnection>>,
+    kind: PluginKind,
+    plugin: &Path,
+) -> Result<HostConnection> {
+    let connection = connection
+        .lock()
+        .map_err(|_| anyhow!("Plugin host connection lock poisoned"))?
+        .take();
+    match connection {
+        Some(connection) => Ok(connection),
+        None => Ok(HostConnection::launch(kind, plugin)?.0),
+    }
+}
+
+fn owned_args(args: &[impl AsRef<str>]) -> Vec<String> {
+    args.iter().map(|arg| arg.as_ref().to_string()).collect()
+}
+
+/// Creates simulators hosted out of process.
+pub struct RemoteSimulatorFactory {
+    plugin: PathBuf,
+    capabilities: Capabilities,
+    connection: Mutex<Option<HostConnection>>,
+}
+
+impl RemoteSimulatorFactory {
+    /// Launch a host for the simulator plugin at the given path.
+    pub fn launch(plugin: impl AsRef<Path>) -> Result<Self> {
+        let plugin = plugin.as_ref().to_path_buf();
+        let (connection, capabilities) = HostConnection::launch(PluginKind::Simulator, &plugin)?;
+        Ok(Self {
+            plugin,
+            capabilities,
+            connection: Mutex::new(Some(connection)),
+        })
+    }
+}
+
+impl SimulatorInterfaceFactory for RemoteSimulatorFactory {
+    type Interface = RemoteSimulator;
+
+    fn init(
+        self: Arc<Self>,
+        n_qubits: u64,
+        args: &[impl AsRef<str>],
+    ) -> Result<Box<Self::Interface>> {
+        let mut connection =
+            take_connection(&self.connection, PluginKind::Simulator, &self.plugin)?;
+        connection
+            .request(&Request::InitSimulator {
+                n_qubits,
+                args: owned_args(args),
+            })?
+            .unit()?;
+        Ok(Box::new(RemoteSimulator(connection)))
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+}
+
+/// A simulator hosted out of process.
+pub struct RemoteSimulator(HostConnection);
+
+impl SimulatorInterface for RemoteSimulator {
+    fn exit(&mut self) -> Result<()> {
+        self.0.exit()
+    }
+
+    fn shot_start(&mut self, shot_id: u64, seed: u64) -> Result<()> {
+        self.0.shot_start(shot_id, seed, 0)
+    }
+
+    fn shot_end(&mut self) -> Result<()> {
+        self.0.shot_end()
+    }
+
+    fn rxy(&mut self, qubit: u64, theta: f64, phi: f64) -> Result<()> {
+        self.0.request(&Request::Rxy { qubit, theta, phi })?.unit()
+    }
+
+    fn rzz(&mut self, qubit1: u64, qubit2: u64, theta: f64) -> Result<()> {
+        self.0
+            .request(&Request::Rzz {
+                qubit1,
+                qubit2,
+                theta,
+            })?
+            .unit()
+    }
+
+    fn rz(&mut self, qubit: u64, theta: f64) -> Result<()> {
+        self.0.request(&Request::Rz { qubit, theta })?.unit()
+    }
+
+    fn measure(&mut self, qubit: u64) -> Result<bool> {
+        self.0.request(&Request::Measure { qubit })?.bool()
+    }
+
+    fn postselect(&mut self, qubit: u64, target_value: bool) -> Result<()> {
+        self.0
+            .request(&Request::Postselect {
+                qubit,
+                target_value,
+            })?
+            .unit()
+    }
+
+    fn reset(&mut self, qubit: u64) -> Result<()> {
+        self.0.request(&Request::Reset { qubit })?.unit()
+    }
+
+    fn get_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        self.0.request(&Request::GetMetric { nth_metric })?.metric()
+    }
+
+    fn dump_state(&mut self, file: &Path, qubits: &[u64]) -> Result<()> {
+        self.0
+            .request(&Request::DumpState {
+                file: file.to_path_buf(),
+                qubits: qubits.to_vec(),
+            })?
+            .unit()
+    }
+}
+
+/// Creates error models hosted out of process. The error model's simulator
+/// is loaded within the same host.
+pub struct RemoteErrorModelFactory {
+    plugin: PathBuf,
+    capabilities: Capabilities,
+    connection: Mutex<Option<HostConnection>>,
+}
+
+impl RemoteErrorModelFactory {
+    /// Launch a host for the error model plugin at the given path.
+    pub fn launch(plugin: impl AsRef<Path>) -> Result<Self> {
+        let plugin = plugin.as_ref().to_path_buf();
+        let (connection, capabilities) = HostConnection::launch(PluginKind::ErrorModel, &plugin)?;
+        Ok(Self {
+            plugin,
+            capabilities,
+            connection: Mutex::new(Some(connection)),
+        })
+    }
+}
+
+impl ErrorModelInterfaceFactory for RemoteErrorModelFactory {
+    type Interface = RemoteErrorModel;
+
+    fn init(
+        self: Arc<Self>,
+        n_qubits: u64,
+        error_model_args: &[impl AsRef<str>],
+        simulator_plugin: &impl AsRef<OsStr>,
+        simulator_args: &[impl AsRef<str>],
+    ) -> Result<Box<Self::Interface>> {
+        let mut connection =
+            take_connection(&self.connection, PluginKind::ErrorModel, &self.plugin)?;
+        connection
+            .request(&Request::InitErrorModel {
+                n_qubits,
+                args: owned_args(error_model_args),
+                simulator_path: PathBuf::from(simulator_plugin.as_ref()),
+                simulator_args: owned_args(simulator_args),
+            })?
+            .unit()?;
+        Ok(Box::new(RemoteErrorModel(connection)))
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+}
+
+/// An error model hosted out of process.
+pub struct RemoteErrorModel(HostConnection);
+
+impl ErrorModelInterface for RemoteErrorModel {
+    fn exit(&mut self) -> Result<()> {
+        self.0.exit()
+    }
+
+    fn shot_start(&mut self, shot_id: u64, seed: u64, simulator_seed: u64) -> Result<()> {
+        self.0.shot_start(shot_id, seed, simulator_seed)
+    }
+
+    fn shot_end(&mut self) -> Result<()> {
+        self.0.shot_end()
+    }
+
+    fn dump_simulator_state(&mut self, file: &Path, qubits: &[u64]) -> Result<()> {
+        self.0
+            .request(&Request::DumpState {
+                file: file.to_path_buf(),
+                qubits: qubits.to_vec(),
+            })?
+            .unit()
+    }
+
+    fn handle_operations(&mut self, operations: BatchOperation) -> Result<BatchResult> {
+        self.0
+            .request(&Request::HandleOperations(operations))?
+            .results()
+    }
+
+    fn get_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        self.0.request(&Request::GetMetric { nth_metric })?.metric()
+    }
+
+    fn get_simulator_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        self.0
+            .request(&Request::GetSimulatorMetric { nth_metric })?
+            .metric()
+    }
+
+    fn simulator_capabilities(&mut self) -> Result<Option<Capabilities>> {
+        self.0
+            .request(&Request::GetSimulatorCapabilities)?
+            .simulator_capabilities()
+    }
+}
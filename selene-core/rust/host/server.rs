@@ -0,0 +1,157 @@
+//! The host's side of an out-of-process plugin: serving requests by calling
+//! the plugin it has loaded.
+use std::io::{BufRead, Write};
+use std::sync::Arc;
+
+use anyhow::{Result, bail};
+
+use super::protocol::{Reply, Request, Response, receive, send};
+use crate::capabilities::Capabilities;
+use crate::error::ErrorKind;
+use crate::error_model::plugin::ErrorModelPluginInterface;
+use crate::error_model::{ErrorModel, ErrorModelInterface, ErrorModelInterfaceFactory};
+use crate::metadata::PluginKind;
+use crate::simulator::plugin::SimulatorPluginInterface;
+use crate::simulator::{Simulator, SimulatorInterface, SimulatorInterfaceFactory};
+
+#[cfg(test)]
+mod tests;
+
+/// The plugin served by a host, as it progresses from being loaded to being
+/// initialised.
+#[derive(Default)]
+enum Hosted {
+    #[default]
+    Empty,
+    SimulatorPlugin(Arc<SimulatorPluginInterface>),
+    ErrorModelPlugin(Arc<ErrorModelPluginInterface>),
+    Simulator(Simulator),
+    ErrorModel(ErrorModel),
+}
+
+fn unexpected(request: &Request) -> anyhow::Error {
+    ErrorKind::Unsupported.error(format!(
+        "Request {request:?} is not valid for the hosted plugin in its current state"
+    ))
+}
+
+impl Hosted {
+    fn handle(&mut self, request: Request) -> Result<Reply> {
+        match (&mut *self, request) {
+            (Self::Empty, Request::Load { kind, path }) => {
+                let (hosted, capabilities) = match kind {
+                    PluginKind::Simulator => {
+                        let plugin = SimulatorPluginInterface::new_from_file(&path)?;
+                        let capabilities = plugin.capabilities();
+                        (Self::SimulatorPlugin(plugin), capabilities)
+                    }
+                    PluginKind::ErrorModel => {
+                        let plugin = ErrorModelPluginInterface::new_from_file(&path)?;
+                        let capabilities = plugin.capabilities();
+                        (Self::ErrorModelPlugin(plugin), capabilities)
+                    }
+                    PluginKind::Runtime => bail!("Runtimes cannot be hosted out of process"),
+                    PluginKind::EventHook => bail!("Event hooks cannot be hosted out of process"),
+                };
+                *self = hosted;
+                Ok(Reply::Capabilities(capabilities.bits()))
+            }
+            (Self::SimulatorPlugin(plugin), Request::InitSimulator { n_qubits, args }) => {
+                *self = Self::Simulator(Simulator::new(plugin.clone(), n_qubits, &args)?);
+                Ok(Reply::Unit)
+            }
+            (
+                Self::ErrorModelPlugin(plugin),
+                Request::InitErrorModel {
+                    n_qubits,
+                    args,
+                    simulator_path,
+                    simulator_args,
+                },
+            ) => {
+                *self = Self::ErrorModel(ErrorModel::new(
+                    plugin.clone(),
+                    n_qubits,
+                    &args,
+                    &simulator_path,
+                    &simulator_args,
+                )?);
+                Ok(Reply::Unit)
+            }
+            (Self::Simulator(simulator), request) => handle_simulator(simulator, request),
+            (Self::ErrorModel(error_model), request) => handle_error_model(error_model, request),
+            (_, request) => Err(unexpected(&request)),
+        }
+    }
+}
+
+fn handle_simulator(simulator: &mut Simulator, request: Request) -> Result<Reply> {
+    match request {
+        Request::ShotStart { shot_id, seed, .. } => simulator.shot_start(shot_id, seed)?,
+        Request::ShotEnd => simulator.shot_end()?,
+        Request::Rxy { qubit, theta, phi } => simulator.rxy(qubit, theta, phi)?,
+        Request::Rzz {
+            qubit1,
+            qubit2,
+            theta,
+        } => simulator.rzz(qubit1, qubit2, theta)?,
+        Request::Rz { qubit, theta } => simulator.rz(qubit, theta)?,
+        Request::Measure { qubit } => return Ok(Reply::Bool(simulator.measure(qubit)?)),
+        Request::Postselect {
+            qubit,
+            target_value,
+        } => simulator.postselect(qubit, target_value)?,
+        Request::Reset { qubit } => simulator.reset(qubit)?,
+        Request::DumpState { file, qubits } => simulator.dump_state(&file, &qubits)?,
+        Request::GetMetric { nth_metric } => {
+            return Ok(Reply::Metric(simulator.get_metric(nth_metric)?));
+        }
+        Request::Exit => simulator.exit()?,
+        request => return Err(unexpected(&request)),
+    }
+    Ok(Reply::Unit)
+}
+
+fn handle_error_model(error_model: &mut ErrorModel, request: Request) -> Result<Reply> {
+    match request {
+        Request::ShotStart {
+            shot_id,
+            seed,
+            simulator_seed,
+        } => error_model.shot_start(shot_id, seed, simulator_seed)?,
+        Request::ShotEnd => error_model.shot_end()?,
+        Request::DumpState { file, qubits } => error_model.dump_simulator_state(&file, &qubits)?,
+        Request::HandleOperations(operations) => {
+            return Ok(Reply::Results(error_model.handle_operations(operations)?));
+        }
+        Request::GetMetric { nth_metric } => {
+            return Ok(Reply::Metric(error_model.get_metric(nth_metric)?));
+        }
+        Request::GetSimulatorMetric { nth_metric } => {
+            return Ok(Reply::Metric(error_model.get_simulator_metric(nth_metric)?));
+        }
+        Request::GetSimulatorCapabilities => {
+            let capabilities = error_model.simulator_capabilities()?;
+            return Ok(Reply::SimulatorCapabilities(
+                capabilities.map(Capabilities::bits),
+            ));
+        }
+        Request::Exit => error_model.exit()?,
+        request => return Err(unexpected(&request)),
+    }
+    Ok(Reply::Unit)
+}
+
+/// Serve requests until [Request::Exit] has been answered or the connection
+/// is closed.
+pub fn serve(reader: &mut impl BufRead, writer: &mut impl Write) -> Result<()> {
+    let mut hosted = Hosted::default();
+    while let Some(request) = receive::<Request>(reader)? {
+        let exit = matches!(request, Request::Exit);
+        send(writer, &Response::from_result(hosted.handle(request)))?;
+        if exit {
+            break;
+        }
+    }
+    Ok(())
+}
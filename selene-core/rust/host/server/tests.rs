@@ -0,0 +1,102 @@
+use super::*;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+/// Serve the given requests, returning the results of the responses sent.
+fn serve_requests(requests: &[Request]) -> Vec<Result<Reply>> {
+    let mut input = Vec::new();
+    for request in requests {
+        send(&mut input, request).unwrap();
+    }
+    let mut output = Vec::new();
+    serve(&mut Cursor::new(input), &mut output).unwrap();
+    let mut output = Cursor::new(output);
+    let mut results = Vec::new();
+    while let Some(response) = receive::<Response>(&mut output).unwrap() {
+        results.push(response.into_result());
+    }
+    results
+}
+
+fn load(kind: PluginKind) -> Request {
+    Request::Load {
+        kind,
+        path: PathBuf::from("/nonexistent/libplugin.so"),
+    }
+}
+
+#[test]
+fn requests_must_follow_a_load() {
+    let results = serve_requests(&[
+        Request::InitSimulator {
+            n_qubits: 1,
+            args: vec![],
+        },
+        Request::Measure { qubit: 0 },
+        Request::GetSimulatorCapabilities,
+    ]);
+    assert_eq!(results.len(), 3);
+    for result in results {
+        let error = result.unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::Unsupported);
+        assert!(
+            error
+                .to_string()
+                .ends_with("is not valid for the hosted plugin in its current state"),
+            "{error}"
+        );
+    }
+}
+
+#[test]
+fn only_simulators_and_error_models_are_hosted() {
+    let results = serve_requests(&[load(PluginKind::Runtime), load(PluginKind::EventHook)]);
+    let messages: Vec<String> = results
+        .into_iter()
+        .map(|result| result.unwrap_err().to_string())
+        .collect();
+    assert_eq!(
+        messages,
+        [
+            "Runtimes cannot be hosted out of process",
+            "Event hooks cannot be hosted out of process",
+        ]
+    );
+}
+
+#[test]
+fn load_failures_are_reported() {
+    let results = serve_requests(&[load(PluginKind::Simulator), load(PluginKind::ErrorModel)]);
+    assert_eq!(results.len(), 2);
+    for result in results {
+        let error = result.unwrap_err();
+        assert!(
+            error.to_string().contains("/nonexistent/libplugin.so"),
+            "{error}"
+        );
+    }
+}
+
+#[test]
+fn serving_stops_after_exit() {
+    // Exit is answered, even with nothing loaded, and later requests are
+    // left unread.
+    let results = serve_requests(&[Request::Exit, Request::Measure { qubit: 0 }]);
+    assert_eq!(results.len(), 1);
+    assert_eq!(
+        ErrorKind::of(results[0].as_ref().unwrap_err()),
+        ErrorKind::Unsupported
+    );
+}
+
+#[test]
+fn serving_stops_when_the_connection_closes() {
+    assert!(serve_requests(&[]).is_empty());
+    let mut output = Vec::new();
+    let error = serve(&mut Cursor::new(vec![1u8, 0]), &mut output).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Connection to plugin host closed mid-message"
+    );
+    assert!(output.is_empty());
+}
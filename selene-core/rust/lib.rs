@@ -1,5 +1,12 @@
+pub mod capabilities;
+pub mod decoder;
 pub mod encoder;
+pub mod error;
 pub mod error_model;
+pub mod event_hook;
+pub mod host;
+pub mod metadata;
+pub mod registry;
 pub mod runtime;
 pub mod simulator;
 pub mod time;
@@ -0,0 +1,269 @@
+//! Self-description of plugins.
+//!
+//! The `export_*_plugin!` macros export a `selene_*_get_metadata` entry point
+//! returning a [PluginMetadata] as JSON: the plugin's name and version, the API
+//! version it was built against, its capabilities, and the arguments it
+//! accepts. [load_metadata] reads it from a plugin library without
+//! initialising the plugin, so that tools such as configuration generators can
+//! check arguments before a run is launched, and the `selene-plugin-info`
+//! binary prints it.
+//!
+//! Plugins built before the entry point was introduced are described from
+//! their other entry points, without a version or arguments.
+use std::ffi::{CStr, CString, OsStr, c_char};
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize};
+
+use crate::capabilities::Capabilities;
+use crate::error_model::{ErrorModelAPIVersion, ErrorModelInterfaceFactory};
+use crate::event_hook::{EventHookAPIVersion, EventHookInterfaceFactory};
+use crate::runtime::{RuntimeAPIVersion, RuntimeInterfaceFactory};
+use crate::simulator::{SimulatorAPIVersion, SimulatorInterfaceFactory};
+use crate::utils::has_entry_point;
+
+#[cfg(test)]
+mod tests;
+
+/// The kinds of plugin that selene loads.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginKind {
+    Runtime,
+    ErrorModel,
+    Simulator,
+    EventHook,
+}
+
+impl PluginKind {
+    pub const ALL: [Self; 4] = [
+        Self::Runtime,
+        Self::ErrorModel,
+        Self::Simulator,
+        Self::EventHook,
+    ];
+
+    /// The prefix of the plugin's entry points.
+    fn symbol_prefix(self) -> &'static str {
+        match self {
+            Self::Runtime => "selene_runtime",
+            Self::ErrorModel => "selene_error_model",
+            Self::Simulator => "selene_simulator",
+            Self::EventHook => "selene_event_hook",
+        }
+    }
+
+    fn symbol(self, name: &str) -> Vec<u8> {
+        format!("{}_{name}", self.symbol_prefix()).into_bytes()
+    }
+}
+
+impl fmt::Display for PluginKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Runtime => "runtime",
+            Self::ErrorModel => "error model",
+            Self::Simulator => "simulator",
+            Self::EventHook => "event hook",
+        })
+    }
+}
+
+/// An argument accepted by a plugin.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ArgumentMetadata {
+    pub name: String,
+    /// The long flag of the argument, without the leading `--`.
+    pub long: Option<String>,
+    /// The short flag of the argument, without the leading `-`.
+    pub short: Option<char>,
+    pub help: Option<String>,
+    pub required: bool,
+    /// Whether the argument is given by position rather than by flag.
+    pub positional: bool,
+    /// Whether the argument takes a value, rather than being a switch.
+    pub takes_value: bool,
+    /// Whether the argument may be given more than once, or with several
+    /// values.
+    pub multiple: bool,
+    pub default: Vec<String>,
+    /// The values the argument is restricted to, if any.
+    pub possible_values: Vec<String>,
+}
+
+#[cfg(feature = "clap")]
+impl ArgumentMetadata {
+    /// Describe the arguments of a clap parser, such as the `Params` that
+    /// plugins parse their arguments into.
+    pub fn from_clap<P: clap::CommandFactory>() -> Vec<Self> {
+        P::command()
+            .get_arguments()
+            .map(|arg| {
+                let action = arg.get_action();
+                Self {
+                    name: arg.get_id().to_string(),
+                    long: arg.get_long().map(str::to_string),
+                    short: arg.get_short(),
+                    help: arg.get_help().map(|help| help.to_string()),
+                    required: arg.is_required_set(),
+                    positional: arg.is_positional(),
+                    takes_value: action.takes_values(),
+                    multiple: matches!(action, clap::ArgAction::Append | clap::ArgAction::Count)
+                        || arg
+                            .get_num_args()
+                            .is_some_and(|range| range.max_values() > 1),
+                    default: arg
+                        .get_default_values()
+                        .iter()
+                        .map(|value| value.to_string_lossy().into_owned())
+                        .collect(),
+                    possible_values: arg
+                        .get_possible_values()
+                        .iter()
+                        .map(|value| value.get_name().to_string())
+                        .collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// The description of a plugin returned by its `selene_*_get_metadata` entry
+/// point.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PluginMetadata {
+    pub kind: PluginKind,
+    /// The name the plugin is known by, which for the plugins shipped with
+    /// selene is the name it is registered under when linked in (see
+    /// [crate::registry]).
+    pub name: String,
+    /// The cargo package the plugin was built from, if known.
+    pub package: Option<String>,
+    /// The version of the plugin, if known.
+    pub version: Option<String>,
+    /// The version of the plugin API that the plugin was built against.
+    pub api_version: String,
+    pub capabilities: Vec<String>,
+    /// The arguments accepted by the plugin, if it describes them.
+    pub args: Option<Vec<ArgumentMetadata>>,
+}
+
+impl PluginMetadata {
+    pub fn new(
+        kind: PluginKind,
+        name: &str,
+        package: &str,
+        version: &str,
+        api_version: impl fmt::Display,
+        capabilities: Capabilities,
+        args: Option<Vec<ArgumentMetadata>>,
+    ) -> Self {
+        Self {
+            kind,
+            name: name.to_string(),
+            package: Some(package.to_string()),
+            version: Some(version.to_string()),
+            api_version: api_version.to_string(),
+            capabilities: capabilities
+                .names()
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            args,
+        }
+    }
+
+    /// The metadata as a JSON string, as returned by `selene_*_get_metadata`.
+    pub fn to_c_string(&self) -> CString {
+        let json = serde_json::to_string(self).expect("plugin metadata is serializable");
+        // JSON escapes control characters, so contains no interior nul bytes.
+        CString::new(json).expect("JSON contains no nul bytes")
+    }
+}
+
+/// Read the metadata of each plugin exported by the library at the given
+/// path. A library exports at most one plugin of each kind.
+pub fn load_metadata(plugin_file: impl AsRef<OsStr>) -> Result<Vec<PluginMetadata>> {
+    let plugin_file = plugin_file.as_ref();
+    let lib = unsafe { libloading::Library::new(plugin_file) }.map_err(|e| {
+        anyhow!(
+            "Failed to load plugin: {}. Error: {}",
+            plugin_file.to_string_lossy(),
+            e
+        )
+    })?;
+    let mut metadata = Vec::new();
+    for kind in PluginKind::ALL {
+        if !has_entry_point(&lib, &kind.symbol("get_api_version")) {
+            continue;
+        }
+        let exported = unsafe {
+            lib.get::<unsafe extern "C" fn() -> *const c_char>(&kind.symbol("get_metadata"))
+                .ok()
+                .map(|func| CStr::from_ptr(func()).to_string_lossy().into_owned())
+        };
+        metadata.push(match exported {
+            Some(json) => serde_json::from_str(&json)
+                .with_context(|| format!("Invalid metadata for {kind} plugin"))?,
+            None => infer_metadata(&lib, kind, plugin_file)?,
+        });
+    }
+    if metadata.is_empty() {
+        bail!(
+            "{} does not export a selene plugin",
+            plugin_file.to_string_lossy()
+        );
+    }
+    Ok(metadata)
+}
+
+/// Describe a plugin that predates the metadata entry point.
+fn infer_metadata(
+    lib: &libloading::Library,
+    kind: PluginKind,
+    plugin_file: &OsStr,
+) -> Result<PluginMetadata> {
+    let api_version =
+        unsafe { lib.get::<unsafe extern "C" fn() -> u64>(&kind.symbol("get_api_version"))?() };
+    let (api_version, capabilities) = match kind {
+        PluginKind::Runtime => (
+            RuntimeAPIVersion::from(api_version).to_string(),
+            crate::runtime::plugin::RuntimePluginInterface::new_from_file(plugin_file)?
+                .capabilities(),
+        ),
+        PluginKind::ErrorModel => (
+            ErrorModelAPIVersion::from(api_version).to_string(),
+            crate::error_model::plugin::ErrorModelPluginInterface::new_from_file(plugin_file)?
+                .capabilities(),
+        ),
+        PluginKind::Simulator => (
+            SimulatorAPIVersion::from(api_version).to_string(),
+            crate::simulator::plugin::SimulatorPluginInterface::new_from_file(plugin_file)?
+                .capabilities(),
+        ),
+        PluginKind::EventHook => (
+            EventHookAPIVersion::from(api_version).to_string(),
+            crate::event_hook::plugin::EventHookPluginInterface::new_from_file(plugin_file)?
+                .capabilities(),
+        ),
+    };
+    let name = Path::new(plugin_file)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    Ok(PluginMetadata {
+        kind,
+        name: name.strip_prefix("lib").unwrap_or(&name).to_string(),
+        package: None,
+        version: None,
+        api_version,
+        capabilities: capabilities
+            .names()
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+        args: None,
+    })
+}
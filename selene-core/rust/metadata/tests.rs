@@ -0,0 +1,203 @@
+use super::*;
+use crate::simulator::version::CURRENT_API_VERSION;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// A directory for the plugin libraries built by these tests.
+fn scratch_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("selene-core-tests-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Build a simulator plugin library with the given API version, exporting
+/// `selene_simulator_get_metadata` if metadata is given. Selene only looks up
+/// entry points when describing a plugin, so they do nothing.
+fn build_simulator(name: &str, api_version: u64, metadata: Option<&str>) -> PathBuf {
+    let mut source = format!(
+        "#include <stdint.h>\n\
+         uint64_t selene_simulator_get_api_version(void) {{ return {api_version}ULL; }}\n"
+    );
+    for entry_point in [
+        "init",
+        "shot_start",
+        "shot_end",
+        "operation_rxy",
+        "operation_rz",
+        "operation_rzz",
+        "operation_measure",
+        "operation_postselect",
+        "operation_reset",
+        "dump_state",
+    ] {
+        source += &format!("int selene_simulator_{entry_point}(void) {{ return 0; }}\n");
+    }
+    if let Some(metadata) = metadata {
+        let escaped = metadata.replace('\\', "\\\\").replace('"', "\\\"");
+        source += &format!(
+            "const char *selene_simulator_get_metadata(void) {{ return \"{escaped}\"; }}\n"
+        );
+    }
+    let dir = scratch_dir();
+    let source_file = dir.join(format!("{name}.c"));
+    let library = dir.join(format!("lib{name}.so"));
+    std::fs::write(&source_file, source).unwrap();
+    let compiler = std::env::var_os("CC").unwrap_or_else(|| "cc".into());
+    let status = Command::new(compiler)
+        .args(["-shared", "-fPIC", "-o"])
+        .arg(&library)
+        .arg(&source_file)
+        .status()
+        .expect("a C compiler is required to build test plugins");
+    assert!(status.success());
+    library
+}
+
+fn exported_metadata() -> PluginMetadata {
+    PluginMetadata::new(
+        PluginKind::Simulator,
+        "exported",
+        "selene-simulator-exported",
+        "1.2.3",
+        CURRENT_API_VERSION,
+        Capabilities::POSTSELECT | Capabilities::METRICS,
+        Some(vec![ArgumentMetadata {
+            name: "bias".to_string(),
+            long: Some("bias".to_string()),
+            help: Some("The \"probability\" of measuring 1".to_string()),
+            takes_value: true,
+            default: vec!["0.5".to_string()],
+            ..Default::default()
+        }]),
+    )
+}
+
+#[cfg(unix)]
+#[test]
+fn exported_metadata_is_read() {
+    let metadata = exported_metadata();
+    let json = serde_json::to_string(&metadata).unwrap();
+    let library = build_simulator("exported", CURRENT_API_VERSION.into(), Some(&json));
+    assert_eq!(load_metadata(&library).unwrap(), vec![metadata]);
+}
+
+#[cfg(unix)]
+#[test]
+fn metadata_is_inferred_without_the_entry_point() {
+    // Version 0.1.0 predates the capabilities entry point, so capabilities
+    // are inferred from the entry points present.
+    let api_version = SimulatorAPIVersion::from(0x00_00_01_00);
+    let library = build_simulator("inferred", api_version.into(), None);
+    assert_eq!(
+        load_metadata(&library).unwrap(),
+        vec![PluginMetadata {
+            kind: PluginKind::Simulator,
+            name: "inferred".to_string(),
+            package: None,
+            version: None,
+            api_version: "0.1.0".to_string(),
+            capabilities: vec!["postselect".to_string(), "dump_state".to_string()],
+            args: None,
+        }]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn invalid_metadata_is_rejected() {
+    let library = build_simulator("invalid", CURRENT_API_VERSION.into(), Some("{\"kind\": 3}"));
+    let error = load_metadata(&library).unwrap_err();
+    assert_eq!(error.to_string(), "Invalid metadata for simulator plugin");
+}
+
+#[test]
+fn missing_libraries_are_reported() {
+    let error = load_metadata(scratch_dir().join("libmissing.so")).unwrap_err();
+    assert!(error.to_string().starts_with("Failed to load plugin"));
+}
+
+#[test]
+fn metadata_roundtrip() {
+    let metadata = exported_metadata();
+    let json = metadata.to_c_string().into_string().unwrap();
+    let parsed: PluginMetadata = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed, metadata);
+    assert_eq!(parsed.api_version, CURRENT_API_VERSION.to_string());
+    assert_eq!(parsed.capabilities, vec!["postselect", "metrics"]);
+
+    // Metadata from before the package was reported is still accepted.
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    value.as_object_mut().unwrap().remove("package");
+    let parsed: PluginMetadata = serde_json::from_value(value).unwrap();
+    assert_eq!(parsed.package, None);
+}
+
+#[cfg(feature = "clap")]
+mod clap_arguments {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    #[allow(dead_code)]
+    struct Params {
+        /// The probability of an error.
+        #[arg(long, default_value_t = 0.5)]
+        p: f64,
+        #[arg(short, long)]
+        verbose: bool,
+        #[arg(long, value_parser = ["fast", "exact"])]
+        mode: String,
+        #[arg(long, num_args = 1..)]
+        qubits: Vec<u64>,
+        seeds: Vec<u64>,
+    }
+
+    fn argument(arguments: &[ArgumentMetadata], name: &str) -> ArgumentMetadata {
+        arguments
+            .iter()
+            .find(|argument| argument.name == name)
+            .unwrap_or_else(|| panic!("no argument {name}"))
+            .clone()
+    }
+
+    #[test]
+    fn from_clap() {
+        let arguments = ArgumentMetadata::from_clap::<Params>();
+        assert_eq!(
+            argument(&arguments, "p"),
+            ArgumentMetadata {
+                name: "p".to_string(),
+                long: Some("p".to_string()),
+                help: Some("The probability of an error".to_string()),
+                takes_value: true,
+                default: vec!["0.5".to_string()],
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            argument(&arguments, "verbose"),
+            ArgumentMetadata {
+                name: "verbose".to_string(),
+                long: Some("verbose".to_string()),
+                short: Some('v'),
+                possible_values: vec!["true".to_string(), "false".to_string()],
+                ..Default::default()
+            }
+        );
+        assert_eq!(
+            argument(&arguments, "mode"),
+            ArgumentMetadata {
+                name: "mode".to_string(),
+                long: Some("mode".to_string()),
+                required: true,
+                takes_value: true,
+                possible_values: vec!["fast".to_string(), "exact".to_string()],
+                ..Default::default()
+            }
+        );
+        let qubits = argument(&arguments, "qubits");
+        assert!(qubits.takes_value && qubits.multiple && !qubits.required);
+        let seeds = argument(&arguments, "seeds");
+        assert!(seeds.positional && seeds.multiple && seeds.long.is_none());
+    }
+}
@@ -0,0 +1,213 @@
+//! Plugins registered within the process, rather than loaded from a file.
+//!
+//! Any [SimulatorInterfaceFactory], [ErrorModelInterfaceFactory],
+//! [RuntimeInterfaceFactory] or [EventHookInterfaceFactory] can be registered under a name, after which it is
+//! used in place of a plugin file wherever the path `builtin:<name>` is given
+//! (see [builtin_path]). Registered plugins are called directly, so avoid both
+//! the dynamic loading of a plugin library and the overhead of its C
+//! interface.
+//!
+//! The registry belongs to the copy of `selene-core` it is accessed through.
+//! An error model loaded from a plugin file has its own copy, so can only use
+//! simulators loaded from files, whereas a registered error model can use
+//! registered simulators.
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::sync::{Arc, LazyLock, RwLock};
+
+use anyhow::Result;
+
+use crate::capabilities::Capabilities;
+use crate::error::ErrorKind;
+use crate::error_model::{ErrorModel, ErrorModelInterfaceFactory};
+use crate::event_hook::{EventHook, EventHookInterfaceFactory};
+use crate::runtime::{Runtime, RuntimeInterfaceFactory};
+use crate::simulator::{Simulator, SimulatorInterfaceFactory};
+use crate::time::Instant;
+
+#[cfg(test)]
+mod tests;
+
+/// The prefix of plugin paths that refer to registered plugins.
+pub const BUILTIN_PREFIX: &str = "builtin:";
+
+type SimulatorConstructor = dyn Fn(u64, &[String]) -> Result<Simulator> + Send + Sync;
+type ErrorModelConstructor =
+    dyn Fn(u64, &[String], &OsStr, &[String]) -> Result<ErrorModel> + Send + Sync;
+type RuntimeConstructor = dyn Fn(u64, Instant, &[String]) -> Result<Runtime> + Send + Sync;
+type EventHookConstructor = dyn Fn(u64, &[String]) -> Result<EventHook> + Send + Sync;
+
+#[derive(Default)]
+struct Registry {
+    simulators: BTreeMap<String, Arc<SimulatorConstructor>>,
+    simulator_capabilities: BTreeMap<String, Capabilities>,
+    error_models: BTreeMap<String, Arc<ErrorModelConstructor>>,
+    runtimes: BTreeMap<String, Arc<RuntimeConstructor>>,
+    event_hooks: BTreeMap<String, Arc<EventHookConstructor>>,
+}
+
+static REGISTRY: LazyLock<RwLock<Registry>> = LazyLock::new(Default::default);
+
+fn read_registry() -> std::sync::RwLockReadGuard<'static, Registry> {
+    // Registration cannot leave the registry in an inconsistent state, so a
+    // panic while holding the lock can be ignored.
+    REGISTRY.read().unwrap_or_else(|e| e.into_inner())
+}
+
+fn write_registry() -> std::sync::RwLockWriteGuard<'static, Registry> {
+    REGISTRY.write().unwrap_or_else(|e| e.into_inner())
+}
+
+/// The path that refers to the plugin registered under the given name.
+pub fn builtin_path(name: &str) -> String {
+    format!("{BUILTIN_PREFIX}{name}")
+}
+
+/// The name of the registered plugin that a plugin path refers to, if any.
+pub fn builtin_name(path: &OsStr) -> Option<&str> {
+    path.to_str()?.strip_prefix(BUILTIN_PREFIX)
+}
+
+fn not_registered(kind: &str, name: &str) -> anyhow::Error {
+    ErrorKind::InvalidArgument.error(format!("No {kind} is registered as '{name}'"))
+}
+
+/// Register a simulator under the given name, replacing any simulator
+/// previously registered under it.
+pub fn register_simulator<F>(name: impl Into<String>, factory: Arc<F>)
+where
+    F: SimulatorInterfaceFactory + Send + Sync + 'static,
+{
+    let name = name.into();
+    let capabilities = factory.capabilities();
+    let constructor =
+        move |n_qubits: u64, args: &[String]| Simulator::new(factory.clone(), n_qubits, args);
+    let mut registry = write_registry();
+    registry
+        .simulator_capabilities
+        .insert(name.clone(), capabilities);
+    registry.simulators.insert(name, Arc::new(constructor));
+}
+
+/// Register an error model under the given name, replacing any error model
+/// previously registered under it.
+pub fn register_error_model<F>(name: impl Into<String>, factory: Arc<F>)
+where
+    F: ErrorModelInterfaceFactory + Send + Sync + 'static,
+{
+    let constructor = move |n_qubits: u64,
+                            error_model_args: &[String],
+                            simulator_path: &OsStr,
+                            simulator_args: &[String]| {
+        ErrorModel::new(
+            factory.clone(),
+            n_qubits,
+            error_model_args,
+            &simulator_path,
+            simulator_args,
+        )
+    };
+    write_registry()
+        .error_models
+        .insert(name.into(), Arc::new(constructor));
+}
+
+/// Register a runtime under the given name, replacing any runtime previously
+/// registered under it.
+pub fn register_runtime<F>(name: impl Into<String>, factory: Arc<F>)
+where
+    F: RuntimeInterfaceFactory + Send + Sync + 'static,
+{
+    let constructor = move |n_qubits: u64, start: Instant, args: &[String]| {
+        Runtime::new(factory.clone(), n_qubits, start, args)
+    };
+    write_registry()
+        .runtimes
+        .insert(name.into(), Arc::new(constructor));
+}
+
+/// Register an event hook under the given name, replacing any event hook
+/// previously registered under it.
+pub fn register_event_hook<F>(name: impl Into<String>, factory: Arc<F>)
+where
+    F: EventHookInterfaceFactory + Send + Sync + 'static,
+{
+    let constructor =
+        move |n_qubits: u64, args: &[String]| EventHook::new(factory.clone(), n_qubits, args);
+    write_registry()
+        .event_hooks
+        .insert(name.into(), Arc::new(constructor));
+}
+
+/// The arguments as passed to a factory by a plugin library, which prepends a
+/// program name so that they can be parsed as command line arguments.
+fn plugin_args(args: &[impl AsRef<str>]) -> Vec<String> {
+    let mut plugin_args = vec!["lib".to_string()];
+    plugin_args.extend(args.iter().map(|arg| arg.as_ref().to_string()));
+    plugin_args
+}
+
+fn owned_args(args: &[impl AsRef<str>]) -> Vec<String> {
+    args.iter().map(|arg| arg.as_ref().to_string()).collect()
+}
+
+pub(crate) fn new_simulator(
+    name: &str,
+    n_qubits: u64,
+    args: &[impl AsRef<str>],
+) -> Result<Simulator> {
+    // The lock is released before construction, in case the plugin itself
+    // accesses the registry.
+    let constructor = read_registry().simulators.get(name).cloned();
+    let constructor = constructor.ok_or_else(|| not_registered("simulator", name))?;
+    constructor(n_qubits, &plugin_args(args))
+}
+
+/// The capabilities of the simulator registered under the given name, which
+/// are needed without constructing it, as simulators are constructed by the
+/// error model.
+pub(crate) fn simulator_capabilities(name: &str) -> Result<Capabilities> {
+    read_registry()
+        .simulator_capabilities
+        .get(name)
+        .copied()
+        .ok_or_else(|| not_registered("simulator", name))
+}
+
+pub(crate) fn new_error_model(
+    name: &str,
+    n_qubits: u64,
+    error_model_args: &[impl AsRef<str>],
+    simulator_path: &OsStr,
+    simulator_args: &[impl AsRef<str>],
+) -> Result<ErrorModel> {
+    let constructor = read_registry().error_models.get(name).cloned();
+    let constructor = constructor.ok_or_else(|| not_registered("error model", name))?;
+    constructor(
+        n_qubits,
+        &plugin_args(error_model_args),
+        simulator_path,
+        &owned_args(simulator_args),
+    )
+}
+
+pub(crate) fn new_runtime(
+    name: &str,
+    n_qubits: u64,
+    start: Instant,
+    args: &[impl AsRef<str>],
+) -> Result<Runtime> {
+    let constructor = read_registry().runtimes.get(name).cloned();
+    let constructor = constructor.ok_or_else(|| not_registered("runtime", name))?;
+    constructor(n_qubits, start, &plugin_args(args))
+}
+
+pub(crate) fn new_event_hook(
+    name: &str,
+    n_qubits: u64,
+    args: &[impl AsRef<str>],
+) -> Result<EventHook> {
+    let constructor = read_registry().event_hooks.get(name).cloned();
+    let constructor = constructor.ok_or_else(|| not_registered("event hook", name))?;
+    constructor(n_qubits, &plugin_args(args))
+}
@@ -0,0 +1,330 @@
+use super::*;
+use crate::error_model::{BatchResult, ErrorModelInterface};
+use crate::runtime::{BatchOperation, RuntimeInterface};
+use crate::simulator::SimulatorInterface;
+use crate::utils::MetricValue;
+use std::sync::Mutex;
+
+type Calls = Arc<Mutex<Vec<String>>>;
+
+fn record(calls: &Calls, plugin: &str, args: &[impl AsRef<str>]) {
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_ref()).collect();
+    calls
+        .lock()
+        .unwrap()
+        .push(format!("{plugin} {}", args.join(" ")));
+}
+
+fn recorded(calls: &Calls) -> Vec<String> {
+    calls.lock().unwrap().clone()
+}
+
+/// A plugin that does nothing, created by the factories below.
+struct Inert;
+
+impl SimulatorInterface for Inert {
+    fn exit(&mut self) -> Result<()> {
+        Ok(())
+    }
+    fn shot_start(&mut self, _shot_id: u64, _seed: u64) -> Result<()> {
+        Ok(())
+    }
+    fn shot_end(&mut self) -> Result<()> {
+        Ok(())
+    }
+    fn rz(&mut self, _qubit: u64, _theta: f64) -> Result<()> {
+        Ok(())
+    }
+    fn rxy(&mut self, _qubit: u64, _theta: f64, _phi: f64) -> Result<()> {
+        Ok(())
+    }
+    fn rzz(&mut self, _qubit1: u64, _qubit2: u64, _theta: f64) -> Result<()> {
+        Ok(())
+    }
+    fn measure(&mut self, _qubit: u64) -> Result<bool> {
+        Ok(false)
+    }
+    fn reset(&mut self, _qubit: u64) -> Result<()> {
+        Ok(())
+    }
+    fn get_metric(&mut self, _nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        Ok(None)
+    }
+}
+
+/// An error model holding the simulator it loaded.
+struct InertErrorModel(#[allow(dead_code)] Simulator);
+
+impl ErrorModelInterface for InertErrorModel {
+    fn exit(&mut self) -> Result<()> {
+        Ok(())
+    }
+    fn shot_start(&mut self, _shot_id: u64, _seed: u64, _simulator_seed: u64) -> Result<()> {
+        Ok(())
+    }
+    fn shot_end(&mut self) -> Result<()> {
+        Ok(())
+    }
+    fn handle_operations(&mut self, _operations: BatchOperation) -> Result<BatchResult> {
+        Ok(BatchResult::default())
+    }
+    fn get_metric(&mut self, _nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        Ok(None)
+    }
+    fn get_simulator_metric(&mut self, _nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        Ok(None)
+    }
+}
+
+impl RuntimeInterface for Inert {
+    fn exit(&mut self) -> Result<()> {
+        Ok(())
+    }
+    fn get_next_operations(&mut self) -> Result<Option<BatchOperation>> {
+        Ok(None)
+    }
+    fn shot_start(&mut self, _shot_id: u64, _seed: u64) -> Result<()> {
+        Ok(())
+    }
+    fn shot_end(&mut self) -> Result<()> {
+        Ok(())
+    }
+    fn get_metric(&mut self, _nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        Ok(None)
+    }
+    fn qalloc(&mut self) -> Result<u64> {
+        Ok(0)
+    }
+    fn qfree(&mut self, _qubit_id: u64) -> Result<()> {
+        Ok(())
+    }
+    fn rxy_gate(&mut self, _qubit_id: u64, _theta: f64, _phi: f64) -> Result<()> {
+        Ok(())
+    }
+    fn rzz_gate(&mut self, _qubit_id_1: u64, _qubit_id_2: u64, _theta: f64) -> Result<()> {
+        Ok(())
+    }
+    fn rz_gate(&mut self, _qubit_id: u64, _theta: f64) -> Result<()> {
+        Ok(())
+    }
+    fn measure(&mut self, _qubit_id: u64) -> Result<u64> {
+        Ok(0)
+    }
+    fn measure_leaked(&mut self, _qubit_id: u64) -> Result<u64> {
+        Ok(0)
+    }
+    fn reset(&mut self, _qubit_id: u64) -> Result<()> {
+        Ok(())
+    }
+    fn force_result(&mut self, _result_id: u64) -> Result<()> {
+        Ok(())
+    }
+    fn get_bool_result(&mut self, _result_id: u64) -> Result<Option<bool>> {
+        Ok(None)
+    }
+    fn get_u64_result(&mut self, _result_id: u64) -> Result<Option<u64>> {
+        Ok(None)
+    }
+    fn set_bool_result(&mut self, _result_id: u64, _result: bool) -> Result<()> {
+        Ok(())
+    }
+    fn set_u64_result(&mut self, _result_id: u64, _result: u64) -> Result<()> {
+        Ok(())
+    }
+    fn increment_future_refcount(&mut self, _future: u64) -> Result<()> {
+        Ok(())
+    }
+    fn decrement_future_refcount(&mut self, _future: u64) -> Result<()> {
+        Ok(())
+    }
+    fn local_barrier(&mut self, _qubits: &[u64], _sleep_ns: u64) -> Result<()> {
+        Ok(())
+    }
+    fn global_barrier(&mut self, _sleep_ns: u64) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct SimulatorFactory(Calls);
+
+impl SimulatorInterfaceFactory for SimulatorFactory {
+    type Interface = Inert;
+
+    fn init(
+        self: Arc<Self>,
+        n_qubits: u64,
+        args: &[impl AsRef<str>],
+    ) -> Result<Box<Self::Interface>> {
+        record(&self.0, &format!("simulator {n_qubits}"), args);
+        Ok(Box::new(Inert))
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::POSTSELECT
+    }
+}
+
+struct ErrorModelFactory(Calls);
+
+impl ErrorModelInterfaceFactory for ErrorModelFactory {
+    type Interface = InertErrorModel;
+
+    fn init(
+        self: Arc<Self>,
+        n_qubits: u64,
+        error_model_args: &[impl AsRef<str>],
+        simulator_plugin: &impl AsRef<OsStr>,
+        simulator_args: &[impl AsRef<str>],
+    ) -> Result<Box<Self::Interface>> {
+        record(
+            &self.0,
+            &format!("error_model {n_qubits}"),
+            error_model_args,
+        );
+        let simulator = Simulator::load_from_file(simulator_plugin, n_qubits, simulator_args)?;
+        Ok(Box::new(InertErrorModel(simulator)))
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::METRICS
+    }
+}
+
+struct RuntimeFactory(Calls);
+
+impl RuntimeInterfaceFactory for RuntimeFactory {
+    type Interface = Inert;
+
+    fn init(
+        self: Arc<Self>,
+        n_qubits: u64,
+        _start: Instant,
+        args: &[impl AsRef<str>],
+    ) -> Result<Box<Self::Interface>> {
+        record(&self.0, &format!("runtime {n_qubits}"), args);
+        Ok(Box::new(Inert))
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::BATCHING
+    }
+}
+
+#[test]
+fn builtin_paths() {
+    assert_eq!(builtin_path("ideal"), "builtin:ideal");
+    assert_eq!(builtin_name(OsStr::new("builtin:ideal")), Some("ideal"));
+    assert_eq!(builtin_name(OsStr::new("builtin:")), Some(""));
+    assert_eq!(builtin_name(OsStr::new("/lib/libideal.so")), None);
+    assert_eq!(builtin_name(OsStr::new("isolated:builtin:ideal")), None);
+}
+
+#[test]
+fn load_registered_simulator() {
+    let calls = Calls::default();
+    register_simulator(
+        "registry_tests_simulator",
+        Arc::new(SimulatorFactory(calls.clone())),
+    );
+    let path = builtin_path("registry_tests_simulator");
+    assert_eq!(
+        Simulator::capabilities_of(&path).unwrap(),
+        Capabilities::POSTSELECT
+    );
+    // Finding the capabilities does not construct the simulator.
+    assert!(recorded(&calls).is_empty());
+
+    let simulator = Simulator::load_from_file(&path, 3, &["--a", "1"]).unwrap();
+    assert_eq!(simulator.capabilities(), Capabilities::POSTSELECT);
+    assert_eq!(recorded(&calls), vec!["simulator 3 lib --a 1"]);
+}
+
+#[test]
+fn load_registered_error_model() {
+    let calls = Calls::default();
+    register_simulator(
+        "registry_tests_error_model_simulator",
+        Arc::new(SimulatorFactory(calls.clone())),
+    );
+    register_error_model(
+        "registry_tests_error_model",
+        Arc::new(ErrorModelFactory(calls.clone())),
+    );
+    let error_model = ErrorModel::load_from_file(
+        &builtin_path("registry_tests_error_model"),
+        2,
+        &["--p", "0.5"],
+        &builtin_path("registry_tests_error_model_simulator"),
+        &["--seed"],
+    )
+    .unwrap();
+    assert_eq!(error_model.capabilities(), Capabilities::METRICS);
+    // Both the error model and the simulator it loads receive a program name
+    // before their arguments, as they would from a plugin library.
+    assert_eq!(
+        recorded(&calls),
+        vec!["error_model 2 lib --p 0.5", "simulator 2 lib --seed"]
+    );
+}
+
+#[test]
+fn load_registered_runtime() {
+    let calls = Calls::default();
+    register_runtime(
+        "registry_tests_runtime",
+        Arc::new(RuntimeFactory(calls.clone())),
+    );
+    let runtime = Runtime::load_from_file(
+        &builtin_path("registry_tests_runtime"),
+        4,
+        Instant::from(0),
+        &[] as &[&str],
+    )
+    .unwrap();
+    assert_eq!(runtime.capabilities(), Capabilities::BATCHING);
+    assert_eq!(recorded(&calls), vec!["runtime 4 lib"]);
+}
+
+#[test]
+fn registering_again_replaces() {
+    let first = Calls::default();
+    let second = Calls::default();
+    register_runtime(
+        "registry_tests_replaced",
+        Arc::new(RuntimeFactory(first.clone())),
+    );
+    register_runtime(
+        "registry_tests_replaced",
+        Arc::new(RuntimeFactory(second.clone())),
+    );
+    let path = builtin_path("registry_tests_replaced");
+    Runtime::load_from_file(&path, 1, Instant::from(0), &["x"]).unwrap();
+    assert!(recorded(&first).is_empty());
+    assert_eq!(recorded(&second), vec!["runtime 1 lib x"]);
+}
+
+#[test]
+fn unregistered_plugins() {
+    let path = builtin_path("registry_tests_unregistered");
+    let no_args: &[&str] = &[];
+    let errors = [
+        Simulator::load_from_file(&path, 1, no_args).err().unwrap(),
+        Simulator::capabilities_of(&path).unwrap_err(),
+        ErrorModel::load_from_file(&path, 1, no_args, &path, no_args)
+            .err()
+            .unwrap(),
+        Runtime::load_from_file(&path, 1, Instant::from(0), no_args)
+            .err()
+            .unwrap(),
+        EventHook::load_from_file(&path, 1, no_args).err().unwrap(),
+    ];
+    for error in errors {
+        assert_eq!(ErrorKind::of(&error), ErrorKind::InvalidArgument);
+        assert!(
+            error
+                .to_string()
+                .ends_with("is registered as 'registry_tests_unregistered'"),
+            "{error}"
+        );
+    }
+}
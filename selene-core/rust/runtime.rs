@@ -1,15 +1,21 @@
+pub mod allocator;
 pub mod helper;
 pub mod interface;
 pub mod plugin;
 pub mod version;
+pub mod wrapper;
 
-use std::{iter, sync};
+use std::{ffi::OsStr, iter, sync};
+
+use crate::capabilities::Capabilities;
+use crate::error::ErrorKind;
 
 pub use interface::{RuntimeInterface, RuntimeInterfaceFactory};
 pub use version::RuntimeAPIVersion;
 
 use anyhow::Result;
 use delegate::delegate;
+use serde::{Deserialize, Serialize};
 
 /// We assume that operations of the same type can be done in parallel.
 /// The level of parallelism is decided by the runtime - i.e. it can
@@ -21,7 +27,11 @@ use delegate::delegate;
 /// that simulation of the operations itself is done in parallel; in fact,
 /// the interface is currently limited to individual operations, but this
 /// may change in future if it is found to be beneficial for performance.
-#[derive(Debug, Clone, PartialEq)]
+///
+/// Angles are serialised as the bits of their IEEE 754 representation, so
+/// that they are read back exactly, even from formats such as JSON that
+/// round them or cannot represent NaNs and infinities.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Operation {
     Measure {
         qubit_id: u64,
@@ -32,16 +42,20 @@ pub enum Operation {
     },
     RXYGate {
         qubit_id: u64,
+        #[serde(with = "angle_bits")]
         theta: f64,
+        #[serde(with = "angle_bits")]
         phi: f64,
     },
     RZGate {
         qubit_id: u64,
+        #[serde(with = "angle_bits")]
         theta: f64,
     },
     RZZGate {
         qubit_id_1: u64,
         qubit_id_2: u64,
+        #[serde(with = "angle_bits")]
         theta: f64,
     },
     Custom {
@@ -52,9 +66,27 @@ pub enum Operation {
         qubit_id: u64,
         result_id: u64,
     },
+    /// A measurement followed by a reset of the qubit to |0>, performed as
+    /// a single primitive.
+    MeasureReset {
+        qubit_id: u64,
+        result_id: u64,
+    },
 }
 
-#[derive(Default, Clone, Debug)]
+mod angle_bits {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(angle: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(angle.to_bits())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        u64::deserialize(deserializer).map(f64::from_bits)
+    }
+}
+
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
 pub struct BatchOperation {
     ops: Vec<Operation>,
     start: crate::time::Instant,
@@ -83,6 +115,33 @@ impl BatchOperation {
         self.duration
     }
 
+    /// The batch with each [Operation::MeasureReset] replaced by the
+    /// measurement and reset it fuses, for plugins that predate it.
+    pub fn split_measure_reset(self) -> Self {
+        let ops = self
+            .ops
+            .into_iter()
+            .flat_map(|op| match op {
+                Operation::MeasureReset {
+                    qubit_id,
+                    result_id,
+                } => vec![
+                    Operation::Measure {
+                        qubit_id,
+                        result_id,
+                    },
+                    Operation::Reset { qubit_id },
+                ],
+                op => vec![op],
+            })
+            .collect();
+        Self {
+            ops,
+            start: self.start,
+            duration: self.duration,
+        }
+    }
+
     pub fn new(
         ops: Vec<Operation>,
         start: crate::time::Instant,
@@ -114,7 +173,7 @@ impl IntoIterator for BatchOperation {
 /// Usually the wrapped [RuntimeInterface] will be an instance
 /// delegating to an external runtime plugin as defined by
 /// [plugin::RuntimeInterfacePlugin)].
-pub struct Runtime(Box<dyn RuntimeInterface>);
+pub struct Runtime(Box<dyn RuntimeInterface>, Capabilities);
 
 impl Runtime {
     /// Constructs a new Runtime from a [RuntimeInterfaceFactory].
@@ -124,7 +183,35 @@ impl Runtime {
         start: crate::time::Instant,
         args: &[impl AsRef<str>],
     ) -> Result<Self> {
-        Ok(Self(factory.init(n_qubits, start, args)?))
+        let capabilities = factory.capabilities();
+        let mut instance = factory.init(n_qubits, start, args)?;
+        let capabilities = instance.instance_capabilities()?.unwrap_or(capabilities);
+        Ok(Self(instance, capabilities))
+    }
+
+    /// Loads the runtime plugin at the given path, or the registered runtime
+    /// it refers to (see [crate::registry]).
+    pub fn load_from_file(
+        plugin_path: &impl AsRef<OsStr>,
+        n_qubits: u64,
+        start: crate::time::Instant,
+        args: &[impl AsRef<str>],
+    ) -> Result<Self> {
+        if let Some(name) = crate::registry::builtin_name(plugin_path.as_ref()) {
+            return crate::registry::new_runtime(name, n_qubits, start, args);
+        }
+        if crate::host::isolated_plugin(plugin_path.as_ref()).is_some() {
+            return Err(ErrorKind::Unsupported.error("Runtimes cannot be hosted out of process"));
+        }
+        let plugin = plugin::RuntimePluginInterface::new_from_file(plugin_path)?;
+        Self::new(plugin, n_qubits, start, args)
+    }
+}
+
+impl Runtime {
+    /// The optional features supported by the runtime.
+    pub fn capabilities(&self) -> Capabilities {
+        self.1
     }
 }
 
@@ -157,6 +244,7 @@ impl RuntimeInterface for Runtime {
             fn rz_gate(&mut self, qubit_id: u64, theta: f64) -> Result<()>;
             fn measure(&mut self, qubit_id: u64) -> Result<u64>;
             fn measure_leaked(&mut self, qubit_id: u64) -> Result<u64>;
+            fn measure_reset(&mut self, qubit_id: u64) -> Result<u64>;
             fn reset(&mut self, qubit_id: u64) -> Result<()>;
             fn force_result(&mut self, result_id: u64) -> Result<()>;
             fn get_bool_result(&mut self, result_id: u64) -> Result<Option<bool>>;
@@ -166,6 +254,12 @@ impl RuntimeInterface for Runtime {
             fn increment_future_refcount(&mut self, future: u64) -> Result<()>;
             fn decrement_future_refcount(&mut self, future: u64) -> Result<()>;
             fn custom_call(&mut self, custom_tag: u64, data: &[u8]) -> Result<u64>;
+            fn begin_condition(&mut self, result_id: u64, value: bool) -> Result<()>;
+            fn end_condition(&mut self) -> Result<()>;
         }
     }
+
+    fn instance_capabilities(&mut self) -> Result<Option<Capabilities>> {
+        Ok(Some(self.1))
+    }
 }
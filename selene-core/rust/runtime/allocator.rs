@@ -0,0 +1,234 @@
+//! A reusable qubit allocator for runtime plugins.
+//!
+//! Runtimes are responsible for mapping the user program's requests for
+//! fresh qubits onto physical qubit indices. The choice of which free qubit
+//! to hand out matters when studying the effects of qubit reuse (e.g. heating
+//! or crosstalk), so [QubitAllocator] provides a selection of policies, tracks
+//! whether each qubit was left in a known state when it was freed, and keeps
+//! per-qubit reuse counts that runtimes can report as metrics.
+use std::{fmt, str::FromStr};
+
+use anyhow::{Result, bail};
+
+use crate::utils::MetricValue;
+
+#[cfg(test)]
+mod tests;
+
+/// The strategy used to choose a free qubit on allocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AllocationPolicy {
+    /// Always hand out the free qubit with the lowest index.
+    #[default]
+    LowestIndex,
+    /// Hand out the free qubit that was released longest ago, spreading
+    /// usage across the device. Qubits that have never been used in the
+    /// current shot are preferred, lowest index first.
+    LeastRecentlyUsed,
+    /// Hand out the next free qubit after the most recently allocated one,
+    /// wrapping around at the end of the register.
+    RoundRobin,
+}
+
+impl FromStr for AllocationPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "lowest-index" => Ok(Self::LowestIndex),
+            "lru" | "least-recently-used" => Ok(Self::LeastRecentlyUsed),
+            "round-robin" => Ok(Self::RoundRobin),
+            _ => bail!(
+                "unknown allocation policy '{s}', expected one of: lowest-index, lru, round-robin"
+            ),
+        }
+    }
+}
+
+impl fmt::Display for AllocationPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::LowestIndex => "lowest-index",
+            Self::LeastRecentlyUsed => "lru",
+            Self::RoundRobin => "round-robin",
+        })
+    }
+}
+
+/// The result of a successful allocation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Allocation {
+    pub qubit_id: u64,
+    /// Set when the qubit was freed in an unknown state and the allocator
+    /// enforces known states. The runtime should emit a reset on the qubit
+    /// before any other operation.
+    pub needs_reset: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Slot {
+    allocated: bool,
+    /// Whether the qubit has been measured or reset since its last gate.
+    /// Qubits start each shot in |0>, which is a known state.
+    known_state: bool,
+    /// The allocator clock value at which the qubit was last freed,
+    /// or zero if it has not yet been freed in this shot.
+    last_freed: u64,
+    /// Whether the qubit has been allocated in this shot.
+    used: bool,
+    /// The number of times the qubit has been reallocated in this shot.
+    reuses: u64,
+}
+
+impl Slot {
+    /// The slot at the start of a shot.
+    fn fresh() -> Self {
+        Self {
+            known_state: true,
+            ..Default::default()
+        }
+    }
+}
+
+pub struct QubitAllocator {
+    slots: Vec<Slot>,
+    policy: AllocationPolicy,
+    enforce_known_state: bool,
+    clock: u64,
+    next_round_robin: usize,
+    inserted_resets: u64,
+}
+
+impl QubitAllocator {
+    pub fn new(n_qubits: u64, policy: AllocationPolicy, enforce_known_state: bool) -> Self {
+        Self {
+            slots: vec![Slot::fresh(); n_qubits as usize],
+            policy,
+            enforce_known_state,
+            clock: 0,
+            next_round_robin: 0,
+            inserted_resets: 0,
+        }
+    }
+
+    pub fn n_qubits(&self) -> u64 {
+        self.slots.len() as u64
+    }
+
+    pub fn policy(&self) -> AllocationPolicy {
+        self.policy
+    }
+
+    /// Allocate a qubit according to the configured policy, or return
+    /// `None` if every qubit is in use.
+    pub fn alloc(&mut self) -> Option<Allocation> {
+        let index = match self.policy {
+            AllocationPolicy::LowestIndex => self.slots.iter().position(|slot| !slot.allocated),
+            AllocationPolicy::LeastRecentlyUsed => self
+                .slots
+                .iter()
+                .enumerate()
+                .filter(|(_, slot)| !slot.allocated)
+                .min_by_key(|(_, slot)| slot.last_freed)
+                .map(|(i, _)| i),
+            AllocationPolicy::RoundRobin => {
+                let n = self.slots.len();
+                (0..n)
+                    .map(|offset| (self.next_round_robin + offset) % n)
+                    .find(|&i| !self.slots[i].allocated)
+            }
+        }?;
+        self.next_round_robin = index + 1;
+        let slot = &mut self.slots[index];
+        slot.allocated = true;
+        if slot.used {
+            slot.reuses += 1;
+        }
+        slot.used = true;
+        let needs_reset = self.enforce_known_state && !slot.known_state;
+        if needs_reset {
+            slot.known_state = true;
+            self.inserted_resets += 1;
+        }
+        Some(Allocation {
+            qubit_id: index as u64,
+            needs_reset,
+        })
+    }
+
+    /// Release a qubit back to the pool.
+    pub fn free(&mut self, qubit_id: u64) -> Result<()> {
+        let Some(slot) = self.slots.get_mut(qubit_id as usize) else {
+            bail!("freeing out-of-bounds qubit {qubit_id}");
+        };
+        if !slot.allocated {
+            bail!("freeing qubit {qubit_id}, which is not allocated");
+        }
+        self.clock += 1;
+        slot.allocated = false;
+        slot.last_freed = self.clock;
+        Ok(())
+    }
+
+    pub fn is_allocated(&self, qubit_id: u64) -> bool {
+        self.slots
+            .get(qubit_id as usize)
+            .is_some_and(|slot| slot.allocated)
+    }
+
+    /// Record that the qubit has been measured or reset, leaving it in a
+    /// known computational basis state.
+    pub fn mark_known(&mut self, qubit_id: u64) {
+        if let Some(slot) = self.slots.get_mut(qubit_id as usize) {
+            slot.known_state = true;
+        }
+    }
+
+    /// Record that a gate has been applied to the qubit.
+    pub fn mark_unknown(&mut self, qubit_id: u64) {
+        if let Some(slot) = self.slots.get_mut(qubit_id as usize) {
+            slot.known_state = false;
+        }
+    }
+
+    /// The number of times the qubit has been handed out again after its
+    /// first allocation in the current shot.
+    pub fn reuse_count(&self, qubit_id: u64) -> u64 {
+        self.slots
+            .get(qubit_id as usize)
+            .map_or(0, |slot| slot.reuses)
+    }
+
+    /// Return all qubits to their initial state, ready for a new shot. Like
+    /// the other metrics, reuse counts start again from zero, so that they
+    /// do not depend on how shots are split between workers.
+    pub fn reset(&mut self) {
+        self.slots.fill(Slot::fresh());
+        self.clock = 0;
+        self.next_round_robin = 0;
+        self.inserted_resets = 0;
+    }
+
+    /// Provides allocator metrics in the form expected by
+    /// [RuntimeInterface::get_metric](super::RuntimeInterface::get_metric).
+    /// The first metric is the number of resets inserted on allocation,
+    /// followed by the reuse count of each qubit, up to the limit imposed by
+    /// the metric index.
+    pub fn get_metric(&self, nth_metric: u8) -> Option<(String, MetricValue)> {
+        match nth_metric {
+            0 => Some((
+                "inserted_resets".to_string(),
+                MetricValue::U64(self.inserted_resets),
+            )),
+            n => {
+                let qubit_id = n as u64 - 1;
+                (qubit_id < self.n_qubits()).then(|| {
+                    (
+                        format!("qubit_{qubit_id}_reuse_count"),
+                        MetricValue::U64(self.reuse_count(qubit_id)),
+                    )
+                })
+            }
+        }
+    }
+}
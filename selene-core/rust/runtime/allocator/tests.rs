@@ -0,0 +1,112 @@
+use super::*;
+
+fn alloc_ids(allocator: &mut QubitAllocator, n: usize) -> Vec<u64> {
+    (0..n)
+        .map(|_| allocator.alloc().unwrap().qubit_id)
+        .collect()
+}
+
+#[test]
+fn lowest_index() {
+    let mut allocator = QubitAllocator::new(3, AllocationPolicy::LowestIndex, false);
+    assert_eq!(alloc_ids(&mut allocator, 3), vec![0, 1, 2]);
+    assert_eq!(allocator.alloc(), None);
+    allocator.free(2).unwrap();
+    allocator.free(0).unwrap();
+    assert_eq!(alloc_ids(&mut allocator, 2), vec![0, 2]);
+}
+
+#[test]
+fn least_recently_used() {
+    let mut allocator = QubitAllocator::new(4, AllocationPolicy::LeastRecentlyUsed, false);
+    assert_eq!(alloc_ids(&mut allocator, 2), vec![0, 1]);
+    allocator.free(1).unwrap();
+    allocator.free(0).unwrap();
+    // Unused qubits come first, then those freed longest ago.
+    assert_eq!(alloc_ids(&mut allocator, 4), vec![2, 3, 1, 0]);
+}
+
+#[test]
+fn round_robin() {
+    let mut allocator = QubitAllocator::new(3, AllocationPolicy::RoundRobin, false);
+    assert_eq!(alloc_ids(&mut allocator, 2), vec![0, 1]);
+    allocator.free(0).unwrap();
+    // Continues after the last allocation, wrapping around.
+    assert_eq!(alloc_ids(&mut allocator, 2), vec![2, 0]);
+    allocator.free(1).unwrap();
+    assert_eq!(alloc_ids(&mut allocator, 1), vec![1]);
+}
+
+#[test]
+fn needs_reset() {
+    let mut allocator = QubitAllocator::new(1, AllocationPolicy::LowestIndex, true);
+    // Qubits start each shot in a known state.
+    assert!(!allocator.alloc().unwrap().needs_reset);
+    allocator.mark_unknown(0);
+    allocator.free(0).unwrap();
+    assert!(allocator.alloc().unwrap().needs_reset);
+    // The reset leaves the qubit in a known state.
+    allocator.free(0).unwrap();
+    assert!(!allocator.alloc().unwrap().needs_reset);
+    allocator.mark_unknown(0);
+    allocator.mark_known(0);
+    allocator.free(0).unwrap();
+    assert!(!allocator.alloc().unwrap().needs_reset);
+    assert_eq!(
+        allocator.get_metric(0),
+        Some(("inserted_resets".to_string(), MetricValue::U64(1)))
+    );
+
+    // Without enforcement, no resets are requested.
+    let mut allocator = QubitAllocator::new(1, AllocationPolicy::LowestIndex, false);
+    allocator.alloc().unwrap();
+    allocator.mark_unknown(0);
+    allocator.free(0).unwrap();
+    assert!(!allocator.alloc().unwrap().needs_reset);
+}
+
+#[test]
+fn reuse_counts_are_per_shot() {
+    let mut allocator = QubitAllocator::new(2, AllocationPolicy::LowestIndex, false);
+    for _ in 0..3 {
+        for _ in 0..2 {
+            allocator.alloc().unwrap();
+            allocator.free(0).unwrap();
+        }
+        allocator.alloc().unwrap();
+        assert_eq!(allocator.reuse_count(0), 2);
+        assert_eq!(allocator.reuse_count(1), 0);
+        assert_eq!(
+            allocator.get_metric(1),
+            Some(("qubit_0_reuse_count".to_string(), MetricValue::U64(2)))
+        );
+        assert_eq!(allocator.get_metric(3), None);
+        allocator.reset();
+    }
+    // All of the state starts afresh with each shot.
+    assert!(!allocator.is_allocated(0));
+    assert_eq!(allocator.reuse_count(0), 0);
+    assert_eq!(allocator.get_metric(0).unwrap().1, MetricValue::U64(0));
+}
+
+#[test]
+fn free_errors() {
+    let mut allocator = QubitAllocator::new(1, AllocationPolicy::LowestIndex, false);
+    assert!(allocator.free(0).is_err());
+    assert!(allocator.free(1).is_err());
+}
+
+#[test]
+fn parse_policy() {
+    for policy in [
+        AllocationPolicy::LowestIndex,
+        AllocationPolicy::LeastRecentlyUsed,
+        AllocationPolicy::RoundRobin,
+    ] {
+        assert_eq!(
+            policy.to_string().parse::<AllocationPolicy>().unwrap(),
+            policy
+        );
+    }
+    assert!("random".parse::<AllocationPolicy>().is_err());
+}
@@ -105,6 +105,7 @@ impl<F: RuntimeInterfaceFactory> Helper<F> {
                     reset_fn,
                     custom_fn,
                     set_batch_time_fn,
+                    measure_reset_fn,
                     ..
                 } = unsafe { &*callbacks };
                 unsafe { set_batch_time_fn(goi, start.into(), duration.into()) };
@@ -118,6 +119,10 @@ impl<F: RuntimeInterfaceFactory> Helper<F> {
                             qubit_id,
                             result_id,
                         } => unsafe { measure_leaked_fn(goi, qubit_id, result_id) },
+                        Operation::MeasureReset {
+                            qubit_id,
+                            result_id,
+                        } => unsafe { measure_reset_fn(goi, qubit_id, result_id) },
                         Operation::Reset { qubit_id } => unsafe { reset_fn(goi, qubit_id) },
                         Operation::RZGate { qubit_id, theta } => unsafe {
                             rz_fn(goi, qubit_id, theta)
@@ -175,6 +180,22 @@ impl<F: RuntimeInterfaceFactory> Helper<F> {
         )
     }
 
+    pub unsafe fn begin_condition(instance: RuntimeInstance, result_id: u64, value: bool) -> Errno {
+        result_to_errno(
+            "Failed in begin_condition",
+            Self::with_runtime_instance(instance, |runtime| {
+                runtime.begin_condition(result_id, value)
+            }),
+        )
+    }
+
+    pub unsafe fn end_condition(instance: RuntimeInstance) -> Errno {
+        result_to_errno(
+            "Failed in end_condition",
+            Self::with_runtime_instance(instance, |runtime| runtime.end_condition()),
+        )
+    }
+
     pub unsafe fn get_metric(
         instance: RuntimeInstance,
         nth_metric: u8,
@@ -194,6 +215,22 @@ impl<F: RuntimeInterfaceFactory> Helper<F> {
         )
     }
 
+    pub unsafe fn get_instance_capabilities(
+        instance: RuntimeInstance,
+        bits_ptr: *mut u64,
+    ) -> Errno {
+        result_of_errno_to_errno(
+            "Failed to get the instance capabilities",
+            Self::with_runtime_instance(instance, |runtime| {
+                let Some(capabilities) = runtime.instance_capabilities()? else {
+                    return anyhow::Ok(1);
+                };
+                unsafe { *bits_ptr = capabilities.bits() };
+                Ok(0)
+            }),
+        )
+    }
+
     pub unsafe fn qalloc(instance: RuntimeInstance, result: *mut u64) -> Errno {
         result_to_errno(
             "Failed in qalloc",
@@ -271,6 +308,21 @@ impl<F: RuntimeInterfaceFactory> Helper<F> {
         )
     }
 
+    pub unsafe fn measure_reset(
+        instance: RuntimeInstance,
+        qubit_id: u64,
+        result: *mut u64,
+    ) -> Errno {
+        result_to_errno(
+            "Failed in measure_reset",
+            Self::with_runtime_instance(instance, |runtime| {
+                let r = runtime.measure_reset(qubit_id)?;
+                unsafe { *result = r };
+                anyhow::Ok(())
+            }),
+        )
+    }
+
     pub unsafe fn reset(instance: RuntimeInstance, qubit_id: u64) -> Errno {
         result_to_errno(
             "Failed in reset",
@@ -402,8 +454,16 @@ impl<F: RuntimeInterfaceFactory> Helper<F> {
 /// A crate can only export a single runtime plugin (although it may export
 /// other plugins of other types). See the `selene-simple-runtime-plugin` python
 /// package for a fully worked example.
+///
+/// The plugin describes itself in its metadata under the name given after the
+/// factory type, which should be the name it is registered under when linked
+/// into selene (see [crate::registry]), or under its package name if no name
+/// is given.
 macro_rules! export_runtime_plugin {
     ($factory_type:ty) => {
+        $crate::export_runtime_plugin!($factory_type, env!("CARGO_PKG_NAME"));
+    };
+    ($factory_type:ty, $name:expr) => {
         mod _plugin {
             use selene_core::runtime::{
                 interface::RuntimeInterfaceFactory,
@@ -438,10 +498,10 @@ macro_rules! export_runtime_plugin {
             /// validating the API version of the plugin against its own
             /// version.
             ///
-            /// The plans for this validation are a work-in-progress, but
-            /// currently selene will reject any plugin that has a different
-            /// major or minor version than the current Selene version, or with
-            /// a reserved field that is not 0.
+            /// Selene rejects plugins with a different major version, a newer
+            /// minor version, or a minor version older than it supports, as well
+            /// as plugins with a reserved field that is not 0. Plugins of older
+            /// supported minor versions are adapted to.
             #[unsafe(no_mangle)]
             pub unsafe extern "C" fn selene_runtime_get_api_version() -> u64 {
                 CURRENT_API_VERSION.into()
@@ -544,6 +604,33 @@ macro_rules! export_runtime_plugin {
                 Helper::custom_call(instance, tag, data, data_len, result)
             }
 
+            /// This function is called when the user program opens a conditional block.
+            /// Quantum operations issued until the matching call to
+            /// `selene_runtime_end_condition` should only be applied if the result with
+            /// the given ID equals `value`. The runtime need not wait for the result to be
+            /// available: it may keep the conditional operations queued and emit them
+            /// once the result has been set, taking any classical latency into account.
+            ///
+            /// Runtimes that do not support conditional operations should return a
+            /// non-zero error code.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_runtime_begin_condition(
+                instance: RuntimeInstance,
+                result_id: u64,
+                value: bool,
+            ) -> Errno {
+                Helper::begin_condition(instance, result_id, value)
+            }
+
+            /// This function is called when the user program closes the innermost
+            /// conditional block opened with `selene_runtime_begin_condition`.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_runtime_end_condition(
+                instance: RuntimeInstance,
+            ) -> Errno {
+                Helper::end_condition(instance)
+            }
+
             /// This function is called to get the next operations from the runtime. The
             /// runtime should use the [RuntimeGetOperationInterface] callbacks along with
             /// the [RuntimeGetOperationInstance] to provide a list of operations to Selene
@@ -698,6 +785,19 @@ macro_rules! export_runtime_plugin {
                 Helper::measure_leaked(instance, qubit_id, result)
             }
 
+            /// Instruct the runtime to measure a qubit and reset it to the |0> state as a
+            /// single fused primitive, writing a reference ID to the result to the `result`
+            /// pointer. Runtimes that do not export this function receive a measurement
+            /// followed by a reset instead.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_runtime_measure_reset(
+                instance: RuntimeInstance,
+                qubit_id: u64,
+                result: *mut u64,
+            ) -> i32 {
+                Helper::measure_reset(instance, qubit_id, result)
+            }
+
             /// Instruct the runtime to reset a qubit to the |0> state with the given ID.
             /// Note that it is up to the runtime whether or not this reset is applied immediately.
             /// It may wait until it is required, e.g. another gate is applied to the qubit.
@@ -783,6 +883,64 @@ macro_rules! export_runtime_plugin {
             ) -> Errno {
                 Helper::decrement_future_refcount(instance, future_ref)
             }
+
+            /// Get the message describing the latest error returned by the runtime
+            /// in the calling thread, as a null terminated string, or null if there
+            /// has been no error. The string remains valid until the next error.
+            ///
+            /// Selene calls this after an entry point returns an error, and reports
+            /// the message along with the error. Plugins that do not provide this
+            /// entry point should write any details of their errors to stderr.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_runtime_last_error_message() -> *const c_char {
+                selene_core::utils::last_error_message_ptr()
+            }
+
+            /// Get the optional features supported by the runtime, as the bits of a
+            /// `selene_core::capabilities::Capabilities` set. Selene only uses a
+            /// declared capability if the entry points it relies on are present.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_runtime_get_capabilities() -> u64 {
+                <$factory_type as Default>::default().capabilities().bits()
+            }
+
+            /// Get the optional features supported by the runtime instance, writing
+            /// the bits of a `selene_core::capabilities::Capabilities` set to
+            /// `bits_ptr`. Returns 0 if they were written, or 1 if they are those
+            /// reported by `selene_runtime_get_capabilities`.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_runtime_get_instance_capabilities(
+                instance: RuntimeInstance,
+                bits_ptr: *mut u64,
+            ) -> Errno {
+                Helper::get_instance_capabilities(instance, bits_ptr)
+            }
+
+            /// Get a description of the runtime as a nul-terminated JSON string, in
+            /// the form of a `selene_core::metadata::PluginMetadata`: its name and
+            /// version, the API version, its capabilities and the arguments it
+            /// accepts. The string is owned by the plugin and lives as long as it
+            /// is loaded.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_runtime_get_metadata() -> *const c_char {
+                static METADATA: std::sync::OnceLock<std::ffi::CString> =
+                    std::sync::OnceLock::new();
+                METADATA
+                    .get_or_init(|| {
+                        let factory = <$factory_type as Default>::default();
+                        selene_core::metadata::PluginMetadata::new(
+                            selene_core::metadata::PluginKind::Runtime,
+                            $name,
+                            env!("CARGO_PKG_NAME"),
+                            env!("CARGO_PKG_VERSION"),
+                            CURRENT_API_VERSION,
+                            factory.capabilities(),
+                            factory.arguments(),
+                        )
+                        .to_c_string()
+                    })
+                    .as_ptr()
+            }
         }
     };
 }
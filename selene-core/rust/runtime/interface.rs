@@ -1,6 +1,8 @@
 use anyhow::{Result, anyhow};
 use std::sync::Arc;
 
+use crate::capabilities::Capabilities;
+use crate::metadata::ArgumentMetadata;
 use crate::utils::MetricValue;
 
 use super::BatchOperation;
@@ -50,11 +52,40 @@ pub trait RuntimeInterface {
         ))
     }
 
+    /// Begin a conditional block. Quantum operations scheduled until the
+    /// matching `end_condition` should only be applied if the result with
+    /// index `result_id` equals `value` once it is available. Blocks may be
+    /// nested, in which case all enclosing conditions must hold.
+    ///
+    /// Unlike a read of the result by the user program, this does not require
+    /// the result to be available immediately: the runtime may keep the
+    /// conditional operations queued and emit them once the result lands.
+    fn begin_condition(&mut self, _result_id: u64, _value: bool) -> Result<()> {
+        Err(anyhow!(
+            "A conditional operation has been issued to a runtime that does not support conditional operations."
+        ))
+    }
+
+    /// End the innermost conditional block opened with `begin_condition`.
+    fn end_condition(&mut self) -> Result<()> {
+        Err(anyhow!(
+            "A conditional operation has been issued to a runtime that does not support conditional operations."
+        ))
+    }
+
     /// Provide a metric to the output stream.
     ///
     /// Will be called with incrementing `nth_metric` until `None` is returned.
     fn get_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>>;
 
+    /// The optional features supported by this instance, for runtimes whose
+    /// features depend on their arguments, such as those wrapping another
+    /// runtime. `None` if they are those of the factory, see
+    /// [RuntimeInterfaceFactory::capabilities].
+    fn instance_capabilities(&mut self) -> Result<Option<Capabilities>> {
+        Ok(None)
+    }
+
     /// Attempt to allocate a free qubit. If no qubits are available,
     /// then [u64::MAX] should be returned.
     fn qalloc(&mut self) -> Result<u64>;
@@ -83,6 +114,18 @@ pub trait RuntimeInterface {
     /// Schedule a reset of allocated qubit `qubit_id`.
     fn reset(&mut self, qubit_id: u64) -> Result<()>;
 
+    /// Schedule a fused measurement and reset of allocated qubit `qubit_id`.
+    /// The plugin should return a new result index with a reference count of 1.
+    ///
+    /// Runtimes that support the fused primitive should emit a single
+    /// `Operation::MeasureReset`. The default implementation schedules a
+    /// measurement followed by a reset.
+    fn measure_reset(&mut self, qubit_id: u64) -> Result<u64> {
+        let result_id = self.measure(qubit_id)?;
+        self.reset(qubit_id)?;
+        Ok(result_id)
+    }
+
     /// A hint to the plugin that the result with index `result_id` is needed.
     ///
     /// The plugin should ensure that, were the emulator to call
@@ -137,4 +180,18 @@ pub trait RuntimeInterfaceFactory {
         start: crate::time::Instant,
         args: &[impl AsRef<str>],
     ) -> Result<Box<Self::Interface>>;
+
+    /// The optional features supported by the interfaces this factory
+    /// creates. See [Capabilities].
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::empty()
+    }
+
+    /// The arguments accepted by [Self::init], reported in the plugin's
+    /// metadata so that they can be checked before a run. `None` if they are
+    /// not described. See `ArgumentMetadata::from_clap` for factories that
+    /// parse their arguments with clap.
+    fn arguments(&self) -> Option<Vec<ArgumentMetadata>> {
+        None
+    }
 }
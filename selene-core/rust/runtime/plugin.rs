@@ -1,5 +1,9 @@
+use crate::capabilities::Capabilities;
 use crate::runtime::Operation;
-use crate::utils::{MetricValue, check_errno, read_raw_metric, with_strings_to_cargs};
+use crate::utils::{
+    MetricValue, check_plugin_errno, has_entry_point, plugin_capabilities, plugin_error,
+    read_raw_metric, with_strings_to_cargs,
+};
 
 use super::{BatchOperation, RuntimeAPIVersion, RuntimeInterface, RuntimeInterfaceFactory};
 use anyhow::{Result, anyhow};
@@ -16,7 +20,10 @@ pub type Errno = i32;
 /// Provides a runtime engine backend that controls a plugin, in the form of a shared object.
 /// All functions return an i32. Unless otherwise specified, they should return
 /// 0 on success, and non-zero on failure.
-/// When returning failure a plugin should write a short error message to stderr.
+/// When returning failure a plugin should either provide a message through the optional
+/// `selene_runtime_last_error_message` entry point, or write a short error message to
+/// stderr. The value returned may be the code of an [ErrorKind](crate::error::ErrorKind)
+/// to categorise the failure.
 ///
 /// Users should be cautious about the plugins they use, as it is possible that mistakes
 /// or malicious code could be present in the plugin, and as with all external libraries, due
@@ -25,6 +32,9 @@ pub type Errno = i32;
 pub struct RuntimePluginInterface {
     lib: libloading::Library,
     version: RuntimeAPIVersion,
+    capabilities: Capabilities,
+    /// The capabilities the plugin's entry points allow it to declare.
+    available: Capabilities,
     #[borrows(lib)]
     #[covariant]
     init_fn: libloading::Symbol<
@@ -153,6 +163,15 @@ pub struct RuntimePluginInterface {
         unsafe extern "C" fn(handle: RuntimeInstance, qubit: u64, result_id: *mut u64) -> Errno,
     >,
 
+    #[borrows(lib)]
+    #[covariant]
+    measure_reset_fn: Option<
+        libloading::Symbol<
+            'this,
+            unsafe extern "C" fn(handle: RuntimeInstance, qubit: u64, result_id: *mut u64) -> Errno,
+        >,
+    >,
+
     #[borrows(lib)]
     #[covariant]
     reset_fn: libloading::Symbol<
@@ -221,9 +240,47 @@ pub struct RuntimePluginInterface {
             ) -> Errno,
         >,
     >,
+
+    #[borrows(lib)]
+    #[covariant]
+    begin_condition_fn: Option<
+        libloading::Symbol<
+            'this,
+            unsafe extern "C" fn(handle: RuntimeInstance, result_id: u64, value: bool) -> Errno,
+        >,
+    >,
+
+    #[borrows(lib)]
+    #[covariant]
+    end_condition_fn:
+        Option<libloading::Symbol<'this, unsafe extern "C" fn(handle: RuntimeInstance) -> Errno>>,
+
+    #[borrows(lib)]
+    #[covariant]
+    get_instance_capabilities_fn: Option<
+        libloading::Symbol<
+            'this,
+            unsafe extern "C" fn(handle: RuntimeInstance, out_bits: *mut u64) -> Errno,
+        >,
+    >,
+
+    #[borrows(lib)]
+    #[covariant]
+    last_error_message_fn:
+        Option<libloading::Symbol<'this, unsafe extern "C" fn() -> *const ffi::c_char>>,
 }
 
 impl RuntimePluginInterface {
+    /// Checks the errno returned by a call to the plugin, reporting the
+    /// plugin's own message for the error if it provides one.
+    fn check_errno(&self, errno: Errno, mk_err: impl FnOnce() -> anyhow::Error) -> Result<()> {
+        check_plugin_errno(
+            errno,
+            self.borrow_last_error_message_fn().as_deref(),
+            mk_err,
+        )
+    }
+
     /// Loads a runtime plugin from a file.
     pub fn new_from_file(plugin_file: impl AsRef<OsStr>) -> Result<Arc<Self>> {
         let lib = unsafe { libloading::Library::new(plugin_file.as_ref()) }.map_err(|e| {
@@ -246,10 +303,30 @@ impl RuntimePluginInterface {
             }
         };
         version.validate()?;
+        let inferred = Capabilities::CUSTOM_OPS
+            .when(has_entry_point(&lib, b"selene_runtime_custom_call"))
+            | Capabilities::METRICS.when(has_entry_point(&lib, b"selene_runtime_get_metrics"))
+            | Capabilities::CONDITIONS.when(
+                has_entry_point(&lib, b"selene_runtime_begin_condition")
+                    && has_entry_point(&lib, b"selene_runtime_end_condition"),
+            )
+            | Capabilities::MEASURE_RESET
+                .when(has_entry_point(&lib, b"selene_runtime_measure_reset"));
+        // Batching is a matter of the runtime's behaviour rather than of its
+        // entry points, so is available to all runtimes but never inferred.
+        let available = inferred | Capabilities::BATCHING;
+        let capabilities = plugin_capabilities(
+            &lib,
+            b"selene_runtime_get_capabilities",
+            available,
+            inferred,
+        );
 
         let result = RuntimePluginInterfaceTryBuilder {
             lib,
             version,
+            capabilities,
+            available,
             init_fn_builder: |lib| unsafe { lib.get(b"selene_runtime_init") },
             exit_fn_builder: |lib| unsafe { Ok(lib.get(b"selene_runtime_exit").ok()) },
             shot_start_fn_builder: |lib| unsafe { lib.get(b"selene_runtime_shot_start") },
@@ -269,6 +346,9 @@ impl RuntimePluginInterface {
             rz_gate_fn_builder: |lib| unsafe { lib.get(b"selene_runtime_rz_gate") },
             measure_fn_builder: |lib| unsafe { lib.get(b"selene_runtime_measure") },
             measure_leaked_fn_builder: |lib| unsafe { lib.get(b"selene_runtime_measure_leaked") },
+            measure_reset_fn_builder: |lib| unsafe {
+                Ok(lib.get(b"selene_runtime_measure_reset").ok())
+            },
             reset_fn_builder: |lib| unsafe { lib.get(b"selene_runtime_reset") },
             force_result_fn_builder: |lib| unsafe { lib.get(b"selene_runtime_force_result") },
             get_bool_result_fn_builder: |lib| unsafe { lib.get(b"selene_runtime_get_bool_result") },
@@ -284,6 +364,18 @@ impl RuntimePluginInterface {
             custom_call_fn_builder: |lib| unsafe {
                 Ok(lib.get(b"selene_runtime_custom_call").ok())
             },
+            begin_condition_fn_builder: |lib| unsafe {
+                Ok(lib.get(b"selene_runtime_begin_condition").ok())
+            },
+            end_condition_fn_builder: |lib| unsafe {
+                Ok(lib.get(b"selene_runtime_end_condition").ok())
+            },
+            get_instance_capabilities_fn_builder: |lib| unsafe {
+                Ok(lib.get(b"selene_runtime_get_instance_capabilities").ok())
+            },
+            last_error_message_fn_builder: |lib| unsafe {
+                Ok(lib.get(b"selene_runtime_last_error_message").ok())
+            },
         }
         .try_build()?;
         Ok(Arc::new(result))
@@ -293,6 +385,10 @@ impl RuntimePluginInterface {
 impl RuntimeInterfaceFactory for RuntimePluginInterface {
     type Interface = RuntimePlugin;
 
+    fn capabilities(&self) -> Capabilities {
+        *self.borrow_capabilities()
+    }
+
     fn init(
         self: Arc<Self>,
         n_qubits: u64,
@@ -301,7 +397,7 @@ impl RuntimeInterfaceFactory for RuntimePluginInterface {
     ) -> Result<Box<Self::Interface>> {
         let mut instance = std::ptr::null_mut();
         with_strings_to_cargs(args, |argc, argv| {
-            check_errno(
+            self.check_errno(
                 unsafe { self.borrow_init_fn()(&mut instance, n_qubits, start.into(), argc, argv) },
                 || anyhow!("RuntimePluginInterface: init failed"),
             )
@@ -323,15 +419,16 @@ impl RuntimeInterface for RuntimePlugin {
         let Some(exit_fn) = self.interface.borrow_exit_fn() else {
             return Ok(());
         };
-        check_errno(unsafe { exit_fn(self.instance) }, || {
-            anyhow!("RuntimePlugin: exit failed")
-        })
+        self.interface
+            .check_errno(unsafe { exit_fn(self.instance) }, || {
+                anyhow!("RuntimePlugin: exit failed")
+            })
     }
 
     fn get_next_operations(&mut self) -> Result<Option<BatchOperation>> {
         let mut batch_builder = BatchBuilder::default();
         let (instance, interface) = batch_builder.runtime_get_operation();
-        check_errno(
+        self.interface.check_errno(
             unsafe {
                 self.interface.borrow_get_next_operations_fn()(
                     self.instance,
@@ -345,14 +442,14 @@ impl RuntimeInterface for RuntimePlugin {
     }
 
     fn shot_start(&mut self, shot_id: u64, seed: u64) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe { self.interface.borrow_shot_start_fn()(self.instance, shot_id, seed) },
             || anyhow!("RuntimePlugin: shot_start failed"),
         )
     }
 
     fn shot_end(&mut self) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe { self.interface.borrow_shot_end_fn()(self.instance) },
             || anyhow!("RuntimePlugin: shot_end failed"),
         )
@@ -367,10 +464,30 @@ impl RuntimeInterface for RuntimePlugin {
         })
     }
 
+    fn instance_capabilities(&mut self) -> Result<Option<Capabilities>> {
+        let Some(get_instance_capabilities_fn) =
+            self.interface.borrow_get_instance_capabilities_fn()
+        else {
+            return Ok(None);
+        };
+        let mut bits = 0;
+        match unsafe { get_instance_capabilities_fn(self.instance, &mut bits) } {
+            0 => Ok(Some(
+                Capabilities::from_bits_truncate(bits) & *self.interface.borrow_available(),
+            )),
+            1 => Ok(None),
+            errno => Err(plugin_error(
+                errno,
+                self.interface.borrow_last_error_message_fn().as_deref(),
+                || anyhow!("RuntimePlugin: get_instance_capabilities failed"),
+            )),
+        }
+    }
+
     fn qalloc(&mut self) -> Result<u64> {
         let mut result = 0;
         let result_ref = &mut result;
-        check_errno(
+        self.interface.check_errno(
             unsafe { self.interface.borrow_qalloc_fn()(self.instance, result_ref as *mut _) },
             || anyhow!("RuntimePlugin: qalloc failed"),
         )?;
@@ -378,14 +495,14 @@ impl RuntimeInterface for RuntimePlugin {
     }
 
     fn qfree(&mut self, qubit_id: u64) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe { self.interface.borrow_qfree_fn()(self.instance, qubit_id) },
             || anyhow!("RuntimePlugin: qfree failed"),
         )
     }
 
     fn global_barrier(&mut self, sleep_ns: u64) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe { self.interface.borrow_global_barrier_fn()(self.instance, sleep_ns) },
             || anyhow!("RuntimePlugin: global barrier failed"),
         )
@@ -394,7 +511,7 @@ impl RuntimeInterface for RuntimePlugin {
     fn local_barrier(&mut self, qubit_ids: &[u64], sleep_ns: u64) -> Result<()> {
         let qubit_ids_len = qubit_ids.len() as u64;
         let qubit_ids_ptr = qubit_ids.as_ptr();
-        check_errno(
+        self.interface.check_errno(
             unsafe {
                 self.interface.borrow_local_barrier_fn()(
                     self.instance,
@@ -408,14 +525,14 @@ impl RuntimeInterface for RuntimePlugin {
     }
 
     fn rxy_gate(&mut self, qubit_id: u64, theta: f64, phi: f64) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe { self.interface.borrow_rxy_gate_fn()(self.instance, qubit_id, theta, phi) },
             || anyhow!("RuntimePlugin: rxy_gate failed"),
         )
     }
 
     fn rzz_gate(&mut self, qubit_id_1: u64, qubit_id_2: u64, theta: f64) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe {
                 self.interface.borrow_rzz_gate_fn()(self.instance, qubit_id_1, qubit_id_2, theta)
             },
@@ -424,7 +541,7 @@ impl RuntimeInterface for RuntimePlugin {
     }
 
     fn rz_gate(&mut self, qubit_id: u64, theta: f64) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe { self.interface.borrow_rz_gate_fn()(self.instance, qubit_id, theta) },
             || anyhow!("RuntimePlugin: rz_gate failed"),
         )
@@ -433,7 +550,7 @@ impl RuntimeInterface for RuntimePlugin {
     fn measure(&mut self, qubit_id: u64) -> Result<u64> {
         let mut result = 0;
         let result_ref = &mut result;
-        check_errno(
+        self.interface.check_errno(
             unsafe {
                 self.interface.borrow_measure_fn()(self.instance, qubit_id, result_ref as *mut _)
             },
@@ -445,7 +562,7 @@ impl RuntimeInterface for RuntimePlugin {
     fn measure_leaked(&mut self, qubit_id: u64) -> Result<u64> {
         let mut result = 0;
         let result_ref = &mut result;
-        check_errno(
+        self.interface.check_errno(
             unsafe {
                 self.interface.borrow_measure_leaked_fn()(
                     self.instance,
@@ -459,14 +576,31 @@ impl RuntimeInterface for RuntimePlugin {
     }
 
     fn reset(&mut self, qubit_id: u64) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe { self.interface.borrow_reset_fn()(self.instance, qubit_id) },
             || anyhow!("RuntimePlugin: reset failed"),
         )
     }
 
+    fn measure_reset(&mut self, qubit_id: u64) -> Result<u64> {
+        let Some(measure_reset_fn) = self.interface.borrow_measure_reset_fn() else {
+            // Plugins without the fused entry point receive the two halves
+            // as separate calls.
+            let result_id = self.measure(qubit_id)?;
+            self.reset(qubit_id)?;
+            return Ok(result_id);
+        };
+        let mut result = 0;
+        let result_ref = &mut result;
+        self.interface.check_errno(
+            unsafe { measure_reset_fn(self.instance, qubit_id, result_ref as *mut _) },
+            || anyhow!("RuntimePlugin: measure_reset failed"),
+        )?;
+        Ok(result)
+    }
+
     fn force_result(&mut self, result_id: u64) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe { self.interface.borrow_force_result_fn()(self.instance, result_id) },
             || anyhow!("RuntimePlugin: force_result failed"),
         )
@@ -475,7 +609,7 @@ impl RuntimeInterface for RuntimePlugin {
     fn get_bool_result(&mut self, result_id: u64) -> Result<Option<bool>> {
         let mut result = 0i8;
         let result_ref = &mut result;
-        check_errno(
+        self.interface.check_errno(
             unsafe {
                 self.interface.borrow_get_bool_result_fn()(
                     self.instance,
@@ -496,7 +630,7 @@ impl RuntimeInterface for RuntimePlugin {
     fn get_u64_result(&mut self, result_id: u64) -> Result<Option<u64>> {
         let mut result = 0u64;
         let result_ref = &mut result;
-        check_errno(
+        self.interface.check_errno(
             unsafe {
                 self.interface.borrow_get_u64_result_fn()(
                     self.instance,
@@ -514,21 +648,21 @@ impl RuntimeInterface for RuntimePlugin {
     }
 
     fn set_bool_result(&mut self, result_id: u64, result: bool) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe { self.interface.borrow_set_bool_result_fn()(self.instance, result_id, result) },
             || anyhow!("RuntimePlugin: set_bool_result failed"),
         )
     }
 
     fn set_u64_result(&mut self, result_id: u64, result: u64) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe { self.interface.borrow_set_u64_result_fn()(self.instance, result_id, result) },
             || anyhow!("RuntimePlugin: set_u64_result failed"),
         )
     }
 
     fn increment_future_refcount(&mut self, future_ref: u64) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe {
                 self.interface.borrow_increment_future_refcount_fn()(self.instance, future_ref)
             },
@@ -537,7 +671,7 @@ impl RuntimeInterface for RuntimePlugin {
     }
 
     fn decrement_future_refcount(&mut self, future_ref: u64) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe {
                 self.interface.borrow_decrement_future_refcount_fn()(self.instance, future_ref)
             },
@@ -549,7 +683,7 @@ impl RuntimeInterface for RuntimePlugin {
         let mut result = 0;
         let result_ref = &mut result;
         if let Some(custom_call_fn) = self.interface.borrow_custom_call_fn() {
-            check_errno(
+            self.interface.check_errno(
                 unsafe {
                     custom_call_fn(
                         self.instance,
@@ -568,6 +702,30 @@ impl RuntimeInterface for RuntimePlugin {
             ))
         }
     }
+
+    fn begin_condition(&mut self, result_id: u64, value: bool) -> Result<()> {
+        let Some(begin_condition_fn) = self.interface.borrow_begin_condition_fn() else {
+            return Err(anyhow!(
+                "RuntimePlugin: conditional operations not supported by plugin"
+            ));
+        };
+        self.interface.check_errno(
+            unsafe { begin_condition_fn(self.instance, result_id, value) },
+            || anyhow!("RuntimePlugin: begin_condition failed"),
+        )
+    }
+
+    fn end_condition(&mut self) -> Result<()> {
+        let Some(end_condition_fn) = self.interface.borrow_end_condition_fn() else {
+            return Err(anyhow!(
+                "RuntimePlugin: conditional operations not supported by plugin"
+            ));
+        };
+        self.interface
+            .check_errno(unsafe { end_condition_fn(self.instance) }, || {
+                anyhow!("RuntimePlugin: end_condition failed")
+            })
+    }
 }
 
 /// A helper type used by the plugin tooling above to implement
@@ -657,6 +815,20 @@ impl BatchBuilder {
         )
     }
 
+    unsafe extern "C" fn measure_reset(
+        interface: RuntimeGetOperationInstance,
+        qubit_id: u64,
+        result_id: u64,
+    ) {
+        Self::push(
+            interface,
+            Operation::MeasureReset {
+                qubit_id,
+                result_id,
+            },
+        )
+    }
+
     unsafe extern "C" fn reset(interface: RuntimeGetOperationInstance, qubit_id: u64) {
         Self::push(interface, Operation::Reset { qubit_id })
     }
@@ -703,6 +875,7 @@ impl BatchBuilder {
             reset_fn: Self::reset,
             custom_fn: Self::custom,
             set_batch_time_fn: Self::set_batch_time,
+            measure_reset_fn: Self::measure_reset,
             _marker: PhantomData,
         };
         (instance, interface)
@@ -740,6 +913,7 @@ pub struct RuntimeGetOperationInterface<'a> {
     pub custom_fn:
         unsafe extern "C" fn(RuntimeGetOperationInstance, usize, *const ffi::c_void, usize),
     pub set_batch_time_fn: unsafe extern "C" fn(RuntimeGetOperationInstance, u64, u64),
+    pub measure_reset_fn: unsafe extern "C" fn(RuntimeGetOperationInstance, u64, u64),
     _marker: PhantomData<&'a ()>,
 }
 
@@ -766,6 +940,7 @@ impl BatchExtractor {
             reset_fn,
             custom_fn,
             set_batch_time_fn,
+            measure_reset_fn,
             ..
         } = interface_out;
         unsafe { set_batch_time_fn(instance_out, batch.start().into(), batch.duration().into()) };
@@ -779,6 +954,10 @@ impl BatchExtractor {
                     qubit_id,
                     result_id,
                 } => unsafe { measure_leaked_fn(instance_out, *qubit_id, *result_id) },
+                Operation::MeasureReset {
+                    qubit_id,
+                    result_id,
+                } => unsafe { measure_reset_fn(instance_out, *qubit_id, *result_id) },
                 Operation::Reset { qubit_id } => unsafe { reset_fn(instance_out, *qubit_id) },
                 Operation::RXYGate {
                     qubit_id,
@@ -800,6 +979,46 @@ impl BatchExtractor {
             }
         }
     }
+    /// Extraction for plugins that pass a [LegacyRuntimeGetOperationInterface].
+    /// The batch must not contain [Operation::MeasureReset] (see
+    /// [BatchOperation::split_measure_reset]).
+    unsafe extern "C" fn extract_legacy(
+        instance_in: RuntimeExtractOperationInstance,
+        instance_out: RuntimeGetOperationInstance,
+        interface_out: LegacyRuntimeGetOperationInterface,
+    ) {
+        unsafe extern "C" fn no_measure_reset(_: RuntimeGetOperationInstance, _: u64, _: u64) {}
+        let interface_out = RuntimeGetOperationInterface {
+            rzz_fn: interface_out.rzz_fn,
+            rxy_fn: interface_out.rxy_fn,
+            rz_fn: interface_out.rz_fn,
+            measure_fn: interface_out.measure_fn,
+            measure_leaked_fn: interface_out.measure_leaked_fn,
+            reset_fn: interface_out.reset_fn,
+            custom_fn: interface_out.custom_fn,
+            set_batch_time_fn: interface_out.set_batch_time_fn,
+            measure_reset_fn: no_measure_reset,
+            _marker: PhantomData,
+        };
+        unsafe { Self::extract(instance_in, instance_out, interface_out) }
+    }
+
+    /// As [BatchExtractor::runtime_batch_extraction], for plugins built
+    /// against API versions that predate measure_reset_fn.
+    pub fn legacy_runtime_batch_extraction(
+        &mut self,
+    ) -> (
+        RuntimeExtractOperationInstance,
+        LegacyRuntimeExtractOperationInterface<'_>,
+    ) {
+        let instance = &raw mut self.0 as RuntimeExtractOperationInstance;
+        let reoi = LegacyRuntimeExtractOperationInterface {
+            extract_fn: Self::extract_legacy,
+            _marker: PhantomData,
+        };
+        (instance, reoi)
+    }
+
     pub fn runtime_batch_extraction(
         &mut self,
     ) -> (
@@ -826,3 +1045,30 @@ pub struct RuntimeExtractOperationInterface<'a> {
     ),
     _marker: PhantomData<&'a ()>,
 }
+
+/// The [RuntimeGetOperationInterface] of API versions before measure_reset_fn
+/// was added, as passed to the extraction function by older plugins.
+#[repr(C)]
+pub struct LegacyRuntimeGetOperationInterface {
+    pub rzz_fn: unsafe extern "C" fn(RuntimeGetOperationInstance, u64, u64, f64),
+    pub rxy_fn: unsafe extern "C" fn(RuntimeGetOperationInstance, u64, f64, f64),
+    pub rz_fn: unsafe extern "C" fn(RuntimeGetOperationInstance, u64, f64),
+    pub measure_fn: unsafe extern "C" fn(RuntimeGetOperationInstance, u64, u64),
+    pub measure_leaked_fn: unsafe extern "C" fn(RuntimeGetOperationInstance, u64, u64),
+    pub reset_fn: unsafe extern "C" fn(RuntimeGetOperationInstance, u64),
+    pub custom_fn:
+        unsafe extern "C" fn(RuntimeGetOperationInstance, usize, *const ffi::c_void, usize),
+    pub set_batch_time_fn: unsafe extern "C" fn(RuntimeGetOperationInstance, u64, u64),
+}
+
+/// A [RuntimeExtractOperationInterface] for older plugins, with the same
+/// layout, whose extraction function takes a [LegacyRuntimeGetOperationInterface].
+#[repr(C)]
+pub struct LegacyRuntimeExtractOperationInterface<'a> {
+    pub extract_fn: unsafe extern "C" fn(
+        RuntimeExtractOperationInstance,
+        RuntimeGetOperationInstance,
+        LegacyRuntimeGetOperationInterface,
+    ),
+    _marker: PhantomData<&'a ()>,
+}
@@ -1,5 +1,8 @@
 use anyhow::{Result, anyhow};
 
+#[cfg(test)]
+mod tests;
+
 #[derive(Debug, Clone, Copy)]
 #[repr(C)]
 pub struct RuntimeAPIVersion {
@@ -22,6 +25,11 @@ impl From<u64> for RuntimeAPIVersion {
         }
     }
 }
+impl std::fmt::Display for RuntimeAPIVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
 impl From<RuntimeAPIVersion> for u64 {
     fn from(value: RuntimeAPIVersion) -> u64 {
         ((value.reserved as u64) << 24)
@@ -34,22 +42,30 @@ impl From<RuntimeAPIVersion> for u64 {
 pub const CURRENT_API_VERSION: RuntimeAPIVersion = RuntimeAPIVersion {
     reserved: 0,
     major: 0,
-    minor: 2,
-    patch: 0,
+    minor: 3,
+    patch: 1,
 };
 
 // CHANGELOG:
 // 0.0.1: Initial version
 // 0.0.2: Introduced MeasureLeaked, changed get_result to get_bool_result and get_u64_result
+// 0.3.0: Introduced optional begin_condition and end_condition for conditional operations.
+//   Introduced MeasureReset, adding measure_reset_fn to RuntimeGetOperationInterface
+//   and an optional measure_reset entry point
+// 0.3.1: Introduced the optional get_capabilities entry point
+
+/// The oldest minor version of the current major version that selene can still load.
+/// Runtimes from 0.2 lack the optional measure_reset entry point, and receive a
+/// RuntimeGetOperationInterface with a trailing measure_reset_fn they do not read,
+/// so need no further adaptation.
+pub const MIN_SUPPORTED_MINOR: u8 = 2;
 
 impl RuntimeAPIVersion {
+    pub fn minor(&self) -> u8 {
+        self.minor
+    }
+
     pub fn validate(&self) -> Result<()> {
-        // Note: this is a naive check at the moment, as we have not introduced a breaking
-        // change since versioning was introduced. This logic should evolve as and when
-        // changes occur.
-        //
-        // As such, this is mostly a sketch of what the logic may look like.
-        //
         // Reserved must be 0. We may want to attribute meaning to this one day.
         if self.reserved != 0 {
             return Err(anyhow!(
@@ -65,13 +81,21 @@ impl RuntimeAPIVersion {
                 self.major
             ));
         }
-        // If the minor version is different, the plugin is likely incompatible. This function
-        // should be updated to reflect the actual changes in the API. For now we reject.
-        if self.minor != CURRENT_API_VERSION.minor {
+        // Plugins built for a newer minor version may rely on behaviour that selene does
+        // not provide. Plugins built for an older minor version are supported back to
+        // MIN_SUPPORTED_MINOR, with selene adapting to the differences in the API.
+        if self.minor > CURRENT_API_VERSION.minor {
+            return Err(anyhow!(
+                "Runtime API minor version {} is newer than Selene's Runtime API minor version ({}). Upgrade selene to use this plugin.",
+                self.minor,
+                CURRENT_API_VERSION.minor
+            ));
+        }
+        if self.minor < MIN_SUPPORTED_MINOR {
             return Err(anyhow!(
-                "Runtime API minor version must be the same as Selene's Runtime API minor version ({}), got {}",
-                CURRENT_API_VERSION.minor,
-                self.minor
+                "Runtime API minor version {} is no longer supported. Selene supports runtime plugins from minor version {}.",
+                self.minor,
+                MIN_SUPPORTED_MINOR
             ));
         }
         // The patch version might be bumped for changes that are optional and additive.
@@ -0,0 +1,37 @@
+use super::*;
+
+fn version(major: u64, minor: u64, patch: u64) -> RuntimeAPIVersion {
+    RuntimeAPIVersion::from((major << 16) | (minor << 8) | patch)
+}
+
+#[test]
+fn roundtrip() {
+    let encoded: u64 = CURRENT_API_VERSION.into();
+    assert_eq!(encoded, 0x0301);
+    assert_eq!(RuntimeAPIVersion::from(encoded).to_string(), "0.3.1");
+}
+
+#[test]
+fn accepts_supported_minor_versions() {
+    assert!(CURRENT_API_VERSION.validate().is_ok());
+    assert!(version(0, 3, 0).validate().is_ok());
+    // 0.2 plugins are loaded through the compatibility path.
+    assert!(version(0, MIN_SUPPORTED_MINOR as u64, 0).validate().is_ok());
+    assert!(version(0, MIN_SUPPORTED_MINOR as u64, 1).validate().is_ok());
+}
+
+#[test]
+fn rejects_unsupported_versions() {
+    assert!(
+        version(0, MIN_SUPPORTED_MINOR as u64 - 1, 0)
+            .validate()
+            .is_err()
+    );
+    assert!(version(0, 4, 0).validate().is_err());
+    assert!(version(1, 3, 0).validate().is_err());
+    assert!(
+        RuntimeAPIVersion::from(1 << 24 | 0x0300)
+            .validate()
+            .is_err()
+    );
+}
@@ -0,0 +1,271 @@
+//! Composition of runtime plugins.
+//!
+//! A [WrappedRuntime] owns an inner [Runtime], typically loaded from another
+//! plugin with [Runtime::load_from_file], and routes calls to it through a
+//! [RuntimeWrapper]. Every hook on [RuntimeWrapper] defaults to passing the
+//! call straight to the inner runtime, so implementors only override the calls
+//! they wish to intercept. This makes it possible to build gate set
+//! translators, loggers and validators on top of any existing runtime plugin.
+use anyhow::Result;
+
+use crate::capabilities::Capabilities;
+use crate::utils::MetricValue;
+
+use super::{BatchOperation, Runtime, RuntimeInterface};
+
+#[cfg(test)]
+mod tests;
+
+/// Hooks for intercepting the calls made to a wrapped runtime.
+///
+/// Calls from the user program (allocation, gates, measurements, barriers and
+/// so on) may be transformed, rejected or expanded before they reach the inner
+/// runtime, and the batches of operations the inner runtime produces may be
+/// transformed in [RuntimeWrapper::get_next_operations] before they reach the
+/// error model.
+///
+/// Result and reference count bookkeeping is not hookable: those calls are
+/// always passed directly to the inner runtime, which owns the results.
+pub trait RuntimeWrapper {
+    /// The optional features supported by the wrapped runtime. Override this
+    /// if the wrapper adds features to, or removes them from, those of the
+    /// inner runtime.
+    fn capabilities(&self, inner: &Runtime) -> Capabilities {
+        inner.capabilities()
+    }
+
+    fn exit(&mut self, inner: &mut Runtime) -> Result<()> {
+        inner.exit()
+    }
+
+    fn shot_start(&mut self, inner: &mut Runtime, shot_id: u64, seed: u64) -> Result<()> {
+        inner.shot_start(shot_id, seed)
+    }
+
+    fn shot_end(&mut self, inner: &mut Runtime) -> Result<()> {
+        inner.shot_end()
+    }
+
+    /// Retrieve the next batch of operations. Override this to transform the
+    /// batches produced by the inner runtime.
+    fn get_next_operations(&mut self, inner: &mut Runtime) -> Result<Option<BatchOperation>> {
+        inner.get_next_operations()
+    }
+
+    /// Provide metrics belonging to the wrapper. These are reported before
+    /// the metrics of the inner runtime, which are reported after the first
+    /// `None` returned from this function. Metrics may be requested in any
+    /// order, so the wrapper's metrics must be numbered contiguously from
+    /// zero, and a request must not change them.
+    fn get_metric(&mut self, _nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        Ok(None)
+    }
+
+    fn custom_call(&mut self, inner: &mut Runtime, tag: u64, data: &[u8]) -> Result<u64> {
+        inner.custom_call(tag, data)
+    }
+
+    fn begin_condition(&mut self, inner: &mut Runtime, result_id: u64, value: bool) -> Result<()> {
+        inner.begin_condition(result_id, value)
+    }
+
+    fn end_condition(&mut self, inner: &mut Runtime) -> Result<()> {
+        inner.end_condition()
+    }
+
+    fn qalloc(&mut self, inner: &mut Runtime) -> Result<u64> {
+        inner.qalloc()
+    }
+
+    fn qfree(&mut self, inner: &mut Runtime, qubit_id: u64) -> Result<()> {
+        inner.qfree(qubit_id)
+    }
+
+    fn rxy_gate(&mut self, inner: &mut Runtime, qubit_id: u64, theta: f64, phi: f64) -> Result<()> {
+        inner.rxy_gate(qubit_id, theta, phi)
+    }
+
+    fn rzz_gate(
+        &mut self,
+        inner: &mut Runtime,
+        qubit_id_1: u64,
+        qubit_id_2: u64,
+        theta: f64,
+    ) -> Result<()> {
+        inner.rzz_gate(qubit_id_1, qubit_id_2, theta)
+    }
+
+    fn rz_gate(&mut self, inner: &mut Runtime, qubit_id: u64, theta: f64) -> Result<()> {
+        inner.rz_gate(qubit_id, theta)
+    }
+
+    fn measure(&mut self, inner: &mut Runtime, qubit_id: u64) -> Result<u64> {
+        inner.measure(qubit_id)
+    }
+
+    fn measure_leaked(&mut self, inner: &mut Runtime, qubit_id: u64) -> Result<u64> {
+        inner.measure_leaked(qubit_id)
+    }
+
+    fn measure_reset(&mut self, inner: &mut Runtime, qubit_id: u64) -> Result<u64> {
+        inner.measure_reset(qubit_id)
+    }
+
+    fn reset(&mut self, inner: &mut Runtime, qubit_id: u64) -> Result<()> {
+        inner.reset(qubit_id)
+    }
+
+    fn local_barrier(&mut self, inner: &mut Runtime, qubits: &[u64], sleep_ns: u64) -> Result<()> {
+        inner.local_barrier(qubits, sleep_ns)
+    }
+
+    fn global_barrier(&mut self, inner: &mut Runtime, sleep_ns: u64) -> Result<()> {
+        inner.global_barrier(sleep_ns)
+    }
+}
+
+/// A runtime composed of an inner [Runtime] and a [RuntimeWrapper] that
+/// intercepts calls to it.
+pub struct WrappedRuntime<W> {
+    inner: Runtime,
+    wrapper: W,
+}
+
+impl<W: RuntimeWrapper> WrappedRuntime<W> {
+    pub fn new(inner: Runtime, wrapper: W) -> Self {
+        Self { inner, wrapper }
+    }
+
+    pub fn wrapper(&self) -> &W {
+        &self.wrapper
+    }
+
+    pub fn wrapper_mut(&mut self) -> &mut W {
+        &mut self.wrapper
+    }
+}
+
+impl<W: RuntimeWrapper> RuntimeInterface for WrappedRuntime<W> {
+    fn instance_capabilities(&mut self) -> Result<Option<Capabilities>> {
+        Ok(Some(self.wrapper.capabilities(&self.inner)))
+    }
+
+    fn exit(&mut self) -> Result<()> {
+        self.wrapper.exit(&mut self.inner)
+    }
+
+    fn get_next_operations(&mut self) -> Result<Option<BatchOperation>> {
+        self.wrapper.get_next_operations(&mut self.inner)
+    }
+
+    fn shot_start(&mut self, shot_id: u64, seed: u64) -> Result<()> {
+        self.wrapper.shot_start(&mut self.inner, shot_id, seed)
+    }
+
+    fn shot_end(&mut self) -> Result<()> {
+        self.wrapper.shot_end(&mut self.inner)
+    }
+
+    fn custom_call(&mut self, tag: u64, data: &[u8]) -> Result<u64> {
+        self.wrapper.custom_call(&mut self.inner, tag, data)
+    }
+
+    fn begin_condition(&mut self, result_id: u64, value: bool) -> Result<()> {
+        self.wrapper
+            .begin_condition(&mut self.inner, result_id, value)
+    }
+
+    fn end_condition(&mut self) -> Result<()> {
+        self.wrapper.end_condition(&mut self.inner)
+    }
+
+    fn get_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        if let Some(metric) = self.wrapper.get_metric(nth_metric)? {
+            return Ok(Some(metric));
+        }
+        // The inner runtime's metrics follow the wrapper's, which end at the
+        // first `None`. That is at or before `nth_metric`, and is found afresh
+        // on each request so that metrics may be requested in any order.
+        let mut n_wrapper_metrics = 0;
+        while n_wrapper_metrics < nth_metric
+            && self.wrapper.get_metric(n_wrapper_metrics)?.is_some()
+        {
+            n_wrapper_metrics += 1;
+        }
+        self.inner.get_metric(nth_metric - n_wrapper_metrics)
+    }
+
+    fn qalloc(&mut self) -> Result<u64> {
+        self.wrapper.qalloc(&mut self.inner)
+    }
+
+    fn qfree(&mut self, qubit_id: u64) -> Result<()> {
+        self.wrapper.qfree(&mut self.inner, qubit_id)
+    }
+
+    fn rxy_gate(&mut self, qubit_id: u64, theta: f64, phi: f64) -> Result<()> {
+        self.wrapper.rxy_gate(&mut self.inner, qubit_id, theta, phi)
+    }
+
+    fn rzz_gate(&mut self, qubit_id_1: u64, qubit_id_2: u64, theta: f64) -> Result<()> {
+        self.wrapper
+            .rzz_gate(&mut self.inner, qubit_id_1, qubit_id_2, theta)
+    }
+
+    fn rz_gate(&mut self, qubit_id: u64, theta: f64) -> Result<()> {
+        self.wrapper.rz_gate(&mut self.inner, qubit_id, theta)
+    }
+
+    fn measure(&mut self, qubit_id: u64) -> Result<u64> {
+        self.wrapper.measure(&mut self.inner, qubit_id)
+    }
+
+    fn measure_leaked(&mut self, qubit_id: u64) -> Result<u64> {
+        self.wrapper.measure_leaked(&mut self.inner, qubit_id)
+    }
+
+    fn measure_reset(&mut self, qubit_id: u64) -> Result<u64> {
+        self.wrapper.measure_reset(&mut self.inner, qubit_id)
+    }
+
+    fn reset(&mut self, qubit_id: u64) -> Result<()> {
+        self.wrapper.reset(&mut self.inner, qubit_id)
+    }
+
+    fn force_result(&mut self, result_id: u64) -> Result<()> {
+        self.inner.force_result(result_id)
+    }
+
+    fn get_bool_result(&mut self, result_id: u64) -> Result<Option<bool>> {
+        self.inner.get_bool_result(result_id)
+    }
+
+    fn get_u64_result(&mut self, result_id: u64) -> Result<Option<u64>> {
+        self.inner.get_u64_result(result_id)
+    }
+
+    fn set_bool_result(&mut self, result_id: u64, result: bool) -> Result<()> {
+        self.inner.set_bool_result(result_id, result)
+    }
+
+    fn set_u64_result(&mut self, result_id: u64, result: u64) -> Result<()> {
+        self.inner.set_u64_result(result_id, result)
+    }
+
+    fn increment_future_refcount(&mut self, future: u64) -> Result<()> {
+        self.inner.increment_future_refcount(future)
+    }
+
+    fn decrement_future_refcount(&mut self, future: u64) -> Result<()> {
+        self.inner.decrement_future_refcount(future)
+    }
+
+    fn local_barrier(&mut self, qubits: &[u64], sleep_ns: u64) -> Result<()> {
+        self.wrapper
+            .local_barrier(&mut self.inner, qubits, sleep_ns)
+    }
+
+    fn global_barrier(&mut self, sleep_ns: u64) -> Result<()> {
+        self.wrapper.global_barrier(&mut self.inner, sleep_ns)
+    }
+}
@@ -0,0 +1,290 @@
+use super::*;
+use crate::capabilities::Capabilities;
+use crate::runtime::RuntimeInterfaceFactory;
+use std::sync::{Arc, Mutex};
+
+type Calls = Arc<Mutex<Vec<String>>>;
+
+/// A runtime recording the calls it receives, with two metrics of its own.
+struct Recorder(Calls);
+
+impl Recorder {
+    fn record(&self, call: impl Into<String>) {
+        self.0.lock().unwrap().push(call.into());
+    }
+}
+
+impl RuntimeInterface for Recorder {
+    fn exit(&mut self) -> Result<()> {
+        self.record("exit");
+        Ok(())
+    }
+    fn get_next_operations(&mut self) -> Result<Option<BatchOperation>> {
+        self.record("get_next_operations");
+        Ok(None)
+    }
+    fn shot_start(&mut self, shot_id: u64, seed: u64) -> Result<()> {
+        self.record(format!("shot_start {shot_id} {seed}"));
+        Ok(())
+    }
+    fn shot_end(&mut self) -> Result<()> {
+        self.record("shot_end");
+        Ok(())
+    }
+    fn custom_call(&mut self, tag: u64, data: &[u8]) -> Result<u64> {
+        self.record(format!("custom_call {tag} {data:?}"));
+        Ok(14)
+    }
+    fn begin_condition(&mut self, result_id: u64, value: bool) -> Result<()> {
+        self.record(format!("begin_condition {result_id} {value}"));
+        Ok(())
+    }
+    fn end_condition(&mut self) -> Result<()> {
+        self.record("end_condition");
+        Ok(())
+    }
+    fn get_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        Ok((nth_metric < 2).then(|| {
+            (
+                format!("inner_{nth_metric}"),
+                MetricValue::U64(nth_metric.into()),
+            )
+        }))
+    }
+    fn qalloc(&mut self) -> Result<u64> {
+        self.record("qalloc");
+        Ok(7)
+    }
+    fn qfree(&mut self, qubit_id: u64) -> Result<()> {
+        self.record(format!("qfree {qubit_id}"));
+        Ok(())
+    }
+    fn rxy_gate(&mut self, qubit_id: u64, theta: f64, phi: f64) -> Result<()> {
+        self.record(format!("rxy_gate {qubit_id} {theta} {phi}"));
+        Ok(())
+    }
+    fn rzz_gate(&mut self, qubit_id_1: u64, qubit_id_2: u64, theta: f64) -> Result<()> {
+        self.record(format!("rzz_gate {qubit_id_1} {qubit_id_2} {theta}"));
+        Ok(())
+    }
+    fn rz_gate(&mut self, qubit_id: u64, theta: f64) -> Result<()> {
+        self.record(format!("rz_gate {qubit_id} {theta}"));
+        Ok(())
+    }
+    fn measure(&mut self, qubit_id: u64) -> Result<u64> {
+        self.record(format!("measure {qubit_id}"));
+        Ok(11)
+    }
+    fn measure_leaked(&mut self, qubit_id: u64) -> Result<u64> {
+        self.record(format!("measure_leaked {qubit_id}"));
+        Ok(12)
+    }
+    fn measure_reset(&mut self, qubit_id: u64) -> Result<u64> {
+        self.record(format!("measure_reset {qubit_id}"));
+        Ok(13)
+    }
+    fn reset(&mut self, qubit_id: u64) -> Result<()> {
+        self.record(format!("reset {qubit_id}"));
+        Ok(())
+    }
+    fn force_result(&mut self, result_id: u64) -> Result<()> {
+        self.record(format!("force_result {result_id}"));
+        Ok(())
+    }
+    fn get_bool_result(&mut self, result_id: u64) -> Result<Option<bool>> {
+        self.record(format!("get_bool_result {result_id}"));
+        Ok(Some(true))
+    }
+    fn get_u64_result(&mut self, result_id: u64) -> Result<Option<u64>> {
+        self.record(format!("get_u64_result {result_id}"));
+        Ok(Some(5))
+    }
+    fn set_bool_result(&mut self, result_id: u64, result: bool) -> Result<()> {
+        self.record(format!("set_bool_result {result_id} {result}"));
+        Ok(())
+    }
+    fn set_u64_result(&mut self, result_id: u64, result: u64) -> Result<()> {
+        self.record(format!("set_u64_result {result_id} {result}"));
+        Ok(())
+    }
+    fn increment_future_refcount(&mut self, future: u64) -> Result<()> {
+        self.record(format!("increment_future_refcount {future}"));
+        Ok(())
+    }
+    fn decrement_future_refcount(&mut self, future: u64) -> Result<()> {
+        self.record(format!("decrement_future_refcount {future}"));
+        Ok(())
+    }
+    fn local_barrier(&mut self, qubits: &[u64], sleep_ns: u64) -> Result<()> {
+        self.record(format!("local_barrier {qubits:?} {sleep_ns}"));
+        Ok(())
+    }
+    fn global_barrier(&mut self, sleep_ns: u64) -> Result<()> {
+        self.record(format!("global_barrier {sleep_ns}"));
+        Ok(())
+    }
+}
+
+struct RecorderFactory(Calls);
+
+impl RuntimeInterfaceFactory for RecorderFactory {
+    type Interface = Recorder;
+
+    fn init(
+        self: Arc<Self>,
+        _n_qubits: u64,
+        _start: crate::time::Instant,
+        _args: &[impl AsRef<str>],
+    ) -> Result<Box<Self::Interface>> {
+        Ok(Box::new(Recorder(self.0.clone())))
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::CUSTOM_OPS | Capabilities::METRICS
+    }
+}
+
+/// A wrapper that overrides nothing.
+struct PassThrough;
+
+impl RuntimeWrapper for PassThrough {}
+
+/// A wrapper that doubles the angles of RZ gates, counting them in a metric,
+/// and reports a second metric of its own.
+#[derive(Default)]
+struct DoubleRz {
+    rz_gates: u64,
+}
+
+impl RuntimeWrapper for DoubleRz {
+    fn get_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        Ok(match nth_metric {
+            0 => Some(("rz_gates".to_string(), MetricValue::U64(self.rz_gates))),
+            1 => Some(("doubled".to_string(), MetricValue::Bool(true))),
+            _ => None,
+        })
+    }
+
+    fn rz_gate(&mut self, inner: &mut Runtime, qubit_id: u64, theta: f64) -> Result<()> {
+        self.rz_gates += 1;
+        inner.rz_gate(qubit_id, 2.0 * theta)
+    }
+}
+
+fn wrap<W: RuntimeWrapper>(wrapper: W) -> (WrappedRuntime<W>, Calls) {
+    let calls = Calls::default();
+    let inner = Runtime::new(
+        Arc::new(RecorderFactory(calls.clone())),
+        4,
+        Default::default(),
+        &[] as &[&str],
+    )
+    .unwrap();
+    (WrappedRuntime::new(inner, wrapper), calls)
+}
+
+fn take(calls: &Calls) -> Vec<String> {
+    std::mem::take(&mut *calls.lock().unwrap())
+}
+
+#[test]
+fn every_call_is_passed_through() {
+    let (mut runtime, calls) = wrap(PassThrough);
+    runtime.shot_start(3, 4).unwrap();
+    assert_eq!(runtime.qalloc().unwrap(), 7);
+    runtime.rxy_gate(7, 0.5, 0.25).unwrap();
+    runtime.rzz_gate(7, 8, 0.5).unwrap();
+    runtime.rz_gate(7, 0.125).unwrap();
+    assert_eq!(runtime.measure(7).unwrap(), 11);
+    assert_eq!(runtime.measure_leaked(7).unwrap(), 12);
+    assert_eq!(runtime.measure_reset(7).unwrap(), 13);
+    runtime.reset(7).unwrap();
+    assert_eq!(runtime.custom_call(2, &[1, 2]).unwrap(), 14);
+    runtime.begin_condition(11, true).unwrap();
+    runtime.end_condition().unwrap();
+    runtime.local_barrier(&[7, 8], 10).unwrap();
+    runtime.global_barrier(20).unwrap();
+    runtime.force_result(11).unwrap();
+    assert_eq!(runtime.get_bool_result(11).unwrap(), Some(true));
+    assert_eq!(runtime.get_u64_result(12).unwrap(), Some(5));
+    runtime.set_bool_result(11, false).unwrap();
+    runtime.set_u64_result(12, 6).unwrap();
+    runtime.increment_future_refcount(11).unwrap();
+    runtime.decrement_future_refcount(11).unwrap();
+    runtime.qfree(7).unwrap();
+    assert!(runtime.get_next_operations().unwrap().is_none());
+    runtime.shot_end().unwrap();
+    runtime.exit().unwrap();
+    assert_eq!(
+        take(&calls),
+        [
+            "shot_start 3 4",
+            "qalloc",
+            "rxy_gate 7 0.5 0.25",
+            "rzz_gate 7 8 0.5",
+            "rz_gate 7 0.125",
+            "measure 7",
+            "measure_leaked 7",
+            "measure_reset 7",
+            "reset 7",
+            "custom_call 2 [1, 2]",
+            "begin_condition 11 true",
+            "end_condition",
+            "local_barrier [7, 8] 10",
+            "global_barrier 20",
+            "force_result 11",
+            "get_bool_result 11",
+            "get_u64_result 12",
+            "set_bool_result 11 false",
+            "set_u64_result 12 6",
+            "increment_future_refcount 11",
+            "decrement_future_refcount 11",
+            "qfree 7",
+            "get_next_operations",
+            "shot_end",
+            "exit",
+        ]
+    );
+    assert_eq!(
+        runtime.get_metric(0).unwrap(),
+        Some(("inner_0".to_string(), MetricValue::U64(0)))
+    );
+    assert_eq!(
+        runtime.instance_capabilities().unwrap(),
+        Some(Capabilities::CUSTOM_OPS | Capabilities::METRICS)
+    );
+}
+
+#[test]
+fn overriding_one_hook_leaves_the_others() {
+    let (mut runtime, calls) = wrap(DoubleRz::default());
+    runtime.rz_gate(1, 0.25).unwrap();
+    runtime.rxy_gate(1, 0.25, 0.5).unwrap();
+    runtime.rz_gate(2, 0.5).unwrap();
+    assert_eq!(
+        take(&calls),
+        ["rz_gate 1 0.5", "rxy_gate 1 0.25 0.5", "rz_gate 2 1"]
+    );
+    assert_eq!(runtime.wrapper().rz_gates, 2);
+}
+
+#[test]
+fn inner_metrics_follow_the_wrapper_metrics_in_any_order() {
+    let (mut runtime, _) = wrap(DoubleRz::default());
+    runtime.rz_gate(1, 0.25).unwrap();
+    let metric = |name: &str, value| Some((name.to_string(), value));
+    let expected = [
+        metric("rz_gates", MetricValue::U64(1)),
+        metric("doubled", MetricValue::Bool(true)),
+        metric("inner_0", MetricValue::U64(0)),
+        metric("inner_1", MetricValue::U64(1)),
+        None,
+    ];
+    for nth_metric in [3, 0, 2, 4, 1, 2, 3] {
+        assert_eq!(
+            runtime.get_metric(nth_metric).unwrap(),
+            expected[usize::from(nth_metric)],
+            "metric {nth_metric}"
+        );
+    }
+}
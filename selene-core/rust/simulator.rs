@@ -10,6 +10,7 @@ use std::sync::Arc;
 pub use interface::{SimulatorInterface, SimulatorInterfaceFactory};
 pub use version::SimulatorAPIVersion;
 
+use crate::capabilities::Capabilities;
 use crate::utils::MetricValue;
 use anyhow::Result;
 use delegate::delegate;
@@ -22,7 +23,7 @@ use delegate::delegate;
 /// Usually the wrapped [SimulatorInterface] will be an instance
 /// delegating to an external simulator plugin as defined by
 /// [plugin::SimulatorPluginInterface)].
-pub struct Simulator(Box<dyn SimulatorInterface>);
+pub struct Simulator(Box<dyn SimulatorInterface>, Capabilities);
 
 impl Simulator {
     /// Constructs a new Simulator from a [SimulatorInterfaceFactory].
@@ -31,19 +32,52 @@ impl Simulator {
         n_qubits: u64,
         args: &[impl AsRef<str>],
     ) -> Result<Self> {
-        Ok(Self(factory.init(n_qubits, args)?))
+        let capabilities = factory.capabilities();
+        Ok(Self(factory.init(n_qubits, args)?, capabilities))
     }
 
+    /// Loads the simulator plugin at the given path, or the registered
+    /// simulator it refers to (see [crate::registry]). Paths with the
+    /// `isolated:` prefix are hosted out of process (see [crate::host]).
     pub fn load_from_file(
         plugin_path: &impl AsRef<OsStr>,
         n_qubits: u64,
         args: &[impl AsRef<str>],
     ) -> Result<Self> {
+        if let Some(name) = crate::registry::builtin_name(plugin_path.as_ref()) {
+            return crate::registry::new_simulator(name, n_qubits, args);
+        }
+        if let Some(plugin_path) = crate::host::isolated_plugin(plugin_path.as_ref()) {
+            let factory = crate::host::RemoteSimulatorFactory::launch(plugin_path)?;
+            return Self::new(Arc::new(factory), n_qubits, args);
+        }
         let plugin = plugin::SimulatorPluginInterface::new_from_file(plugin_path)?;
         Self::new(plugin, n_qubits, args)
     }
 }
 
+impl Simulator {
+    /// The optional features supported by the simulator.
+    pub fn capabilities(&self) -> Capabilities {
+        self.1
+    }
+
+    /// The optional features supported by the simulator plugin at the given
+    /// path, or the registered simulator it refers to, without constructing
+    /// it. Simulators are constructed by the error model, so this is how their
+    /// capabilities are found.
+    pub fn capabilities_of(plugin_path: &impl AsRef<OsStr>) -> Result<Capabilities> {
+        if let Some(name) = crate::registry::builtin_name(plugin_path.as_ref()) {
+            return crate::registry::simulator_capabilities(name);
+        }
+        if let Some(plugin_path) = crate::host::isolated_plugin(plugin_path.as_ref()) {
+            return Ok(crate::host::RemoteSimulatorFactory::launch(plugin_path)?.capabilities());
+        }
+        let plugin = plugin::SimulatorPluginInterface::new_from_file(plugin_path)?;
+        Ok(plugin.capabilities())
+    }
+}
+
 impl AsRef<dyn SimulatorInterface> for Simulator {
     fn as_ref(&self) -> &(dyn SimulatorInterface + 'static) {
         self.0.as_ref()
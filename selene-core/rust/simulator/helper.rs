@@ -9,7 +9,9 @@ use super::{
     interface::SimulatorInterfaceFactory,
     plugin::{Errno, SimulatorInstance},
 };
-use crate::utils::{convert_cargs_to_strings, result_of_errno_to_errno, result_to_errno};
+use crate::utils::{
+    convert_cargs_to_strings, error_to_errno, result_of_errno_to_errno, result_to_errno,
+};
 
 #[derive(Default)]
 /// A helper struct used by [crate::export_simulator_plugin] to implement the simulator
@@ -148,10 +150,7 @@ impl<F: SimulatorInterfaceFactory> Helper<F> {
         match result {
             Ok(false) => 0,
             Ok(true) => 1,
-            Err(e) => {
-                eprintln!("Failed to measure qubit {qubit}: {e:?}");
-                -1
-            }
+            Err(e) => error_to_errno(format!("Failed to measure qubit {qubit}"), e),
         }
     }
     pub unsafe fn postselect(instance: SimulatorInstance, qubit: u64, target_value: bool) -> Errno {
@@ -204,8 +203,16 @@ macro_rules! crate_to_inline_simulator {
 /// A crate can only export a single runtime plugin (although it may export
 /// other plugins of other types). See the `selene-coinflip-plugin` python package
 /// for a fully worked example.
+///
+/// The plugin describes itself in its metadata under the name given after the
+/// factory type, which should be the name it is registered under when linked
+/// into selene (see [crate::registry]), or under its package name if no name
+/// is given.
 macro_rules! export_simulator_plugin {
     ($factory_type:ty) => {
+        $crate::export_simulator_plugin!($factory_type, env!("CARGO_PKG_NAME"));
+    };
+    ($factory_type:ty, $name:expr) => {
         mod _plugin {
             use selene_core::simulator::{
                 interface::SimulatorInterfaceFactory,
@@ -237,10 +244,10 @@ macro_rules! export_simulator_plugin {
             /// validating the API version of the plugin against its own
             /// version.
             ///
-            /// The plans for this validation are a work-in-progress, but
-            /// currently selene will reject any plugin that has a different
-            /// major or minor version than the current Selene version, or with
-            /// a reserved field that is not 0.
+            /// Selene rejects plugins with a different major version, a newer
+            /// minor version, or a minor version older than it supports, as well
+            /// as plugins with a reserved field that is not 0. Plugins of older
+            /// supported minor versions are adapted to.
             #[unsafe(no_mangle)]
             pub unsafe extern "C" fn selene_simulator_get_api_version() -> u64 {
                 // The API version is defined in the version module
@@ -432,6 +439,52 @@ macro_rules! export_simulator_plugin {
             ) -> i32 {
                 Helper::dump_state(instance, file, qubits, n_qubits)
             }
+
+            /// Get the message describing the latest error returned by the simulator
+            /// in the calling thread, as a null terminated string, or null if there
+            /// has been no error. The string remains valid until the next error.
+            ///
+            /// Selene calls this after an entry point returns an error, and reports
+            /// the message along with the error. Plugins that do not provide this
+            /// entry point should write any details of their errors to stderr.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_simulator_last_error_message() -> *const c_char {
+                selene_core::utils::last_error_message_ptr()
+            }
+
+            /// Get the optional features supported by the simulator, as the bits of a
+            /// `selene_core::capabilities::Capabilities` set. Selene only uses a
+            /// declared capability if the entry points it relies on are present.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_simulator_get_capabilities() -> u64 {
+                <$factory_type as Default>::default().capabilities().bits()
+            }
+
+            /// Get a description of the simulator as a nul-terminated JSON string, in
+            /// the form of a `selene_core::metadata::PluginMetadata`: its name and
+            /// version, the API version, its capabilities and the arguments it
+            /// accepts. The string is owned by the plugin and lives as long as it
+            /// is loaded.
+            #[unsafe(no_mangle)]
+            pub unsafe extern "C" fn selene_simulator_get_metadata() -> *const c_char {
+                static METADATA: std::sync::OnceLock<std::ffi::CString> =
+                    std::sync::OnceLock::new();
+                METADATA
+                    .get_or_init(|| {
+                        let factory = <$factory_type as Default>::default();
+                        selene_core::metadata::PluginMetadata::new(
+                            selene_core::metadata::PluginKind::Simulator,
+                            $name,
+                            env!("CARGO_PKG_NAME"),
+                            env!("CARGO_PKG_VERSION"),
+                            CURRENT_API_VERSION,
+                            factory.capabilities(),
+                            factory.arguments(),
+                        )
+                        .to_c_string()
+                    })
+                    .as_ptr()
+            }
         }
     };
 }
@@ -1,6 +1,8 @@
 use anyhow::Result;
 use std::sync::Arc;
 
+use crate::capabilities::Capabilities;
+use crate::metadata::ArgumentMetadata;
 use crate::utils::MetricValue;
 
 pub trait SimulatorInterface {
@@ -66,4 +68,18 @@ pub trait SimulatorInterfaceFactory {
         n_qubits: u64,
         args: &[impl AsRef<str>],
     ) -> Result<Box<Self::Interface>>;
+
+    /// The optional features supported by the interfaces this factory
+    /// creates. See [Capabilities].
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::empty()
+    }
+
+    /// The arguments accepted by [Self::init], reported in the plugin's
+    /// metadata so that they can be checked before a run. `None` if they are
+    /// not described. See `ArgumentMetadata::from_clap` for factories that
+    /// parse their arguments with clap.
+    fn arguments(&self) -> Option<Vec<ArgumentMetadata>> {
+        None
+    }
 }
@@ -1,5 +1,10 @@
 use super::{SimulatorAPIVersion, SimulatorInterface, SimulatorInterfaceFactory};
-use crate::utils::{MetricValue, check_errno, read_raw_metric, with_strings_to_cargs};
+use crate::capabilities::Capabilities;
+use crate::error::ErrorKind;
+use crate::utils::{
+    MetricValue, check_plugin_errno, has_entry_point, plugin_capabilities, plugin_error,
+    read_raw_metric, with_strings_to_cargs,
+};
 use anyhow::{Result, anyhow};
 use libloading;
 use ouroboros::self_referencing;
@@ -101,6 +106,14 @@ pub type Errno = i32;
 ///    there are still measurements to replay, the plugin can return an error code to indicate
 ///    that it is not a faithful replay.
 ///
+/// - (optional) `const char* selene_simulator_last_error_message()`
+///    If present, returns a message describing the latest error returned by the plugin in the
+///    calling thread, which selene reports along with the error. Otherwise, plugins should
+///    write details of errors to stderr.
+///
+/// A nonzero value returned on failure may be the code of an [ErrorKind] to categorise the
+/// error, e.g. [ErrorKind::QubitOutOfBounds]. Any other value is treated as an internal error.
+///
 /// Plugins are used allow implementations of simulation backends to be written and
 /// distributed independently of selene. Users should be cautious about the plugins they use,
 /// as it is possible that mistakes or malicious code could be present in the plugin, and, as
@@ -111,6 +124,7 @@ pub struct SimulatorPluginInterface {
     lib: libloading::Library,
     name: String,
     version: SimulatorAPIVersion,
+    capabilities: Capabilities,
     #[borrows(lib)]
     #[covariant]
     init_fn: libloading::Symbol<
@@ -208,8 +222,23 @@ pub struct SimulatorPluginInterface {
             n_qubits: u64,
         ) -> Errno,
     >,
+
+    #[borrows(lib)]
+    #[covariant]
+    last_error_message_fn:
+        Option<libloading::Symbol<'this, unsafe extern "C" fn() -> *const c_char>>,
 }
 impl SimulatorPluginInterface {
+    /// Checks the errno returned by a call to the plugin, reporting the
+    /// plugin's own message for the error if it provides one.
+    fn check_errno(&self, errno: Errno, mk_err: impl FnOnce() -> anyhow::Error) -> Result<()> {
+        check_plugin_errno(
+            errno,
+            self.borrow_last_error_message_fn().as_deref(),
+            mk_err,
+        )
+    }
+
     pub fn new_from_file(plugin_file: impl AsRef<OsStr>) -> Result<Arc<Self>> {
         let lib = unsafe { libloading::Library::new(plugin_file.as_ref()) }.map_err(|e| {
             anyhow!(
@@ -247,9 +276,22 @@ impl SimulatorPluginInterface {
                 "Unknown".to_string()
             }
         };
+        let inferred = Capabilities::POSTSELECT.when(has_entry_point(
+            &lib,
+            b"selene_simulator_operation_postselect",
+        )) | Capabilities::METRICS
+            .when(has_entry_point(&lib, b"selene_simulator_get_metrics"))
+            | Capabilities::DUMP_STATE;
+        let capabilities = plugin_capabilities(
+            &lib,
+            b"selene_simulator_get_capabilities",
+            inferred,
+            inferred,
+        );
         let result = SimulatorPluginInterfaceTryBuilder {
             lib,
             version,
+            capabilities,
             name,
             init_fn_builder: |lib| unsafe { lib.get(b"selene_simulator_init") },
             exit_fn_builder: |lib| unsafe { Ok(lib.get(b"selene_simulator_exit").ok()) },
@@ -267,6 +309,9 @@ impl SimulatorPluginInterface {
                 Ok(lib.get(b"selene_simulator_get_metrics").ok())
             },
             dump_state_fn_builder: |lib| unsafe { lib.get(b"selene_simulator_dump_state") },
+            last_error_message_fn_builder: |lib| unsafe {
+                Ok(lib.get(b"selene_simulator_last_error_message").ok())
+            },
         }
         .try_build()?;
         Ok(Arc::new(result))
@@ -281,6 +326,10 @@ pub struct SimulatorPlugin {
 impl SimulatorInterfaceFactory for SimulatorPluginInterface {
     type Interface = SimulatorPlugin;
 
+    fn capabilities(&self) -> Capabilities {
+        *self.borrow_capabilities()
+    }
+
     fn init(
         self: Arc<Self>,
         n_qubits: u64,
@@ -288,7 +337,7 @@ impl SimulatorInterfaceFactory for SimulatorPluginInterface {
     ) -> Result<Box<Self::Interface>> {
         let mut instance = std::ptr::null_mut();
         with_strings_to_cargs(args, |argc, argv| {
-            check_errno(
+            self.check_errno(
                 unsafe { self.borrow_init_fn()(&mut instance, n_qubits, argc, argv) },
                 || anyhow!("SimulatorPlugin: init failed"),
             )
@@ -305,12 +354,13 @@ impl SimulatorInterface for SimulatorPlugin {
         let Some(exit_fn) = self.interface.borrow_exit_fn() else {
             return Ok(());
         };
-        check_errno(unsafe { exit_fn(self.instance) }, || {
-            anyhow!("SimulatorPlugin: exit failed")
-        })
+        self.interface
+            .check_errno(unsafe { exit_fn(self.instance) }, || {
+                anyhow!("SimulatorPlugin: exit failed")
+            })
     }
     fn shot_start(&mut self, shot_id: u64, seed: u64) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe { self.interface.borrow_shot_start_fn()(self.instance, shot_id, seed) },
             || {
                 anyhow!(
@@ -321,7 +371,7 @@ impl SimulatorInterface for SimulatorPlugin {
         )
     }
     fn shot_end(&mut self) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe { self.interface.borrow_shot_end_fn()(self.instance) },
             || {
                 anyhow!(
@@ -332,7 +382,7 @@ impl SimulatorInterface for SimulatorPlugin {
         )
     }
     fn rxy(&mut self, qubit: u64, theta: f64, phi: f64) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe { self.interface.borrow_rxy_fn()(self.instance, qubit, theta, phi) },
             || {
                 anyhow!(
@@ -343,7 +393,7 @@ impl SimulatorInterface for SimulatorPlugin {
         )
     }
     fn rz(&mut self, qubit: u64, theta: f64) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe { self.interface.borrow_rz_fn()(self.instance, qubit, theta) },
             || {
                 anyhow!(
@@ -354,7 +404,7 @@ impl SimulatorInterface for SimulatorPlugin {
         )
     }
     fn rzz(&mut self, qubit1: u64, qubit2: u64, theta: f64) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe { (self.interface.borrow_rzz_fn())(self.instance, qubit1, qubit2, theta) },
             || {
                 anyhow!(
@@ -369,19 +419,25 @@ impl SimulatorInterface for SimulatorPlugin {
         match result {
             0 => Ok(false),
             1 => Ok(true),
-            _ => Err(anyhow!(
-                "SimulatorPlugin({}): measure failed",
-                self.interface.borrow_name()
+            errno => Err(plugin_error(
+                errno,
+                self.interface.borrow_last_error_message_fn().as_deref(),
+                || {
+                    anyhow!(
+                        "SimulatorPlugin({}): measure failed",
+                        self.interface.borrow_name()
+                    )
+                },
             )),
         }
     }
     fn postselect(&mut self, qubit: u64, target_value: bool) -> Result<()> {
         let Some(postselect_fn) = self.interface.borrow_postselect_fn() else {
-            return Err(anyhow!(
-                "The chosen simulator does not support postselection"
-            ));
+            return Err(
+                ErrorKind::Unsupported.error("The chosen simulator does not support postselection")
+            );
         };
-        check_errno(
+        self.interface.check_errno(
             unsafe { postselect_fn(self.instance, qubit, target_value) },
             || {
                 anyhow!(
@@ -392,7 +448,7 @@ impl SimulatorInterface for SimulatorPlugin {
         )
     }
     fn reset(&mut self, qubit: u64) -> Result<()> {
-        check_errno(
+        self.interface.check_errno(
             unsafe { (self.interface.borrow_reset_fn())(self.instance, qubit) },
             || {
                 anyhow!(
@@ -420,7 +476,7 @@ impl SimulatorInterface for SimulatorPlugin {
             )
         })?;
         let safe_filename = std::ffi::CString::new(filename).unwrap();
-        check_errno(
+        self.interface.check_errno(
             unsafe {
                 (self.interface.borrow_dump_state_fn())(
                     self.instance,
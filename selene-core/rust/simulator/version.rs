@@ -22,6 +22,11 @@ impl From<u64> for SimulatorAPIVersion {
         }
     }
 }
+impl std::fmt::Display for SimulatorAPIVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
 impl From<SimulatorAPIVersion> for u64 {
     fn from(value: SimulatorAPIVersion) -> u64 {
         ((value.reserved as u64) << 24)
@@ -35,17 +40,22 @@ pub const CURRENT_API_VERSION: SimulatorAPIVersion = SimulatorAPIVersion {
     reserved: 0,
     major: 0,
     minor: 1,
-    patch: 0,
+    patch: 1,
 };
 
+// Changelog:
+// 0.1.0: Initial version.
+// 0.1.1: Introduced the optional get_capabilities entry point
+
+/// The oldest minor version of the current major version that selene can still load.
+pub const MIN_SUPPORTED_MINOR: u8 = 1;
+
 impl SimulatorAPIVersion {
+    pub fn minor(&self) -> u8 {
+        self.minor
+    }
+
     pub fn validate(&self) -> Result<()> {
-        // Note: this is a naive check at the moment, as we have not introduced a breaking
-        // change since versioning was introduced. This logic should evolve as and when
-        // changes occur.
-        //
-        // As such, this is mostly a sketch of what the logic may look like.
-        //
         // Reserved must be 0. We may want to attribute meaning to this one day.
         if self.reserved != 0 {
             return Err(anyhow!(
@@ -61,13 +71,21 @@ impl SimulatorAPIVersion {
                 self.major
             ));
         }
-        // If the minor version is different, the plugin might be incompatible. This function
-        // should be updated to reflect the actual changes in the API. For now we reject.
-        if self.minor != CURRENT_API_VERSION.minor {
+        // Plugins built for a newer minor version may rely on behaviour that selene does
+        // not provide. Plugins built for an older minor version are supported back to
+        // MIN_SUPPORTED_MINOR, with selene adapting to the differences in the API.
+        if self.minor > CURRENT_API_VERSION.minor {
+            return Err(anyhow!(
+                "Simulator API minor version {} is newer than Selene's Simulator API minor version ({}). Upgrade selene to use this plugin.",
+                self.minor,
+                CURRENT_API_VERSION.minor
+            ));
+        }
+        if self.minor < MIN_SUPPORTED_MINOR {
             return Err(anyhow!(
-                "API minor version must be the same as Selene's API minor version ({}), got {}",
-                CURRENT_API_VERSION.minor,
-                self.minor
+                "Simulator API minor version {} is no longer supported. Selene supports simulator plugins from minor version {}.",
+                self.minor,
+                MIN_SUPPORTED_MINOR
             ));
         }
         // The patch version might be bumped for changes that are optional and additive.
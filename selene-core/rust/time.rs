@@ -1,7 +1,21 @@
 use std::ops;
 
+use serde::{Deserialize, Serialize};
+
 #[derive(
-    Default, Copy, Clone, Eq, PartialEq, Hash, Debug, derive_more::From, derive_more::Into,
+    Default,
+    Copy,
+    Clone,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Hash,
+    Debug,
+    derive_more::From,
+    derive_more::Into,
+    Serialize,
+    Deserialize,
 )]
 pub struct Instant(u64);
 
@@ -17,6 +31,8 @@ pub struct Instant(u64);
     derive_more::Into,
     derive_more::Add,
     derive_more::AddAssign,
+    Serialize,
+    Deserialize,
 )]
 /// Duration in nanoseconds
 pub struct Duration(u64);
@@ -1,8 +1,12 @@
-use core::{ffi, fmt};
+use core::ffi;
+use std::cell::RefCell;
 use std::ffi::CString;
 
 use anyhow::bail;
+use serde::{Deserialize, Serialize};
 
+use crate::capabilities::Capabilities;
+use crate::error::ErrorKind;
 use crate::runtime::plugin::Errno;
 
 #[repr(C)]
@@ -17,6 +21,7 @@ pub fn selene_str_to_bytes(s: SeleneStr) -> &'static [u8] {
 }
 
 /// Represents different types of metric values.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum MetricValue {
     Bool(bool),
     I64(i64),
@@ -143,52 +148,116 @@ pub fn check_errno<E>(errno: Errno, mut mk_err: impl FnMut() -> E) -> Result<(),
     if errno != 0 { Err(mk_err()) } else { Ok(()) }
 }
 
+/// Checks the errno returned by a call to a plugin, returning an error of the
+/// [ErrorKind] indicated by the errno. The error holds the message reported by
+/// the plugin through `last_error_message`, if the plugin provides one and it
+/// returns a message, with the context created by `mk_err`.
+pub fn check_plugin_errno(
+    errno: Errno,
+    last_error_message: Option<&unsafe extern "C" fn() -> *const ffi::c_char>,
+    mk_err: impl FnOnce() -> anyhow::Error,
+) -> anyhow::Result<()> {
+    if errno == 0 {
+        return Ok(());
+    }
+    Err(plugin_error(errno, last_error_message, mk_err))
+}
+
+/// Creates the error for a failed plugin call, as described in
+/// [check_plugin_errno].
+pub fn plugin_error(
+    errno: Errno,
+    last_error_message: Option<&unsafe extern "C" fn() -> *const ffi::c_char>,
+    mk_err: impl FnOnce() -> anyhow::Error,
+) -> anyhow::Error {
+    let kind = ErrorKind::from_errno(errno);
+    let message = last_error_message
+        .map(|last_error_message| unsafe { last_error_message() })
+        .filter(|message| !message.is_null())
+        .map(|message| {
+            unsafe { std::ffi::CStr::from_ptr(message) }
+                .to_string_lossy()
+                .into_owned()
+        });
+    match message {
+        Some(message) => kind.error(message).context(mk_err().to_string()),
+        None if kind == ErrorKind::Internal => mk_err(),
+        None => kind.error(mk_err()),
+    }
+}
+
+thread_local! {
+    static LAST_ERROR_MESSAGE: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Records the message of the latest error in this thread, to be returned to
+/// the host through a plugin's `last_error_message` entry point.
+pub fn set_last_error_message(message: impl AsRef<str>) {
+    let message =
+        CString::new(message.as_ref().replace('\0', "\\0")).expect("null bytes have been escaped");
+    LAST_ERROR_MESSAGE.with_borrow_mut(|last| *last = Some(message));
+}
+
+/// Returns the message recorded by [set_last_error_message] as a null
+/// terminated string, or null if no error has been recorded in this thread.
+/// The pointer remains valid until the next error is recorded.
+pub fn last_error_message_ptr() -> *const ffi::c_char {
+    LAST_ERROR_MESSAGE.with_borrow(|last| {
+        last.as_ref()
+            .map_or(std::ptr::null(), |message| message.as_ptr())
+    })
+}
+
 /// Converts a ~Result~ to an errno value.
 ///
 /// # Arguments
 ///
-/// * `msg` - A message to print if the result is an error.
+/// * `msg` - A message describing the operation, used if the result is an error.
 /// * `r` - The result to convert.
 ///
 /// # Returns
 ///
-/// Returns `0` if the result is `Ok`. Otherwise, prints the error message to
-/// stderr and returns `-1`.
-pub fn result_to_errno<E: fmt::Display>(msg: impl AsRef<str>, r: Result<(), E>) -> Errno {
+/// Returns `0` if the result is `Ok`. Otherwise, records the error message to
+/// be returned by the plugin's `last_error_message` entry point, and returns
+/// the errno of the error's [ErrorKind].
+pub fn result_to_errno<E: Into<anyhow::Error>>(msg: impl AsRef<str>, r: Result<(), E>) -> Errno {
     let Err(e) = r else { return 0 };
-
-    eprintln!("Error: {}\n{e}", msg.as_ref());
-    -1
+    error_to_errno(msg, e.into())
 }
 /// Converts a ~Result~ of ~errno~ to an errno value. If the
 /// result has an errno already, that value is used. However,
-/// if the result itself is an Err, -1 is used.
+/// if the result itself is an Err, the errno of its [ErrorKind]
+/// is used.
 ///
 /// This is useful for functions that have successful errno
 /// values that are non-zero, such as writing metrics.
 ///
 /// # Arguments
 ///
-/// * `msg` - A message to print if the result is an error.
+/// * `msg` - A message describing the operation, used if the result is an error.
 /// * `r` - The result to convert.
 ///
 /// # Returns
 ///
 /// Returns `n` if the result is `Ok(n)`.
-/// Returnns `-1` if the result is Err
-pub fn result_of_errno_to_errno<E: fmt::Display>(
+/// Returns the errno of the error's kind if the result is Err
+pub fn result_of_errno_to_errno<E: Into<anyhow::Error>>(
     msg: impl AsRef<str>,
     r: Result<Errno, E>,
 ) -> Errno {
     match r {
         Ok(n) => n,
-        Err(e) => {
-            eprintln!("Error: {}\n{e}", msg.as_ref());
-            -1
-        }
+        Err(e) => error_to_errno(msg, e.into()),
     }
 }
 
+/// Records the message of an error raised within a plugin, and returns the
+/// errno of its kind.
+pub fn error_to_errno(msg: impl AsRef<str>, e: anyhow::Error) -> Errno {
+    set_last_error_message(format!("{}: {e:#}", msg.as_ref()));
+    ErrorKind::of(&e).errno()
+}
+
 pub fn read_raw_metric(
     mut get_metric: impl FnMut(*mut ffi::c_char, *mut u8, *mut u64) -> Errno,
 ) -> anyhow::Result<Option<(String, MetricValue)>> {
@@ -234,3 +303,26 @@ pub fn read_raw_metric(
         Ok(Some((tag_str, metric)))
     }
 }
+
+/// Whether a plugin library provides the given entry point.
+pub fn has_entry_point(lib: &libloading::Library, symbol: &[u8]) -> bool {
+    unsafe { lib.get::<unsafe extern "C" fn()>(symbol) }.is_ok()
+}
+
+/// The capabilities of a plugin library. Those declared through the given
+/// entry point are restricted to those `available`, given the plugin's entry
+/// points. Plugins that predate the entry point are taken to support all
+/// capabilities `inferred` from their entry points.
+pub fn plugin_capabilities(
+    lib: &libloading::Library,
+    symbol: &[u8],
+    available: Capabilities,
+    inferred: Capabilities,
+) -> Capabilities {
+    match unsafe { lib.get::<unsafe extern "C" fn() -> u64>(symbol) } {
+        Ok(get_capabilities) => {
+            Capabilities::from_bits_truncate(unsafe { get_capabilities() }) & available
+        }
+        Err(_) => inferred,
+    }
+}
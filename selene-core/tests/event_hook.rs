@@ -0,0 +1,89 @@
+//! Tests of event hooks loaded from plugin libraries, using a stub event hook
+//! built from C at test time that exports only the required entry points.
+#![cfg(unix)]
+
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::Once;
+
+use selene_core::capabilities::Capabilities;
+use selene_core::error::ErrorKind;
+use selene_core::event_hook::version::CURRENT_API_VERSION;
+use selene_core::event_hook::{EventHook, EventHookInterface, Operation, ResultValue};
+use selene_core::runtime::{self, BatchOperation};
+
+/// An event hook that exports nothing beyond the required entry points, and
+/// fails to start shot 7.
+fn stub_event_hook() -> PathBuf {
+    static BUILD: Once = Once::new();
+    let dir = std::env::temp_dir().join(format!(
+        "selene-core-event-hook-tests-{}",
+        std::process::id()
+    ));
+    let library = dir.join("libstub.so");
+    BUILD.call_once(|| {
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = format!(
+            r#"#include <stdint.h>
+
+static int instance;
+
+uint64_t selene_event_hook_get_api_version(void) {{ return {api_version}ULL; }}
+
+int32_t selene_event_hook_init(void **handle, uint64_t n_qubits, uint32_t argc,
+                               const char *const *argv) {{
+    *handle = &instance;
+    return 0;
+}}
+int32_t selene_event_hook_shot_start(void *handle, uint64_t shot_id, uint64_t seed) {{
+    return shot_id == 7 ? {unsupported} : 0;
+}}
+int32_t selene_event_hook_shot_end(void *handle) {{ return 0; }}
+"#,
+            api_version = u64::from(CURRENT_API_VERSION),
+            unsupported = ErrorKind::Unsupported.errno(),
+        );
+        let source_file = dir.join("stub.c");
+        std::fs::write(&source_file, source).unwrap();
+        let compiler = std::env::var_os("CC").unwrap_or_else(|| "cc".into());
+        let status = Command::new(compiler)
+            .args(["-shared", "-fPIC", "-o"])
+            .arg(&library)
+            .arg(&source_file)
+            .status()
+            .expect("a C compiler is required to build test plugins");
+        assert!(status.success());
+    });
+    library
+}
+
+#[test]
+fn optional_entry_points_may_be_missing() {
+    let mut hook = EventHook::load_from_file(&stub_event_hook(), 4, &["--unused"]).unwrap();
+    // Without a get_metrics entry point, no metrics are inferred.
+    assert_eq!(hook.capabilities(), Capabilities::empty());
+    hook.shot_start(0, 1234).unwrap();
+    hook.on_user_call(&Operation::RZZ(0, 1, 0.5)).unwrap();
+    let batch = BatchOperation::new(
+        vec![runtime::Operation::Reset { qubit_id: 2 }],
+        Default::default(),
+        Default::default(),
+    );
+    hook.on_runtime_batch(&batch).unwrap();
+    hook.on_error_model_op(0, &runtime::Operation::Reset { qubit_id: 2 })
+        .unwrap();
+    hook.on_result(0, ResultValue::Bool(true)).unwrap();
+    hook.on_result(1, ResultValue::U64(2)).unwrap();
+    assert!(hook.get_metric(0).unwrap().is_none());
+    hook.shot_end().unwrap();
+    hook.exit().unwrap();
+}
+
+#[test]
+fn errors_keep_their_kind() {
+    let mut hook = EventHook::load_from_file(&stub_event_hook(), 4, &[] as &[&str]).unwrap();
+    let error = hook.shot_start(7, 0).unwrap_err();
+    assert_eq!(ErrorKind::of(&error), ErrorKind::Unsupported);
+    assert!(error.to_string().contains("shot_start failed"), "{error}");
+    hook.shot_start(8, 0).unwrap();
+}
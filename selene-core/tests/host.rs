@@ -0,0 +1,191 @@
+//! Tests of simulators hosted out of process by the selene-plugin-host
+//! binary, loading a stub simulator plugin built from C at test time.
+#![cfg(unix)]
+
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Arc, Once};
+
+use selene_core::capabilities::Capabilities;
+use selene_core::error::ErrorKind;
+use selene_core::host::{HOST_ENV, RemoteSimulatorFactory};
+use selene_core::simulator::version::CURRENT_API_VERSION;
+use selene_core::simulator::{SimulatorInterface, SimulatorInterfaceFactory};
+use selene_core::utils::MetricValue;
+
+/// A simulator that reports the last angle it was given as a metric, and
+/// measures odd qubits as 1. Measuring qubit 7 fails, and measuring qubit 13
+/// crashes the host.
+fn stub_simulator() -> PathBuf {
+    static BUILD: Once = Once::new();
+    let dir = std::env::temp_dir().join(format!("selene-core-host-tests-{}", std::process::id()));
+    let library = dir.join("libstub.so");
+    BUILD.call_once(|| {
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = format!(
+            r#"#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+#include <string.h>
+
+static uint64_t last_angle;
+
+uint64_t selene_simulator_get_api_version(void) {{ return {api_version}ULL; }}
+uint64_t selene_simulator_get_capabilities(void) {{ return {capabilities}ULL; }}
+const char *selene_simulator_last_error_message(void) {{ return "qubit 7 is broken"; }}
+
+int32_t selene_simulator_init(void **instance, uint64_t n_qubits, uint32_t argc,
+                              const char *const *argv) {{
+    *instance = &last_angle;
+    return 0;
+}}
+int32_t selene_simulator_exit(void *instance) {{ return 0; }}
+int32_t selene_simulator_shot_start(void *instance, uint64_t shot_id, uint64_t seed) {{
+    return 0;
+}}
+int32_t selene_simulator_shot_end(void *instance) {{ return 0; }}
+int32_t selene_simulator_operation_rxy(void *instance, uint64_t qubit, double theta,
+                                       double phi) {{
+    memcpy(&last_angle, &theta, sizeof theta);
+    return 0;
+}}
+int32_t selene_simulator_operation_rzz(void *instance, uint64_t qubit1, uint64_t qubit2,
+                                       double theta) {{
+    memcpy(&last_angle, &theta, sizeof theta);
+    return 0;
+}}
+int32_t selene_simulator_operation_rz(void *instance, uint64_t qubit, double theta) {{
+    memcpy(&last_angle, &theta, sizeof theta);
+    return 0;
+}}
+int32_t selene_simulator_operation_measure(void *instance, uint64_t qubit) {{
+    if (qubit == 7) return {shot_failed};
+    if (qubit == 13) abort();
+    return qubit & 1;
+}}
+int32_t selene_simulator_operation_postselect(void *instance, uint64_t qubit,
+                                              bool target_value) {{
+    return 0;
+}}
+int32_t selene_simulator_operation_reset(void *instance, uint64_t qubit) {{ return 0; }}
+int32_t selene_simulator_get_metrics(void *instance, uint8_t nth_metric, char *tag,
+                                     uint8_t *data_type, uint64_t *data) {{
+    if (nth_metric != 0) return 1;
+    strcpy(tag, "last_angle");
+    *data_type = 3;
+    *data = last_angle;
+    return 0;
+}}
+int32_t selene_simulator_dump_state(void *instance, const char *file,
+                                    const uint64_t *qubits, uint64_t n_qubits) {{
+    return 0;
+}}
+"#,
+            api_version = u64::from(CURRENT_API_VERSION),
+            capabilities = Capabilities::POSTSELECT.bits(),
+            shot_failed = ErrorKind::ShotFailed.errno(),
+        );
+        let source_file = dir.join("stub.c");
+        std::fs::write(&source_file, source).unwrap();
+        let compiler = std::env::var_os("CC").unwrap_or_else(|| "cc".into());
+        let status = Command::new(compiler)
+            .args(["-shared", "-fPIC", "-o"])
+            .arg(&library)
+            .arg(&source_file)
+            .status()
+            .expect("a C compiler is required to build test plugins");
+        assert!(status.success());
+        // SAFETY: every test sets the same value, before launching a host.
+        unsafe { std::env::set_var(HOST_ENV, env!("CARGO_BIN_EXE_selene-plugin-host")) };
+    });
+    library
+}
+
+fn launch() -> (Arc<RemoteSimulatorFactory>, Box<impl SimulatorInterface>) {
+    let factory = Arc::new(RemoteSimulatorFactory::launch(stub_simulator()).unwrap());
+    let simulator = factory.clone().init(20, &["--unused"]).unwrap();
+    (factory, simulator)
+}
+
+fn last_angle(simulator: &mut impl SimulatorInterface) -> f64 {
+    match simulator.get_metric(0).unwrap() {
+        Some((tag, MetricValue::F64(angle))) if tag == "last_angle" => angle,
+        metric => panic!("unexpected metric {metric:?}"),
+    }
+}
+
+#[test]
+fn operations_are_forwarded() {
+    let (factory, mut simulator) = launch();
+    assert_eq!(factory.capabilities(), Capabilities::POSTSELECT);
+    simulator.shot_start(0, 1234).unwrap();
+    simulator.rz(0, 0.5).unwrap();
+    assert_eq!(last_angle(&mut *simulator), 0.5);
+    assert!(simulator.measure(3).unwrap());
+    assert!(!simulator.measure(4).unwrap());
+    simulator.postselect(0, true).unwrap();
+    simulator.reset(0).unwrap();
+    assert!(simulator.get_metric(1).unwrap().is_none());
+    simulator.shot_end().unwrap();
+    simulator.exit().unwrap();
+}
+
+#[test]
+fn angles_reach_the_plugin_exactly() {
+    let (_factory, mut simulator) = launch();
+    simulator.shot_start(0, 0).unwrap();
+    for angle in [
+        f64::NAN,
+        f64::from_bits(0x7ff0_0000_dead_beef),
+        f64::NEG_INFINITY,
+        -0.0,
+        f64::MIN_POSITIVE / 3.0,
+        0.1 + 0.2,
+    ] {
+        simulator.rxy(1, angle, 0.0).unwrap();
+        assert_eq!(last_angle(&mut *simulator).to_bits(), angle.to_bits());
+        simulator.rzz(1, 2, -angle).unwrap();
+        assert_eq!(last_angle(&mut *simulator).to_bits(), (-angle).to_bits());
+    }
+}
+
+#[test]
+fn plugin_errors_are_forwarded() {
+    let (_factory, mut simulator) = launch();
+    simulator.shot_start(0, 0).unwrap();
+    let error = simulator.measure(7).unwrap_err();
+    assert_eq!(ErrorKind::of(&error), ErrorKind::ShotFailed);
+    assert!(error.to_string().ends_with("qubit 7 is broken"), "{error}");
+    // The host carries on after an error.
+    assert!(simulator.measure(1).unwrap());
+}
+
+#[test]
+fn crashes_are_reported() {
+    let (_factory, mut simulator) = launch();
+    simulator.shot_start(5, 0).unwrap();
+    let error = simulator.measure(13).unwrap_err();
+    assert_eq!(ErrorKind::of(&error), ErrorKind::PluginCrashed);
+    let message = error.to_string();
+    assert!(
+        message.starts_with("The simulator plugin '") && message.contains("during shot 5"),
+        "{message}"
+    );
+    // Later requests report the same crash, but the shot can still be ended.
+    let error = simulator.measure(1).unwrap_err();
+    assert_eq!(error.to_string(), message);
+    simulator.shot_end().unwrap();
+    simulator.exit().unwrap();
+}
+
+#[test]
+fn each_instance_has_its_own_host() {
+    let (factory, mut first) = launch();
+    let mut second = factory.init(20, &[] as &[&str]).unwrap();
+    first.shot_start(0, 0).unwrap();
+    second.shot_start(0, 0).unwrap();
+    first.rz(0, 1.0).unwrap();
+    second.rz(0, 2.0).unwrap();
+    assert_eq!(last_angle(&mut *first), 1.0);
+    assert_eq!(last_angle(&mut *second), 2.0);
+}
@@ -22,12 +22,20 @@ class DepolarizingPlugin(ErrorModel):
             be between 0 and 1 (inclusive).
         p_init (float): The error probability for initialization operations.
             Must be between 0 and 1 (inclusive).
+        p_meas_reset_readout (float | None): The error probability for the
+            readout of a fused measure-and-reset operation. If None, p_meas
+            is used.
+        p_meas_reset_init (float | None): The error probability for the
+            reinitialization of a fused measure-and-reset operation, applied
+            independently of its readout error. If None, p_init is used.
     """
 
     p_1q: float = 0.0
     p_2q: float = 0.0
     p_meas: float = 0.0
     p_init: float = 0.0
+    p_meas_reset_readout: float | None = None
+    p_meas_reset_init: float | None = None
 
     def __post_init__(self):
         assert 0 <= self.p_1q <= 1, (
@@ -42,6 +50,11 @@ class DepolarizingPlugin(ErrorModel):
         assert 0 <= self.p_init <= 1, (
             f"error_probability for p_init ({self.p_init}) must be between 0 and 1 (both inclusive)"
         )
+        for name in ("p_meas_reset_readout", "p_meas_reset_init"):
+            value = getattr(self, name)
+            assert value is None or 0 <= value <= 1, (
+                f"error_probability for {name} ({value}) must be between 0 and 1 (both inclusive)"
+            )
 
     @property
     def library_file(self):
@@ -62,4 +75,11 @@ class DepolarizingPlugin(ErrorModel):
             f"--p-2q={self.p_2q}",
             f"--p-meas={self.p_meas}",
             f"--p-init={self.p_init}",
+        ] + [
+            f"--{name.replace('_', '-')}={value}"
+            for name, value in (
+                ("p_meas_reset_readout", self.p_meas_reset_readout),
+                ("p_meas_reset_init", self.p_meas_reset_init),
+            )
+            if value is not None
         ]
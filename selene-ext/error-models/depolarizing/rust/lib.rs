@@ -2,14 +2,18 @@ use anyhow::{Result, anyhow};
 use clap::Parser;
 use rand::{Rng, SeedableRng};
 use rand_pcg::Pcg64Mcg;
+use selene_core::capabilities::Capabilities;
 use selene_core::error_model::interface::ErrorModelInterfaceFactory;
-use selene_core::error_model::{BatchResult, ErrorModelInterface};
-use selene_core::export_error_model_plugin;
+use selene_core::error_model::{BatchResult, ErrorModelInterface, InjectedOperation};
+use selene_core::metadata::ArgumentMetadata;
 use selene_core::runtime::{BatchOperation, Operation};
 use selene_core::simulator::{Simulator, SimulatorInterface};
 use selene_core::utils::MetricValue;
 use std::ffi::OsStr;
 
+#[cfg(test)]
+mod tests;
+
 #[derive(Parser, Debug)]
 struct Params {
     /// The probability of a single-qubit gate error
@@ -24,6 +28,15 @@ struct Params {
     /// The probability of an initialization error
     #[arg(long)]
     p_init: f64,
+    /// The probability of a readout error in a fused measure-and-reset.
+    /// If not provided, p_meas is used.
+    #[arg(long)]
+    p_meas_reset_readout: Option<f64>,
+    /// The probability of an initialization error in a fused
+    /// measure-and-reset, independent of its readout error. If not provided,
+    /// p_init is used.
+    #[arg(long)]
+    p_meas_reset_init: Option<f64>,
 }
 #[derive(Default)]
 struct Stats {
@@ -66,23 +79,59 @@ pub struct DepolarizingErrorModel {
     simulator: Simulator,
     error_params: Params,
     stats: Stats,
+    /// The errors applied while handling the current batch, reported in its
+    /// results.
+    injected: Vec<InjectedOperation>,
+    /// The number of the current batch's operations applied so far, which is
+    /// the position of any error applied now.
+    position: u64,
 }
 
 impl DepolarizingErrorModel {
+    fn new(n_qubits: u64, simulator: Simulator, params: Params) -> Self {
+        Self {
+            n_qubits,
+            rng: Pcg64Mcg::seed_from_u64(0),
+            simulator,
+            error_params: params,
+            stats: Stats::default(),
+            injected: Vec::new(),
+            position: 0,
+        }
+    }
+
     pub fn apply_error(&mut self, qubit: u64, error: ErrorType) -> Result<()> {
-        match error {
-            ErrorType::I => (),
+        let operation = match error {
+            ErrorType::I => return Ok(()),
             ErrorType::X => {
                 self.simulator.rxy(qubit, std::f64::consts::PI, 0.0)?;
+                Operation::RXYGate {
+                    qubit_id: qubit,
+                    theta: std::f64::consts::PI,
+                    phi: 0.0,
+                }
             }
             ErrorType::Y => {
                 self.simulator
                     .rxy(qubit, std::f64::consts::PI, std::f64::consts::PI / 2.0)?;
+                Operation::RXYGate {
+                    qubit_id: qubit,
+                    theta: std::f64::consts::PI,
+                    phi: std::f64::consts::PI / 2.0,
+                }
             }
             ErrorType::Z => {
                 self.simulator.rz(qubit, std::f64::consts::PI)?;
+                Operation::RZGate {
+                    qubit_id: qubit,
+                    theta: std::f64::consts::PI,
+                }
             }
-        }
+        };
+        self.injected.push(InjectedOperation {
+            position: self.position,
+            operation,
+        });
         Ok(())
     }
     fn maybe_apply_1q_error(&mut self, q0: u64) -> Result<()> {
@@ -175,8 +224,11 @@ impl DepolarizingErrorModel {
         Ok(())
     }
     fn maybe_flip_measurement(&mut self, _qubit: u64, result: bool) -> bool {
+        self.maybe_flip_measurement_with(self.error_params.p_meas, result)
+    }
+    fn maybe_flip_measurement_with(&mut self, p_meas: f64, result: bool) -> bool {
         let val = self.rng.random::<f64>();
-        let flip = val < self.error_params.p_meas;
+        let flip = val < p_meas;
         self.stats.measure_count += 1;
         if flip {
             self.stats.measure_errors += 1;
@@ -186,9 +238,12 @@ impl DepolarizingErrorModel {
         }
     }
     fn maybe_flip_on_init(&mut self, qubit: u64) -> Result<()> {
+        self.maybe_flip_on_init_with(self.error_params.p_init, qubit)
+    }
+    fn maybe_flip_on_init_with(&mut self, p_init: f64, qubit: u64) -> Result<()> {
         self.stats.init_count += 1;
         let val = self.rng.random::<f64>();
-        if val < self.error_params.p_init {
+        if val < p_init {
             self.stats.init_errors += 1;
             self.apply_error(qubit, ErrorType::X)?;
         }
@@ -214,7 +269,11 @@ impl ErrorModelInterface for DepolarizingErrorModel {
 
     fn handle_operations(&mut self, operations: BatchOperation) -> Result<BatchResult> {
         let mut results = BatchResult::default();
-        for op in operations {
+        self.injected.clear();
+        for (index, op) in operations.into_iter().enumerate() {
+            // Errors on gates and measurements are applied before them, and
+            // errors on initialisation after the reset.
+            self.position = index as u64;
             match op {
                 Operation::RXYGate {
                     qubit_id,
@@ -254,8 +313,29 @@ impl ErrorModelInterface for DepolarizingErrorModel {
                     let modified_measurement = self.maybe_flip_measurement(qubit_id, measurement);
                     results.set_u64_result(result_id, if modified_measurement { 1 } else { 0 });
                 }
+                Operation::MeasureReset {
+                    qubit_id,
+                    result_id,
+                } => {
+                    let p_meas = self
+                        .error_params
+                        .p_meas_reset_readout
+                        .unwrap_or(self.error_params.p_meas);
+                    let p_init = self
+                        .error_params
+                        .p_meas_reset_init
+                        .unwrap_or(self.error_params.p_init);
+                    let measurement = self.simulator.measure(qubit_id)?;
+                    let modified_measurement =
+                        self.maybe_flip_measurement_with(p_meas, measurement);
+                    self.simulator.reset(qubit_id)?;
+                    self.position += 1;
+                    self.maybe_flip_on_init_with(p_init, qubit_id)?;
+                    results.set_bool_result(result_id, modified_measurement);
+                }
                 Operation::Reset { qubit_id } => {
                     self.simulator.reset(qubit_id)?;
+                    self.position += 1;
                     self.maybe_flip_on_init(qubit_id)?;
                 }
                 Operation::Custom { .. } => {
@@ -263,6 +343,7 @@ impl ErrorModelInterface for DepolarizingErrorModel {
                 }
             }
         }
+        results.injected_operations.append(&mut self.injected);
         Ok(results)
     }
 
@@ -370,14 +451,30 @@ impl ErrorModelInterface for DepolarizingErrorModel {
     fn get_simulator_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
         self.simulator.get_metric(nth_metric)
     }
+
+    fn simulator_capabilities(&mut self) -> Result<Option<Capabilities>> {
+        Ok(Some(self.simulator.capabilities()))
+    }
 }
 
+/// The name the plugin is known by, under which selene registers it when it
+/// is linked in.
+pub const PLUGIN_NAME: &str = "depolarizing";
+
 #[derive(Default)]
 pub struct DepolarizingErrorModelFactory;
 
 impl ErrorModelInterfaceFactory for DepolarizingErrorModelFactory {
     type Interface = DepolarizingErrorModel;
 
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::METRICS
+    }
+
+    fn arguments(&self) -> Option<Vec<ArgumentMetadata>> {
+        Some(ArgumentMetadata::from_clap::<Params>())
+    }
+
     fn init(
         self: std::sync::Arc<Self>,
         n_qubits: u64,
@@ -393,16 +490,13 @@ impl ErrorModelInterfaceFactory for DepolarizingErrorModelFactory {
             Ok(params) => {
                 let simulator =
                     Simulator::load_from_file(simulator_path, n_qubits, simulator_args)?;
-                Ok(Box::new(DepolarizingErrorModel {
-                    n_qubits,
-                    rng: Pcg64Mcg::seed_from_u64(0),
-                    simulator,
-                    error_params: params,
-                    stats: Stats::default(),
-                }))
+                Ok(Box::new(DepolarizingErrorModel::new(
+                    n_qubits, simulator, params,
+                )))
             }
         }
     }
 }
 
-export_error_model_plugin!(crate::DepolarizingErrorModelFactory);
+#[cfg(feature = "export")]
+selene_core::export_error_model_plugin!(crate::DepolarizingErrorModelFactory, crate::PLUGIN_NAME);
@@ -0,0 +1,195 @@
+use super::*;
+use selene_core::simulator::SimulatorInterfaceFactory;
+use std::sync::{Arc, Mutex};
+
+type Calls = Arc<Mutex<Vec<String>>>;
+
+/// A simulator that records the calls made to it, and measures every qubit
+/// as 0.
+struct RecordingSimulator(Calls);
+
+impl SimulatorInterface for RecordingSimulator {
+    fn exit(&mut self) -> Result<()> {
+        Ok(())
+    }
+    fn shot_start(&mut self, _shot_id: u64, _seed: u64) -> Result<()> {
+        Ok(())
+    }
+    fn shot_end(&mut self) -> Result<()> {
+        Ok(())
+    }
+    fn rz(&mut self, qubit: u64, theta: f64) -> Result<()> {
+        self.0.lock().unwrap().push(format!("rz {qubit} {theta}"));
+        Ok(())
+    }
+    fn rxy(&mut self, qubit: u64, theta: f64, phi: f64) -> Result<()> {
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("rxy {qubit} {theta} {phi}"));
+        Ok(())
+    }
+    fn rzz(&mut self, qubit1: u64, qubit2: u64, theta: f64) -> Result<()> {
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("rzz {qubit1} {qubit2} {theta}"));
+        Ok(())
+    }
+    fn measure(&mut self, qubit: u64) -> Result<bool> {
+        self.0.lock().unwrap().push(format!("measure {qubit}"));
+        Ok(false)
+    }
+    fn reset(&mut self, qubit: u64) -> Result<()> {
+        self.0.lock().unwrap().push(format!("reset {qubit}"));
+        Ok(())
+    }
+    fn get_metric(&mut self, _nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        Ok(None)
+    }
+}
+
+struct RecordingSimulatorFactory(Calls);
+
+impl SimulatorInterfaceFactory for RecordingSimulatorFactory {
+    type Interface = RecordingSimulator;
+
+    fn init(
+        self: Arc<Self>,
+        _n_qubits: u64,
+        _args: &[impl AsRef<str>],
+    ) -> Result<Box<Self::Interface>> {
+        Ok(Box::new(RecordingSimulator(self.0.clone())))
+    }
+}
+
+fn depolarizing(p_meas: f64, p_init: f64, args: &[&str]) -> (DepolarizingErrorModel, Calls) {
+    with_params(
+        &[
+            &[
+                "--p-1q=0",
+                "--p-2q=0",
+                &format!("--p-meas={p_meas}"),
+                &format!("--p-init={p_init}"),
+            ],
+            args,
+        ]
+        .concat(),
+    )
+}
+
+fn with_params(args: &[&str]) -> (DepolarizingErrorModel, Calls) {
+    let calls = Calls::default();
+    let factory = Arc::new(RecordingSimulatorFactory(calls.clone()));
+    let simulator = Simulator::new(factory, 1, &[] as &[&str]).unwrap();
+    let params = Params::parse_from(["depolarizing"].iter().chain(args));
+    let mut error_model = DepolarizingErrorModel::new(1, simulator, params);
+    error_model.shot_start(0, 0, 0).unwrap();
+    (error_model, calls)
+}
+
+fn measure_reset(error_model: &mut DepolarizingErrorModel) -> BatchResult {
+    let batch = BatchOperation::new(
+        vec![Operation::MeasureReset {
+            qubit_id: 0,
+            result_id: 3,
+        }],
+        Default::default(),
+        Default::default(),
+    );
+    error_model.handle_operations(batch).unwrap()
+}
+
+/// An X error applied after the first `position` operations of a batch.
+fn x_error(position: u64) -> InjectedOperation {
+    InjectedOperation {
+        position,
+        operation: Operation::RXYGate {
+            qubit_id: 0,
+            theta: std::f64::consts::PI,
+            phi: 0.0,
+        },
+    }
+}
+
+#[test]
+fn measure_reset_without_errors() {
+    let (mut error_model, calls) = depolarizing(0.0, 0.0, &[]);
+    let results = measure_reset(&mut error_model);
+    assert_eq!(results.bool_results.len(), 1);
+    assert_eq!(results.bool_results[0].result_id, 3);
+    assert!(!results.bool_results[0].value);
+    assert!(results.injected_operations.is_empty());
+    assert_eq!(*calls.lock().unwrap(), vec!["measure 0", "reset 0"]);
+}
+
+#[test]
+fn measure_reset_readout_error() {
+    let (mut error_model, calls) = depolarizing(0.0, 0.0, &["--p-meas-reset-readout=1"]);
+    let results = measure_reset(&mut error_model);
+    assert!(results.bool_results[0].value);
+    assert!(results.injected_operations.is_empty());
+    assert_eq!(*calls.lock().unwrap(), vec!["measure 0", "reset 0"]);
+}
+
+#[test]
+fn measure_reset_init_error() {
+    let (mut error_model, calls) = depolarizing(0.0, 0.0, &["--p-meas-reset-init=1"]);
+    let results = measure_reset(&mut error_model);
+    assert!(!results.bool_results[0].value);
+    // The error follows the reset, so comes after the batch's operation.
+    assert_eq!(results.injected_operations, vec![x_error(1)]);
+    assert_eq!(
+        *calls.lock().unwrap(),
+        vec![
+            "measure 0".to_string(),
+            "reset 0".to_string(),
+            format!("rxy 0 {} 0", std::f64::consts::PI),
+        ]
+    );
+}
+
+#[test]
+fn measure_reset_falls_back_to_p_meas_and_p_init() {
+    let (mut error_model, _) = depolarizing(1.0, 1.0, &[]);
+    let results = measure_reset(&mut error_model);
+    assert!(results.bool_results[0].value);
+    assert_eq!(results.injected_operations, vec![x_error(1)]);
+
+    // Each half can be overridden separately.
+    let (mut error_model, _) = depolarizing(1.0, 1.0, &["--p-meas-reset-init=0"]);
+    let results = measure_reset(&mut error_model);
+    assert!(results.bool_results[0].value);
+    assert!(results.injected_operations.is_empty());
+}
+
+#[test]
+fn gate_errors_precede_their_gates() {
+    let (mut error_model, _) = with_params(&["--p-1q=1", "--p-2q=0", "--p-meas=0", "--p-init=1"]);
+    let batch = BatchOperation::new(
+        vec![
+            Operation::RZGate {
+                qubit_id: 0,
+                theta: 0.5,
+            },
+            Operation::Reset { qubit_id: 0 },
+            Operation::RXYGate {
+                qubit_id: 0,
+                theta: 0.5,
+                phi: 0.0,
+            },
+        ],
+        Default::default(),
+        Default::default(),
+    );
+    let results = error_model.handle_operations(batch).unwrap();
+    let positions: Vec<u64> = results
+        .injected_operations
+        .iter()
+        .map(|injected| injected.position)
+        .collect();
+    // An error before the rz gate, one after the reset and one before the
+    // rxy gate, which is applied after the reset.
+    assert_eq!(positions, vec![0, 2, 2]);
+    assert_eq!(results.injected_operations[1], x_error(2));
+}
@@ -1,7 +1,8 @@
 use anyhow::{Result, anyhow};
+use selene_core::capabilities::Capabilities;
 use selene_core::error_model::interface::ErrorModelInterfaceFactory;
 use selene_core::error_model::{BatchResult, ErrorModelInterface};
-use selene_core::export_error_model_plugin;
+use selene_core::metadata::ArgumentMetadata;
 use selene_core::runtime::{BatchOperation, Operation};
 use selene_core::simulator::{Simulator, SimulatorInterface};
 use selene_core::utils::MetricValue;
@@ -61,6 +62,14 @@ impl ErrorModelInterface for IdealErrorModel {
                     let measurement = self.simulator.measure(qubit_id)?;
                     results.set_u64_result(result_id, measurement.into());
                 }
+                Operation::MeasureReset {
+                    qubit_id,
+                    result_id,
+                } => {
+                    let measurement = self.simulator.measure(qubit_id)?;
+                    self.simulator.reset(qubit_id)?;
+                    results.set_bool_result(result_id, measurement);
+                }
 
                 Operation::Reset { qubit_id } => {
                     self.simulator.reset(qubit_id)?;
@@ -81,17 +90,33 @@ impl ErrorModelInterface for IdealErrorModel {
         self.simulator.get_metric(nth_metric)
     }
 
+    fn simulator_capabilities(&mut self) -> Result<Option<Capabilities>> {
+        Ok(Some(self.simulator.capabilities()))
+    }
+
     fn dump_simulator_state(&mut self, file: &std::path::Path, qubits: &[u64]) -> Result<()> {
         self.simulator.dump_state(file, qubits)
     }
 }
 
+/// The name the plugin is known by, under which selene registers it when it
+/// is linked in.
+pub const PLUGIN_NAME: &str = "ideal";
+
 #[derive(Default)]
 pub struct IdealErrorModelFactory;
 
 impl ErrorModelInterfaceFactory for IdealErrorModelFactory {
     type Interface = IdealErrorModel;
 
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::DUMP_STATE
+    }
+
+    fn arguments(&self) -> Option<Vec<ArgumentMetadata>> {
+        Some(Vec::new())
+    }
+
     fn init(
         self: std::sync::Arc<Self>,
         n_qubits: u64,
@@ -114,4 +139,5 @@ impl ErrorModelInterfaceFactory for IdealErrorModelFactory {
     }
 }
 
-export_error_model_plugin!(crate::IdealErrorModelFactory);
+#[cfg(feature = "export")]
+selene_core::export_error_model_plugin!(crate::IdealErrorModelFactory, crate::PLUGIN_NAME);
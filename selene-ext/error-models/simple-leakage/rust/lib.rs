@@ -2,9 +2,11 @@ use anyhow::{Result, anyhow, bail};
 use clap::Parser;
 use rand::{Rng, SeedableRng};
 use rand_pcg::Pcg64Mcg;
+use selene_core::capabilities::Capabilities;
+use selene_core::error::ErrorKind;
 use selene_core::error_model::interface::ErrorModelInterfaceFactory;
 use selene_core::error_model::{BatchResult, ErrorModelInterface};
-use selene_core::export_error_model_plugin;
+use selene_core::metadata::ArgumentMetadata;
 use selene_core::runtime::{BatchOperation, Operation};
 use selene_core::simulator::{Simulator, SimulatorInterface};
 use selene_core::utils::MetricValue;
@@ -37,11 +39,10 @@ pub struct SimpleLeakageErrorModel {
 impl SimpleLeakageErrorModel {
     fn is_leaked(&self, qubit: u64) -> Result<bool> {
         if qubit >= self.n_qubits {
-            bail!(
+            bail!(ErrorKind::QubitOutOfBounds.error(format!(
                 "Qubit ID {} is out of bounds for this error model with {} qubits",
-                qubit,
-                self.n_qubits
-            );
+                qubit, self.n_qubits
+            )));
         }
         Ok(self.leak_register[qubit as usize])
     }
@@ -138,6 +139,22 @@ impl ErrorModelInterface for SimpleLeakageErrorModel {
                     };
                     results.set_u64_result(result_id, measurement);
                 }
+                Operation::MeasureReset {
+                    qubit_id,
+                    result_id,
+                } => {
+                    // The reset half of the primitive returns a leaked qubit
+                    // to the computational subspace.
+                    let measurement = if self.leak_register[qubit_id as usize] {
+                        self.rng
+                            .random_bool(self.error_params.leak_measurement_bias)
+                    } else {
+                        self.simulator.measure(qubit_id)?
+                    };
+                    self.simulator.reset(qubit_id)?;
+                    self.leak_register[qubit_id as usize] = false;
+                    results.set_bool_result(result_id, measurement);
+                }
                 Operation::Reset { qubit_id } => {
                     self.simulator.reset(qubit_id)?;
                     self.leak_register[qubit_id as usize] = false;
@@ -166,14 +183,30 @@ impl ErrorModelInterface for SimpleLeakageErrorModel {
     fn get_simulator_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
         self.simulator.get_metric(nth_metric)
     }
+
+    fn simulator_capabilities(&mut self) -> Result<Option<Capabilities>> {
+        Ok(Some(self.simulator.capabilities()))
+    }
 }
 
+/// The name the plugin is known by, under which selene registers it when it
+/// is linked in.
+pub const PLUGIN_NAME: &str = "simple_leakage";
+
 #[derive(Default)]
 pub struct SimpleLeakageErrorModelFactory;
 
 impl ErrorModelInterfaceFactory for SimpleLeakageErrorModelFactory {
     type Interface = SimpleLeakageErrorModel;
 
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::METRICS
+    }
+
+    fn arguments(&self) -> Option<Vec<ArgumentMetadata>> {
+        Some(ArgumentMetadata::from_clap::<Params>())
+    }
+
     fn init(
         self: std::sync::Arc<Self>,
         n_qubits: u64,
@@ -203,4 +236,5 @@ impl ErrorModelInterfaceFactory for SimpleLeakageErrorModelFactory {
     }
 }
 
-export_error_model_plugin!(crate::SimpleLeakageErrorModelFactory);
+#[cfg(feature = "export")]
+selene_core::export_error_model_plugin!(crate::SimpleLeakageErrorModelFactory, crate::PLUGIN_NAME);
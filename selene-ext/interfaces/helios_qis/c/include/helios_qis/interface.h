@@ -47,6 +47,8 @@ EXPORT void print_int_arr(cl_string tag, uint64_t _unused, struct cl_array* arr)
 EXPORT void print_uint_arr(cl_string tag, uint64_t _unused, struct cl_array* arr);
 EXPORT void print_float_arr(cl_string tag, uint64_t _unused, struct cl_array* arr);
 EXPORT void print_state_result(cl_string tag, uint64_t unused, struct cl_array* qubits);
+// Codes below 1000 end only the current shot, except for 998 and 999,
+// which are reserved for errors raised by selene.
 EXPORT void panic(int32_t error_code, cl_string message);
 EXPORT void panic_str(int32_t error_code, char const* message);
 EXPORT void random_seed(uint64_t seed);
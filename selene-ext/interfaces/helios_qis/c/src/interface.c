@@ -42,7 +42,10 @@
 #error "Invalid SELENE_LOG_LEVEL"
 #endif
 
-jmp_buf user_program_jmpbuf;
+// Shots may be run on several worker threads (see selene_run_workers), each
+// with its own instance, so the state used by the user program's calls is
+// thread local.
+_Thread_local jmp_buf user_program_jmpbuf;
 
 void panic_impl(int32_t error_code) {
     longjmp(user_program_jmpbuf, error_code);
@@ -105,33 +108,22 @@ struct selene_string_t parse_c_string(char const* str) {
 }
 
 
-SeleneInstance* selene_instance = 0;
+_Thread_local SeleneInstance* selene_instance = 0;
 
 // defined by hybrid user program compiler
 extern uint64_t qmain(uint64_t);
 
-// The entrypoint of the resulting executable
-int main(int argc, char** argv) {
-    DIAGNOSTIC("selene_init() with args:\n");
-    for (int i = 0; i < argc; ++i) {
-        DIAGNOSTIC("   %d: %s\n", i, argv[i]);
-    }
-    if(argc < 3 || strcmp(argv[1], "--configuration") != 0){
-        ERROR("Usage: %s --configuration <configuration_file>\n", argv[0]);
-        return 1;
-    }
-    char const* configuration_file = argv[2];
-    struct selene_void_result_t void_result = selene_load_config(&selene_instance, configuration_file);
-    if (void_result.error_code != 0) {
-        ERROR("Error initializing selene: error code %" PRIu32 "\n", void_result.error_code);
-        return void_result.error_code;
-    }
+// Runs every shot of a worker's instance
+uint32_t run_shots(SeleneInstance* instance, void* user_data) {
+    (void)user_data;
+    selene_instance = instance;
     struct selene_u64_result_t n_shots = selene_shot_count(selene_instance);
     if (n_shots.error_code != 0) {
-        ERROR("Error fetching shot count from selene: error code %" PRIu32 "\n", void_result.error_code);
+        ERROR("Error fetching shot count from selene: error code %" PRIu32 "\n", n_shots.error_code);
         return n_shots.error_code;
     }
     DIAGNOSTIC("Number of shots: %" PRIu64 "\n", n_shots.value);
+    struct selene_void_result_t void_result;
     bool do_continue = true;
     for(uint64_t current_shot = 0; do_continue && (current_shot < n_shots.value); ++current_shot){
         DIAGNOSTIC("Starting shot %" PRIu64 "\n", current_shot);
@@ -155,7 +147,31 @@ int main(int argc, char** argv) {
             return void_result.error_code;
         }
     }
-    selene_exit(selene_instance);
+    return 0;
+}
+
+// The entrypoint of the resulting executable
+int main(int argc, char** argv) {
+    DIAGNOSTIC("selene_init() with args:\n");
+    for (int i = 0; i < argc; ++i) {
+        DIAGNOSTIC("   %d: %s\n", i, argv[i]);
+    }
+    if(argc < 3 || strcmp(argv[1], "--configuration") != 0){
+        ERROR("Usage: %s --configuration <configuration_file>\n", argv[0]);
+        return 1;
+    }
+    char const* configuration_file = argv[2];
+    SeleneInstance* instance = 0;
+    struct selene_void_result_t void_result = selene_load_config(&instance, configuration_file);
+    if (void_result.error_code != 0) {
+        ERROR("Error initializing selene: error code %" PRIu32 "\n", void_result.error_code);
+        return void_result.error_code;
+    }
+    void_result = selene_run_workers(instance, run_shots, NULL);
+    if (void_result.error_code != 0) {
+        return void_result.error_code;
+    }
+    selene_exit(instance);
 }
 
 uint64_t ___qalloc() {
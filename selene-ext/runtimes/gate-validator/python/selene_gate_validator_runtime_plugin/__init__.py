@@ -0,0 +1,3 @@
+from .plugin import GateValidatorRuntimePlugin
+
+__all__ = ["GateValidatorRuntimePlugin"]
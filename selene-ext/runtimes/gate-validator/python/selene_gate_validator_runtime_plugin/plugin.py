@@ -0,0 +1,81 @@
+import platform
+from dataclasses import dataclass
+from pathlib import Path
+
+from selene_core import Runtime
+
+
+@dataclass
+class GateValidatorRuntimePlugin(Runtime):
+    """
+    A plugin that wraps another runtime, checking the angles of gates issued by
+    the user program against a grid of permitted angles before passing them on.
+
+    Grids are given in half-turns (multiples of pi) and compared modulo a full
+    turn. Each grid is one of:
+        - "any": every angle is permitted (the default).
+        - "step:<x>": only integer multiples of x are permitted, e.g. "step:0.25"
+          for multiples of pi/4.
+        - a comma-separated list of permitted angles, e.g. "0.5,-0.5" for +-pi/2.
+
+    The number of offending gates of each kind is reported through the runtime
+    metrics, followed by the metrics of the wrapped runtime.
+
+    Attributes:
+        runtime (Runtime): The runtime to wrap.
+        rxy_theta (str): The grid of permitted RXY theta angles.
+        rxy_phi (str): The grid of permitted RXY phi angles.
+        rz (str): The grid of permitted RZ angles.
+        rzz (str): The grid of permitted RZZ angles.
+        on_violation (str): "reject" to fail the shot when an angle is not on its
+            grid, or "snap" to replace it with the nearest permitted angle.
+        tolerance (float): The maximum distance, in half-turns, between an angle
+            and its grid for the angle to be accepted unchanged.
+    """
+
+    runtime: Runtime | None = None
+    rxy_theta: str = "any"
+    rxy_phi: str = "any"
+    rz: str = "any"
+    rzz: str = "any"
+    on_violation: str = "reject"
+    tolerance: float = 1e-9
+
+    def __post_init__(self):
+        assert self.runtime is not None, (
+            "A runtime must be provided to the gate validator runtime plugin"
+        )
+        assert self.on_violation in ("reject", "snap"), (
+            f"on_violation ({self.on_violation}) must be one of 'reject' or 'snap'"
+        )
+        assert self.tolerance >= 0, (
+            f"tolerance ({self.tolerance}) must be non-negative"
+        )
+
+    @property
+    def library_file(self):
+        libdir = Path(__file__).parent / "_dist/lib/"
+        match platform.system():
+            case "Linux":
+                return libdir / "libselene_gate_validator_runtime.so"
+            case "Darwin":
+                return libdir / "libselene_gate_validator_runtime.dylib"
+            case "Windows":
+                return libdir / "selene_gate_validator_runtime.dll"
+            case _:
+                raise RuntimeError(f"Unsupported platform: {platform.system()}")
+
+    @property
+    def library_search_dirs(self):
+        return self.runtime.library_search_dirs
+
+    def get_init_args(self):
+        return [
+            f"--wrapped-path={self.runtime.library_file}",
+            f"--rxy-theta={self.rxy_theta}",
+            f"--rxy-phi={self.rxy_phi}",
+            f"--rz={self.rz}",
+            f"--rzz={self.rzz}",
+            f"--on-violation={self.on_violation}",
+            f"--tolerance={self.tolerance}",
+        ] + [f"--wrapped-arg={arg}" for arg in self.runtime.get_init_args()]
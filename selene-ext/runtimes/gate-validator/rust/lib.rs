@@ -0,0 +1,288 @@
+use std::{f64::consts::PI, fmt, str::FromStr};
+
+use anyhow::{Result, anyhow};
+use clap::{Parser, ValueEnum};
+use selene_core::capabilities::Capabilities;
+use selene_core::error::ErrorKind;
+use selene_core::metadata::ArgumentMetadata;
+use selene_core::{
+    runtime::{
+        Runtime, RuntimeInterface,
+        interface::RuntimeInterfaceFactory,
+        wrapper::{RuntimeWrapper, WrappedRuntime},
+    },
+    utils::MetricValue,
+};
+
+#[cfg(test)]
+mod tests;
+
+/// The set of angles permitted for a gate parameter, expressed in
+/// half-turns (multiples of π). Angles are compared modulo a full turn.
+#[derive(Debug, Clone, Default)]
+enum AngleGrid {
+    /// Any angle is permitted.
+    #[default]
+    Any,
+    /// Only integer multiples of the given step are permitted.
+    Step(f64),
+    /// Only the listed angles are permitted.
+    Values(Vec<f64>),
+}
+
+/// Wrap an angle in half-turns into the range [-1, 1).
+fn normalize(half_turns: f64) -> f64 {
+    (half_turns + 1.0).rem_euclid(2.0) - 1.0
+}
+
+impl AngleGrid {
+    /// Find the permitted angle closest to `half_turns`, returning it
+    /// along with its distance from `half_turns`.
+    fn nearest(&self, half_turns: f64) -> (f64, f64) {
+        let distance = |allowed: f64| normalize(half_turns - allowed).abs();
+        match self {
+            Self::Any => (half_turns, 0.0),
+            Self::Step(step) => {
+                let allowed = (normalize(half_turns) / step).round() * step;
+                (allowed, distance(allowed))
+            }
+            Self::Values(values) => values
+                .iter()
+                .map(|&allowed| (allowed, distance(allowed)))
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .expect("grids are parsed with at least one value"),
+        }
+    }
+}
+
+impl FromStr for AngleGrid {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s == "any" {
+            return Ok(Self::Any);
+        }
+        if let Some(step) = s.strip_prefix("step:") {
+            let step: f64 = step.parse().map_err(|e| {
+                ErrorKind::InvalidArgument.error(format!("invalid grid step '{step}': {e}"))
+            })?;
+            if !(step.is_finite() && step > 0.0) {
+                return Err(ErrorKind::InvalidArgument
+                    .error(format!("grid step must be positive and finite, got {step}")));
+            }
+            return Ok(Self::Step(step));
+        }
+        let values = s
+            .split(',')
+            .map(|value| {
+                value.trim().parse::<f64>().map_err(|e| {
+                    ErrorKind::InvalidArgument.error(format!("invalid grid angle '{value}': {e}"))
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::Values(values))
+    }
+}
+
+impl fmt::Display for AngleGrid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => f.write_str("any"),
+            Self::Step(step) => write!(f, "step:{step}"),
+            Self::Values(values) => {
+                let values: Vec<String> = values.iter().map(f64::to_string).collect();
+                f.write_str(&values.join(","))
+            }
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum OnViolation {
+    /// Fail the shot with an error
+    Reject,
+    /// Replace the angle with the nearest permitted angle
+    Snap,
+}
+
+#[derive(Parser, Debug)]
+struct Params {
+    /// The path to the wrapped runtime plugin
+    #[arg(long)]
+    wrapped_path: String,
+    /// Arguments for the wrapped runtime plugin
+    #[arg(long)]
+    wrapped_arg: Vec<String>,
+    /// Permitted theta angles of RXY gates, in half-turns. One of "any",
+    /// "step:<x>" or a comma-separated list of angles
+    #[arg(long, default_value_t = AngleGrid::Any)]
+    rxy_theta: AngleGrid,
+    /// Permitted phi angles of RXY gates, in half-turns
+    #[arg(long, default_value_t = AngleGrid::Any)]
+    rxy_phi: AngleGrid,
+    /// Permitted angles of RZ gates, in half-turns
+    #[arg(long, default_value_t = AngleGrid::Any)]
+    rz: AngleGrid,
+    /// Permitted angles of RZZ gates, in half-turns
+    #[arg(long, default_value_t = AngleGrid::Any)]
+    rzz: AngleGrid,
+    /// The maximum distance, in half-turns, between an angle and the grid
+    /// for the angle to be accepted unchanged
+    #[arg(long, default_value_t = 1e-9)]
+    tolerance: f64,
+    /// What to do when a gate angle is not on the grid
+    #[arg(long, value_enum, default_value_t = OnViolation::Reject)]
+    on_violation: OnViolation,
+}
+
+/// Checks the angles of gates issued by the user program against a
+/// configured grid before passing them to the wrapped runtime.
+///
+/// This can be used to check that a program targets a restricted native
+/// gate set (e.g. only RZZ(±π/2)), or to coerce it onto one.
+pub struct GateValidator {
+    params: Params,
+    rxy_violations: u64,
+    rz_violations: u64,
+    rzz_violations: u64,
+}
+
+impl GateValidator {
+    /// Validate an angle in radians against `grid`. Returns the angle to pass
+    /// on to the wrapped runtime, or `None` if the angle is not on the grid.
+    fn check(&self, grid: &AngleGrid, radians: f64) -> Option<f64> {
+        let (allowed, distance) = grid.nearest(radians / PI);
+        if distance <= self.params.tolerance {
+            Some(radians)
+        } else {
+            Some(allowed * PI).filter(|_| self.params.on_violation == OnViolation::Snap)
+        }
+    }
+}
+
+impl RuntimeWrapper for GateValidator {
+    fn capabilities(&self, inner: &Runtime) -> Capabilities {
+        inner.capabilities() | Capabilities::METRICS
+    }
+
+    fn shot_start(&mut self, inner: &mut Runtime, shot_id: u64, seed: u64) -> Result<()> {
+        self.rxy_violations = 0;
+        self.rz_violations = 0;
+        self.rzz_violations = 0;
+        inner.shot_start(shot_id, seed)
+    }
+
+    fn get_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        let violations =
+            |name: &str, count: u64| Some((format!("{name}_violations"), MetricValue::U64(count)));
+        Ok(match nth_metric {
+            0 => violations("rxy", self.rxy_violations),
+            1 => violations("rz", self.rz_violations),
+            2 => violations("rzz", self.rzz_violations),
+            _ => None,
+        })
+    }
+
+    fn rxy_gate(&mut self, inner: &mut Runtime, qubit_id: u64, theta: f64, phi: f64) -> Result<()> {
+        let checked_theta = self.check(&self.params.rxy_theta, theta);
+        let checked_phi = self.check(&self.params.rxy_phi, phi);
+        if checked_theta != Some(theta) || checked_phi != Some(phi) {
+            self.rxy_violations += 1;
+        }
+        match (checked_theta, checked_phi) {
+            (Some(theta), Some(phi)) => inner.rxy_gate(qubit_id, theta, phi),
+            _ => Err(ErrorKind::Unsupported.error(format!(
+                "RXY({theta}, {phi}) on qubit {qubit_id} is not permitted: theta must be on grid '{}' and phi on grid '{}' (in half-turns)",
+                self.params.rxy_theta,
+                self.params.rxy_phi
+            ))),
+        }
+    }
+
+    fn rz_gate(&mut self, inner: &mut Runtime, qubit_id: u64, theta: f64) -> Result<()> {
+        let checked = self.check(&self.params.rz, theta);
+        if checked != Some(theta) {
+            self.rz_violations += 1;
+        }
+        match checked {
+            Some(theta) => inner.rz_gate(qubit_id, theta),
+            None => Err(ErrorKind::Unsupported.error(format!(
+                "RZ({theta}) on qubit {qubit_id} is not permitted: theta must be on grid '{}' (in half-turns)",
+                self.params.rz
+            ))),
+        }
+    }
+
+    fn rzz_gate(
+        &mut self,
+        inner: &mut Runtime,
+        qubit_id_1: u64,
+        qubit_id_2: u64,
+        theta: f64,
+    ) -> Result<()> {
+        let checked = self.check(&self.params.rzz, theta);
+        if checked != Some(theta) {
+            self.rzz_violations += 1;
+        }
+        match checked {
+            Some(theta) => inner.rzz_gate(qubit_id_1, qubit_id_2, theta),
+            None => Err(ErrorKind::Unsupported.error(format!(
+                "RZZ({theta}) on qubits {qubit_id_1} and {qubit_id_2} is not permitted: theta must be on grid '{}' (in half-turns)",
+                self.params.rzz
+            ))),
+        }
+    }
+}
+
+/// The name the plugin is known by, under which selene registers it when it
+/// is linked in.
+pub const PLUGIN_NAME: &str = "gate_validator";
+
+#[derive(Default)]
+pub struct GateValidatorRuntimeFactory;
+
+impl RuntimeInterfaceFactory for GateValidatorRuntimeFactory {
+    type Interface = WrappedRuntime<GateValidator>;
+
+    /// The validator's own features. Those of the runtime it wraps depend on
+    /// its arguments, so are added when an instance is created.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::METRICS
+    }
+
+    fn arguments(&self) -> Option<Vec<ArgumentMetadata>> {
+        Some(ArgumentMetadata::from_clap::<Params>())
+    }
+
+    fn init(
+        self: std::sync::Arc<Self>,
+        n_qubits: u64,
+        start: selene_core::time::Instant,
+        args: &[impl AsRef<str>],
+    ) -> Result<Box<Self::Interface>> {
+        let params = Params::try_parse_from(args.iter().map(|s| s.as_ref())).map_err(|e| {
+            anyhow!(
+                "Error parsing arguments to gate validator runtime plugin: {}",
+                e
+            )
+        })?;
+        let wrapped = Runtime::load_from_file(
+            &params.wrapped_path,
+            n_qubits,
+            start,
+            params.wrapped_arg.as_ref(),
+        )?;
+        Ok(Box::new(WrappedRuntime::new(
+            wrapped,
+            GateValidator {
+                params,
+                rxy_violations: 0,
+                rz_violations: 0,
+                rzz_violations: 0,
+            },
+        )))
+    }
+}
+
+#[cfg(feature = "export")]
+selene_core::export_runtime_plugin!(crate::GateValidatorRuntimeFactory, crate::PLUGIN_NAME);
@@ -0,0 +1,246 @@
+use super::*;
+use selene_core::runtime::Operation;
+use selene_simple_runtime::SimpleRuntimeFactory;
+use std::sync::Arc;
+
+fn grid(s: &str) -> AngleGrid {
+    s.parse().unwrap()
+}
+
+fn validator(args: &[&str]) -> WrappedRuntime<GateValidator> {
+    let params = Params::parse_from(
+        ["gate_validator", "--wrapped-path=builtin:simple"]
+            .into_iter()
+            .chain(args.iter().copied()),
+    );
+    let inner = Runtime::new(
+        Arc::new(SimpleRuntimeFactory),
+        4,
+        Default::default(),
+        &["simple"],
+    )
+    .unwrap();
+    WrappedRuntime::new(
+        inner,
+        GateValidator {
+            params,
+            rxy_violations: 0,
+            rz_violations: 0,
+            rzz_violations: 0,
+        },
+    )
+}
+
+/// Emit every ready operation of the wrapped runtime.
+fn ops(runtime: &mut WrappedRuntime<GateValidator>) -> Vec<Operation> {
+    let mut ops = Vec::new();
+    while let Some(batch) = runtime.get_next_operations().unwrap() {
+        ops.extend(batch);
+    }
+    ops
+}
+
+fn metrics(runtime: &mut WrappedRuntime<GateValidator>) -> Vec<(String, MetricValue)> {
+    (0..)
+        .map_while(|n| runtime.get_metric(n).unwrap())
+        .collect()
+}
+
+#[test]
+fn grids_are_parsed() {
+    assert!(matches!(grid("any"), AngleGrid::Any));
+    assert!(matches!(grid("step:0.5"), AngleGrid::Step(step) if step == 0.5));
+    assert!(matches!(grid("0, 0.5,-0.5"), AngleGrid::Values(values) if values == [0.0, 0.5, -0.5]));
+    for s in ["any", "step:0.25", "0,0.5,-0.5"] {
+        assert_eq!(grid(s).to_string(), s);
+    }
+}
+
+#[test]
+fn invalid_grids_are_rejected() {
+    for s in [
+        "step:",
+        "step:x",
+        "step:0",
+        "step:-0.5",
+        "step:inf",
+        "0,x",
+        "",
+    ] {
+        let err = s.parse::<AngleGrid>().unwrap_err();
+        assert_eq!(
+            ErrorKind::of(&err),
+            ErrorKind::InvalidArgument,
+            "{s}: {err}"
+        );
+    }
+}
+
+#[test]
+fn angles_are_normalized_to_a_turn() {
+    assert_eq!(normalize(0.5), 0.5);
+    assert_eq!(normalize(1.0), -1.0);
+    assert_eq!(normalize(-1.0), -1.0);
+    assert_eq!(normalize(2.5), 0.5);
+    assert_eq!(normalize(-1.5), 0.5);
+}
+
+fn assert_nearest(grid: &AngleGrid, half_turns: f64, allowed: f64, distance: f64) {
+    let nearest = grid.nearest(half_turns);
+    assert!(
+        nearest.0 == allowed && (nearest.1 - distance).abs() < 1e-12,
+        "nearest {half_turns} on '{grid}' is {nearest:?}"
+    );
+}
+
+#[test]
+fn nearest_angles_are_found_modulo_a_turn() {
+    assert_nearest(&AngleGrid::Any, 0.3, 0.3, 0.0);
+    assert_nearest(&grid("step:0.5"), 0.5, 0.5, 0.0);
+    assert_nearest(&grid("step:0.5"), 0.7, 0.5, 0.2);
+    assert_nearest(&grid("step:0.5"), 2.5, 0.5, 0.0);
+    assert_nearest(&grid("0.5,-0.5"), -0.4, -0.5, 0.1);
+    // 1.9 half-turns is 0.1 from 0, and 0.4 from 1.5.
+    assert_nearest(&grid("0,1.5"), 1.9, 0.0, 0.1);
+}
+
+#[test]
+fn angles_on_the_grid_are_passed_on() {
+    let mut runtime = validator(&["--rz=step:0.5", "--rzz=0.5,-0.5", "--tolerance=1e-6"]);
+    let q0 = runtime.qalloc().unwrap();
+    let q1 = runtime.qalloc().unwrap();
+    runtime.rz_gate(q0, PI / 2.0 + 1e-7).unwrap();
+    runtime.rzz_gate(q0, q1, -PI / 2.0).unwrap();
+    runtime.rxy_gate(q1, 0.123, 0.456).unwrap();
+    assert_eq!(
+        ops(&mut runtime),
+        vec![
+            Operation::RZGate {
+                qubit_id: q0,
+                theta: PI / 2.0 + 1e-7
+            },
+            Operation::RZZGate {
+                qubit_id_1: q0,
+                qubit_id_2: q1,
+                theta: -PI / 2.0
+            },
+            Operation::RXYGate {
+                qubit_id: q1,
+                theta: 0.123,
+                phi: 0.456
+            },
+        ]
+    );
+}
+
+#[test]
+fn angles_off_the_grid_are_rejected() {
+    let mut runtime = validator(&["--rxy-theta=0.5", "--rz=step:0.5", "--rzz=0.5"]);
+    let q0 = runtime.qalloc().unwrap();
+    let q1 = runtime.qalloc().unwrap();
+    for err in [
+        runtime.rxy_gate(q0, PI / 4.0, 0.0).unwrap_err(),
+        runtime.rz_gate(q0, 0.1).unwrap_err(),
+        runtime.rzz_gate(q0, q1, PI).unwrap_err(),
+    ] {
+        assert_eq!(ErrorKind::of(&err), ErrorKind::Unsupported, "{err}");
+    }
+    assert_eq!(ops(&mut runtime), vec![]);
+}
+
+#[test]
+fn angles_off_the_grid_are_snapped() {
+    let mut runtime = validator(&["--rxy-phi=0,0.5", "--rz=step:0.5", "--on-violation=snap"]);
+    let q = runtime.qalloc().unwrap();
+    runtime.rxy_gate(q, 0.3, PI * 0.4).unwrap();
+    runtime.rz_gate(q, PI * 2.3).unwrap();
+    assert_eq!(
+        ops(&mut runtime),
+        vec![
+            Operation::RXYGate {
+                qubit_id: q,
+                theta: 0.3,
+                phi: PI / 2.0
+            },
+            Operation::RZGate {
+                qubit_id: q,
+                theta: PI / 2.0
+            },
+        ]
+    );
+}
+
+#[test]
+fn violations_are_counted_per_shot_before_the_inner_metrics() {
+    let mut runtime = validator(&["--rz=0", "--rzz=0", "--on-violation=snap"]);
+    runtime.shot_start(0, 0).unwrap();
+    let q0 = runtime.qalloc().unwrap();
+    let q1 = runtime.qalloc().unwrap();
+    runtime.rz_gate(q0, 1.0).unwrap();
+    runtime.rz_gate(q0, 0.0).unwrap();
+    runtime.rz_gate(q1, 2.0).unwrap();
+    runtime.rzz_gate(q0, q1, 1.0).unwrap();
+    assert_eq!(
+        metrics(&mut runtime)[..4],
+        [
+            ("rxy_violations".to_string(), MetricValue::U64(0)),
+            ("rz_violations".to_string(), MetricValue::U64(2)),
+            ("rzz_violations".to_string(), MetricValue::U64(1)),
+            ("skipped_conditional_ops".to_string(), MetricValue::U64(0)),
+        ]
+    );
+    runtime.shot_end().unwrap();
+
+    runtime.shot_start(1, 1).unwrap();
+    assert_eq!(
+        metrics(&mut runtime)[..3],
+        [
+            ("rxy_violations".to_string(), MetricValue::U64(0)),
+            ("rz_violations".to_string(), MetricValue::U64(0)),
+            ("rzz_violations".to_string(), MetricValue::U64(0)),
+        ]
+    );
+}
+
+#[test]
+fn capabilities_are_those_of_the_wrapped_runtime() {
+    selene_core::registry::register_runtime(
+        selene_simple_runtime::PLUGIN_NAME,
+        Arc::new(SimpleRuntimeFactory),
+    );
+    let runtime = Runtime::new(
+        Arc::new(GateValidatorRuntimeFactory),
+        4,
+        Default::default(),
+        &["gate_validator", "--wrapped-path=builtin:simple"],
+    )
+    .unwrap();
+    // The validator passes conditions and measure-resets on to the simple
+    // runtime, so supports them too.
+    assert_eq!(
+        runtime.capabilities(),
+        SimpleRuntimeFactory.capabilities() | Capabilities::METRICS
+    );
+    assert!(
+        runtime
+            .capabilities()
+            .contains(Capabilities::CONDITIONS | Capabilities::MEASURE_RESET)
+    );
+
+    let err = Runtime::new(
+        Arc::new(GateValidatorRuntimeFactory),
+        4,
+        Default::default(),
+        &[
+            "gate_validator",
+            "--wrapped-path=builtin:simple",
+            "--rz=step:0",
+        ],
+    )
+    .err()
+    .unwrap();
+    assert!(
+        format!("{err:#}").contains("grid step must be positive"),
+        "{err:#}"
+    );
+}
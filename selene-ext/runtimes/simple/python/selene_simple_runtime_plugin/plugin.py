@@ -14,8 +14,32 @@ class SimpleRuntimePlugin(Runtime):
     not performed immediately, but is stored in a queue. Upon the request for a measurement
     result, operations before and including the measurement are performed in order to
     retrieve the result.
+
+    Attributes:
+        allocation_policy (str): The policy used to choose a free qubit when the
+            program allocates one. One of "lowest-index" (the default), "lru"
+            (least recently freed, to spread reuse across the device) or
+            "round-robin".
+        reset_on_reuse (bool): If True, a qubit that was freed without being
+            measured or reset since its last gate has a reset inserted when it
+            is next allocated.
+        classical_latency_ns (int): The delay, in nanoseconds, between a
+            measurement result becoming available and the start of operations
+            conditioned upon it.
     """
 
+    allocation_policy: str = "lowest-index"
+    reset_on_reuse: bool = False
+    classical_latency_ns: int = 0
+
+    def __post_init__(self):
+        assert self.allocation_policy in ("lowest-index", "lru", "round-robin"), (
+            f"allocation_policy ({self.allocation_policy}) must be one of 'lowest-index', 'lru' or 'round-robin'"
+        )
+        assert self.classical_latency_ns >= 0, (
+            f"classical_latency_ns ({self.classical_latency_ns}) must be non-negative"
+        )
+
     @property
     def library_file(self):
         libdir = Path(__file__).parent / "_dist/lib/"
@@ -30,7 +54,7 @@ class SimpleRuntimePlugin(Runtime):
                 raise RuntimeError(f"Unsupported platform: {platform.system()}")
 
     def get_init_args(self):
-        """
-        There are no init args for the simple runtime.
-        """
-        return []
+        return [
+            f"--allocation-policy={self.allocation_policy}",
+            f"--classical-latency-ns={self.classical_latency_ns}",
+        ] + (["--reset-on-reuse"] if self.reset_on_reuse else [])
@@ -1,16 +1,44 @@
 use std::collections::VecDeque;
 
-use anyhow::{Result, bail};
+use anyhow::{Result, anyhow, bail};
+use clap::Parser;
+use selene_core::capabilities::Capabilities;
+use selene_core::error::ErrorKind;
+use selene_core::metadata::ArgumentMetadata;
 use selene_core::{
-    export_runtime_plugin,
-    runtime::{BatchOperation, Operation, RuntimeInterface, interface::RuntimeInterfaceFactory},
+    runtime::{
+        BatchOperation, Operation, RuntimeInterface,
+        allocator::{AllocationPolicy, QubitAllocator},
+        interface::RuntimeInterfaceFactory,
+    },
     utils::MetricValue,
 };
 
-#[derive(Debug, Clone, PartialEq)]
-enum QubitStatus {
-    Free,
-    Active,
+#[cfg(test)]
+mod tests;
+
+#[derive(Parser, Debug)]
+struct Params {
+    /// The policy used to choose a free qubit on allocation
+    /// (lowest-index, lru or round-robin)
+    #[arg(long, default_value_t = AllocationPolicy::LowestIndex)]
+    allocation_policy: AllocationPolicy,
+    /// Insert a reset when reallocating a qubit that was freed
+    /// without being measured or reset
+    #[arg(long)]
+    reset_on_reuse: bool,
+    /// The delay, in nanoseconds, between a measurement result becoming
+    /// available and the start of operations conditioned upon it
+    #[arg(long, default_value_t = 0)]
+    classical_latency_ns: u64,
+}
+
+// An operation waiting in the queue, along with the (result_id, value)
+// pairs that must all hold for it to be applied.
+#[derive(Debug, Clone)]
+struct QueuedOperation {
+    op: Operation,
+    conditions: Vec<(u64, bool)>,
 }
 
 // We model bool and u64 results through the same
@@ -19,54 +47,159 @@ enum QubitStatus {
 #[derive(Debug, Clone)]
 struct FutureResult {
     measured: bool,
+    /// Set when the measurement was dropped because a condition on it did
+    /// not hold, so the result will never land.
+    skipped: bool,
     value: u64,
+    landed_at: selene_core::time::Instant,
+}
+
+impl FutureResult {
+    fn pending() -> Self {
+        Self {
+            measured: false,
+            skipped: false,
+            value: 0,
+            landed_at: Default::default(),
+        }
+    }
 }
 
-struct SimpleRuntime {
-    qubits: Vec<QubitStatus>,
-    operation_queue: VecDeque<BatchOperation>,
+pub struct SimpleRuntime {
+    qubits: QubitAllocator,
+    operation_queue: VecDeque<QueuedOperation>,
     future_results: Vec<FutureResult>,
+    conditions: Vec<(u64, bool)>,
+    classical_latency: selene_core::time::Duration,
+    skipped_conditional_ops: u64,
     start: selene_core::time::Instant,
+    now: selene_core::time::Instant,
 }
 
 impl SimpleRuntime {
-    pub fn new(n_qubits: u64, start: selene_core::time::Instant) -> Self {
+    fn new(n_qubits: u64, start: selene_core::time::Instant, params: &Params) -> Self {
         Self {
-            qubits: vec![QubitStatus::Free; n_qubits as usize],
+            qubits: QubitAllocator::new(n_qubits, params.allocation_policy, params.reset_on_reuse),
             operation_queue: VecDeque::with_capacity(10000),
             future_results: Vec::with_capacity(1000),
+            conditions: Vec::new(),
+            classical_latency: params.classical_latency_ns.into(),
+            skipped_conditional_ops: 0,
             start,
+            now: start,
         }
     }
 
     pub fn push(&mut self, op: Operation) {
-        self.operation_queue.push_back(BatchOperation::new(
-            vec![op],
-            self.start,
-            Default::default(),
-        ));
+        self.operation_queue.push_back(QueuedOperation {
+            op,
+            conditions: self.conditions.clone(),
+        });
+    }
+
+    /// Drop a conditional operation whose conditions do not hold. The result
+    /// of a dropped measurement never lands, so operations conditioned upon
+    /// it are dropped in turn, and reading it is an error.
+    fn skip(&mut self, op: Operation) {
+        self.skipped_conditional_ops += 1;
+        if let Operation::Measure { result_id, .. }
+        | Operation::MeasureLeaked { result_id, .. }
+        | Operation::MeasureReset { result_id, .. } = op
+        {
+            self.future_results[result_id as usize].skipped = true;
+        }
+    }
+
+    /// Mark a qubit as being in a known state after it is measured or reset.
+    /// Within a condition the operation may yet be dropped, so the qubit is
+    /// left as it was.
+    fn mark_known(&mut self, qubit_id: u64) {
+        if self.conditions.is_empty() {
+            self.qubits.mark_known(qubit_id);
+        }
+    }
+
+    fn get_result(&self, result_id: u64) -> Result<Option<u64>> {
+        let Some(result) = self.future_results.get(result_id as usize) else {
+            bail!("getting out-of-bounds measurement {result_id}");
+        };
+        if result.skipped {
+            bail!(ErrorKind::InvalidArgument.error(format!(
+                "reading measurement {result_id}, which was skipped as its conditions did not hold"
+            )));
+        }
+        Ok(result.measured.then_some(result.value))
+    }
+
+    fn check_bounds(&self, qubit_id: u64, action: &str) -> Result<()> {
+        if qubit_id >= self.qubits.n_qubits() {
+            bail!(
+                ErrorKind::QubitOutOfBounds
+                    .error(format!("{action} out-of-bounds qubit {qubit_id}"))
+            );
+        }
+        Ok(())
     }
 }
 
 impl RuntimeInterface for SimpleRuntime {
     fn exit(&mut self) -> Result<()> {
         self.operation_queue.clear();
-        self.qubits.clear();
+        self.qubits.reset();
         self.future_results.clear();
+        self.conditions.clear();
         Ok(())
     }
     // Engine ops
     fn get_next_operations(&mut self) -> Result<Option<BatchOperation>> {
-        Ok(self.operation_queue.pop_front())
+        while let Some(queued) = self.operation_queue.front() {
+            // Conditional operations are dropped as soon as any condition
+            // is known not to hold. Otherwise they stay at the front of the
+            // queue until every result they depend on has landed, and are
+            // then emitted no earlier than the classical latency after the
+            // latest of those results.
+            let mut start = self.now;
+            let mut satisfied = true;
+            let mut pending = false;
+            for &(result_id, value) in queued.conditions.iter() {
+                let result = &self.future_results[result_id as usize];
+                if result.skipped {
+                    satisfied = false;
+                } else if !result.measured {
+                    pending = true;
+                } else {
+                    satisfied &= (result.value > 0) == value;
+                    start = start.max(result.landed_at + self.classical_latency);
+                }
+            }
+            if satisfied && pending {
+                return Ok(None);
+            }
+            let queued = self.operation_queue.pop_front().unwrap();
+            if !satisfied {
+                self.skip(queued.op);
+                continue;
+            }
+            self.now = start;
+            return Ok(Some(BatchOperation::new(
+                vec![queued.op],
+                start,
+                Default::default(),
+            )));
+        }
+        Ok(None)
     }
 
     fn shot_start(&mut self, _shot_id: u64, _seed: u64) -> Result<()> {
         Ok(())
     }
     fn shot_end(&mut self) -> Result<()> {
-        self.qubits = vec![QubitStatus::Free; self.qubits.len()];
+        self.qubits.reset();
         self.operation_queue.clear();
         self.future_results.clear();
+        self.conditions.clear();
+        self.skipped_conditional_ops = 0;
+        self.now = self.start;
         Ok(())
     }
     fn global_barrier(&mut self, _sleep_ns: u64) -> Result<()> {
@@ -81,29 +214,30 @@ impl RuntimeInterface for SimpleRuntime {
     }
     // Allocation
     fn qalloc(&mut self) -> Result<u64> {
-        for (i, qubit) in self.qubits.iter_mut().enumerate() {
-            if *qubit == QubitStatus::Free {
-                *qubit = QubitStatus::Active;
-                return Ok(i as u64);
-            }
+        let Some(allocation) = self.qubits.alloc() else {
+            return Ok(u64::MAX);
+        };
+        if allocation.needs_reset {
+            // Allocation itself is never conditional, so neither is the
+            // reset that prepares the qubit for reuse.
+            self.operation_queue.push_back(QueuedOperation {
+                op: Operation::Reset {
+                    qubit_id: allocation.qubit_id,
+                },
+                conditions: Vec::new(),
+            });
         }
-        Ok(u64::MAX)
+        Ok(allocation.qubit_id)
     }
     fn qfree(&mut self, qubit_id: u64) -> Result<()> {
-        if qubit_id >= self.qubits.len() as u64 {
-            bail!("freeing out-of-bounds qubit {qubit_id}")
-        } else {
-            self.qubits[qubit_id as usize] = QubitStatus::Free;
-            Ok(())
-        }
+        self.qubits.free(qubit_id)
     }
     fn rxy_gate(&mut self, qubit_id: u64, theta: f64, phi: f64) -> Result<()> {
-        if qubit_id >= self.qubits.len() as u64 {
-            bail!("applying rxy gate to out-of-bounds qubit {qubit_id}");
-        }
-        let QubitStatus::Active = self.qubits[qubit_id as usize] else {
+        self.check_bounds(qubit_id, "applying rxy gate to")?;
+        if !self.qubits.is_allocated(qubit_id) {
             bail!("Qubit {qubit_id} is not active");
-        };
+        }
+        self.qubits.mark_unknown(qubit_id);
         self.push(Operation::RXYGate {
             qubit_id,
             theta,
@@ -112,12 +246,18 @@ impl RuntimeInterface for SimpleRuntime {
         Ok(())
     }
     fn rzz_gate(&mut self, qubit_id_1: u64, qubit_id_2: u64, theta: f64) -> Result<()> {
-        if qubit_id_1 >= self.qubits.len() as u64 {
-            bail!("applying rzz gate to out-of-bounds qubit1 {qubit_id_1}");
+        if qubit_id_1 >= self.qubits.n_qubits() {
+            bail!(ErrorKind::QubitOutOfBounds.error(format!(
+                "applying rzz gate to out-of-bounds qubit1 {qubit_id_1}"
+            )));
         }
-        if qubit_id_2 >= self.qubits.len() as u64 {
-            bail!("applying rzz gate to out-of-bounds qubit2 {qubit_id_2}");
+        if qubit_id_2 >= self.qubits.n_qubits() {
+            bail!(ErrorKind::QubitOutOfBounds.error(format!(
+                "applying rzz gate to out-of-bounds qubit2 {qubit_id_2}"
+            )));
         }
+        self.qubits.mark_unknown(qubit_id_1);
+        self.qubits.mark_unknown(qubit_id_2);
         self.push(Operation::RZZGate {
             qubit_id_1,
             qubit_id_2,
@@ -126,25 +266,20 @@ impl RuntimeInterface for SimpleRuntime {
         Ok(())
     }
     fn rz_gate(&mut self, qubit_id: u64, theta: f64) -> Result<()> {
-        if qubit_id >= self.qubits.len() as u64 {
-            bail!("applying rz gate to out-of-bounds qubit {qubit_id}");
-        }
-        let QubitStatus::Active = self.qubits[qubit_id as usize] else {
+        self.check_bounds(qubit_id, "applying rz gate to")?;
+        if !self.qubits.is_allocated(qubit_id) {
             bail!("Qubit {qubit_id} is not active");
-        };
+        }
+        self.qubits.mark_unknown(qubit_id);
         self.push(Operation::RZGate { qubit_id, theta });
         Ok(())
     }
     // Lifetime ops
     fn measure(&mut self, qubit_id: u64) -> Result<u64> {
-        if qubit_id >= self.qubits.len() as u64 {
-            bail!("measuring out-of-bounds qubit {qubit_id}")
-        }
+        self.check_bounds(qubit_id, "measuring")?;
+        self.mark_known(qubit_id);
         let result_id = self.future_results.len() as u64;
-        self.future_results.push(FutureResult {
-            measured: false,
-            value: 0,
-        });
+        self.future_results.push(FutureResult::pending());
         self.push(Operation::Measure {
             qubit_id,
             result_id,
@@ -152,14 +287,10 @@ impl RuntimeInterface for SimpleRuntime {
         Ok(result_id)
     }
     fn measure_leaked(&mut self, qubit_id: u64) -> Result<u64> {
-        if qubit_id >= self.qubits.len() as u64 {
-            bail!("measuring out-of-bounds qubit {qubit_id}")
-        }
+        self.check_bounds(qubit_id, "measuring")?;
+        self.mark_known(qubit_id);
         let result_id = self.future_results.len() as u64;
-        self.future_results.push(FutureResult {
-            measured: false,
-            value: 0,
-        });
+        self.future_results.push(FutureResult::pending());
         self.push(Operation::MeasureLeaked {
             qubit_id,
             result_id,
@@ -167,10 +298,21 @@ impl RuntimeInterface for SimpleRuntime {
         Ok(result_id)
     }
 
+    fn measure_reset(&mut self, qubit_id: u64) -> Result<u64> {
+        self.check_bounds(qubit_id, "measuring")?;
+        self.mark_known(qubit_id);
+        let result_id = self.future_results.len() as u64;
+        self.future_results.push(FutureResult::pending());
+        self.push(Operation::MeasureReset {
+            qubit_id,
+            result_id,
+        });
+        Ok(result_id)
+    }
+
     fn reset(&mut self, qubit_id: u64) -> Result<()> {
-        if qubit_id >= self.qubits.len() as u64 {
-            bail!("resetting out-of-bounds qubit {qubit_id}")
-        }
+        self.check_bounds(qubit_id, "resetting")?;
+        self.mark_known(qubit_id);
         self.push(Operation::Reset { qubit_id });
         Ok(())
     }
@@ -183,15 +325,7 @@ impl RuntimeInterface for SimpleRuntime {
         Ok(())
     }
     fn get_bool_result(&mut self, result_id: u64) -> Result<Option<bool>> {
-        if result_id >= self.future_results.len() as u64 {
-            bail!("getting out-of-bounds measurement {result_id}");
-        }
-        let result = &self.future_results[result_id as usize];
-        Ok(if result.measured {
-            Some(result.value > 0)
-        } else {
-            None
-        })
+        Ok(self.get_result(result_id)?.map(|value| value > 0))
     }
     fn set_bool_result(&mut self, result_id: u64, result: bool) -> Result<()> {
         if result_id >= self.future_results.len() as u64 {
@@ -199,18 +333,11 @@ impl RuntimeInterface for SimpleRuntime {
         }
         self.future_results[result_id as usize].value = if result { 1 } else { 0 };
         self.future_results[result_id as usize].measured = true;
+        self.future_results[result_id as usize].landed_at = self.now;
         Ok(())
     }
     fn get_u64_result(&mut self, result_id: u64) -> Result<Option<u64>> {
-        if result_id >= self.future_results.len() as u64 {
-            bail!("getting out-of-bounds measurement {result_id}");
-        }
-        let result = &self.future_results[result_id as usize];
-        Ok(if result.measured {
-            Some(result.value)
-        } else {
-            None
-        })
+        self.get_result(result_id)
     }
     fn set_u64_result(&mut self, result_id: u64, result: u64) -> Result<()> {
         if result_id >= self.future_results.len() as u64 {
@@ -218,6 +345,7 @@ impl RuntimeInterface for SimpleRuntime {
         }
         self.future_results[result_id as usize].value = result;
         self.future_results[result_id as usize].measured = true;
+        self.future_results[result_id as usize].landed_at = self.now;
         Ok(())
     }
 
@@ -227,25 +355,62 @@ impl RuntimeInterface for SimpleRuntime {
     fn decrement_future_refcount(&mut self, _future_ref: u64) -> Result<()> {
         Ok(())
     }
-    fn get_metric(&mut self, _nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
-        Ok(None)
+    fn begin_condition(&mut self, result_id: u64, value: bool) -> Result<()> {
+        if result_id >= self.future_results.len() as u64 {
+            bail!("conditioning on out-of-bounds measurement {result_id}");
+        }
+        self.conditions.push((result_id, value));
+        Ok(())
+    }
+    fn end_condition(&mut self) -> Result<()> {
+        if self.conditions.pop().is_none() {
+            bail!("ending a condition when no conditional block is open");
+        }
+        Ok(())
+    }
+    fn get_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        match nth_metric {
+            0 => Ok(Some((
+                "skipped_conditional_ops".to_string(),
+                MetricValue::U64(self.skipped_conditional_ops),
+            ))),
+            n => Ok(self.qubits.get_metric(n - 1)),
+        }
     }
 }
 
+/// The name the plugin is known by, under which selene registers it when it
+/// is linked in.
+pub const PLUGIN_NAME: &str = "simple";
+
 #[derive(Default)]
-struct SimpleRuntimeFactory;
+pub struct SimpleRuntimeFactory;
 
 impl RuntimeInterfaceFactory for SimpleRuntimeFactory {
     type Interface = SimpleRuntime;
 
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::BATCHING
+            | Capabilities::METRICS
+            | Capabilities::CONDITIONS
+            | Capabilities::MEASURE_RESET
+    }
+
+    fn arguments(&self) -> Option<Vec<ArgumentMetadata>> {
+        Some(ArgumentMetadata::from_clap::<Params>())
+    }
+
     fn init(
         self: std::sync::Arc<Self>,
         n_qubits: u64,
         start: selene_core::time::Instant,
-        _args: &[impl AsRef<str>],
+        args: &[impl AsRef<str>],
     ) -> Result<Box<Self::Interface>> {
-        Ok(Box::new(SimpleRuntime::new(n_qubits, start)))
+        let params = Params::try_parse_from(args.iter().map(|s| s.as_ref()))
+            .map_err(|e| anyhow!("Error parsing arguments to simple runtime plugin: {}", e))?;
+        Ok(Box::new(SimpleRuntime::new(n_qubits, start, &params)))
     }
 }
 
-export_runtime_plugin!(crate::SimpleRuntimeFactory);
+#[cfg(feature = "export")]
+selene_core::export_runtime_plugin!(crate::SimpleRuntimeFactory, crate::PLUGIN_NAME);
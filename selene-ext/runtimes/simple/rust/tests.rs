@@ -0,0 +1,189 @@
+use super::*;
+
+fn runtime(args: &[&str]) -> SimpleRuntime {
+    let params = Params::parse_from(std::iter::once("simple").chain(args.iter().copied()));
+    SimpleRuntime::new(4, Default::default(), &params)
+}
+
+/// Emit every ready operation, returning the operations along with their
+/// start times.
+fn drain(runtime: &mut SimpleRuntime) -> Vec<(Operation, u64)> {
+    let mut ops = Vec::new();
+    while let Some(batch) = runtime.get_next_operations().unwrap() {
+        let start = batch.start().into();
+        ops.extend(batch.into_iter().map(|op| (op, start)));
+    }
+    ops
+}
+
+fn ops(runtime: &mut SimpleRuntime) -> Vec<Operation> {
+    drain(runtime).into_iter().map(|(op, _)| op).collect()
+}
+
+fn measure(runtime: &mut SimpleRuntime, qubit_id: u64, value: bool) -> u64 {
+    let result_id = runtime.measure(qubit_id).unwrap();
+    assert_eq!(
+        ops(runtime),
+        vec![Operation::Measure {
+            qubit_id,
+            result_id
+        }]
+    );
+    runtime.set_bool_result(result_id, value).unwrap();
+    result_id
+}
+
+#[test]
+fn conditions_hold() {
+    let mut runtime = runtime(&["--classical-latency-ns=100"]);
+    let q = runtime.qalloc().unwrap();
+    let r = measure(&mut runtime, q, true);
+    runtime.begin_condition(r, true).unwrap();
+    runtime.reset(q).unwrap();
+    runtime.end_condition().unwrap();
+    runtime.rz_gate(q, 0.5).unwrap();
+    assert_eq!(
+        drain(&mut runtime),
+        vec![
+            (Operation::Reset { qubit_id: q }, 100),
+            (
+                Operation::RZGate {
+                    qubit_id: q,
+                    theta: 0.5
+                },
+                100
+            ),
+        ]
+    );
+}
+
+#[test]
+fn false_condition_is_dropped() {
+    let mut runtime = runtime(&[]);
+    let q = runtime.qalloc().unwrap();
+    let r = measure(&mut runtime, q, false);
+    runtime.begin_condition(r, true).unwrap();
+    runtime.reset(q).unwrap();
+    runtime.end_condition().unwrap();
+    runtime.rz_gate(q, 0.5).unwrap();
+    assert_eq!(
+        ops(&mut runtime),
+        vec![Operation::RZGate {
+            qubit_id: q,
+            theta: 0.5
+        }]
+    );
+    assert_eq!(
+        runtime.get_metric(0).unwrap(),
+        Some(("skipped_conditional_ops".to_string(), MetricValue::U64(1)))
+    );
+}
+
+#[test]
+fn nested_conditions() {
+    let mut runtime = runtime(&[]);
+    let q = runtime.qalloc().unwrap();
+    let r0 = measure(&mut runtime, q, true);
+    let r1 = measure(&mut runtime, q, false);
+    runtime.begin_condition(r0, true).unwrap();
+    runtime.rz_gate(q, 0.25).unwrap();
+    runtime.begin_condition(r1, true).unwrap();
+    runtime.rz_gate(q, 0.5).unwrap();
+    runtime.end_condition().unwrap();
+    runtime.rz_gate(q, 0.75).unwrap();
+    runtime.end_condition().unwrap();
+    let thetas: Vec<f64> = ops(&mut runtime)
+        .into_iter()
+        .map(|op| match op {
+            Operation::RZGate { theta, .. } => theta,
+            op => panic!("unexpected operation {op:?}"),
+        })
+        .collect();
+    assert_eq!(thetas, vec![0.25, 0.75]);
+    assert!(runtime.end_condition().is_err());
+}
+
+#[test]
+fn unresolved_condition_waits() {
+    let mut runtime = runtime(&[]);
+    let q = runtime.qalloc().unwrap();
+    let r = runtime.measure(q).unwrap();
+    assert_eq!(ops(&mut runtime).len(), 1);
+    runtime.begin_condition(r, true).unwrap();
+    runtime.reset(q).unwrap();
+    runtime.end_condition().unwrap();
+    runtime.rz_gate(q, 0.5).unwrap();
+    // Nothing is emitted until the result lands.
+    assert_eq!(ops(&mut runtime), vec![]);
+    assert_eq!(runtime.get_bool_result(r).unwrap(), None);
+    runtime.set_bool_result(r, true).unwrap();
+    assert_eq!(ops(&mut runtime).len(), 2);
+}
+
+#[test]
+fn skipped_measurement_does_not_block() {
+    let mut runtime = runtime(&[]);
+    let q = runtime.qalloc().unwrap();
+    let r0 = measure(&mut runtime, q, false);
+    runtime.begin_condition(r0, true).unwrap();
+    let r1 = runtime.measure(q).unwrap();
+    runtime.end_condition().unwrap();
+    runtime.begin_condition(r1, false).unwrap();
+    runtime.reset(q).unwrap();
+    runtime.end_condition().unwrap();
+    runtime.rz_gate(q, 0.5).unwrap();
+    // The measurement of r1 is dropped, so the reset conditioned on it is
+    // too, and the unconditional gate after them is emitted.
+    assert_eq!(
+        ops(&mut runtime),
+        vec![Operation::RZGate {
+            qubit_id: q,
+            theta: 0.5
+        }]
+    );
+    let error = runtime.get_bool_result(r1).unwrap_err();
+    assert_eq!(ErrorKind::of(&error), ErrorKind::InvalidArgument);
+    assert!(runtime.get_u64_result(r1).is_err());
+}
+
+#[test]
+fn known_false_condition_drops_before_others_land() {
+    let mut runtime = runtime(&[]);
+    let q = runtime.qalloc().unwrap();
+    let r0 = measure(&mut runtime, q, false);
+    let r1 = runtime.measure(q).unwrap();
+    assert_eq!(ops(&mut runtime).len(), 1);
+    runtime.begin_condition(r1, true).unwrap();
+    runtime.begin_condition(r0, true).unwrap();
+    runtime.reset(q).unwrap();
+    runtime.end_condition().unwrap();
+    runtime.end_condition().unwrap();
+    runtime.rz_gate(q, 0.5).unwrap();
+    // r0 does not hold, so the reset is dropped without waiting for r1.
+    assert_eq!(ops(&mut runtime).len(), 1);
+}
+
+#[test]
+fn conditional_resets_leave_qubits_unknown() {
+    let mut runtime = runtime(&["--reset-on-reuse"]);
+    let q = runtime.qalloc().unwrap();
+    let r = measure(&mut runtime, q, false);
+    runtime.rxy_gate(q, 0.5, 0.0).unwrap();
+    runtime.begin_condition(r, true).unwrap();
+    runtime.reset(q).unwrap();
+    runtime.end_condition().unwrap();
+    runtime.qfree(q).unwrap();
+    assert_eq!(runtime.qalloc().unwrap(), q);
+    // The conditional reset is dropped, so the qubit is reset on reuse.
+    assert_eq!(
+        ops(&mut runtime),
+        vec![
+            Operation::RXYGate {
+                qubit_id: q,
+                theta: 0.5,
+                phi: 0.0
+            },
+            Operation::Reset { qubit_id: q }
+        ]
+    );
+}
@@ -14,8 +14,25 @@ class SoftRZRuntimePlugin(Runtime):
     not performed immediately, but is stored in a queue. Upon the request for a measurement
     result, operations before and including the measurement are performed in order to
     retrieve the result.
+
+    Attributes:
+        allocation_policy (str): The policy used to choose a free qubit when the
+            program allocates one. One of "lowest-index" (the default), "lru"
+            (least recently freed, to spread reuse across the device) or
+            "round-robin".
+        reset_on_reuse (bool): If True, a qubit that was freed without being
+            measured or reset since its last gate has a reset inserted when it
+            is next allocated.
     """
 
+    allocation_policy: str = "lowest-index"
+    reset_on_reuse: bool = False
+
+    def __post_init__(self):
+        assert self.allocation_policy in ("lowest-index", "lru", "round-robin"), (
+            f"allocation_policy ({self.allocation_policy}) must be one of 'lowest-index', 'lru' or 'round-robin'"
+        )
+
     @property
     def library_file(self):
         libdir = Path(__file__).parent / "_dist/lib/"
@@ -30,7 +47,6 @@ class SoftRZRuntimePlugin(Runtime):
                 raise RuntimeError(f"Unsupported platform: {platform.system()}")
 
     def get_init_args(self):
-        """
-        There are no init args for the soft_rz runtime.
-        """
-        return []
+        return [f"--allocation-policy={self.allocation_policy}"] + (
+            ["--reset-on-reuse"] if self.reset_on_reuse else []
+        )
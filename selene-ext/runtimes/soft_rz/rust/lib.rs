@@ -1,16 +1,29 @@
 use std::collections::VecDeque;
 
-use anyhow::{Result, bail};
+use anyhow::{Result, anyhow, bail};
+use clap::Parser;
+use selene_core::capabilities::Capabilities;
+use selene_core::error::ErrorKind;
+use selene_core::metadata::ArgumentMetadata;
 use selene_core::{
-    export_runtime_plugin,
-    runtime::{BatchOperation, Operation, RuntimeInterface, interface::RuntimeInterfaceFactory},
+    runtime::{
+        BatchOperation, Operation, RuntimeInterface,
+        allocator::{AllocationPolicy, QubitAllocator},
+        interface::RuntimeInterfaceFactory,
+    },
     utils::MetricValue,
 };
 
-#[derive(Debug, Clone, PartialEq)]
-enum QubitStatus {
-    Free,
-    Active { phase: f64 },
+#[derive(Parser, Debug)]
+struct Params {
+    /// The policy used to choose a free qubit on allocation
+    /// (lowest-index, lru or round-robin)
+    #[arg(long, default_value_t = AllocationPolicy::LowestIndex)]
+    allocation_policy: AllocationPolicy,
+    /// Insert a reset when reallocating a qubit that was freed
+    /// without being measured or reset
+    #[arg(long)]
+    reset_on_reuse: bool,
 }
 
 // Encompass both bool and u64 results in a single type.
@@ -21,8 +34,10 @@ struct FutureResult {
     value: u64,
 }
 
-struct SoftRZRuntime {
-    qubits: Vec<QubitStatus>,
+pub struct SoftRZRuntime {
+    allocator: QubitAllocator,
+    /// The Z phase accumulated on each allocated qubit.
+    phases: Vec<f64>,
     operation_queue: VecDeque<BatchOperation>,
     flush_size: usize,
     future_results: Vec<FutureResult>,
@@ -30,9 +45,14 @@ struct SoftRZRuntime {
 }
 
 impl SoftRZRuntime {
-    pub fn new(n_qubits: u64, start: selene_core::time::Instant) -> Self {
+    fn new(n_qubits: u64, start: selene_core::time::Instant, params: &Params) -> Self {
         Self {
-            qubits: vec![QubitStatus::Free; n_qubits as usize],
+            phases: vec![0.0; n_qubits as usize],
+            allocator: QubitAllocator::new(
+                n_qubits,
+                params.allocation_policy,
+                params.reset_on_reuse,
+            ),
             operation_queue: VecDeque::with_capacity(10000),
             flush_size: 0,
             future_results: Vec::with_capacity(1000),
@@ -52,7 +72,7 @@ impl SoftRZRuntime {
 impl RuntimeInterface for SoftRZRuntime {
     fn exit(&mut self) -> Result<()> {
         self.operation_queue.clear();
-        self.qubits.clear();
+        self.allocator.reset();
         self.flush_size = 0;
         self.future_results.clear();
         Ok(())
@@ -74,7 +94,7 @@ impl RuntimeInterface for SoftRZRuntime {
         Ok(())
     }
     fn shot_end(&mut self) -> Result<()> {
-        self.qubits = vec![QubitStatus::Free; self.qubits.len()];
+        self.allocator.reset();
         self.operation_queue.clear();
         self.flush_size = 0;
         self.future_results.clear();
@@ -123,6 +143,11 @@ impl RuntimeInterface for SoftRZRuntime {
                             last_op_using_qubits = i;
                         }
                     }
+                    Operation::MeasureReset { qubit_id, .. } => {
+                        if qubits.contains(qubit_id) {
+                            last_op_using_qubits = i;
+                        }
+                    }
                     Operation::Reset { qubit_id, .. } => {
                         if qubits.contains(qubit_id) {
                             last_op_using_qubits = i;
@@ -137,29 +162,30 @@ impl RuntimeInterface for SoftRZRuntime {
     }
     // Allocation
     fn qalloc(&mut self) -> Result<u64> {
-        for (i, qubit) in self.qubits.iter_mut().enumerate() {
-            if *qubit == QubitStatus::Free {
-                *qubit = QubitStatus::Active { phase: 0.0 };
-                return Ok(i as u64);
-            }
+        let Some(allocation) = self.allocator.alloc() else {
+            return Ok(u64::MAX);
+        };
+        let qubit_id = allocation.qubit_id;
+        self.phases[qubit_id as usize] = 0.0;
+        if allocation.needs_reset {
+            self.push(Operation::Reset { qubit_id });
         }
-        Ok(u64::MAX)
+        Ok(qubit_id)
     }
     fn qfree(&mut self, qubit_id: u64) -> Result<()> {
-        if qubit_id >= self.qubits.len() as u64 {
-            bail!("freeing out-of-bounds qubit {qubit_id}")
-        } else {
-            self.qubits[qubit_id as usize] = QubitStatus::Free;
-            Ok(())
-        }
+        self.allocator.free(qubit_id)
     }
     fn rxy_gate(&mut self, qubit_id: u64, theta: f64, phi: f64) -> Result<()> {
-        if qubit_id >= self.qubits.len() as u64 {
-            bail!("applying rxy gate to out-of-bounds qubit {qubit_id}");
+        if qubit_id >= self.allocator.n_qubits() {
+            bail!(ErrorKind::QubitOutOfBounds.error(format!(
+                "applying rxy gate to out-of-bounds qubit {qubit_id}"
+            )));
         }
-        let QubitStatus::Active { phase } = self.qubits[qubit_id as usize] else {
+        if !self.allocator.is_allocated(qubit_id) {
             bail!("Qubit {qubit_id} is not active");
-        };
+        }
+        self.allocator.mark_unknown(qubit_id);
+        let phase = self.phases[qubit_id as usize];
         self.push(Operation::RXYGate {
             qubit_id,
             theta,
@@ -168,12 +194,18 @@ impl RuntimeInterface for SoftRZRuntime {
         Ok(())
     }
     fn rzz_gate(&mut self, qubit_id_1: u64, qubit_id_2: u64, theta: f64) -> Result<()> {
-        if qubit_id_1 >= self.qubits.len() as u64 {
-            bail!("applying rzz gate to out-of-bounds qubit1 {qubit_id_1}");
+        if qubit_id_1 >= self.allocator.n_qubits() {
+            bail!(ErrorKind::QubitOutOfBounds.error(format!(
+                "applying rzz gate to out-of-bounds qubit1 {qubit_id_1}"
+            )));
         }
-        if qubit_id_2 >= self.qubits.len() as u64 {
-            bail!("applying rzz gate to out-of-bounds qubit2 {qubit_id_2}");
+        if qubit_id_2 >= self.allocator.n_qubits() {
+            bail!(ErrorKind::QubitOutOfBounds.error(format!(
+                "applying rzz gate to out-of-bounds qubit2 {qubit_id_2}"
+            )));
         }
+        self.allocator.mark_unknown(qubit_id_1);
+        self.allocator.mark_unknown(qubit_id_2);
         self.push(Operation::RZZGate {
             qubit_id_1,
             qubit_id_2,
@@ -182,25 +214,29 @@ impl RuntimeInterface for SoftRZRuntime {
         Ok(())
     }
     fn rz_gate(&mut self, qubit_id: u64, theta: f64) -> Result<()> {
-        if qubit_id >= self.qubits.len() as u64 {
-            bail!("applying rz gate to out-of-bounds qubit {qubit_id}");
+        if qubit_id >= self.allocator.n_qubits() {
+            bail!(ErrorKind::QubitOutOfBounds.error(format!(
+                "applying rz gate to out-of-bounds qubit {qubit_id}"
+            )));
         }
-        let QubitStatus::Active { phase } = self.qubits[qubit_id as usize] else {
+        if !self.allocator.is_allocated(qubit_id) {
             bail!("Qubit {qubit_id} is not active");
-        };
+        }
         // We don't apply an RZ gate. Instead, we accumulate a phase, and mutate
         // RXY gates' phi parameters to account for the phase shift. RZZ and measurement
         // are unaffected.
-        self.qubits[qubit_id as usize] = QubitStatus::Active {
-            phase: phase + theta,
-        };
+        self.phases[qubit_id as usize] += theta;
         Ok(())
     }
     // Lifetime ops
     fn measure(&mut self, qubit_id: u64) -> Result<u64> {
-        if qubit_id >= self.qubits.len() as u64 {
-            bail!("measuring out-of-bounds qubit {qubit_id}")
+        if qubit_id >= self.allocator.n_qubits() {
+            bail!(
+                ErrorKind::QubitOutOfBounds
+                    .error(format!("measuring out-of-bounds qubit {qubit_id}"))
+            )
         }
+        self.allocator.mark_known(qubit_id);
         let result_id = self.future_results.len() as u64;
         self.future_results.push(FutureResult {
             is_set: false,
@@ -213,9 +249,13 @@ impl RuntimeInterface for SoftRZRuntime {
         Ok(result_id)
     }
     fn measure_leaked(&mut self, qubit_id: u64) -> Result<u64> {
-        if qubit_id >= self.qubits.len() as u64 {
-            bail!("leak-measuring out-of-bounds qubit {qubit_id}")
+        if qubit_id >= self.allocator.n_qubits() {
+            bail!(
+                ErrorKind::QubitOutOfBounds
+                    .error(format!("leak-measuring out-of-bounds qubit {qubit_id}"))
+            )
         }
+        self.allocator.mark_known(qubit_id);
         let result_id = self.future_results.len() as u64;
         self.future_results.push(FutureResult {
             is_set: false,
@@ -228,10 +268,34 @@ impl RuntimeInterface for SoftRZRuntime {
         Ok(result_id)
     }
 
+    fn measure_reset(&mut self, qubit_id: u64) -> Result<u64> {
+        if qubit_id >= self.allocator.n_qubits() {
+            bail!(
+                ErrorKind::QubitOutOfBounds
+                    .error(format!("measuring out-of-bounds qubit {qubit_id}"))
+            )
+        }
+        self.allocator.mark_known(qubit_id);
+        let result_id = self.future_results.len() as u64;
+        self.future_results.push(FutureResult {
+            is_set: false,
+            value: 0,
+        });
+        self.push(Operation::MeasureReset {
+            qubit_id,
+            result_id,
+        });
+        Ok(result_id)
+    }
+
     fn reset(&mut self, qubit_id: u64) -> Result<()> {
-        if qubit_id >= self.qubits.len() as u64 {
-            bail!("resetting out-of-bounds qubit {qubit_id}")
+        if qubit_id >= self.allocator.n_qubits() {
+            bail!(
+                ErrorKind::QubitOutOfBounds
+                    .error(format!("resetting out-of-bounds qubit {qubit_id}"))
+            )
         }
+        self.allocator.mark_known(qubit_id);
         self.push(Operation::Reset { qubit_id });
         Ok(())
     }
@@ -244,6 +308,10 @@ impl RuntimeInterface for SoftRZRuntime {
                 if let Operation::Measure {
                     result_id: measure_result_id,
                     ..
+                }
+                | Operation::MeasureReset {
+                    result_id: measure_result_id,
+                    ..
                 } = op
                 {
                     if result_id == *measure_result_id {
@@ -300,25 +368,40 @@ impl RuntimeInterface for SoftRZRuntime {
     fn decrement_future_refcount(&mut self, _future_ref: u64) -> Result<()> {
         Ok(())
     }
-    fn get_metric(&mut self, _nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
-        Ok(None)
+    fn get_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        Ok(self.allocator.get_metric(nth_metric))
     }
 }
 
+/// The name the plugin is known by, under which selene registers it when it
+/// is linked in.
+pub const PLUGIN_NAME: &str = "soft_rz";
+
 #[derive(Default)]
-struct SoftRZRuntimeFactory;
+pub struct SoftRZRuntimeFactory;
 
 impl RuntimeInterfaceFactory for SoftRZRuntimeFactory {
     type Interface = SoftRZRuntime;
 
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::METRICS | Capabilities::MEASURE_RESET
+    }
+
+    fn arguments(&self) -> Option<Vec<ArgumentMetadata>> {
+        Some(ArgumentMetadata::from_clap::<Params>())
+    }
+
     fn init(
         self: std::sync::Arc<Self>,
         n_qubits: u64,
         start: selene_core::time::Instant,
-        _args: &[impl AsRef<str>],
+        args: &[impl AsRef<str>],
     ) -> Result<Box<Self::Interface>> {
-        Ok(Box::new(SoftRZRuntime::new(n_qubits, start)))
+        let params = Params::try_parse_from(args.iter().map(|s| s.as_ref()))
+            .map_err(|e| anyhow!("Error parsing arguments to soft_rz runtime plugin: {}", e))?;
+        Ok(Box::new(SoftRZRuntime::new(n_qubits, start, &params)))
     }
 }
 
-export_runtime_plugin!(crate::SoftRZRuntimeFactory);
+#[cfg(feature = "export")]
+selene_core::export_runtime_plugin!(crate::SoftRZRuntimeFactory, crate::PLUGIN_NAME);
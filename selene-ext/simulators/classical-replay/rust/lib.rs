@@ -1,6 +1,8 @@
 use anyhow::{Result, anyhow};
 use clap::Parser;
-use selene_core::export_simulator_plugin;
+use selene_core::capabilities::Capabilities;
+use selene_core::error::ErrorKind;
+use selene_core::metadata::ArgumentMetadata;
 use selene_core::simulator::SimulatorInterface;
 use selene_core::simulator::interface::SimulatorInterfaceFactory;
 use selene_core::utils::MetricValue;
@@ -67,10 +69,10 @@ impl SimulatorInterface for ClassicalReplaySimulator {
         if q0 < self.n_qubits {
             Ok(())
         } else {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "RXY(q0={q0}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         }
     }
 
@@ -78,10 +80,10 @@ impl SimulatorInterface for ClassicalReplaySimulator {
         if q0 < self.n_qubits && q1 < self.n_qubits {
             Ok(())
         } else {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "RZZ(q0={q0}, q1={q1}) is out of bounds. q0 and q1 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         }
     }
 
@@ -89,19 +91,19 @@ impl SimulatorInterface for ClassicalReplaySimulator {
         if q0 < self.n_qubits {
             Ok(())
         } else {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "RZ(q0={q0}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         }
     }
 
     fn measure(&mut self, q0: u64) -> Result<bool> {
         if q0 >= self.n_qubits {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "Measure(q0={q0}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         } else if self.next_measurement_index < self.current_shot_measurements.len() {
             let measurement = self.current_shot_measurements[self.next_measurement_index];
             self.next_measurement_index += 1;
@@ -116,10 +118,10 @@ impl SimulatorInterface for ClassicalReplaySimulator {
 
     fn reset(&mut self, q0: u64) -> Result<()> {
         if q0 >= self.n_qubits {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "Reset(q0={q0}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         } else {
             Ok(())
         }
@@ -135,12 +137,24 @@ impl SimulatorInterface for ClassicalReplaySimulator {
     }
 }
 
+/// The name the plugin is known by, under which selene registers it when it
+/// is linked in.
+pub const PLUGIN_NAME: &str = "classical_replay";
+
 #[derive(Default)]
 pub struct ClassicalReplaySimulatorFactory;
 
 impl SimulatorInterfaceFactory for ClassicalReplaySimulatorFactory {
     type Interface = ClassicalReplaySimulator;
 
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::METRICS
+    }
+
+    fn arguments(&self) -> Option<Vec<ArgumentMetadata>> {
+        Some(ArgumentMetadata::from_clap::<Params>())
+    }
+
     fn init(
         self: std::sync::Arc<Self>,
         n_qubits: u64,
@@ -164,4 +178,5 @@ impl SimulatorInterfaceFactory for ClassicalReplaySimulatorFactory {
         }
     }
 }
-export_simulator_plugin!(crate::ClassicalReplaySimulatorFactory);
+#[cfg(feature = "export")]
+selene_core::export_simulator_plugin!(crate::ClassicalReplaySimulatorFactory, crate::PLUGIN_NAME);
@@ -2,7 +2,9 @@ use anyhow::{Result, anyhow};
 use clap::Parser;
 use rand::{Rng, SeedableRng};
 use rand_pcg::Pcg64Mcg;
-use selene_core::export_simulator_plugin;
+use selene_core::capabilities::Capabilities;
+use selene_core::error::ErrorKind;
+use selene_core::metadata::ArgumentMetadata;
 use selene_core::simulator::SimulatorInterface;
 use selene_core::simulator::interface::SimulatorInterfaceFactory;
 use selene_core::utils::MetricValue;
@@ -52,10 +54,10 @@ impl SimulatorInterface for CoinflipSimulator {
         if q0 < self.n_qubits {
             Ok(())
         } else {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "RXY(q0={q0}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         }
     }
 
@@ -63,10 +65,10 @@ impl SimulatorInterface for CoinflipSimulator {
         if q0 < self.n_qubits && q1 < self.n_qubits {
             Ok(())
         } else {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "RZZ(q0={q0}, q1={q1}) is out of bounds. q0 and q1 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         }
     }
 
@@ -74,10 +76,10 @@ impl SimulatorInterface for CoinflipSimulator {
         if q0 < self.n_qubits {
             Ok(())
         } else {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "RZ(q0={q0}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         }
     }
 
@@ -91,10 +93,10 @@ impl SimulatorInterface for CoinflipSimulator {
                 Ok(false)
             }
         } else {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "Measure(q0={q0}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         }
     }
 
@@ -102,10 +104,10 @@ impl SimulatorInterface for CoinflipSimulator {
         if q0 < self.n_qubits {
             Ok(())
         } else {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "Postselect(q0={q0}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         }
     }
 
@@ -113,10 +115,10 @@ impl SimulatorInterface for CoinflipSimulator {
         if q0 < self.n_qubits {
             Ok(())
         } else {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "Reset(q0={q0}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         }
     }
 
@@ -137,12 +139,24 @@ impl SimulatorInterface for CoinflipSimulator {
     }
 }
 
+/// The name the plugin is known by, under which selene registers it when it
+/// is linked in.
+pub const PLUGIN_NAME: &str = "coinflip";
+
 #[derive(Default)]
 pub struct CoinflipSimulatorFactory;
 
 impl SimulatorInterfaceFactory for CoinflipSimulatorFactory {
     type Interface = CoinflipSimulator;
 
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::POSTSELECT | Capabilities::METRICS
+    }
+
+    fn arguments(&self) -> Option<Vec<ArgumentMetadata>> {
+        Some(ArgumentMetadata::from_clap::<Params>())
+    }
+
     fn init(
         self: std::sync::Arc<Self>,
         n_qubits: u64,
@@ -163,4 +177,5 @@ impl SimulatorInterfaceFactory for CoinflipSimulatorFactory {
     }
 }
 
-export_simulator_plugin!(crate::CoinflipSimulatorFactory);
+#[cfg(feature = "export")]
+selene_core::export_simulator_plugin!(crate::CoinflipSimulatorFactory, crate::PLUGIN_NAME);
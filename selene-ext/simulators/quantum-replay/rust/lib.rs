@@ -1,6 +1,8 @@
 use anyhow::{Result, anyhow};
 use clap::Parser;
-use selene_core::export_simulator_plugin;
+use selene_core::capabilities::Capabilities;
+use selene_core::error::ErrorKind;
+use selene_core::metadata::ArgumentMetadata;
 use selene_core::simulator::interface::SimulatorInterfaceFactory;
 use selene_core::simulator::{Simulator, SimulatorInterface};
 use selene_core::utils::MetricValue;
@@ -87,10 +89,10 @@ impl SimulatorInterface for QuantumReplaySimulator {
         if q0 < self.n_qubits {
             self.wrapped.rxy(q0, theta, phi)
         } else {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "RXY(q0={q0}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         }
     }
 
@@ -98,10 +100,10 @@ impl SimulatorInterface for QuantumReplaySimulator {
         if q0 < self.n_qubits && q1 < self.n_qubits {
             self.wrapped.rzz(q0, q1, theta)
         } else {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "RZZ(q0={q0}, q1={q1}) is out of bounds. q0 and q1 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         }
     }
 
@@ -109,19 +111,19 @@ impl SimulatorInterface for QuantumReplaySimulator {
         if q0 < self.n_qubits {
             self.wrapped.rz(q0, theta)
         } else {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "RZ(q0={q0}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         }
     }
 
     fn measure(&mut self, q0: u64) -> Result<bool> {
         if q0 >= self.n_qubits {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "Measure(q0={q0}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         } else if self.next_postselection_index < self.current_shot_postselections.len() {
             // postselect
             let measurement = self.current_shot_postselections[self.next_postselection_index];
@@ -142,10 +144,10 @@ impl SimulatorInterface for QuantumReplaySimulator {
 
     fn reset(&mut self, q0: u64) -> Result<()> {
         if q0 >= self.n_qubits {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "Reset(q0={q0}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         } else {
             self.wrapped.reset(q0)
         }
@@ -165,12 +167,24 @@ impl SimulatorInterface for QuantumReplaySimulator {
     }
 }
 
+/// The name the plugin is known by, under which selene registers it when it
+/// is linked in.
+pub const PLUGIN_NAME: &str = "quantum_replay";
+
 #[derive(Default)]
 pub struct QuantumReplaySimulatorFactory;
 
 impl SimulatorInterfaceFactory for QuantumReplaySimulatorFactory {
     type Interface = QuantumReplaySimulator;
 
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::METRICS
+    }
+
+    fn arguments(&self) -> Option<Vec<ArgumentMetadata>> {
+        Some(ArgumentMetadata::from_clap::<Params>())
+    }
+
     fn init(
         self: std::sync::Arc<Self>,
         n_qubits: u64,
@@ -205,4 +219,5 @@ impl SimulatorInterfaceFactory for QuantumReplaySimulatorFactory {
     }
 }
 
-export_simulator_plugin!(crate::QuantumReplaySimulatorFactory);
+#[cfg(feature = "export")]
+selene_core::export_simulator_plugin!(crate::QuantumReplaySimulatorFactory, crate::PLUGIN_NAME);
@@ -1,5 +1,7 @@
-use anyhow::{Result, anyhow, bail};
-use selene_core::export_simulator_plugin;
+use anyhow::{Result, bail};
+use selene_core::capabilities::Capabilities;
+use selene_core::error::ErrorKind;
+use selene_core::metadata::ArgumentMetadata;
 use selene_core::simulator::SimulatorInterface;
 use selene_core::simulator::interface::SimulatorInterfaceFactory;
 use selene_core::utils::MetricValue;
@@ -57,10 +59,10 @@ impl SimulatorInterface for QuestSimulator {
 
     fn rz(&mut self, q0: u64, theta: f64) -> Result<()> {
         if q0 >= self.n_qubits {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "RZ(q0={q0}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         } else {
             unsafe { quest_sys::rotateZ(self.qureg, q0 as c_int, theta) };
             Ok(())
@@ -69,10 +71,10 @@ impl SimulatorInterface for QuestSimulator {
 
     fn rxy(&mut self, q0: u64, theta: f64, phi: f64) -> Result<()> {
         if q0 >= self.n_qubits {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "RXY(q0={q0}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         } else {
             unsafe {
                 quest_sys::rotateZ(self.qureg, q0 as c_int, -phi);
@@ -85,10 +87,10 @@ impl SimulatorInterface for QuestSimulator {
 
     fn rzz(&mut self, q0: u64, q1: u64, theta: f64) -> Result<()> {
         if q0 >= self.n_qubits || q1 >= self.n_qubits {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "RZZ(q0={q0}, q1={q1}) is out of bounds. q0 and q1 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         } else {
             let cos = theta.cos();
             let sin = theta.sin();
@@ -113,10 +115,10 @@ impl SimulatorInterface for QuestSimulator {
 
     fn measure(&mut self, q0: u64) -> Result<bool> {
         if q0 >= self.n_qubits {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "Measure(q0={q0}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         } else {
             Ok(unsafe { quest_sys::measure(self.qureg, q0 as i32) } > 0)
         }
@@ -124,10 +126,10 @@ impl SimulatorInterface for QuestSimulator {
 
     fn postselect(&mut self, q0: u64, target_value: bool) -> Result<()> {
         if q0 >= self.n_qubits {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "Postselect(q0={q0}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         } else {
             let target_value = if target_value { 1 } else { 0 };
             unsafe {
@@ -136,9 +138,9 @@ impl SimulatorInterface for QuestSimulator {
             let postselect_probability = unsafe { quest_sys::calcTotalProb(self.qureg) };
             self.cumulative_postselect_probability *= postselect_probability;
             if postselect_probability < 1e-10 {
-                return Err(anyhow!(
+                return Err(ErrorKind::PostselectionImpossible.error(format!(
                     "Postselection of {target_value} on qubit {q0} is too unlikely to postselect. The probability of this outcome is {postselect_probability:.2e}.",
-                ));
+                )));
             }
             let scale = 1.0 / postselect_probability.sqrt();
             // Rescale the state vector to maintain normalization
@@ -155,10 +157,10 @@ impl SimulatorInterface for QuestSimulator {
 
     fn reset(&mut self, q0: u64) -> Result<()> {
         if q0 >= self.n_qubits {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "Reset(q0={q0}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         } else {
             let outcome = unsafe { quest_sys::measure(self.qureg, q0 as i32) };
             if outcome == 1 {
@@ -197,6 +199,10 @@ impl SimulatorInterface for QuestSimulator {
     }
 }
 
+/// The name the plugin is known by, under which selene registers it when it
+/// is linked in.
+pub const PLUGIN_NAME: &str = "quest";
+
 #[derive(Default)]
 pub struct QuestSimulatorFactory;
 
@@ -204,9 +210,9 @@ fn check_memory(n_qubits: u64) -> Result<()> {
     if n_qubits == 0 {
         bail!("Number of qubits must be greater than 0");
     } else if n_qubits > 60 {
-        bail!(
+        bail!(ErrorKind::OutOfMemory.error(
             "It is impossible to describe more than 60 qubits in a statevector on a computer with a 64-bit address space."
-        );
+        ));
     }
     // check against the maximum size of a 64-bit address space
     let bytes_required = bytesize::ByteSize::b(16 * (1 << n_qubits));
@@ -225,9 +231,9 @@ fn check_memory(n_qubits: u64) -> Result<()> {
     } else {
         let bytes_available = bytesize::ByteSize::b(reported_available);
         if bytes_required > bytes_available {
-            bail!(
+            bail!(ErrorKind::OutOfMemory.error(format!(
                 "Insufficient memory available ({bytes_available}) to allocate a state vector of {n_qubits} qubits ({bytes_required}).",
-            );
+            )));
         }
     }
     Ok(())
@@ -236,6 +242,14 @@ fn check_memory(n_qubits: u64) -> Result<()> {
 impl SimulatorInterfaceFactory for QuestSimulatorFactory {
     type Interface = QuestSimulator;
 
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::POSTSELECT | Capabilities::DUMP_STATE | Capabilities::METRICS
+    }
+
+    fn arguments(&self) -> Option<Vec<ArgumentMetadata>> {
+        Some(Vec::new())
+    }
+
     fn init(
         self: std::sync::Arc<Self>,
         n_qubits: u64,
@@ -261,4 +275,5 @@ impl SimulatorInterfaceFactory for QuestSimulatorFactory {
     }
 }
 
-export_simulator_plugin!(crate::QuestSimulatorFactory);
+#[cfg(feature = "export")]
+selene_core::export_simulator_plugin!(crate::QuestSimulatorFactory, crate::PLUGIN_NAME);
@@ -6,7 +6,9 @@ mod tests;
 
 use anyhow::{Result, anyhow};
 use clap::Parser;
-use selene_core::export_simulator_plugin;
+use selene_core::capabilities::Capabilities;
+use selene_core::error::ErrorKind;
+use selene_core::metadata::ArgumentMetadata;
 use selene_core::simulator::SimulatorInterface;
 use selene_core::simulator::interface::SimulatorInterfaceFactory;
 use selene_core::utils::MetricValue;
@@ -64,10 +66,10 @@ impl SimulatorInterface for StimSimulator {
 
     fn rxy(&mut self, q0: u64, theta: f64, phi: f64) -> Result<()> {
         if q0 >= self.n_qubits {
-            return Err(anyhow!(
+            return Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "RXYGate(q0={q0}, theta={theta}, phi={phi}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ));
+            )));
         }
 
         let approx_theta = self.get_approximate_angle(theta);
@@ -80,9 +82,9 @@ impl SimulatorInterface for StimSimulator {
             ApproxAngle::Pi => self.simulator.z(q0_u32),
             ApproxAngle::Frac3Pi2 => self.simulator.sqrt_z(q0_u32),
             ApproxAngle::NoSuitableApproximation => {
-                return Err(anyhow!(
+                return Err(ErrorKind::Unsupported.error(format!(
                     "RXYGate(q0={q0}, theta={theta}, phi={phi}) is not representable in stabiliser form. Angles must be (approximate) multiples of pi/2 in order to use Stim."
-                ));
+                )));
             }
         }
         match approx_theta {
@@ -91,9 +93,9 @@ impl SimulatorInterface for StimSimulator {
             ApproxAngle::Pi => self.simulator.x(q0_u32),
             ApproxAngle::Frac3Pi2 => self.simulator.sqrt_x_dag(q0_u32),
             ApproxAngle::NoSuitableApproximation => {
-                return Err(anyhow!(
+                return Err(ErrorKind::Unsupported.error(format!(
                     "RXYGate(q0={q0}, theta={theta}, phi={phi}) is not representable in stabiliser form. Angles must be (approximate) multiples of pi/2 in order to use Stim."
-                ));
+                )));
             }
         }
         match approx_phi {
@@ -102,9 +104,9 @@ impl SimulatorInterface for StimSimulator {
             ApproxAngle::Pi => self.simulator.z(q0_u32),
             ApproxAngle::Frac3Pi2 => self.simulator.sqrt_z_dag(q0_u32),
             ApproxAngle::NoSuitableApproximation => {
-                return Err(anyhow!(
+                return Err(ErrorKind::Unsupported.error(format!(
                     "RXYGate(q0={q0}, theta={theta}, phi={phi}) is not representable in stabiliser form. Angles must be (approximate) multiples of pi/2 in order to use Stim."
-                ));
+                )));
             }
         }
         Ok(())
@@ -112,10 +114,10 @@ impl SimulatorInterface for StimSimulator {
 
     fn rz(&mut self, q0: u64, theta: f64) -> Result<()> {
         if q0 >= self.n_qubits {
-            return Err(anyhow!(
+            return Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "RZGate(q0={q0}, theta={theta}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ));
+            )));
         }
 
         let approx = self.get_approximate_angle(theta);
@@ -127,9 +129,9 @@ impl SimulatorInterface for StimSimulator {
             ApproxAngle::Pi => self.simulator.z(q0_u32),
             ApproxAngle::Frac3Pi2 => self.simulator.sqrt_z_dag(q0_u32),
             ApproxAngle::NoSuitableApproximation => {
-                return Err(anyhow!(
+                return Err(ErrorKind::Unsupported.error(format!(
                     "RZGate(q0={q0}, theta={theta}) is not representable in stabiliser form. Angles must be (approximate) multiples of pi/2 in order to use Stim."
-                ));
+                )));
             }
         }
         Ok(())
@@ -137,10 +139,10 @@ impl SimulatorInterface for StimSimulator {
 
     fn rzz(&mut self, q0: u64, q1: u64, theta: f64) -> Result<()> {
         if q0 >= self.n_qubits || q1 >= self.n_qubits {
-            return Err(anyhow!(
+            return Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "RZZGate(q0={q0}, q1={q1}, theta={theta}) is out of bounds. q0 and q1 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ));
+            )));
         }
 
         let q0_u32: u32 = q0.try_into().unwrap();
@@ -156,9 +158,9 @@ impl SimulatorInterface for StimSimulator {
             }
             ApproxAngle::Frac3Pi2 => self.simulator.sqrt_zz_dag(q0_u32, q1_u32),
             ApproxAngle::NoSuitableApproximation => {
-                return Err(anyhow!(
+                return Err(ErrorKind::Unsupported.error(format!(
                     "RZZGate(q0={q0}, q1={q1}, theta={theta}) is not representable in stabiliser form. Angles must be (approximate) multiples of pi/2 in order to use Stim."
-                ));
+                )));
             }
         }
         Ok(())
@@ -166,10 +168,10 @@ impl SimulatorInterface for StimSimulator {
 
     fn measure(&mut self, qubit: u64) -> Result<bool> {
         if qubit >= self.n_qubits {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "Measure(qubit={qubit}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         } else {
             let q_u32: u32 = qubit.try_into()?;
             Ok(self.simulator.mz(q_u32))
@@ -178,27 +180,27 @@ impl SimulatorInterface for StimSimulator {
 
     fn postselect(&mut self, qubit: u64, target_value: bool) -> Result<()> {
         if qubit >= self.n_qubits {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "Postselect(qubit={qubit}, target_value={target_value}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         } else {
             let q_u32: u32 = qubit.try_into()?;
             match self.simulator.postselect_z(q_u32, target_value) {
                 true => Ok(()),
-                false => Err(anyhow!(
+                false => Err(ErrorKind::PostselectionImpossible.error(format!(
                     "Postselect(qubit={qubit}, target_value={target_value}) failed.",
-                )),
+                ))),
             }
         }
     }
 
     fn reset(&mut self, qubit: u64) -> Result<()> {
         if qubit >= self.n_qubits {
-            Err(anyhow!(
+            Err(ErrorKind::QubitOutOfBounds.error(format!(
                 "Reset(qubit={qubit}) is out of bounds. q0 must be less than the number of qubits ({}).",
                 self.n_qubits
-            ))
+            )))
         } else {
             let q_u32: u32 = qubit.try_into()?;
             if self.simulator.mz(q_u32) {
@@ -213,12 +215,24 @@ impl SimulatorInterface for StimSimulator {
     }
 }
 
+/// The name the plugin is known by, under which selene registers it when it
+/// is linked in.
+pub const PLUGIN_NAME: &str = "stim";
+
 #[derive(Default)]
 pub struct StimSimulatorFactory;
 
 impl SimulatorInterfaceFactory for StimSimulatorFactory {
     type Interface = StimSimulator;
 
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::POSTSELECT
+    }
+
+    fn arguments(&self) -> Option<Vec<ArgumentMetadata>> {
+        Some(ArgumentMetadata::from_clap::<Params>())
+    }
+
     fn init(
         self: std::sync::Arc<Self>,
         n_qubits: u64,
@@ -239,4 +253,5 @@ impl SimulatorInterfaceFactory for StimSimulatorFactory {
     }
 }
 
-export_simulator_plugin!(crate::StimSimulatorFactory);
+#[cfg(feature = "export")]
+selene_core::export_simulator_plugin!(crate::StimSimulatorFactory, crate::PLUGIN_NAME);
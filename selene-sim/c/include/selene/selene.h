@@ -5,6 +5,17 @@
 #include <stdlib.h>
 
 
+/**
+ * Plugin roles, identifying the plugin that a `selene_config_*_plugin*` call refers to.
+ */
+#define SELENE_PLUGIN_SIMULATOR 0
+
+#define SELENE_PLUGIN_ERROR_MODEL 1
+
+#define SELENE_PLUGIN_RUNTIME 2
+
+typedef struct SeleneConfig SeleneConfig;
+
 typedef struct SeleneInstance SeleneInstance;
 
 typedef struct selene_u64_result_t {
@@ -42,6 +53,163 @@ typedef struct selene_u32_result_t {
   uint32_t value;
 } selene_u32_result_t;
 
+/**
+ * A writer supplied by the host, to which the result stream is written in place
+ * of the configured output stream.
+ */
+typedef struct selene_output_writer_t {
+  /**
+   * Passed to each of the functions below.
+   */
+  void *user_data;
+  /**
+   * Writes up to `length` bytes from `data`, returning the number of bytes
+   * written, or a negative value on failure.
+   */
+  int64_t (*write)(void*, const uint8_t*, uint64_t);
+  /**
+   * Optionally flushes any data buffered by the host, returning zero on success.
+   */
+  int32_t (*flush)(void*);
+  /**
+   * Optionally called once, when selene no longer needs the writer.
+   */
+  void (*close)(void*);
+} selene_output_writer_t;
+
+/**
+ * A function run by each worker in `selene_run_workers`, returning zero on success.
+ */
+typedef uint32_t (*selene_worker_callback_t)(struct SeleneInstance *instance, void *user_data);
+
+/**
+ * Begins a block of operations conditioned on a future
+ *
+ * Quantum operations issued until the matching `selene_end_condition`
+ * are only applied if the result referenced by `r` equals `value`.
+ * Unlike reading the future, this does not force the runtime to flush.
+ */
+struct selene_void_result_t selene_begin_condition(struct SeleneInstance *instance,
+                                                   uint64_t r,
+                                                   bool value);
+
+/**
+ * Adds an event hook loaded from `file`, with no arguments and a random seed,
+ * after any already added. If `file` is null, the event hook linked into
+ * selene under `name` is used instead. Its metrics are written under `name`.
+ */
+struct selene_void_result_t selene_config_add_event_hook_plugin(SeleneConfig *config,
+                                                                const char *name,
+                                                                const char *file);
+
+/**
+ * Appends an argument to those passed to the event hook most recently added
+ * with `selene_config_add_event_hook_plugin`.
+ */
+struct selene_void_result_t selene_config_add_event_hook_plugin_arg(SeleneConfig *config,
+                                                                    const char *arg);
+
+/**
+ * Appends an argument to those passed to the plugin with the given role, which
+ * must have been set with `selene_config_set_plugin`.
+ */
+struct selene_void_result_t selene_config_add_plugin_arg(SeleneConfig *config,
+                                                         uint32_t role,
+                                                         const char *arg);
+
+void selene_config_free(SeleneConfig *config);
+
+/**
+ * Creates an empty configuration, to be populated with the `selene_config_set_*`
+ * functions and passed to `selene_instance_from_config`. It must be released
+ * with `selene_config_free`.
+ */
+SeleneConfig *selene_config_new(void);
+
+struct selene_void_result_t selene_config_set_artifact_dir(SeleneConfig *config,
+                                                           const char *artifact_dir);
+
+/**
+ * Sets what to do when an error is raised during a shot: "abort" to end the
+ * run, or "skip_shot" to record the failure of the shot and carry on with the
+ * next one.
+ */
+struct selene_void_result_t selene_config_set_error_policy(SeleneConfig *config,
+                                                           const char *policy);
+
+struct selene_void_result_t selene_config_set_event_hooks(SeleneConfig *config,
+                                                          bool provide_instruction_log,
+                                                          bool provide_metrics);
+
+struct selene_void_result_t selene_config_set_n_qubits(SeleneConfig *config, uint64_t n_qubits);
+
+/**
+ * Writes the operations emitted by the runtime in each shot to the artifact
+ * directory, in the given `format`: "openqasm3", "stim" or "json". If
+ * `format` is null, they are not written.
+ */
+struct selene_void_result_t selene_config_set_operation_log(SeleneConfig *config,
+                                                            const char *format);
+
+/**
+ * Sets the format of the result stream: "binary", "json_lines" or "arrow_ipc".
+ */
+struct selene_void_result_t selene_config_set_output_format(SeleneConfig *config,
+                                                            const char *format);
+
+/**
+ * Sets the output stream, in any of the forms accepted in a config file. It is
+ * not used if an output writer is passed to `selene_instance_from_config`.
+ */
+struct selene_void_result_t selene_config_set_output_stream(SeleneConfig *config,
+                                                            const char *output_stream);
+
+/**
+ * Sets the plugin with the given role (see `SELENE_PLUGIN_*`) to the plugin
+ * loaded from `file`, with no arguments and a random seed. If `file` is null,
+ * the plugin linked into selene under `name` is used instead.
+ */
+struct selene_void_result_t selene_config_set_plugin(SeleneConfig *config,
+                                                     uint32_t role,
+                                                     const char *name,
+                                                     const char *file);
+
+/**
+ * Sets whether the plugin with the given role, which must have been set with
+ * `selene_config_set_plugin`, is hosted in a separate process. Only
+ * simulators and error models loaded from files can be hosted out of process.
+ */
+struct selene_void_result_t selene_config_set_plugin_isolated(SeleneConfig *config,
+                                                              uint32_t role,
+                                                              bool isolated);
+
+/**
+ * Sets the seed of the plugin with the given role, which must have been set
+ * with `selene_config_set_plugin`.
+ */
+struct selene_void_result_t selene_config_set_plugin_seed(SeleneConfig *config,
+                                                          uint32_t role,
+                                                          uint64_t seed);
+
+/**
+ * Sets the limits on the resources used by each shot. A limit of zero does not
+ * apply. A shot that exceeds a limit ends with an error of kind
+ * `SeleneErrorKind_ShotLimitExceeded`, and the run carries on with the next
+ * shot.
+ */
+struct selene_void_result_t selene_config_set_shot_limits(SeleneConfig *config,
+                                                          uint64_t max_operations,
+                                                          uint64_t max_batches,
+                                                          double max_wall_time,
+                                                          uint64_t max_qubits);
+
+struct selene_void_result_t selene_config_set_shots(SeleneConfig *config,
+                                                    uint64_t count,
+                                                    uint64_t offset,
+                                                    uint64_t increment);
+
+struct selene_void_result_t selene_config_set_workers(SeleneConfig *config, uint64_t workers);
+
 struct selene_u64_result_t selene_custom_runtime_call(struct SeleneInstance *instance,
                                                       uint64_t tag,
                                                       const uint8_t *data,
@@ -52,6 +220,11 @@ struct selene_void_result_t selene_dump_state(struct SeleneInstance *instance,
                                               const uint64_t *qubits,
                                               uint64_t qubits_length);
 
+/**
+ * Ends the innermost conditional block
+ */
+struct selene_void_result_t selene_end_condition(struct SeleneInstance *instance);
+
 struct selene_void_result_t selene_exit(struct SeleneInstance *instance);
 
 /**
@@ -71,6 +244,26 @@ struct selene_u64_result_t selene_get_tc(struct SeleneInstance *instance);
 struct selene_void_result_t selene_global_barrier(struct SeleneInstance *instance,
                                                   uint64_t sleep_time);
 
+/**
+ * Creates an instance from a configuration built with the `selene_config_*`
+ * functions. The configuration is not consumed, and may be reused.
+ *
+ * If `writer` is not null, the result stream is written to it rather than to
+ * the configured output stream, and its `close` function is called once the
+ * instance is exited, or immediately if creating the instance fails.
+ */
+struct selene_void_result_t selene_instance_from_config(struct SeleneInstance **instance,
+                                                        const SeleneConfig *config,
+                                                        const struct selene_output_writer_t *writer);
+
+/**
+ * Returns a message describing the latest error returned by a selene call in the
+ * calling thread, as a null terminated string, or null if no call has failed. The
+ * error code returned by the call identifies the category of the error. The string
+ * remains valid until the next failing call on the same thread.
+ */
+const char *selene_last_error_message(void);
+
 struct selene_void_result_t selene_load_config(struct SeleneInstance **instance,
                                                const char *config_file);
 
@@ -79,6 +272,18 @@ struct selene_void_result_t selene_local_barrier(struct SeleneInstance *instance
                                                  uint64_t qubit_ids_length,
                                                  uint64_t sleep_time);
 
+/**
+ * Reads up to `capacity` bytes of output from the named memory sink into `buffer`,
+ * blocking until data is available. A value of zero indicates that the emulator has
+ * finished writing and all output has been consumed.
+ *
+ * `name` must be a null terminated UTF-8 string, and `buffer` must point to at least
+ * `capacity` writable bytes. Otherwise an invalid argument error is returned.
+ */
+struct selene_u64_result_t selene_memory_sink_read(const char *name,
+                                                   uint8_t *buffer,
+                                                   uint64_t capacity);
+
 struct selene_void_result_t selene_on_shot_end(struct SeleneInstance *instance);
 
 struct selene_void_result_t selene_on_shot_start(struct SeleneInstance *instance,
@@ -115,6 +320,12 @@ struct selene_void_result_t selene_print_i64_array(struct SeleneInstance *instan
                                                    const int64_t *ptr,
                                                    uint64_t length);
 
+/**
+ * Write a panic to the result stream, ending the shot if `error_code` is
+ * below 1000 and the run otherwise. Codes 998 and 999 are reserved for
+ * errors raised by selene (see `SeleneErrorKind`), and must not be used by
+ * user programs.
+ */
 struct selene_void_result_t selene_print_panic(struct SeleneInstance *instance,
                                                struct selene_string_t message,
                                                uint32_t error_code);
@@ -144,8 +355,20 @@ struct selene_future_result_t selene_qubit_lazy_measure(struct SeleneInstance *i
 struct selene_future_result_t selene_qubit_lazy_measure_leaked(struct SeleneInstance *instance,
                                                                uint64_t q);
 
+/**
+ * Performs a lazy measurement followed by a reset, as a single fused operation
+ */
+struct selene_future_result_t selene_qubit_lazy_measure_reset(struct SeleneInstance *instance,
+                                                              uint64_t q);
+
 struct selene_bool_result_t selene_qubit_measure(struct SeleneInstance *instance, uint64_t q);
 
+/**
+ * Performs a lazy fused measurement and reset, then frees the qubit
+ */
+struct selene_future_result_t selene_qubit_measure_and_free(struct SeleneInstance *instance,
+                                                            uint64_t q);
+
 struct selene_void_result_t selene_qubit_reset(struct SeleneInstance *instance, uint64_t q);
 
 /**
@@ -201,6 +424,16 @@ struct selene_void_result_t selene_refcount_decrement(struct SeleneInstance *ins
  */
 struct selene_void_result_t selene_refcount_increment(struct SeleneInstance *instance, uint64_t r);
 
+/**
+ * Runs all configured shots, calling `callback` with each worker's instance from
+ * the worker's thread. The callback should run every shot of the instance it is
+ * given (see `selene_shot_count`) but must not exit it. The output of all workers
+ * is written to this instance's output stream in shot order.
+ */
+struct selene_void_result_t selene_run_workers(struct SeleneInstance *instance,
+                                               selene_worker_callback_t callback,
+                                               void *user_data);
+
 struct selene_void_result_t selene_rxy(struct SeleneInstance *instance,
                                        uint64_t qubit_id,
                                        double theta,
@@ -3,7 +3,7 @@ of hybrid quantum circuits.
 """
 
 from .build import BuildMethod, BitcodeString, build
-from .instance import SeleneInstance
+from .instance import SeleneInstance, ShotLimits
 from .backends import *  # noqa: F403
 from .event_hooks import *  # noqa: F403
 
@@ -15,5 +15,5 @@ import pathlib
 dist_dir = pathlib.Path(__file__).parent / "_dist"
 
 __all__ = (
-    ["BuildMethod", "BitcodeString", "build", "SeleneInstance"] + backends + event_hooks
+    ["BuildMethod", "BitcodeString", "build", "SeleneInstance", "ShotLimits"] + backends + event_hooks
 )
@@ -1,4 +1,7 @@
+from selene_gate_validator_runtime_plugin import (
+    GateValidatorRuntimePlugin as GateValidatorRuntime,
+)
 from selene_simple_runtime_plugin import SimpleRuntimePlugin as SimpleRuntime
 from selene_soft_rz_runtime_plugin import SoftRZRuntimePlugin as SoftRZRuntime
 
-__all__ = ["SimpleRuntime", "SoftRZRuntime"]
+__all__ = ["GateValidatorRuntime", "SimpleRuntime", "SoftRZRuntime"]
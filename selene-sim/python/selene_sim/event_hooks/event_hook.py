@@ -23,6 +23,15 @@ class EventHook(ABC):
         """
         pass
 
+    def get_selene_config(self) -> dict:
+        """
+        Define the `event_hooks` section of Selene's configuration that is
+        required by this EventHook. By default, each of the flags returned
+        by get_selene_flags is enabled. Override this for options that take
+        a value.
+        """
+        return {flag: True for flag in self.get_selene_flags()}
+
     @abstractmethod
     def try_invoke(self, tag: str, data: list) -> bool:
         """
@@ -91,6 +100,12 @@ class MultiEventHook(EventHook):
             args.update(hook.get_selene_flags())
         return list(args)
 
+    def get_selene_config(self) -> dict:
+        config: dict = {}
+        for hook in self.event_hooks:
+            config.update(hook.get_selene_config())
+        return config
+
     def try_invoke(self, tag: str, data: list) -> bool:
         success = False
         for hook in self.event_hooks:
@@ -4,8 +4,8 @@ instructions from the INSTRUCTIONLOG tag emitted by Selene.
 
 This allows the user to extract the instructions requested by the
 user program as a pytket.Circuit, and the batches of instructions
-issued by the runtime as a list of dictionaries, on a shot-by-shot
-basis.
+issued by the runtime and the operations and results of the error
+model as lists of dictionaries, on a shot-by-shot basis.
 """
 
 from abc import ABC, abstractmethod
@@ -283,6 +283,25 @@ class MeasureLeakedRequest(Operation):
         return MeasureLeakedRequest(qubit=next(it))
 
 
+@dataclass
+class MeasureResetRequest(Operation):
+    qubit: int
+
+    def append_to_circuit(self, circuit: "pytket.Circuit"):
+        assert PYTKET_AVAILABLE, "pytket is not available"
+        for i in range(circuit.n_bits, self.qubit + 1):
+            circuit.add_bit(pytket.Bit(i))
+        circuit.Measure(qubit=self.qubit, bit=self.qubit)
+        circuit.Reset(qubit=self.qubit)
+
+    def to_dict(self) -> dict:
+        return {"op": "MeasureResetRequest", "qubit": self.qubit}
+
+    @staticmethod
+    def from_iterator(it: Iterator):
+        return MeasureResetRequest(qubit=next(it))
+
+
 @dataclass
 class FutureRead(Operation):
     qubit: int
@@ -300,6 +319,67 @@ class FutureRead(Operation):
         return FutureRead(qubit=next(it))
 
 
+@dataclass
+class BeginCondition(Operation):
+    result: int
+    value: bool
+
+    def append_to_circuit(self, circuit: "pytket.Circuit"):
+        pass
+
+    def to_dict(self) -> dict:
+        return {"op": "BeginCondition", "result": self.result, "value": self.value}
+
+    @staticmethod
+    def from_iterator(it: Iterator):
+        return BeginCondition(result=next(it), value=bool(next(it)))
+
+
+@dataclass
+class EndCondition(Operation):
+    def append_to_circuit(self, circuit: "pytket.Circuit"):
+        pass
+
+    def to_dict(self) -> dict:
+        return {"op": "EndCondition"}
+
+    @staticmethod
+    def from_iterator(it: Iterator):
+        return EndCondition()
+
+
+@dataclass
+class BoolResult(Operation):
+    result: int
+    value: bool
+
+    def append_to_circuit(self, circuit: "pytket.Circuit"):
+        pass
+
+    def to_dict(self) -> dict:
+        return {"op": "BoolResult", "result": self.result, "value": self.value}
+
+    @staticmethod
+    def from_iterator(it: Iterator):
+        return BoolResult(result=next(it), value=bool(next(it)))
+
+
+@dataclass
+class U64Result(Operation):
+    result: int
+    value: int
+
+    def append_to_circuit(self, circuit: "pytket.Circuit"):
+        pass
+
+    def to_dict(self) -> dict:
+        return {"op": "U64Result", "result": self.result, "value": self.value}
+
+    @staticmethod
+    def from_iterator(it: Iterator):
+        return U64Result(result=next(it), value=next(it))
+
+
 class Source(Enum):
     """
     Selene provides the source of each instruction as an
@@ -370,6 +450,16 @@ class Instruction:
                 operation = GlobalBarrier.from_iterator(it)
             case 12:
                 operation = MeasureLeakedRequest.from_iterator(it)
+            case 13:
+                operation = BeginCondition.from_iterator(it)
+            case 14:
+                operation = EndCondition.from_iterator(it)
+            case 15:
+                operation = MeasureResetRequest.from_iterator(it)
+            case 16:
+                operation = BoolResult.from_iterator(it)
+            case 17:
+                operation = U64Result.from_iterator(it)
         if operation is None:
             raise ValueError(f"Unknown instruction operation index {operation_idx}")
         return Instruction(source=source, operation=operation)
@@ -422,6 +512,14 @@ class ShotInstructions:
     def get_optimiser_output(self) -> list[dict[Any, Any]]:
         return self._get_list_of_dicts(Source.OPTIMISER)
 
+    def get_error_model_output(self) -> list[dict[Any, Any]]:
+        """
+        The operations injected by the error model, such as Pauli errors,
+        and the measurement results it set, for error models that report
+        them.
+        """
+        return self._get_list_of_dicts(Source.ERROR_MODEL)
+
     def dump(self) -> None:
         for instruction in self:
             print(f"{instruction.source}: {instruction.operation}")
@@ -0,0 +1,43 @@
+"""
+Provides OperationLogExport, an event hook that has Selene write the
+operations emitted by the runtime in each shot to the artifact directory
+of the run, for use with external tools and other simulators.
+"""
+
+from typing import Literal
+
+from .event_hook import EventHook
+
+OperationLogFormat = Literal["openqasm3", "stim", "json"]
+
+
+class OperationLogExport(EventHook):
+    """
+    Has Selene write the operations emitted by the runtime in each shot to
+    `shot_<shot_id>.<extension>` in the artifact directory of the run, as
+    OpenQASM 3 (`.qasm`), as a Stim circuit (`.stim`), or as JSON (`.json`).
+
+    Stim circuits can only hold Clifford operations, so with the "stim"
+    format, shots with angles that are not multiples of pi/2 are written as
+    OpenQASM 3 instead.
+
+    JSON logs can be replayed through another error model or simulator,
+    without the original program, with the `selene-replay` binary.
+    """
+
+    format: OperationLogFormat
+
+    def __init__(self, format: OperationLogFormat = "openqasm3"):
+        self.format = format
+
+    def get_selene_flags(self) -> list[str]:
+        return []
+
+    def get_selene_config(self) -> dict:
+        return {"operation_log": self.format}
+
+    def try_invoke(self, tag: str, data: list) -> bool:
+        return False
+
+    def on_new_shot(self) -> None:
+        pass
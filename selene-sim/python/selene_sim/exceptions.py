@@ -1,3 +1,6 @@
+from enum import IntEnum
+
+
 def maybe_provide_log(name, contents):
     if len(contents) == 0:
         return ""
@@ -74,6 +77,31 @@ class SeleneRuntimeError(Exception):
         )
 
 
+class SeleneErrorKind(IntEnum):
+    """
+    The codes with which selene reports errors raised by the emulator itself,
+    or by its plugins, rather than by the user program.
+
+    SHOT_LIMIT_EXCEEDED and SHOT_FAILED lie below 1000, among the codes that
+    user programs use for panics ending only the current shot. They are
+    reserved for selene, and user programs must not panic with them.
+    """
+
+    NULL_INSTANCE = 100000
+    INTERNAL = 100001
+    QUBIT_OUT_OF_BOUNDS = 100002
+    OUT_OF_MEMORY = 100003
+    POSTSELECTION_IMPOSSIBLE = 100004
+    INVALID_ARGUMENT = 100005
+    UNSUPPORTED = 100006
+    OUTPUT = 100007
+    PLUGIN_CRASHED = 100008
+    # Ends only the shot that exceeded its limits, rather than the run
+    SHOT_LIMIT_EXCEEDED = 999
+    # Ends only the shot that failed, when shots are skipped on error
+    SHOT_FAILED = 998
+
+
 class SelenePanicError(Exception):
     """
     Raised when the user program issues a panic with error_code
@@ -89,6 +117,18 @@ class SelenePanicError(Exception):
     def __reduce__(self):
         return (self.__class__, (self.message, self.code, self.stdout, self.stderr))
 
+    @property
+    def kind(self) -> SeleneErrorKind | None:
+        """
+        The category of the error if it was raised by selene or one of its
+        plugins, or None if the panic was issued by the user program. Codes
+        reserved for selene are always reported as raised by selene.
+        """
+        try:
+            return SeleneErrorKind(self.code)
+        except ValueError:
+            return None
+
     def __str__(self):
         return (
             f"Panic (#{self.code}): {self.message}"
@@ -2,6 +2,7 @@ import shutil
 from pathlib import Path
 from typing import Iterator
 from dataclasses import dataclass
+from datetime import timedelta
 import yaml
 
 
@@ -25,6 +26,7 @@ class ShotSpec:
     count: int
     offset: int = 0
     increment: int = 1
+    workers: int = 1
 
     def __contains__(self, shot: int) -> bool:
         delta = shot - self.offset
@@ -41,11 +43,57 @@ yaml.SafeDumper.add_representer(
             "count": data.count,
             "offset": data.offset,
             "increment": data.increment,
+            "workers": data.workers,
         },
     ),
 )
 
 
+@dataclass
+class ShotLimits:
+    """
+    Limits on the resources used by each shot, enforced by selene itself.
+    A shot that exceeds one of them ends with a shot exit of code
+    SeleneErrorKind.SHOT_LIMIT_EXCEEDED, and the run carries on with the
+    next shot. Limits that are None do not apply.
+
+    Attributes:
+        max_operations: The maximum number of operations that the user
+                        program may issue.
+        max_batches: The maximum number of batches of operations that the
+                     runtime may emit.
+        max_wall_time: The maximum wall-clock time of the shot. Unlike the
+                       per_shot timeout, this is only checked when the user
+                       program calls into selene, and ends the shot rather
+                       than the run.
+        max_qubits: The maximum number of qubits that may be allocated at once.
+    """
+
+    max_operations: int | None = None
+    max_batches: int | None = None
+    max_wall_time: timedelta | float | None = None
+    max_qubits: int | None = None
+
+
+def _represent_shot_limits(dumper, data: ShotLimits):
+    max_wall_time = data.max_wall_time
+    if isinstance(max_wall_time, timedelta):
+        max_wall_time = max_wall_time.total_seconds()
+    limits = {
+        "max_operations": data.max_operations,
+        "max_batches": data.max_batches,
+        "max_wall_time": max_wall_time,
+        "max_qubits": data.max_qubits,
+    }
+    return dumper.represent_mapping(
+        "tag:yaml.org,2002:map",
+        {key: value for key, value in limits.items() if value is not None},
+    )
+
+
+yaml.SafeDumper.add_representer(ShotLimits, _represent_shot_limits)
+
+
 @dataclass
 class SeleneInstance:
     """
@@ -163,7 +211,10 @@ class SeleneInstance:
         shot_offset: int = 0,
         shot_increment: int = 1,
         n_processes: int = 1,
+        n_threads: int = 1,
         parse_results: bool = True,
+        shot_limits: ShotLimits | None = None,
+        skip_failed_shots: bool = False,
     ) -> Iterator[Iterator[TaggedResult]]:
         """
         Run the compiled program through multiple selene shots.
@@ -187,6 +238,11 @@ class SeleneInstance:
             random_seed: The random seed to use for the simulator, error model,
                          and runtime if they have not been set explicitly. On
                          each shot, the random seed will be incremented by 1.
+            n_processes: The number of selene processes to run shots across.
+            n_threads: The number of worker threads each process runs its
+                       shots across, each with its own simulator, error model
+                       and runtime instances. Results are still reported in
+                       shot order.
             parse_results:
                 Whether to interpret tags in the result stream.
                 If True (default), tags will be stripped, interpreted,
@@ -207,6 +263,15 @@ class SeleneInstance:
                 Setting to True provides the high level Selene interface, and
                 using False allows for Selene to be used as an intermediate
                 component for use with an external result stream handler.
+            shot_limits: Limits on the resources used by each shot, which
+                         end the shot if exceeded (see ShotLimits).
+            skip_failed_shots: If True, an error raised by selene or its plugins
+                               during a shot ends only that shot, which reports
+                               a shot exit with code SeleneErrorKind.SHOT_FAILED,
+                               and the run carries on with the next shot. The
+                               failed shots are listed in failed_shots.yaml in
+                               each process's artifact directory. If False
+                               (default), such errors end the run.
         """
 
         self._check_health()
@@ -220,12 +285,16 @@ class SeleneInstance:
         for component in (simulator, error_model, runtime):
             library_search_dirs.extend(component.library_search_dirs)
         global_configuration = {
-            "event_hooks": {flag: True for flag in event_hook.get_selene_flags()},
+            "event_hooks": event_hook.get_selene_config(),
             "n_qubits": n_qubits,
             "simulator": self._get_component_config(simulator, random_seed),
             "error_model": self._get_component_config(error_model, random_seed),
             "runtime": self._get_component_config(runtime, random_seed),
         }
+        if shot_limits is not None:
+            global_configuration["limits"] = shot_limits
+        if skip_failed_shots:
+            global_configuration["on_error"] = "skip_shot"
         with TCPStream(
             timeout=timeout,
             logfile=results_logfile,
@@ -239,6 +308,7 @@ class SeleneInstance:
                     offset=shot_offset + i * shot_increment,
                     increment=shot_increment * n_processes,
                     count=n_shots // n_processes + (n_shots % n_processes > i),
+                    workers=max(1, n_threads),
                 )
                 run_directory = self._create_new_run_directory()
                 artifact_directory = run_directory / "artifacts"
@@ -291,6 +361,7 @@ class SeleneInstance:
         random_seed: int | None = None,
         shot_offset: int = 0,
         parse_results: bool = True,
+        shot_limits: ShotLimits | None = None,
     ) -> Iterator[TaggedResult]:
         """
         Run the compiled program through a single selene shot.
@@ -308,6 +379,7 @@ class SeleneInstance:
             results_logfile: The file to write the results to (if any)
             random_seed: The random seed to use for the simulator, error model,
                          and runtime if they have not been set explicitly
+            shot_limits: Limits on the resources used by the shot
         """
         shot_generator = self.run_shots(
             simulator=simulator,
@@ -322,6 +394,7 @@ class SeleneInstance:
             random_seed=random_seed,
             shot_offset=shot_offset,
             parse_results=parse_results,
+            shot_limits=shot_limits,
         )
         # We cannot simply yield from the shot generator, as this can
         # cause lifetime issues with the run_shots generator.
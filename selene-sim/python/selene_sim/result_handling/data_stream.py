@@ -1,13 +1,64 @@
 import socket
+import zlib
 from pathlib import Path
 from abc import ABC, abstractmethod
 from typing import BinaryIO
 import struct
 from selectors import DefaultSelector, EVENT_READ
 from dataclasses import dataclass
-from selene_sim.exceptions import SeleneStartupError, SeleneTimeoutError
+from selene_sim.exceptions import (
+    SeleneRuntimeError,
+    SeleneStartupError,
+    SeleneTimeoutError,
+)
 from selene_sim.timeout import Timeout, Timer
 
+GZIP_MAGIC = b"\x1f\x8b"
+ZSTD_MAGIC = b"\x28\xb5\x2f\xfd"
+
+
+class StreamDecompressor:
+    """
+    Detects and undoes gzip or zstd framing of a result stream that is fed in
+    incrementally. An uncompressed stream begins with a time cursor of zero,
+    so it cannot be confused with the gzip or zstd magic bytes, and is passed
+    through unchanged.
+    """
+
+    def __init__(self):
+        self.prefix = b""
+        self.detected = False
+        self.decompressor = None
+
+    def feed(self, data: bytes) -> bytes:
+        """
+        Feed raw bytes from the stream, returning any decompressed bytes that
+        are now available. Feeding empty bytes signals the end of the stream.
+        """
+        if not self.detected:
+            self.prefix += data
+            if data and len(self.prefix) < len(ZSTD_MAGIC):
+                return b""
+            data, self.prefix = self.prefix, b""
+            self.detected = True
+            if data.startswith(ZSTD_MAGIC):
+                self.decompressor = _zstd_decompressobj()
+            elif data.startswith(GZIP_MAGIC):
+                self.decompressor = zlib.decompressobj(wbits=16 + zlib.MAX_WBITS)
+        if self.decompressor is None:
+            return data
+        return self.decompressor.decompress(data)
+
+
+def _zstd_decompressobj():
+    try:
+        import zstandard
+    except ImportError as e:
+        raise SeleneRuntimeError(
+            "The result stream is zstd-compressed, which requires the 'zstandard' package"
+        ) from e
+    return zstandard.ZstdDecompressor().decompressobj()
+
 
 class DataStream(ABC):
     """Base for classes capable of streaming results"""
@@ -54,6 +105,7 @@ class TCPClient:
         self.address = sock.getsockname()
         self.configuration = configuration
         self.receive_buffer = b""
+        self.decompressor = StreamDecompressor()
         self.logfile_handle: BinaryIO | None = None
         self.is_open = True
         if logfile is not None:
@@ -62,10 +114,11 @@ class TCPClient:
     def sync(self):
         data = self.socket.recv(4096)
         if not data:
+            self.receive_buffer += self.decompressor.feed(b"")
             self.is_open = False
             self.close()
             return
-        self.receive_buffer += data
+        self.receive_buffer += self.decompressor.feed(data)
         if self.logfile_handle is not None:
             self.logfile_handle.write(data)
 
@@ -302,6 +355,8 @@ class TCPStream(DataStream):
 class FileStream(DataStream):
     def __init__(self, filename: Path, verbose: bool = False):
         self.handle = filename.open("rb")
+        self.decompressor = StreamDecompressor()
+        self.buffer = b""
         self.done = False
 
     def __del__(self):
@@ -311,7 +366,13 @@ class FileStream(DataStream):
             pass
 
     def try_read(self, length: int) -> bytes:
-        result = self.handle.read(length)
+        while len(self.buffer) < length:
+            data = self.handle.read(max(length, 4096))
+            self.buffer += self.decompressor.feed(data)
+            if not data:
+                break
+        result = self.buffer[:length]
+        self.buffer = self.buffer[length:]
         return result
 
     def read_chunk(self, length: int) -> bytes:
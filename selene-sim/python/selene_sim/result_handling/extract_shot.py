@@ -17,6 +17,10 @@ class ShotExitMessage:
     e.g. as a result of a user program error that applies only to the current
     shot. The current shot will terminate gracefully with a ShotEnd, possibly
     after some metrics, and the next shot (if any) will proceed as normal.
+
+    Codes 998 and 999 are reserved for shots ended by selene itself (see
+    SeleneErrorKind.SHOT_FAILED and SeleneErrorKind.SHOT_LIMIT_EXCEEDED), and
+    all other codes below 1000 come from the user program.
     """
 
     message: str
@@ -112,6 +112,24 @@ def test_repetition(snapshot):
 
     assert results_ending_in_5_but_with_5_processes == results_ending_in_5
 
+    # and multi-threaded runs, alone and combined with multiple processes
+    for n_processes in (1, 2):
+        results_ending_in_5_but_with_threads = list(
+            dict(a)
+            for a in runner.run_shots(
+                simulator=simulator,
+                error_model=error_model,
+                n_qubits=5,
+                n_shots=100,
+                shot_offset=5,
+                shot_increment=10,
+                n_processes=n_processes,
+                n_threads=3,
+            )
+        )
+
+        assert results_ending_in_5_but_with_threads == results_ending_in_5
+
 
 if __name__ == "__main__":
     test_repetition()
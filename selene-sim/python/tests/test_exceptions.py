@@ -6,6 +6,7 @@ from selene_sim.exceptions import (
     SeleneStartupError,
     SeleneRuntimeError,
     SelenePanicError,
+    SeleneErrorKind,
 )
 
 
@@ -55,3 +56,16 @@ def test_pickle_and_unpickle_selene_startup_error(error_class, kwargs):
 
     for k, v in kwargs.items():
         assert getattr(unpickled, k) == v
+
+
+@pytest.mark.parametrize(
+    "code, kind",
+    [
+        (100002, SeleneErrorKind.QUBIT_OUT_OF_BOUNDS),
+        (100004, SeleneErrorKind.POSTSELECTION_IMPOSSIBLE),
+        (1001, None),
+    ],
+)
+def test_panic_error_kind(code, kind):
+    error = SelenePanicError(message="Exception", code=code)
+    assert error.kind == kind
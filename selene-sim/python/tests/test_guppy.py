@@ -633,6 +633,12 @@ def test_circuit_output():
     expected_circuit.Measure(0, 0)
     assert user_circuit == expected_circuit
 
+    # the error model reports the measurement outcomes it set
+    outcomes = [
+        bool(entry["value"]) for entry in circuits.shots[2].get_error_model_output()
+    ]
+    assert outcomes == [True, True, True]
+
 
 def test_cy():
     """Test CY is implemented correctly."""
@@ -1,10 +1,13 @@
+import pytest
+
 from guppylang.decorator import guppy
 from guppylang.std.quantum import qubit, measure, h, cx
 from guppylang.std.builtins import result
 
 from selene_sim.build import build
-from selene_sim import Quest, SimpleRuntime, SoftRZRuntime
+from selene_sim import GateValidatorRuntime, Quest, SimpleRuntime, SoftRZRuntime
 from selene_sim.event_hooks import MetricStore
+from selene_sim.exceptions import SelenePanicError
 
 
 def test_simple_vs_softrz():
@@ -70,3 +73,51 @@ def test_simple_vs_softrz():
     assert sum_up(soft_metrics, "individual_count") < sum_up(
         simple_metrics, "individual_count"
     )
+
+
+def test_gate_validator():
+    @guppy
+    def main() -> None:
+        q0: qubit = qubit()
+        q1: qubit = qubit()
+        h(q0)
+        cx(q0, q1)
+        result("c0", measure(q0))
+        result("c1", measure(q1))
+
+    runner = build(main.compile(), "gate_validator")
+    simulator = Quest(random_seed=561278)
+
+    simple = dict(runner.run(simulator, runtime=SimpleRuntime(), n_qubits=2))
+
+    # H and CX decompose into rotations by multiples of pi/4, so a grid
+    # of quarter half-turns passes every gate through unchanged.
+    metric_store = MetricStore()
+    validated = dict(
+        runner.run(
+            simulator,
+            runtime=GateValidatorRuntime(
+                runtime=SimpleRuntime(),
+                rxy_theta="step:0.25",
+                rxy_phi="step:0.25",
+                rz="step:0.25",
+                rzz="step:0.25",
+            ),
+            n_qubits=2,
+            event_hook=metric_store,
+        )
+    )
+    assert validated == simple
+    runtime_metrics = metric_store.shots[0]["runtime"]
+    assert runtime_metrics["rxy_violations"] == 0
+    assert runtime_metrics["rz_violations"] == 0
+    assert runtime_metrics["rzz_violations"] == 0
+
+    with pytest.raises(SelenePanicError, match="not permitted"):
+        dict(
+            runner.run(
+                simulator,
+                runtime=GateValidatorRuntime(runtime=SimpleRuntime(), rxy_theta="0"),
+                n_qubits=2,
+            )
+        )
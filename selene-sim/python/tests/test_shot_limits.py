@@ -0,0 +1,52 @@
+import pytest
+from guppylang.decorator import guppy
+from guppylang.std.builtins import result
+from guppylang.std.quantum import (
+    h,
+    measure,
+    qubit,
+)
+from selene_sim import Quest, ShotLimits
+from selene_sim.build import build
+from selene_sim.exceptions import SeleneErrorKind
+
+
+@pytest.fixture(scope="module")
+def non_terminating():
+    @guppy
+    def prog() -> None:
+        while True:
+            q0: qubit = qubit()
+            h(q0)
+            result("r", measure(q0))
+
+    return build(prog.compile())
+
+
+@pytest.mark.parametrize(
+    "shot_limits, message",
+    [
+        (ShotLimits(max_operations=100), "limit of 100 operations"),
+        (ShotLimits(max_batches=100), "limit of 100 runtime batches"),
+        (ShotLimits(max_wall_time=0.1), "wall-clock limit of 0.1s"),
+    ],
+)
+def test_shot_limits(non_terminating, shot_limits, message):
+    # Exceeding a limit ends the shot it occurs in, but the run carries on,
+    # so every shot is reported.
+    shots = [
+        list(shot)
+        for shot in non_terminating.run_shots(
+            Quest(random_seed=1234),
+            n_qubits=1,
+            n_shots=3,
+            shot_limits=shot_limits,
+        )
+    ]
+    assert len(shots) == 3
+    for shot_id, shot in enumerate(shots):
+        tag, code = shot[-1]
+        assert tag.startswith(f"exit: Shot {shot_id} exceeded")
+        assert message in tag
+        assert code == SeleneErrorKind.SHOT_LIMIT_EXCEEDED
+        assert any(tag == "r" for tag, _ in shot[:-1])
@@ -0,0 +1,57 @@
+//! Replay recorded operation logs through an error model and simulator.
+//!
+//! Usage: `selene-replay <CONFIG> <LOG>...`
+//!
+//! Each log is a `shot_<shot_id>.json` file written by the operation log in
+//! its JSON format, and is replayed with the error model and simulator of the
+//! given configuration, as described in [selene::replay].
+use std::io::Write;
+use std::process::ExitCode;
+
+use anyhow::Result;
+use selene::replay::{Replayer, read_operation_log, recorded_shot_id};
+use selene::selene_instance::configuration::Configuration;
+
+const USAGE: &str = "Usage: selene-replay <CONFIG> <LOG>...
+
+Replays the operations recorded in each JSON operation log through the error
+model and simulator of the given configuration, without the original program.
+Each log is replayed as the shot in its file name (shot_<shot_id>.json), or as
+shot 0 otherwise, and one line of JSON is printed for each with the
+measurement results in the order they were recorded.";
+
+fn run(config: &str, logs: &[String]) -> Result<()> {
+    let config = Configuration::from_file(config)?;
+    let mut replayer = Replayer::new(&config)?;
+    let mut stdout = std::io::stdout().lock();
+    for log in logs {
+        let batches = read_operation_log(log)?;
+        let shot = replayer.replay_shot(recorded_shot_id(log).unwrap_or(0), batches)?;
+        serde_json::to_writer(&mut stdout, &shot)?;
+        writeln!(stdout)?;
+    }
+    replayer.exit()
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        eprintln!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    let [config, logs @ ..] = args.as_slice() else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+    if logs.is_empty() {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+    match run(config, logs) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Rerun a single shot of a previous run and verify that it reproduces the
+//! original results.
+//!
+//! Usage: `selene-reproduce <PROGRAM> <CONFIG> <SHOT> [<STREAM>]`
+//!
+//! Reruns the shot with the instruction log enabled and compares it with the
+//! original result stream, as described in [selene::reproduce].
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use anyhow::{Context, Result, anyhow};
+use selene::reproduce::{ShotReproducer, compare_shots, read_shot};
+
+const USAGE: &str = "Usage: selene-reproduce <PROGRAM> <CONFIG> <SHOT> [<STREAM>]
+
+Reruns shot <SHOT> of the selene program <PROGRAM> with the configuration
+<CONFIG> of the original run, enabling the instruction log and writing the
+results and any state dumps to reproduce_shot_<SHOT> in the artifact
+directory of the run. The shot is then compared with the original result
+stream <STREAM>, which defaults to the output file of the configuration.
+
+Exits with a failure if the shot is not reproduced exactly.";
+
+fn run(args: &[String]) -> Result<bool> {
+    let [program, config, shot_id, stream @ ..] = args else {
+        unreachable!("main checks the number of arguments");
+    };
+    let shot_id: u64 = shot_id
+        .parse()
+        .with_context(|| format!("Invalid shot id '{shot_id}'"))?;
+    let reproducer = ShotReproducer::new(program, config)?;
+    let original_stream = match stream {
+        [] => reproducer.original_stream().ok_or_else(|| {
+            anyhow!("The configuration does not write results to a file, so a stream must be given")
+        })?,
+        [stream, ..] => PathBuf::from(stream),
+    };
+    let original = read_shot(&original_stream, shot_id)?;
+
+    let reproduced = reproducer.run(shot_id, reproducer.default_output_dir(shot_id))?;
+    eprintln!(
+        "Reproduced shot {shot_id} in '{}'",
+        reproduced.output_path.display()
+    );
+    match compare_shots(&original, &reproduced.messages()?)? {
+        None => {
+            eprintln!("The shot matches the original");
+            Ok(true)
+        }
+        Some(mismatch) => {
+            eprintln!("{mismatch}");
+            Ok(false)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        eprintln!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    if !(3..=4).contains(&args.len()) {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+    match run(&args) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("Error: {e:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,34 +1,47 @@
-use crate::event_hooks::{EventHook, MultiEventHook, Operation};
+use crate::event_hooks::plugin::PluginEventHook;
+use crate::event_hooks::{EventHook, MultiEventHook, Operation, ResultValue};
 use crate::selene_instance::configuration::Configuration;
+use crate::selene_instance::limits::ShotLimiter;
 use anyhow::{Result, anyhow};
-use selene_core::error_model::plugin::ErrorModelPluginInterface;
+use selene_core::capabilities::Capabilities;
+use selene_core::error::ErrorKind;
 use selene_core::error_model::{ErrorModel, ErrorModelInterface};
-use selene_core::runtime::plugin::RuntimePluginInterface;
 use selene_core::runtime::{Runtime, RuntimeInterface as _};
+use selene_core::simulator::Simulator;
 
 pub struct Emulator {
     pub runtime: Runtime,
     pub error_model: ErrorModel,
+    /// The capabilities of the simulator, which is owned by the error model.
+    pub simulator_capabilities: Capabilities,
     pub event_hooks: MultiEventHook,
+    pub limits: ShotLimiter,
 }
 
 // User-issued function calls
 impl Emulator {
     pub fn from_configuration(config: &Configuration) -> Result<Self> {
+        if config
+            .event_hooks
+            .plugins
+            .iter()
+            .any(|plugin| plugin.isolated)
+        {
+            return Err(
+                ErrorKind::InvalidArgument.error("Event hooks cannot be hosted out of process")
+            );
+        }
         let n_qubits = config.n_qubits;
-        let error_model_plugin =
-            ErrorModelPluginInterface::new_from_file(&config.error_model.file)?;
-        let error_model = ErrorModel::new(
-            error_model_plugin,
-            n_qubits,
-            config.error_model.args.as_ref(),
-            &config.simulator.file,
-            config.simulator.args.as_ref(),
-        )?;
+        let mut error_model = Self::load_error_model(config)?;
+        // The simulator has been loaded by the error model, which reports its
+        // capabilities unless it predates doing so.
+        let simulator_capabilities = match error_model.simulator_capabilities()? {
+            Some(capabilities) => capabilities,
+            None => Simulator::capabilities_of(&config.simulator.path())?,
+        };
 
-        let runtime_plugin = RuntimePluginInterface::new_from_file(&config.runtime.file)?;
-        let runtime = Runtime::new(
-            runtime_plugin,
+        let runtime = Runtime::load_from_file(
+            &config.runtime.path(),
             n_qubits,
             selene_core::time::Instant::default(),
             config.runtime.args.as_ref(),
@@ -46,12 +59,44 @@ impl Emulator {
                 crate::event_hooks::instruction_log::InstructionLog::default(),
             ));
         }
+        if let Some(format) = config.event_hooks.operation_log {
+            event_hooks.add_hook(Box::new(
+                crate::event_hooks::operation_log::OperationLog::new(
+                    format,
+                    config.artifact_dir.clone(),
+                    n_qubits,
+                ),
+            ));
+        }
+        for plugin in &config.event_hooks.plugins {
+            event_hooks.add_hook(Box::new(PluginEventHook::load(plugin, n_qubits)?));
+        }
         Ok(Self {
             runtime,
             error_model,
+            simulator_capabilities,
             event_hooks,
+            limits: ShotLimiter::new(config.limits.clone()),
         })
     }
+    /// Loads the error model of the configuration, along with its simulator.
+    pub fn load_error_model(config: &Configuration) -> Result<ErrorModel> {
+        crate::plugins::register_builtin_plugins();
+        if [&config.simulator, &config.error_model]
+            .iter()
+            .any(|plugin| plugin.isolated && plugin.file.is_none())
+        {
+            return Err(ErrorKind::InvalidArgument
+                .error("Plugins linked into selene cannot be hosted out of process"));
+        }
+        ErrorModel::load_from_file(
+            &config.error_model.path(),
+            config.n_qubits,
+            config.error_model.args.as_ref(),
+            &config.simulator.path(),
+            config.simulator.args.as_ref(),
+        )
+    }
     pub fn poke(&mut self) -> Result<()> {
         self.process_runtime()
     }
@@ -61,67 +106,100 @@ impl Emulator {
         self.error_model.dump_simulator_state(file, qubits)
     }
     pub fn user_issued_qalloc(&mut self) -> Result<u64> {
+        self.limits.qalloc()?;
         let address = self.runtime.qalloc()?;
+        self.limits.allocated(address);
         //self.user_program_metrics.increment_qalloc();
-        self.event_hooks.on_user_call(&Operation::QAlloc(address));
+        self.event_hooks.on_user_call(&Operation::QAlloc(address))?;
         self.process_runtime()?;
         Ok(address)
     }
     pub fn user_issued_qfree(&mut self, address: u64) -> Result<()> {
+        self.limits.operation()?;
         self.runtime.qfree(address)?;
+        self.limits.freed();
         //self.user_program_metrics.increment_qfree();
-        self.event_hooks.on_user_call(&Operation::QFree(address));
+        self.event_hooks.on_user_call(&Operation::QFree(address))?;
         self.process_runtime()
     }
     pub fn user_issued_local_barrier(&mut self, qubits: &[u64], sleep_time: u64) -> Result<()> {
+        self.limits.operation()?;
         self.runtime.local_barrier(qubits, sleep_time)?;
         self.event_hooks
-            .on_user_call(&Operation::LocalBarrier(qubits.to_vec(), sleep_time));
+            .on_user_call(&Operation::LocalBarrier(qubits.to_vec(), sleep_time))?;
         self.process_runtime()
     }
     pub fn user_issued_global_barrier(&mut self, sleep_time: u64) -> Result<()> {
+        self.limits.operation()?;
         self.runtime.global_barrier(sleep_time)?;
         self.event_hooks
-            .on_user_call(&Operation::GlobalBarrier(sleep_time));
+            .on_user_call(&Operation::GlobalBarrier(sleep_time))?;
         self.process_runtime()
     }
     pub fn user_issued_rxy(&mut self, q0: u64, theta: f64, phi: f64) -> Result<()> {
+        self.limits.operation()?;
         self.runtime.rxy_gate(q0, theta, phi)?;
         //self.user_program_metrics.increment_rxy();
         self.event_hooks
-            .on_user_call(&Operation::RXY(q0, theta, phi));
+            .on_user_call(&Operation::RXY(q0, theta, phi))?;
         self.process_runtime()
     }
     pub fn user_issued_rzz(&mut self, q0: u64, q1: u64, theta: f64) -> Result<()> {
+        self.limits.operation()?;
         self.runtime.rzz_gate(q0, q1, theta)?;
         //self.user_program_metrics.increment_rzz();
         self.event_hooks
-            .on_user_call(&Operation::RZZ(q0, q1, theta));
+            .on_user_call(&Operation::RZZ(q0, q1, theta))?;
         self.process_runtime()
     }
     pub fn user_issued_rz(&mut self, q0: u64, theta: f64) -> Result<()> {
+        self.limits.operation()?;
         self.runtime.rz_gate(q0, theta)?;
         //self.user_program_metrics.increment_rz();
-        self.event_hooks.on_user_call(&Operation::RZ(q0, theta));
+        self.event_hooks.on_user_call(&Operation::RZ(q0, theta))?;
         self.process_runtime()
     }
     pub fn user_issued_reset(&mut self, q0: u64) -> Result<()> {
+        self.limits.operation()?;
         self.runtime.reset(q0)?;
         //self.user_program_metrics.increment_reset();
-        self.event_hooks.on_user_call(&Operation::Reset(q0));
+        self.event_hooks.on_user_call(&Operation::Reset(q0))?;
         self.process_runtime()
     }
     pub fn user_issued_lazy_measure(&mut self, q0: u64) -> Result<u64> {
+        self.limits.operation()?;
         let result_id = self.runtime.measure(q0)?;
         self.event_hooks
-            .on_user_call(&Operation::MeasureRequest(q0));
+            .on_user_call(&Operation::MeasureRequest(q0))?;
         self.process_runtime()?;
         Ok(result_id)
     }
     pub fn user_issued_lazy_measure_leaked(&mut self, q0: u64) -> Result<u64> {
+        self.limits.operation()?;
         let result_id = self.runtime.measure_leaked(q0)?;
         self.event_hooks
-            .on_user_call(&Operation::MeasureLeakedRequest(q0));
+            .on_user_call(&Operation::MeasureLeakedRequest(q0))?;
+        self.process_runtime()?;
+        Ok(result_id)
+    }
+    pub fn user_issued_lazy_measure_reset(&mut self, q0: u64) -> Result<u64> {
+        self.limits.operation()?;
+        let result_id = self.runtime.measure_reset(q0)?;
+        self.event_hooks
+            .on_user_call(&Operation::MeasureResetRequest(q0))?;
+        self.process_runtime()?;
+        Ok(result_id)
+    }
+    /// Measures, resets and frees a qubit with a single round trip
+    /// through the runtime.
+    pub fn user_issued_measure_and_free(&mut self, q0: u64) -> Result<u64> {
+        self.limits.operation()?;
+        let result_id = self.runtime.measure_reset(q0)?;
+        self.event_hooks
+            .on_user_call(&Operation::MeasureResetRequest(q0))?;
+        self.runtime.qfree(q0)?;
+        self.limits.freed();
+        self.event_hooks.on_user_call(&Operation::QFree(q0))?;
         self.process_runtime()?;
         Ok(result_id)
     }
@@ -138,8 +216,9 @@ impl Emulator {
         self.process_runtime()
     }
     pub fn user_issued_read_future_bool(&mut self, result_id: u64) -> Result<bool> {
+        self.limits.operation()?;
         self.event_hooks
-            .on_user_call(&Operation::FutureRead(result_id));
+            .on_user_call(&Operation::FutureRead(result_id))?;
         match self.runtime.get_bool_result(result_id)? {
             Some(value) => Ok(value),
             None => {
@@ -155,8 +234,9 @@ impl Emulator {
         }
     }
     pub fn user_issued_read_future_u64(&mut self, result_id: u64) -> Result<u64> {
+        self.limits.operation()?;
         self.event_hooks
-            .on_user_call(&Operation::FutureRead(result_id));
+            .on_user_call(&Operation::FutureRead(result_id))?;
         match self.runtime.get_u64_result(result_id)? {
             Some(value) => Ok(value),
             None => {
@@ -172,7 +252,22 @@ impl Emulator {
         }
     }
 
+    pub fn user_issued_begin_condition(&mut self, result_id: u64, value: bool) -> Result<()> {
+        self.limits.operation()?;
+        self.runtime.begin_condition(result_id, value)?;
+        self.event_hooks
+            .on_user_call(&Operation::BeginCondition(result_id, value))?;
+        self.process_runtime()
+    }
+    pub fn user_issued_end_condition(&mut self) -> Result<()> {
+        self.limits.operation()?;
+        self.runtime.end_condition()?;
+        self.event_hooks.on_user_call(&Operation::EndCondition)?;
+        self.process_runtime()
+    }
+
     pub fn custom_runtime_call(&mut self, tag: u64, data: &[u8]) -> Result<u64> {
+        self.limits.operation()?;
         let result = self.runtime.custom_call(tag, data)?;
         self.process_runtime()?;
         Ok(result)
@@ -183,14 +278,23 @@ impl Emulator {
 impl Emulator {
     fn process_runtime(&mut self) -> Result<()> {
         while let Some(batch) = self.runtime.get_next_operations()? {
-            self.event_hooks.on_runtime_batch(&batch);
+            self.limits.batch()?;
+            self.event_hooks.on_runtime_batch(&batch)?;
             //self.post_runtime_metrics.update(&batch);
             let results = self.error_model.handle_operations(batch)?;
+            for injected in results.injected_operations.iter() {
+                self.event_hooks
+                    .on_error_model_op(injected.position, &injected.operation)?;
+            }
             for bool_result in results.bool_results {
+                self.event_hooks
+                    .on_result(bool_result.result_id, ResultValue::Bool(bool_result.value))?;
                 self.runtime
                     .set_bool_result(bool_result.result_id, bool_result.value)?;
             }
             for u64_result in results.u64_results {
+                self.event_hooks
+                    .on_result(u64_result.result_id, ResultValue::U64(u64_result.value))?;
                 self.runtime
                     .set_u64_result(u64_result.result_id, u64_result.value)?;
             }
@@ -1,38 +1,47 @@
-use selene_core::encoder::{OutputStream, OutputStreamError};
-use selene_core::runtime::BatchOperation;
+use anyhow::Result;
+use selene_core::encoder::OutputStream;
+use selene_core::runtime::{self, BatchOperation};
 
 pub mod instruction_log;
 pub mod metrics;
+pub mod operation_log;
+pub mod plugin;
 
-#[derive(Clone)]
-pub enum Operation {
-    QFree(u64),
-    QAlloc(u64),
-    RXY(u64, f64, f64),
-    RZZ(u64, u64, f64),
-    RZ(u64, f64),
-    Reset(u64),
-    MeasureRequest(u64),
-    MeasureLeakedRequest(u64),
-    FutureRead(u64),
-    BatchStart(u64, u64),
-    GlobalBarrier(u64),
-    LocalBarrier(Vec<u64>, u64),
-    Custom(u64, Vec<u8>),
-}
+pub use selene_core::event_hook::{Operation, ResultValue};
 
+/// Observes the operations of a run. An error returned from any of the
+/// functions fails the operation that triggered it.
 pub trait EventHook {
-    fn on_user_call(&mut self, _: &Operation) {}
-    fn on_runtime_batch(&mut self, _: &BatchOperation) {}
-    fn write(
-        &self,
-        _time_cursor: u64,
-        _encoder: &mut OutputStream,
-    ) -> Result<(), OutputStreamError> {
+    fn on_user_call(&mut self, _: &Operation) -> Result<()> {
+        Ok(())
+    }
+    fn on_runtime_batch(&mut self, _: &BatchOperation) -> Result<()> {
+        Ok(())
+    }
+    /// Called with each operation the error model reports having injected
+    /// while handling a batch, in the order it applied them, along with the
+    /// number of the batch's operations applied before it.
+    fn on_error_model_op(&mut self, _position: u64, _: &runtime::Operation) -> Result<()> {
+        Ok(())
+    }
+    /// Called with each result set by the error model, before it is passed
+    /// to the runtime.
+    fn on_result(&mut self, _result_id: u64, _value: ResultValue) -> Result<()> {
+        Ok(())
+    }
+    fn write(&mut self, _time_cursor: u64, _encoder: &mut OutputStream) -> Result<()> {
+        Ok(())
+    }
+    fn on_shot_start(&mut self, _shot_id: u64) -> Result<()> {
+        Ok(())
+    }
+    fn on_shot_end(&mut self) -> Result<()> {
+        Ok(())
+    }
+    /// Called once the run is over.
+    fn exit(&mut self) -> Result<()> {
         Ok(())
     }
-    fn on_shot_start(&mut self, _shot_id: u64) {}
-    fn on_shot_end(&mut self) {}
 }
 
 #[derive(Default)]
@@ -46,30 +55,55 @@ impl MultiEventHook {
 }
 
 impl EventHook for MultiEventHook {
-    fn on_user_call(&mut self, operation: &Operation) {
+    fn on_user_call(&mut self, operation: &Operation) -> Result<()> {
         for hook in self.hooks.iter_mut() {
-            hook.on_user_call(operation);
+            hook.on_user_call(operation)?;
         }
+        Ok(())
     }
-    fn on_runtime_batch(&mut self, operation: &BatchOperation) {
+    fn on_runtime_batch(&mut self, operation: &BatchOperation) -> Result<()> {
         for hook in self.hooks.iter_mut() {
-            hook.on_runtime_batch(operation);
+            hook.on_runtime_batch(operation)?;
         }
+        Ok(())
     }
-    fn write(&self, time_cursor: u64, encoder: &mut OutputStream) -> Result<(), OutputStreamError> {
-        for hook in self.hooks.iter() {
-            hook.write(time_cursor, encoder)?;
+    fn on_error_model_op(&mut self, position: u64, operation: &runtime::Operation) -> Result<()> {
+        for hook in self.hooks.iter_mut() {
+            hook.on_error_model_op(position, operation)?;
         }
         Ok(())
     }
-    fn on_shot_start(&mut self, shot_id: u64) {
+    fn on_result(&mut self, result_id: u64, value: ResultValue) -> Result<()> {
         for hook in self.hooks.iter_mut() {
-            hook.on_shot_start(shot_id);
+            hook.on_result(result_id, value)?;
         }
+        Ok(())
     }
-    fn on_shot_end(&mut self) {
+    fn write(&mut self, time_cursor: u64, encoder: &mut OutputStream) -> Result<()> {
         for hook in self.hooks.iter_mut() {
-            hook.on_shot_end();
+            hook.write(time_cursor, encoder)?;
         }
+        Ok(())
+    }
+    /// Every hook is told of the start and end of a shot, even if an earlier
+    /// one fails, so that all of them are ready for the next shot. The first
+    /// error is returned.
+    fn on_shot_start(&mut self, shot_id: u64) -> Result<()> {
+        self.hooks
+            .iter_mut()
+            .map(|hook| hook.on_shot_start(shot_id))
+            .fold(Ok(()), Result::and)
+    }
+    fn on_shot_end(&mut self) -> Result<()> {
+        self.hooks
+            .iter_mut()
+            .map(|hook| hook.on_shot_end())
+            .fold(Ok(()), Result::and)
+    }
+    fn exit(&mut self) -> Result<()> {
+        self.hooks
+            .iter_mut()
+            .map(|hook| hook.exit())
+            .fold(Ok(()), Result::and)
     }
 }
@@ -1,7 +1,10 @@
-use crate::event_hooks::{EventHook, Operation};
+use crate::event_hooks::{EventHook, Operation, ResultValue};
 use selene_core::encoder::{OutputStream, OutputStreamError};
 use selene_core::runtime::{self, BatchOperation};
 
+#[cfg(test)]
+mod tests;
+
 pub struct Instruction {
     pub source: Source,
     pub operation: Operation,
@@ -82,69 +85,150 @@ impl Instruction {
                 encoder.write(12u64)?;
                 encoder.write(*qubit1)?;
             }
+            Operation::BeginCondition(result_id, value) => {
+                encoder.write(13u64)?;
+                encoder.write(*result_id)?;
+                encoder.write(*value as u64)?;
+            }
+            Operation::EndCondition => {
+                encoder.write(14u64)?;
+            }
+            Operation::MeasureResetRequest(qubit1) => {
+                encoder.write(15u64)?;
+                encoder.write(*qubit1)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An entry of the instruction log: an instruction, or a result set by the
+/// error model.
+pub enum Entry {
+    Instruction(Instruction),
+    Result(u64, ResultValue),
+}
+
+impl Entry {
+    pub fn write(&self, encoder: &mut OutputStream) -> Result<(), OutputStreamError> {
+        match self {
+            Entry::Instruction(instruction) => instruction.write(encoder)?,
+            Entry::Result(result_id, value) => {
+                encoder.write(Source::ErrorModel as u64)?;
+                match value {
+                    ResultValue::Bool(value) => {
+                        encoder.write(16u64)?;
+                        encoder.write(*result_id)?;
+                        encoder.write(*value as u64)?;
+                    }
+                    ResultValue::U64(value) => {
+                        encoder.write(17u64)?;
+                        encoder.write(*result_id)?;
+                        encoder.write(*value)?;
+                    }
+                }
+            }
         }
         Ok(())
     }
 }
 
+/// Maps an operation emitted by the runtime, or injected by the error model,
+/// to the operation logged for it.
+fn log_operation(op: &runtime::Operation) -> Operation {
+    match op {
+        runtime::Operation::Reset { qubit_id } => Operation::Reset(*qubit_id),
+        runtime::Operation::RXYGate {
+            qubit_id,
+            theta,
+            phi,
+        } => Operation::RXY(*qubit_id, *theta, *phi),
+        runtime::Operation::RZZGate {
+            qubit_id_1,
+            qubit_id_2,
+            theta,
+        } => Operation::RZZ(*qubit_id_1, *qubit_id_2, *theta),
+        runtime::Operation::RZGate { qubit_id, theta } => Operation::RZ(*qubit_id, *theta),
+        runtime::Operation::Measure { qubit_id, .. } => Operation::FutureRead(*qubit_id),
+        runtime::Operation::MeasureLeaked { qubit_id, .. } => Operation::FutureRead(*qubit_id),
+        runtime::Operation::MeasureReset { qubit_id, .. } => {
+            Operation::MeasureResetRequest(*qubit_id)
+        }
+        runtime::Operation::Custom { custom_tag, data } => {
+            Operation::Custom(*custom_tag as u64, data.to_vec())
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct InstructionLog {
-    entries: Vec<Instruction>,
+    entries: Vec<Entry>,
+    /// The index of the entry of the first operation of the last batch.
+    batch_start: usize,
+    /// The number of operations injected into the last batch so far.
+    batch_injected: usize,
 }
 
 impl EventHook for InstructionLog {
-    fn on_user_call(&mut self, operation: &Operation) {
-        self.entries.push(Instruction {
+    fn on_user_call(&mut self, operation: &Operation) -> anyhow::Result<()> {
+        self.entries.push(Entry::Instruction(Instruction {
             source: Source::UserProgram,
             operation: operation.clone(),
-        });
+        }));
+        Ok(())
     }
-    fn on_runtime_batch(&mut self, batch: &BatchOperation) {
+    fn on_runtime_batch(&mut self, batch: &BatchOperation) -> anyhow::Result<()> {
         let start = u64::from(batch.start());
         let duration = u64::from(batch.duration());
-        self.entries.push(Instruction {
+        self.entries.push(Entry::Instruction(Instruction {
             source: Source::RuntimeOptimiser,
             operation: Operation::BatchStart(start, duration),
-        });
+        }));
+        self.batch_start = self.entries.len();
+        self.batch_injected = 0;
         for op in batch.iter_ops() {
-            let operation = match op {
-                runtime::Operation::Reset { qubit_id } => Operation::Reset(*qubit_id),
-                runtime::Operation::RXYGate {
-                    qubit_id,
-                    theta,
-                    phi,
-                } => Operation::RXY(*qubit_id, *theta, *phi),
-                runtime::Operation::RZZGate {
-                    qubit_id_1,
-                    qubit_id_2,
-                    theta,
-                } => Operation::RZZ(*qubit_id_1, *qubit_id_2, *theta),
-                runtime::Operation::RZGate { qubit_id, theta } => Operation::RZ(*qubit_id, *theta),
-                runtime::Operation::Measure { qubit_id, .. } => Operation::FutureRead(*qubit_id),
-                runtime::Operation::MeasureLeaked { qubit_id, .. } => {
-                    Operation::FutureRead(*qubit_id)
-                }
-                runtime::Operation::Custom { custom_tag, data } => {
-                    Operation::Custom(*custom_tag as u64, data.to_vec())
-                }
-            };
-            self.entries.push(Instruction {
+            self.entries.push(Entry::Instruction(Instruction {
                 source: Source::RuntimeOptimiser,
-                operation,
-            });
+                operation: log_operation(op),
+            }));
         }
+        Ok(())
+    }
+    fn on_error_model_op(
+        &mut self,
+        position: u64,
+        operation: &runtime::Operation,
+    ) -> anyhow::Result<()> {
+        // Injected operations arrive in the order they were applied, so each
+        // follows the batch's operations before it and those injected before
+        // it.
+        let index =
+            (self.batch_start + position as usize + self.batch_injected).min(self.entries.len());
+        self.entries.insert(
+            index,
+            Entry::Instruction(Instruction {
+                source: Source::ErrorModel,
+                operation: log_operation(operation),
+            }),
+        );
+        self.batch_injected += 1;
+        Ok(())
     }
-    fn write(&self, time_cursor: u64, encoder: &mut OutputStream) -> Result<(), OutputStreamError> {
+    fn on_result(&mut self, result_id: u64, value: ResultValue) -> anyhow::Result<()> {
+        self.entries.push(Entry::Result(result_id, value));
+        Ok(())
+    }
+    fn write(&mut self, time_cursor: u64, encoder: &mut OutputStream) -> anyhow::Result<()> {
         encoder.begin_message(time_cursor)?;
         encoder.write("INSTRUCTIONLOG")?;
-        for instruction in self.entries.iter() {
-            instruction.write(encoder)?;
+        for entry in self.entries.iter() {
+            entry.write(encoder)?;
         }
         encoder.end_message()?;
         Ok(())
     }
-    fn on_shot_start(&mut self, _shot_id: u64) {
+    fn on_shot_start(&mut self, _shot_id: u64) -> anyhow::Result<()> {
         self.entries.clear();
+        Ok(())
     }
-    fn on_shot_end(&mut self) {}
 }
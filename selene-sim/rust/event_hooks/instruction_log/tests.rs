@@ -0,0 +1,68 @@
+use super::*;
+
+fn rz(qubit_id: u64) -> runtime::Operation {
+    runtime::Operation::RZGate {
+        qubit_id,
+        theta: 0.5,
+    }
+}
+
+/// The logged instructions, with the operations of the error model marked.
+fn logged(log: &InstructionLog) -> Vec<String> {
+    log.entries
+        .iter()
+        .map(|entry| match entry {
+            Entry::Instruction(Instruction {
+                source: Source::ErrorModel,
+                operation,
+            }) => format!("error {operation:?}"),
+            Entry::Instruction(Instruction { operation, .. }) => format!("{operation:?}"),
+            Entry::Result(result_id, value) => format!("result {result_id} {value:?}"),
+        })
+        .collect()
+}
+
+#[test]
+fn injected_operations_are_interleaved() {
+    let mut log = InstructionLog::default();
+    log.on_user_call(&Operation::QAlloc(0)).unwrap();
+    let batch = BatchOperation::new(
+        vec![rz(0), rz(1), rz(2)],
+        Default::default(),
+        Default::default(),
+    );
+    log.on_runtime_batch(&batch).unwrap();
+    log.on_error_model_op(0, &rz(10)).unwrap();
+    log.on_error_model_op(2, &rz(11)).unwrap();
+    log.on_error_model_op(2, &rz(12)).unwrap();
+    log.on_error_model_op(3, &rz(13)).unwrap();
+    log.on_result(4, ResultValue::Bool(true)).unwrap();
+    assert_eq!(
+        logged(&log),
+        vec![
+            "QAlloc(0)",
+            "BatchStart(0, 0)",
+            "error RZ(10, 0.5)",
+            "RZ(0, 0.5)",
+            "RZ(1, 0.5)",
+            "error RZ(11, 0.5)",
+            "error RZ(12, 0.5)",
+            "RZ(2, 0.5)",
+            "error RZ(13, 0.5)",
+            "result 4 Bool(true)",
+        ]
+    );
+
+    // Positions refer to the latest batch.
+    log.on_runtime_batch(&BatchOperation::new(
+        vec![rz(3)],
+        Default::default(),
+        Default::default(),
+    ))
+    .unwrap();
+    log.on_error_model_op(1, &rz(14)).unwrap();
+    assert_eq!(
+        logged(&log)[10..],
+        ["BatchStart(0, 0)", "RZ(3, 0.5)", "error RZ(14, 0.5)"]
+    );
+}
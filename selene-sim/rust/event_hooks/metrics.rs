@@ -11,6 +11,7 @@ struct UserProgramMetrics {
     reset_count: u64,
     measure_request_count: u64,
     measure_leaked_request_count: u64,
+    measure_reset_request_count: u64,
     future_read_count: u64,
     rxy_count: u64,
     rz_count: u64,
@@ -34,6 +35,7 @@ impl UserProgramMetrics {
             Operation::Reset(_) => self.reset_count += 1,
             Operation::MeasureRequest(_) => self.measure_request_count += 1,
             Operation::MeasureLeakedRequest(_) => self.measure_leaked_request_count += 1,
+            Operation::MeasureResetRequest(_) => self.measure_reset_request_count += 1,
             Operation::FutureRead(_) => self.future_read_count += 1,
             Operation::RXY(..) => self.rxy_count += 1,
             Operation::RZ(..) => self.rz_count += 1,
@@ -69,6 +71,10 @@ impl UserProgramMetrics {
         encoder.write(self.measure_leaked_request_count)?;
         encoder.end_message()?;
         encoder.begin_message(time_cursor)?;
+        encoder.write("METRICS:INT:user_program:measure_reset_request_count")?;
+        encoder.write(self.measure_reset_request_count)?;
+        encoder.end_message()?;
+        encoder.begin_message(time_cursor)?;
         encoder.write("METRICS:INT:user_program:measure_read_count")?;
         encoder.write(self.future_read_count)?;
         encoder.end_message()?;
@@ -111,6 +117,8 @@ struct PostRuntimeMetrics {
     measure_individual_count: u64,
     measure_leaked_batch_count: u64,
     measure_leaked_individual_count: u64,
+    measure_reset_batch_count: u64,
+    measure_reset_individual_count: u64,
     reset_batch_count: u64,
     reset_individual_count: u64,
     rxy_batch_count: u64,
@@ -129,6 +137,7 @@ impl PostRuntimeMetrics {
         let mut rz_count = 0;
         let mut measure_count = 0;
         let mut measure_leaked_count = 0;
+        let mut measure_reset_count = 0;
         let mut reset_count = 0;
         let mut custom_op_count = 0;
         for op in batch.iter_ops() {
@@ -148,6 +157,9 @@ impl PostRuntimeMetrics {
                 runtime::Operation::MeasureLeaked { .. } => {
                     measure_leaked_count += 1;
                 }
+                runtime::Operation::MeasureReset { .. } => {
+                    measure_reset_count += 1;
+                }
                 runtime::Operation::Reset { .. } => {
                     reset_count += 1;
                 }
@@ -181,6 +193,10 @@ impl PostRuntimeMetrics {
             self.measure_leaked_batch_count += 1;
             self.measure_leaked_individual_count += measure_leaked_count;
         }
+        if measure_reset_count > 0 {
+            self.measure_reset_batch_count += 1;
+            self.measure_reset_individual_count += measure_reset_count;
+        }
         if reset_count > 0 {
             self.reset_batch_count += 1;
             self.reset_individual_count += reset_count;
@@ -220,6 +236,14 @@ impl PostRuntimeMetrics {
         encoder.write(self.measure_leaked_individual_count)?;
         encoder.end_message()?;
         encoder.begin_message(time_cursor)?;
+        encoder.write("METRICS:INT:post_runtime:measure_reset_batch_count")?;
+        encoder.write(self.measure_reset_batch_count)?;
+        encoder.end_message()?;
+        encoder.begin_message(time_cursor)?;
+        encoder.write("METRICS:INT:post_runtime:measure_reset_individual_count")?;
+        encoder.write(self.measure_reset_individual_count)?;
+        encoder.end_message()?;
+        encoder.begin_message(time_cursor)?;
         encoder.write("METRICS:INT:post_runtime:reset_batch_count")?;
         encoder.write(self.reset_batch_count)?;
         encoder.end_message()?;
@@ -264,20 +288,22 @@ pub struct HighLevelMetrics {
     post_runtime_metrics: PostRuntimeMetrics,
 }
 impl EventHook for HighLevelMetrics {
-    fn on_user_call(&mut self, operation: &Operation) {
+    fn on_user_call(&mut self, operation: &Operation) -> anyhow::Result<()> {
         self.user_program_metrics.update(operation);
+        Ok(())
     }
-    fn on_runtime_batch(&mut self, batch: &BatchOperation) {
+    fn on_runtime_batch(&mut self, batch: &BatchOperation) -> anyhow::Result<()> {
         self.post_runtime_metrics.update(batch);
+        Ok(())
     }
-    fn write(&self, time_cursor: u64, encoder: &mut OutputStream) -> Result<(), OutputStreamError> {
+    fn write(&mut self, time_cursor: u64, encoder: &mut OutputStream) -> anyhow::Result<()> {
         self.user_program_metrics.write(time_cursor, encoder)?;
         self.post_runtime_metrics.write(time_cursor, encoder)?;
         Ok(())
     }
-    fn on_shot_start(&mut self, _shot_id: u64) {
+    fn on_shot_start(&mut self, _shot_id: u64) -> anyhow::Result<()> {
         self.user_program_metrics = UserProgramMetrics::default();
         self.post_runtime_metrics = PostRuntimeMetrics::default();
+        Ok(())
     }
-    fn on_shot_end(&mut self) {}
 }
@@ -0,0 +1,261 @@
+//! Writes the operations emitted by the runtime in each shot to the artifact
+//! directory, as configured by
+//! [EventHookConfig::operation_log](crate::selene_instance::configuration::EventHookConfig::operation_log).
+//!
+//! Unlike the instruction log, which is written to the result stream, this
+//! records the operations after the runtime has processed them, along with the
+//! result id of each measurement, so that the trajectory of a shot can be fed
+//! into external tools and other simulators. Each shot is written to
+//! `shot_<shot_id>.<extension>`, with the extension given by its
+//! [OperationLogFormat].
+use anyhow::{Context, Result};
+use selene_core::runtime::{BatchOperation, Operation};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+use crate::event_hooks::EventHook;
+
+#[cfg(test)]
+mod tests;
+
+/// The format in which the operations of each shot are written.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationLogFormat {
+    /// OpenQASM 3, in a `.qasm` file, with a bit named `r<result_id>` for
+    /// each measurement result.
+    Openqasm3,
+    /// A Stim circuit, in a `.stim` file, with a `TICK` after each batch.
+    /// Stim can only represent Clifford operations, so shots with angles that
+    /// are not multiples of pi/2 are written as OpenQASM 3 instead.
+    Stim,
+    /// The batches themselves as JSON, in a `.json` file, from which they can
+    /// be read back without loss, e.g. to be replayed by [crate::replay].
+    /// Angles are given as the bits of their IEEE 754 representation, as
+    /// decimal numbers would round them.
+    Json,
+}
+
+/// How far from a multiple of pi/2 an angle may be, in units of pi/2, to be
+/// treated as Clifford. This matches the default threshold of the Stim
+/// simulator.
+const CLIFFORD_THRESHOLD: f64 = 1e-4;
+
+pub struct OperationLog {
+    format: OperationLogFormat,
+    artifact_dir: PathBuf,
+    n_qubits: u64,
+    shot_id: u64,
+    batches: Vec<BatchOperation>,
+}
+
+impl OperationLog {
+    pub fn new(format: OperationLogFormat, artifact_dir: PathBuf, n_qubits: u64) -> Self {
+        Self {
+            format,
+            artifact_dir,
+            n_qubits,
+            shot_id: 0,
+            batches: Vec::new(),
+        }
+    }
+
+    fn contents(&self) -> Result<(&'static str, String)> {
+        match self.format {
+            OperationLogFormat::Openqasm3 => {
+                Ok(("qasm", to_openqasm3(&self.batches, self.n_qubits)))
+            }
+            OperationLogFormat::Stim => Ok(match to_stim(&self.batches) {
+                Some(circuit) => ("stim", circuit),
+                None => ("qasm", to_openqasm3(&self.batches, self.n_qubits)),
+            }),
+            OperationLogFormat::Json => Ok(("json", serde_json::to_string(&self.batches)?)),
+        }
+    }
+}
+
+impl EventHook for OperationLog {
+    fn on_runtime_batch(&mut self, batch: &BatchOperation) -> Result<()> {
+        if !batch.is_empty() {
+            self.batches.push(batch.clone());
+        }
+        Ok(())
+    }
+    fn on_shot_start(&mut self, shot_id: u64) -> Result<()> {
+        self.shot_id = shot_id;
+        self.batches.clear();
+        Ok(())
+    }
+    fn on_shot_end(&mut self) -> Result<()> {
+        let (extension, contents) = self.contents()?;
+        let path = self
+            .artifact_dir
+            .join(format!("shot_{}.{extension}", self.shot_id));
+        std::fs::write(&path, contents)
+            .with_context(|| format!("Failed to write the operation log to '{}'", path.display()))
+    }
+}
+
+/// The number of quarter turns, from 0 to 3, that `angle` approximates, or
+/// `None` if it is not close to a multiple of pi/2.
+fn quarter_turns(angle: f64) -> Option<u8> {
+    let quarters = angle / std::f64::consts::FRAC_PI_2;
+    let rounded = quarters.round();
+    ((quarters - rounded).abs() < CLIFFORD_THRESHOLD).then(|| rounded.rem_euclid(4.0) as u8)
+}
+
+/// Writes the batches as an OpenQASM 3 program over `n_qubits` qubits.
+///
+/// Selene's RXY and RZZ gates are not in `stdgates.inc`, so the program
+/// defines them in terms of its gates.
+pub fn to_openqasm3(batches: &[BatchOperation], n_qubits: u64) -> String {
+    let result_ids: BTreeSet<u64> = batches
+        .iter()
+        .flat_map(|batch| batch.iter_ops())
+        .filter_map(|op| match op {
+            Operation::Measure { result_id, .. }
+            | Operation::MeasureLeaked { result_id, .. }
+            | Operation::MeasureReset { result_id, .. } => Some(*result_id),
+            _ => None,
+        })
+        .collect();
+
+    let mut out = String::new();
+    out.push_str("OPENQASM 3.0;\n");
+    out.push_str("include \"stdgates.inc\";\n\n");
+    out.push_str("gate rxy(theta, phi) a { rz(-phi) a; rx(theta) a; rz(phi) a; }\n");
+    out.push_str("gate rzz(theta) a, b { cx a, b; rz(theta) b; cx a, b; }\n\n");
+    let _ = writeln!(out, "qubit[{n_qubits}] q;");
+    for result_id in &result_ids {
+        let _ = writeln!(out, "bit r{result_id};");
+    }
+    for batch in batches {
+        let _ = writeln!(
+            out,
+            "\n// batch at {}ns for {}ns",
+            u64::from(batch.start()),
+            u64::from(batch.duration())
+        );
+        for op in batch.iter_ops() {
+            let _ = match op {
+                Operation::RXYGate {
+                    qubit_id,
+                    theta,
+                    phi,
+                } => writeln!(out, "rxy({theta:?}, {phi:?}) q[{qubit_id}];"),
+                Operation::RZGate { qubit_id, theta } => {
+                    writeln!(out, "rz({theta:?}) q[{qubit_id}];")
+                }
+                Operation::RZZGate {
+                    qubit_id_1,
+                    qubit_id_2,
+                    theta,
+                } => writeln!(out, "rzz({theta:?}) q[{qubit_id_1}], q[{qubit_id_2}];"),
+                Operation::Measure {
+                    qubit_id,
+                    result_id,
+                } => writeln!(out, "r{result_id} = measure q[{qubit_id}];"),
+                Operation::MeasureLeaked {
+                    qubit_id,
+                    result_id,
+                } => writeln!(
+                    out,
+                    "r{result_id} = measure q[{qubit_id}]; // leakage-detecting"
+                ),
+                Operation::MeasureReset {
+                    qubit_id,
+                    result_id,
+                } => writeln!(
+                    out,
+                    "r{result_id} = measure q[{qubit_id}];\nreset q[{qubit_id}];"
+                ),
+                Operation::Reset { qubit_id } => writeln!(out, "reset q[{qubit_id}];"),
+                Operation::Custom { custom_tag, data } => writeln!(
+                    out,
+                    "// custom operation {custom_tag} with {} bytes of data",
+                    data.len()
+                ),
+            };
+        }
+    }
+    out
+}
+
+/// Writes the batches as a Stim circuit, or returns `None` if any of their
+/// angles are not Clifford.
+///
+/// The gates are decomposed as by the Stim simulator, up to global phase.
+pub fn to_stim(batches: &[BatchOperation]) -> Option<String> {
+    const RZ: [Option<&str>; 4] = [None, Some("S"), Some("Z"), Some("S_DAG")];
+    const RZ_DAG: [Option<&str>; 4] = [None, Some("S_DAG"), Some("Z"), Some("S")];
+    const RX: [Option<&str>; 4] = [None, Some("SQRT_X"), Some("X"), Some("SQRT_X_DAG")];
+    const RZZ: [Option<&str>; 4] = [None, Some("SQRT_ZZ"), Some("Z"), Some("SQRT_ZZ_DAG")];
+
+    let mut out = String::new();
+    for batch in batches {
+        for op in batch.iter_ops() {
+            let mut gate = |name: Option<&str>, targets: &str| {
+                if let Some(name) = name {
+                    let _ = writeln!(out, "{name} {targets}");
+                }
+            };
+            match op {
+                Operation::RXYGate {
+                    qubit_id,
+                    theta,
+                    phi,
+                } => {
+                    let theta = quarter_turns(*theta)? as usize;
+                    let phi = quarter_turns(*phi)? as usize;
+                    let target = qubit_id.to_string();
+                    gate(RZ_DAG[phi], &target);
+                    gate(RX[theta], &target);
+                    gate(RZ[phi], &target);
+                }
+                Operation::RZGate { qubit_id, theta } => {
+                    gate(RZ[quarter_turns(*theta)? as usize], &qubit_id.to_string());
+                }
+                Operation::RZZGate {
+                    qubit_id_1,
+                    qubit_id_2,
+                    theta,
+                } => {
+                    let targets = format!("{qubit_id_1} {qubit_id_2}");
+                    gate(RZZ[quarter_turns(*theta)? as usize], &targets);
+                }
+                Operation::Measure {
+                    qubit_id,
+                    result_id,
+                } => {
+                    let _ = writeln!(out, "M {qubit_id} # r{result_id}");
+                }
+                Operation::MeasureLeaked {
+                    qubit_id,
+                    result_id,
+                } => {
+                    let _ = writeln!(out, "M {qubit_id} # r{result_id}, leakage-detecting");
+                }
+                Operation::MeasureReset {
+                    qubit_id,
+                    result_id,
+                } => {
+                    let _ = writeln!(out, "MR {qubit_id} # r{result_id}");
+                }
+                Operation::Reset { qubit_id } => {
+                    let _ = writeln!(out, "R {qubit_id}");
+                }
+                Operation::Custom { custom_tag, data } => {
+                    let _ = writeln!(
+                        out,
+                        "# custom operation {custom_tag} with {} bytes of data",
+                        data.len()
+                    );
+                }
+            }
+        }
+        out.push_str("TICK\n");
+    }
+    Some(out)
+}
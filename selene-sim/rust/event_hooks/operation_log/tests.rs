@@ -0,0 +1,376 @@
+use super::*;
+use crate::test_utils::scratch_dir;
+use std::f64::consts::{FRAC_PI_2, PI};
+
+fn batch(start: u64, duration: u64, ops: Vec<Operation>) -> BatchOperation {
+    BatchOperation::new(ops, start.into(), duration.into())
+}
+
+fn rxy(qubit_id: u64, theta: f64, phi: f64) -> Operation {
+    Operation::RXYGate {
+        qubit_id,
+        theta,
+        phi,
+    }
+}
+
+#[test]
+fn quarter_turns_of_angles() {
+    for (angle, expected) in [
+        (0.0, Some(0)),
+        (-0.0, Some(0)),
+        (FRAC_PI_2, Some(1)),
+        (PI, Some(2)),
+        (-FRAC_PI_2, Some(3)),
+        (2.0 * PI, Some(0)),
+        (5.0 * FRAC_PI_2, Some(1)),
+        (-7.0 * FRAC_PI_2, Some(1)),
+        // Within the threshold of a multiple of pi/2.
+        (PI + 1e-6, Some(2)),
+        (PI - 1e-6, Some(2)),
+        (PI + 1e-3, None),
+        (0.3, None),
+        (f64::NAN, None),
+        (f64::INFINITY, None),
+    ] {
+        assert_eq!(quarter_turns(angle), expected, "{angle}");
+    }
+}
+
+#[test]
+fn openqasm3() {
+    let batches = [
+        batch(
+            5,
+            10,
+            vec![
+                rxy(0, 0.5, 0.25),
+                Operation::RZZGate {
+                    qubit_id_1: 0,
+                    qubit_id_2: 1,
+                    theta: 1.5,
+                },
+                Operation::RZGate {
+                    qubit_id: 1,
+                    theta: -0.1,
+                },
+                Operation::MeasureReset {
+                    qubit_id: 1,
+                    result_id: 4,
+                },
+            ],
+        ),
+        batch(
+            20,
+            0,
+            vec![
+                Operation::Measure {
+                    qubit_id: 0,
+                    result_id: 2,
+                },
+                Operation::MeasureLeaked {
+                    qubit_id: 1,
+                    result_id: 7,
+                },
+                Operation::Reset { qubit_id: 0 },
+                Operation::Custom {
+                    custom_tag: 9,
+                    data: Box::new([1, 2, 3]),
+                },
+            ],
+        ),
+    ];
+    assert_eq!(
+        to_openqasm3(&batches, 2),
+        r#"OPENQASM 3.0;
+include "stdgates.inc";
+
+gate rxy(theta, phi) a { rz(-phi) a; rx(theta) a; rz(phi) a; }
+gate rzz(theta) a, b { cx a, b; rz(theta) b; cx a, b; }
+
+qubit[2] q;
+bit r2;
+bit r4;
+bit r7;
+
+// batch at 5ns for 10ns
+rxy(0.5, 0.25) q[0];
+rzz(1.5) q[0], q[1];
+rz(-0.1) q[1];
+r4 = measure q[1];
+reset q[1];
+
+// batch at 20ns for 0ns
+r2 = measure q[0];
+r7 = measure q[1]; // leakage-detecting
+reset q[0];
+// custom operation 9 with 3 bytes of data
+"#
+    );
+}
+
+#[test]
+fn stim() {
+    let batches = [
+        batch(
+            0,
+            1,
+            vec![
+                rxy(0, PI, 0.0),
+                rxy(1, FRAC_PI_2, FRAC_PI_2),
+                Operation::RZGate {
+                    qubit_id: 0,
+                    theta: -FRAC_PI_2,
+                },
+                Operation::RZZGate {
+                    qubit_id_1: 0,
+                    qubit_id_2: 1,
+                    theta: FRAC_PI_2,
+                },
+                // Whole turns are dropped.
+                Operation::RZGate {
+                    qubit_id: 1,
+                    theta: 2.0 * PI,
+                },
+            ],
+        ),
+        batch(
+            1,
+            1,
+            vec![
+                Operation::Measure {
+                    qubit_id: 0,
+                    result_id: 1,
+                },
+                Operation::MeasureReset {
+                    qubit_id: 1,
+                    result_id: 2,
+                },
+                Operation::MeasureLeaked {
+                    qubit_id: 0,
+                    result_id: 3,
+                },
+                Operation::Reset { qubit_id: 0 },
+            ],
+        ),
+    ];
+    assert_eq!(
+        to_stim(&batches).unwrap(),
+        "X 0\n\
+         S_DAG 1\nSQRT_X 1\nS 1\n\
+         S_DAG 0\n\
+         SQRT_ZZ 0 1\n\
+         TICK\n\
+         M 0 # r1\n\
+         MR 1 # r2\n\
+         M 0 # r3, leakage-detecting\n\
+         R 0\n\
+         TICK\n"
+    );
+    let non_clifford = [batch(0, 1, vec![rxy(0, PI, 0.3)])];
+    assert_eq!(to_stim(&non_clifford), None);
+}
+
+/// A complex number, as (re, im).
+type Complex = (f64, f64);
+
+fn mul(a: Complex, b: Complex) -> Complex {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+fn add(a: Complex, b: Complex) -> Complex {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+type Matrix = [[Complex; 2]; 2];
+
+fn matmul(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut product = [[(0.0, 0.0); 2]; 2];
+    for (i, row) in product.iter_mut().enumerate() {
+        for (j, entry) in row.iter_mut().enumerate() {
+            *entry = add(mul(a[i][0], b[0][j]), mul(a[i][1], b[1][j]));
+        }
+    }
+    product
+}
+
+/// The unitary of a single qubit Stim gate.
+fn stim_gate(name: &str) -> Matrix {
+    let h = 0.5;
+    match name {
+        "X" => [[(0.0, 0.0), (1.0, 0.0)], [(1.0, 0.0), (0.0, 0.0)]],
+        "Z" => [[(1.0, 0.0), (0.0, 0.0)], [(0.0, 0.0), (-1.0, 0.0)]],
+        "S" => [[(1.0, 0.0), (0.0, 0.0)], [(0.0, 0.0), (0.0, 1.0)]],
+        "S_DAG" => [[(1.0, 0.0), (0.0, 0.0)], [(0.0, 0.0), (0.0, -1.0)]],
+        "SQRT_X" => [[(h, h), (h, -h)], [(h, -h), (h, h)]],
+        "SQRT_X_DAG" => [[(h, -h), (h, h)], [(h, h), (h, -h)]],
+        name => panic!("unexpected gate {name}"),
+    }
+}
+
+/// The unitary of Selene's RXY gate.
+fn rxy_unitary(theta: f64, phi: f64) -> Matrix {
+    let (c, s) = ((theta / 2.0).cos(), (theta / 2.0).sin());
+    [
+        [(c, 0.0), mul((0.0, -s), (phi.cos(), -phi.sin()))],
+        [mul((0.0, -s), (phi.cos(), phi.sin())), (c, 0.0)],
+    ]
+}
+
+/// Whether two unitaries are equal up to global phase.
+fn equal_up_to_phase(a: &Matrix, b: &Matrix) -> bool {
+    // The inner product of unitaries equal up to phase has modulus 2.
+    let mut inner = (0.0, 0.0);
+    for i in 0..2 {
+        for j in 0..2 {
+            inner = add(inner, mul((a[i][j].0, -a[i][j].1), b[i][j]));
+        }
+    }
+    ((inner.0 * inner.0 + inner.1 * inner.1).sqrt() - 2.0).abs() < 1e-9
+}
+
+#[test]
+fn stim_decomposes_rxy_exactly() {
+    for theta in 0..4 {
+        for phi in 0..4 {
+            let (theta, phi) = (theta as f64 * FRAC_PI_2, phi as f64 * FRAC_PI_2);
+            let circuit = to_stim(&[batch(0, 1, vec![rxy(0, theta, phi)])]).unwrap();
+            let identity = [[(1.0, 0.0), (0.0, 0.0)], [(0.0, 0.0), (1.0, 0.0)]];
+            let unitary = circuit
+                .lines()
+                .filter(|line| *line != "TICK")
+                .map(|line| stim_gate(line.strip_suffix(" 0").unwrap()))
+                .fold(identity, |unitary, gate| matmul(&gate, &unitary));
+            assert!(
+                equal_up_to_phase(&unitary, &rxy_unitary(theta, phi)),
+                "rxy({theta}, {phi}) as {circuit:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn stim_decomposes_rzz_exactly() {
+    // RZZ is diagonal, with a relative phase of e^(i theta) on the states of
+    // odd parity. SQRT_ZZ applies i, and Z on both qubits applies -1.
+    for (quarters, expected) in [(0, None), (1, Some("SQRT_ZZ")), (2, Some("Z"))] {
+        let theta = quarters as f64 * FRAC_PI_2;
+        let rzz = Operation::RZZGate {
+            qubit_id_1: 3,
+            qubit_id_2: 4,
+            theta,
+        };
+        let circuit = to_stim(&[batch(0, 1, vec![rzz])]).unwrap();
+        let expected = match expected {
+            Some(gate) => format!("{gate} 3 4\nTICK\n"),
+            None => "TICK\n".to_string(),
+        };
+        assert_eq!(circuit, expected);
+    }
+    let rzz = Operation::RZZGate {
+        qubit_id_1: 3,
+        qubit_id_2: 4,
+        theta: -FRAC_PI_2,
+    };
+    assert_eq!(
+        to_stim(&[batch(0, 1, vec![rzz])]).unwrap(),
+        "SQRT_ZZ_DAG 3 4\nTICK\n"
+    );
+}
+
+/// Logs a shot with the given batches, returning the directory written to and
+/// the files in it.
+fn log_shot(
+    name: &str,
+    format: OperationLogFormat,
+    batches: &[BatchOperation],
+) -> (PathBuf, Vec<String>) {
+    let dir = scratch_dir(name);
+    let mut log = OperationLog::new(format, dir.clone(), 2);
+    log.on_shot_start(3).unwrap();
+    for batch in batches {
+        log.on_runtime_batch(batch).unwrap();
+    }
+    log.on_shot_end().unwrap();
+    let mut files: Vec<String> = std::fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    (dir, files)
+}
+
+#[test]
+fn stim_falls_back_to_openqasm3() {
+    let clifford = [batch(0, 1, vec![rxy(0, PI, 0.0)])];
+    let (_, files) = log_shot("operation_log_stim", OperationLogFormat::Stim, &clifford);
+    assert_eq!(files, ["shot_3.stim"]);
+    let non_clifford = [batch(0, 1, vec![rxy(0, 0.3, 0.0)])];
+    let (dir, files) = log_shot(
+        "operation_log_fallback",
+        OperationLogFormat::Stim,
+        &non_clifford,
+    );
+    assert_eq!(files, ["shot_3.qasm"]);
+    let contents = std::fs::read_to_string(dir.join("shot_3.qasm")).unwrap();
+    assert_eq!(contents, to_openqasm3(&non_clifford, 2));
+}
+
+#[test]
+fn json_is_lossless() {
+    let angles = [
+        f64::NAN,
+        f64::from_bits(0x7ff0_0000_dead_beef),
+        f64::INFINITY,
+        -0.0,
+        f64::MIN_POSITIVE / 3.0,
+        0.1 + 0.2,
+    ];
+    let batches: Vec<BatchOperation> = angles
+        .iter()
+        .enumerate()
+        .map(|(i, &angle)| {
+            batch(
+                i as u64,
+                1,
+                vec![
+                    rxy(0, angle, -angle),
+                    Operation::RZGate {
+                        qubit_id: 1,
+                        theta: angle,
+                    },
+                    Operation::RZZGate {
+                        qubit_id_1: 0,
+                        qubit_id_2: 1,
+                        theta: angle,
+                    },
+                ],
+            )
+        })
+        // Empty batches are not logged.
+        .chain([batch(10, 0, vec![])])
+        .collect();
+    let (dir, files) = log_shot("operation_log_json", OperationLogFormat::Json, &batches);
+    assert_eq!(files, ["shot_3.json"]);
+
+    let read = crate::replay::read_operation_log(dir.join("shot_3.json")).unwrap();
+    assert_eq!(read.len(), angles.len());
+    let bits = |batches: &[BatchOperation]| -> Vec<u64> {
+        batches
+            .iter()
+            .flat_map(|batch| batch.iter_ops())
+            .flat_map(|op| match op {
+                Operation::RXYGate { theta, phi, .. } => vec![theta.to_bits(), phi.to_bits()],
+                Operation::RZGate { theta, .. } | Operation::RZZGate { theta, .. } => {
+                    vec![theta.to_bits()]
+                }
+                op => panic!("unexpected operation {op:?}"),
+            })
+            .collect()
+    };
+    assert_eq!(bits(&read), bits(&batches[..angles.len()]));
+    for (read, batch) in read.iter().zip(&batches) {
+        assert_eq!(read.start(), batch.start());
+        assert_eq!(read.duration(), batch.duration());
+    }
+}
@@ -0,0 +1,84 @@
+//! Event hooks loaded from plugins, as configured in
+//! [EventHookConfig::plugins](crate::selene_instance::configuration::EventHookConfig::plugins).
+//!
+//! Plugins are told of each event, and their metrics are written to the result
+//! stream at the end of each shot under the name of the plugin, e.g.
+//! `METRICS:INT:<name>:<tag>`.
+use anyhow::Result;
+use selene_core::encoder::OutputStream;
+use selene_core::event_hook::{EventHookInterface, Operation, ResultValue};
+use selene_core::runtime::{self, BatchOperation};
+use selene_core::utils::MetricValue;
+
+use crate::event_hooks::EventHook;
+use crate::selene_instance::configuration::PluginConfig;
+
+#[cfg(test)]
+mod tests;
+
+pub struct PluginEventHook {
+    name: String,
+    seed: u64,
+    hook: selene_core::event_hook::EventHook,
+}
+
+impl PluginEventHook {
+    pub fn load(config: &PluginConfig, n_qubits: u64) -> Result<Self> {
+        let hook = selene_core::event_hook::EventHook::load_from_file(
+            &config.path(),
+            n_qubits,
+            config.args.as_ref(),
+        )?;
+        Ok(Self {
+            name: config.name.clone(),
+            seed: config.seed,
+            hook,
+        })
+    }
+}
+
+impl EventHook for PluginEventHook {
+    fn on_user_call(&mut self, operation: &Operation) -> Result<()> {
+        self.hook.on_user_call(operation)
+    }
+    fn on_runtime_batch(&mut self, batch: &BatchOperation) -> Result<()> {
+        self.hook.on_runtime_batch(batch)
+    }
+    fn on_error_model_op(&mut self, position: u64, operation: &runtime::Operation) -> Result<()> {
+        self.hook.on_error_model_op(position, operation)
+    }
+    fn on_result(&mut self, result_id: u64, value: ResultValue) -> Result<()> {
+        self.hook.on_result(result_id, value)
+    }
+    fn write(&mut self, time_cursor: u64, encoder: &mut OutputStream) -> Result<()> {
+        for nth_metric in 0u8..255u8 {
+            let Some((tag, value)) = self.hook.get_metric(nth_metric)? else {
+                break;
+            };
+            let type_str = match value {
+                MetricValue::Bool(_) => "BOOL",
+                MetricValue::I64(_) | MetricValue::U64(_) => "INT",
+                MetricValue::F64(_) => "FLOAT",
+            };
+            encoder.begin_message(time_cursor)?;
+            encoder.write(format!("METRICS:{type_str}:{}:{tag}", self.name).as_str())?;
+            match value {
+                MetricValue::Bool(v) => encoder.write(v)?,
+                MetricValue::I64(v) => encoder.write(v)?,
+                MetricValue::U64(v) => encoder.write(v)?,
+                MetricValue::F64(v) => encoder.write(v)?,
+            };
+            encoder.end_message()?;
+        }
+        Ok(())
+    }
+    fn on_shot_start(&mut self, shot_id: u64) -> Result<()> {
+        self.hook.shot_start(shot_id, self.seed + shot_id)
+    }
+    fn on_shot_end(&mut self) -> Result<()> {
+        self.hook.shot_end()
+    }
+    fn exit(&mut self) -> Result<()> {
+        self.hook.exit()
+    }
+}
@@ -0,0 +1,248 @@
+use super::*;
+use crate::selene_instance::SeleneInstance;
+use crate::selene_instance::configuration::ConfigurationBuilder;
+use crate::selene_instance::program::Shot;
+use crate::test_utils::{SharedBuffer, read_shots, run_program, test_config};
+use selene_core::decoder::{self, MessageKind};
+use selene_core::event_hook::EventHookInterfaceFactory;
+use selene_core::registry::register_event_hook;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::{Arc, Mutex, Once};
+
+/// Builds the example event hook in `selene-core/examples/event_hook`,
+/// returning the path to its library.
+fn example_hook() -> PathBuf {
+    static BUILD: Once = Once::new();
+    let manifest = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../selene-core/examples/event_hook/Cargo.toml");
+    // Shared between runs, so that the example is only rebuilt on changes.
+    let target_dir = std::env::temp_dir().join("selene-example-event-hook");
+    BUILD.call_once(|| {
+        let status = Command::new(env!("CARGO"))
+            .args(["build", "--quiet"])
+            .arg("--manifest-path")
+            .arg(&manifest)
+            .arg("--target-dir")
+            .arg(&target_dir)
+            .status()
+            .unwrap();
+        assert!(status.success(), "failed to build the example event hook");
+    });
+    target_dir.join("debug").join(format!(
+        "{}selene_example_event_hook{}",
+        std::env::consts::DLL_PREFIX,
+        std::env::consts::DLL_SUFFIX
+    ))
+}
+
+/// Two gates on each of two qubits, one of them two-qubit, and a
+/// measurement of each.
+fn program(shot: &mut Shot) -> anyhow::Result<()> {
+    let q0 = shot.qalloc()?.unwrap();
+    let q1 = shot.qalloc()?.unwrap();
+    shot.rxy(&q0, 0.5, 0.0)?;
+    shot.rz(&q1, 0.25)?;
+    shot.rzz(&q0, &q1, 0.125)?;
+    let first = shot.lazy_measure(&q0)?;
+    let second = shot.lazy_measure(&q1)?;
+    shot.read(&first)?;
+    shot.read(&second)?;
+    Ok(())
+}
+
+fn metrics(shot: &decoder::Shot) -> Vec<(String, MetricValue)> {
+    shot.messages
+        .iter()
+        .filter_map(|message| match message.kind() {
+            Ok(MessageKind::Metric { value, .. }) => Some((message.tag.clone(), value)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn exits(shot: &decoder::Shot) -> Vec<String> {
+    shot.messages
+        .iter()
+        .filter_map(|message| match message.kind() {
+            Ok(MessageKind::Exit { message, .. }) => Some(message.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn with_example_hook(name: &str, args: &[&str]) -> ConfigurationBuilder {
+    test_config(name)
+        // Each single-qubit gate is followed by an error.
+        .error_model(
+            PluginConfig::builtin("depolarizing")
+                .with_seed(2)
+                .with_args(["--p-1q=1", "--p-2q=0", "--p-meas=0", "--p-init=0"]),
+        )
+        .event_hook_plugin(PluginConfig::new("counter", example_hook()).with_args(args.to_vec()))
+        .shots(2, 0, 1)
+}
+
+#[test]
+fn example_hook_sees_every_event() {
+    let config = with_example_hook("example_event_hook", &[]);
+    let shots = read_shots(&run_program(config, program));
+    assert_eq!(shots.len(), 2);
+    for shot in &shots {
+        assert!(exits(shot).is_empty());
+        let int = |name: &str, value: u64| {
+            (
+                format!("METRICS:INT:counter:{name}"),
+                MetricValue::U64(value),
+            )
+        };
+        // The program's calls are two allocations, three gates and two
+        // measurements and reads, and the runtime emits each operation in a
+        // batch of its own.
+        assert_eq!(
+            metrics(shot),
+            vec![
+                int("user_calls", 9),
+                int("two_qubit_gates", 1),
+                int("runtime_batches", 5),
+                int("runtime_operations", 5),
+                int("error_model_operations", 2),
+                int("results", 2),
+            ]
+        );
+    }
+}
+
+#[test]
+fn example_hook_fails_shots() {
+    let config = with_example_hook("example_event_hook_limit", &["--max-two-qubit-gates=0"]);
+    let shots = read_shots(&run_program(config, program));
+    let [message] = exits(&shots[0]).try_into().unwrap();
+    assert!(
+        message.ends_with("RZZ(0, 1) exceeds the limit of 0 two-qubit gates"),
+        "{message}"
+    );
+}
+
+#[test]
+fn example_hook_arguments_are_checked() {
+    let config = with_example_hook("example_event_hook_arguments", &["--colour=blue"])
+        .build()
+        .unwrap();
+    let error = SeleneInstance::with_writer(config, Box::new(SharedBuffer::default()))
+        .err()
+        .unwrap();
+    assert!(
+        format!("{error:#}").contains("Unknown argument '--colour=blue'"),
+        "{error:#}"
+    );
+}
+
+type Events = Arc<Mutex<Vec<String>>>;
+
+/// Records the events it is told of, and reports a metric of each type.
+struct RecordingHook {
+    events: Events,
+}
+
+impl EventHookInterface for RecordingHook {
+    fn shot_start(&mut self, shot_id: u64, seed: u64) -> Result<()> {
+        self.record(format!("shot_start {shot_id} {seed}"))
+    }
+    fn shot_end(&mut self) -> Result<()> {
+        self.record("shot_end".to_string())
+    }
+    fn on_user_call(&mut self, operation: &Operation) -> Result<()> {
+        self.record(format!("user {operation:?}"))
+    }
+    fn on_runtime_batch(&mut self, batch: &BatchOperation) -> Result<()> {
+        self.record(format!("batch of {}", batch.len()))
+    }
+    fn on_result(&mut self, result_id: u64, value: ResultValue) -> Result<()> {
+        self.record(format!("result {result_id} {value:?}"))
+    }
+    fn get_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        Ok(match nth_metric {
+            0 => Some(("flag".to_string(), MetricValue::Bool(true))),
+            1 => Some(("offset".to_string(), MetricValue::I64(-3))),
+            2 => Some(("ratio".to_string(), MetricValue::F64(0.5))),
+            _ => None,
+        })
+    }
+}
+
+impl RecordingHook {
+    fn record(&mut self, event: String) -> Result<()> {
+        self.events.lock().unwrap().push(event);
+        Ok(())
+    }
+}
+
+struct RecordingHookFactory {
+    events: Events,
+}
+
+impl EventHookInterfaceFactory for RecordingHookFactory {
+    type Interface = RecordingHook;
+
+    fn init(
+        self: Arc<Self>,
+        _n_qubits: u64,
+        _args: &[impl AsRef<str>],
+    ) -> Result<Box<Self::Interface>> {
+        Ok(Box::new(RecordingHook {
+            events: self.events.clone(),
+        }))
+    }
+}
+
+#[test]
+fn hooks_are_seeded_per_shot_and_report_typed_metrics() {
+    let events = Events::default();
+    register_event_hook(
+        "recording_hook",
+        Arc::new(RecordingHookFactory {
+            events: events.clone(),
+        }),
+    );
+    let config = test_config("recording_event_hook")
+        .event_hook_plugin(PluginConfig::builtin("recording_hook").with_seed(100))
+        .shots(2, 5, 3);
+    let shots = read_shots(&run_program(config, |shot: &mut Shot| {
+        let q = shot.qalloc()?.unwrap();
+        shot.measure(&q)?;
+        Ok(())
+    }));
+    let expected_shot = |shot_id: u64| {
+        vec![
+            format!("shot_start {shot_id} {}", 100 + shot_id),
+            "user QAlloc(0)".to_string(),
+            "user MeasureRequest(0)".to_string(),
+            "batch of 1".to_string(),
+            "result 0 Bool(true)".to_string(),
+            "user FutureRead(0)".to_string(),
+            "shot_end".to_string(),
+        ]
+    };
+    let expected: Vec<String> = [5, 8].into_iter().flat_map(expected_shot).collect();
+    assert_eq!(*events.lock().unwrap(), expected);
+    for shot in &shots {
+        assert_eq!(
+            metrics(shot),
+            vec![
+                (
+                    "METRICS:BOOL:recording_hook:flag".to_string(),
+                    MetricValue::Bool(true)
+                ),
+                (
+                    "METRICS:INT:recording_hook:offset".to_string(),
+                    MetricValue::I64(-3)
+                ),
+                (
+                    "METRICS:FLOAT:recording_hook:ratio".to_string(),
+                    MetricValue::F64(0.5)
+                ),
+            ]
+        );
+    }
+}
@@ -1,6 +1,18 @@
 use super::selene_instance::SeleneInstance;
-use crate::selene_instance::configuration::Configuration;
+use crate::selene_instance::configuration::{
+    Configuration, ConfigurationBuilder, PluginConfig, PluginRole,
+};
+use crate::selene_instance::limits::ShotLimits;
+use crate::selene_instance::memory_sink::MemorySinkReader;
+use crate::selene_instance::output_format::OutputFormat;
+use crate::selene_instance::recovery::ErrorPolicy;
 use anyhow::Result;
+use selene_core::error::ErrorKind;
+use selene_core::utils::{last_error_message_ptr, set_last_error_message};
+use std::io::{Read, Write};
+
+#[cfg(test)]
+mod tests;
 
 #[repr(C)]
 pub struct VoidResult {
@@ -144,39 +156,44 @@ impl std::fmt::Display for WrappedString {
     }
 }
 
+/// Reports an error raised outside of a running instance, before the result stream
+/// is available or after it has been closed.
+fn report_startup_error(message: impl AsRef<str>) {
+    eprintln!("{}", message.as_ref());
+    set_last_error_message(message);
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn selene_load_config(
     instance: *mut *mut SeleneInstance,
     config_file: *const std::ffi::c_char,
 ) -> VoidResult {
-    let config_file = match unsafe { std::ffi::CStr::from_ptr(config_file) }.to_str() {
+    if instance.is_null() {
+        report_startup_error("Error: selene_load_config called with a null instance pointer");
+        return VoidResult::err(ErrorKind::NullInstance.code());
+    }
+    let config_file = match str_arg(config_file, "config file path") {
         Ok(config_file) => config_file,
         Err(e) => {
-            eprintln!("Error parsing config file path: {e}");
-            return VoidResult::err(1);
-        }
-    };
-    let config_file_handle = match std::fs::File::open(config_file) {
-        Ok(file) => file,
-        Err(e) => {
-            eprintln!("Error opening config file: {e}");
-            return VoidResult::err(1);
+            report_startup_error(format!("Error parsing config file path: {e:#}"));
+            return VoidResult::err(ErrorKind::InvalidArgument.code());
         }
     };
-    let config: Configuration = match serde_yml::from_reader(config_file_handle) {
+    let config = match Configuration::from_file(config_file) {
         Ok(config) => config,
         Err(e) => {
-            eprintln!("Error parsing config file: {e}");
-            return VoidResult::err(1);
+            report_startup_error(format!("{e:#}"));
+            return VoidResult::err(ErrorKind::InvalidArgument.code());
         }
     };
 
     let new_instance = match SeleneInstance::new(config) {
         Ok(instance) => Box::into_raw(Box::new(instance)),
-        Err(_) => {
+        Err(e) => {
             // The error has already been printed to stderr, before the result stream
             // was written to.
-            return VoidResult::err(1);
+            set_last_error_message(format!("{e:#}"));
+            return VoidResult::err(ErrorKind::of(&e).code());
         }
     };
     unsafe {
@@ -185,20 +202,473 @@ pub unsafe extern "C" fn selene_load_config(
     VoidResult::ok()
 }
 
+/// Plugin roles, identifying the plugin that a `selene_config_*_plugin*` call refers to.
+pub const SELENE_PLUGIN_SIMULATOR: u32 = 0;
+pub const SELENE_PLUGIN_ERROR_MODEL: u32 = 1;
+pub const SELENE_PLUGIN_RUNTIME: u32 = 2;
+
+fn plugin_role(role: u32) -> Result<PluginRole> {
+    match role {
+        SELENE_PLUGIN_SIMULATOR => Ok(PluginRole::Simulator),
+        SELENE_PLUGIN_ERROR_MODEL => Ok(PluginRole::ErrorModel),
+        SELENE_PLUGIN_RUNTIME => Ok(PluginRole::Runtime),
+        other => Err(ErrorKind::InvalidArgument.error(format!("Unknown plugin role {other}"))),
+    }
+}
+
+fn str_arg<'a>(value: *const std::ffi::c_char, name: &str) -> Result<&'a str> {
+    if value.is_null() {
+        return Err(ErrorKind::InvalidArgument.error(format!("{name} is null")));
+    }
+    unsafe { std::ffi::CStr::from_ptr(value) }
+        .to_str()
+        .map_err(|e| ErrorKind::InvalidArgument.error(format!("{name} is not valid UTF-8: {e}")))
+}
+
+fn with_config<F>(config: *mut ConfigurationBuilder, f: F) -> VoidResult
+where
+    F: FnOnce(&mut ConfigurationBuilder) -> Result<()>,
+{
+    if config.is_null() {
+        return VoidResult::err(null_instance_error());
+    }
+    match f(unsafe { &mut *config }) {
+        Ok(()) => VoidResult::ok(),
+        Err(e) => {
+            set_last_error_message(format!("{e:#}"));
+            VoidResult::err(ErrorKind::of(&e).code())
+        }
+    }
+}
+
+/// Applies a builder method to the configuration.
+fn update_config<F>(config: *mut ConfigurationBuilder, f: F) -> VoidResult
+where
+    F: FnOnce(ConfigurationBuilder) -> ConfigurationBuilder,
+{
+    with_config(config, |config| {
+        *config = f(std::mem::take(config));
+        Ok(())
+    })
+}
+
+/// Creates an empty configuration, to be populated with the `selene_config_set_*`
+/// functions and passed to `selene_instance_from_config`. It must be released
+/// with `selene_config_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn selene_config_new() -> *mut ConfigurationBuilder {
+    Box::into_raw(Box::new(Configuration::builder()))
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_config_free(config: *mut ConfigurationBuilder) {
+    if !config.is_null() {
+        drop(unsafe { Box::from_raw(config) });
+    }
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_config_set_n_qubits(
+    config: *mut ConfigurationBuilder,
+    n_qubits: u64,
+) -> VoidResult {
+    update_config(config, |config| config.n_qubits(n_qubits))
+}
+
+/// Sets the output stream, in any of the forms accepted in a config file. It is
+/// not used if an output writer is passed to `selene_instance_from_config`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_config_set_output_stream(
+    config: *mut ConfigurationBuilder,
+    output_stream: *const std::ffi::c_char,
+) -> VoidResult {
+    with_config(config, |config| {
+        let output_stream = str_arg(output_stream, "output_stream")?;
+        *config = std::mem::take(config).output_stream(output_stream);
+        Ok(())
+    })
+}
+
+/// Sets the format of the result stream: "binary", "json_lines" or "arrow_ipc".
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_config_set_output_format(
+    config: *mut ConfigurationBuilder,
+    format: *const std::ffi::c_char,
+) -> VoidResult {
+    with_config(config, |config| {
+        let format = str_arg(format, "format")?;
+        let format: OutputFormat = serde_yml::from_str(format).map_err(|_| {
+            ErrorKind::InvalidArgument.error(format!("Unknown output format '{format}'"))
+        })?;
+        *config = std::mem::take(config).output_format(format);
+        Ok(())
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_config_set_artifact_dir(
+    config: *mut ConfigurationBuilder,
+    artifact_dir: *const std::ffi::c_char,
+) -> VoidResult {
+    with_config(config, |config| {
+        let artifact_dir = str_arg(artifact_dir, "artifact_dir")?;
+        *config = std::mem::take(config).artifact_dir(artifact_dir);
+        Ok(())
+    })
+}
+
+/// Sets the plugin with the given role (see `SELENE_PLUGIN_*`) to the plugin
+/// loaded from `file`, with no arguments and a random seed. If `file` is null,
+/// the plugin linked into selene under `name` is used instead.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_config_set_plugin(
+    config: *mut ConfigurationBuilder,
+    role: u32,
+    name: *const std::ffi::c_char,
+    file: *const std::ffi::c_char,
+) -> VoidResult {
+    with_config(config, |config| {
+        let name = str_arg(name, "name")?;
+        let plugin = if file.is_null() {
+            PluginConfig::builtin(name)
+        } else {
+            PluginConfig::new(name, str_arg(file, "file")?)
+        };
+        *config.plugin_mut(plugin_role(role)?) = Some(plugin);
+        Ok(())
+    })
+}
+
+/// Appends an argument to those passed to the plugin with the given role, which
+/// must have been set with `selene_config_set_plugin`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_config_add_plugin_arg(
+    config: *mut ConfigurationBuilder,
+    role: u32,
+    arg: *const std::ffi::c_char,
+) -> VoidResult {
+    with_config(config, |config| {
+        let arg = str_arg(arg, "arg")?;
+        configured_plugin(config, role)?.args.push(arg.to_string());
+        Ok(())
+    })
+}
+
+/// Sets whether the plugin with the given role, which must have been set with
+/// `selene_config_set_plugin`, is hosted in a separate process. Only
+/// simulators and error models loaded from files can be hosted out of process.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_config_set_plugin_isolated(
+    config: *mut ConfigurationBuilder,
+    role: u32,
+    isolated: bool,
+) -> VoidResult {
+    with_config(config, |config| {
+        configured_plugin(config, role)?.isolated = isolated;
+        Ok(())
+    })
+}
+
+/// Sets the seed of the plugin with the given role, which must have been set
+/// with `selene_config_set_plugin`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_config_set_plugin_seed(
+    config: *mut ConfigurationBuilder,
+    role: u32,
+    seed: u64,
+) -> VoidResult {
+    with_config(config, |config| {
+        configured_plugin(config, role)?.seed = seed;
+        Ok(())
+    })
+}
+
+fn configured_plugin(config: &mut ConfigurationBuilder, role: u32) -> Result<&mut PluginConfig> {
+    let role = plugin_role(role)?;
+    config
+        .plugin_mut(role)
+        .as_mut()
+        .ok_or_else(|| ErrorKind::InvalidArgument.error(format!("No {role:?} plugin has been set")))
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_config_set_shots(
+    config: *mut ConfigurationBuilder,
+    count: u64,
+    offset: u64,
+    increment: u64,
+) -> VoidResult {
+    update_config(config, |config| config.shots(count, offset, increment))
+}
+
+/// Sets the limits on the resources used by each shot. A limit of zero does not
+/// apply. A shot that exceeds a limit ends with an error of kind
+/// `SeleneErrorKind_ShotLimitExceeded`, and the run carries on with the next
+/// shot.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_config_set_shot_limits(
+    config: *mut ConfigurationBuilder,
+    max_operations: u64,
+    max_batches: u64,
+    max_wall_time: f64,
+    max_qubits: u64,
+) -> VoidResult {
+    let limit = |value: u64| (value > 0).then_some(value);
+    update_config(config, |config| {
+        config.limits(ShotLimits {
+            max_operations: limit(max_operations),
+            max_batches: limit(max_batches),
+            max_wall_time: (max_wall_time > 0.0).then_some(max_wall_time),
+            max_qubits: limit(max_qubits),
+        })
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_config_set_workers(
+    config: *mut ConfigurationBuilder,
+    workers: u64,
+) -> VoidResult {
+    update_config(config, |config| config.workers(workers))
+}
+
+/// Sets what to do when an error is raised during a shot: "abort" to end the
+/// run, or "skip_shot" to record the failure of the shot and carry on with the
+/// next one.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_config_set_error_policy(
+    config: *mut ConfigurationBuilder,
+    policy: *const std::ffi::c_char,
+) -> VoidResult {
+    with_config(config, |config| {
+        let policy = str_arg(policy, "policy")?;
+        let policy: ErrorPolicy = serde_yml::from_str(policy).map_err(|_| {
+            ErrorKind::InvalidArgument.error(format!("Unknown error policy '{policy}'"))
+        })?;
+        *config = std::mem::take(config).on_error(policy);
+        Ok(())
+    })
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_config_set_event_hooks(
+    config: *mut ConfigurationBuilder,
+    provide_instruction_log: bool,
+    provide_metrics: bool,
+) -> VoidResult {
+    with_config(config, |config| {
+        let event_hooks = config.event_hooks_mut();
+        event_hooks.provide_instruction_log = provide_instruction_log;
+        event_hooks.provide_metrics = provide_metrics;
+        Ok(())
+    })
+}
+
+/// Writes the operations emitted by the runtime in each shot to the artifact
+/// directory, in the given `format`: "openqasm3", "stim" or "json". If
+/// `format` is null, they are not written.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_config_set_operation_log(
+    config: *mut ConfigurationBuilder,
+    format: *const std::ffi::c_char,
+) -> VoidResult {
+    with_config(config, |config| {
+        let format = if format.is_null() {
+            None
+        } else {
+            let format = str_arg(format, "format")?;
+            Some(serde_yml::from_str(format).map_err(|_| {
+                ErrorKind::InvalidArgument.error(format!("Unknown operation log format '{format}'"))
+            })?)
+        };
+        config.event_hooks_mut().operation_log = format;
+        Ok(())
+    })
+}
+
+/// Adds an event hook loaded from `file`, with no arguments and a random seed,
+/// after any already added. If `file` is null, the event hook linked into
+/// selene under `name` is used instead. Its metrics are written under `name`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_config_add_event_hook_plugin(
+    config: *mut ConfigurationBuilder,
+    name: *const std::ffi::c_char,
+    file: *const std::ffi::c_char,
+) -> VoidResult {
+    with_config(config, |config| {
+        let name = str_arg(name, "name")?;
+        let plugin = if file.is_null() {
+            PluginConfig::builtin(name)
+        } else {
+            PluginConfig::new(name, str_arg(file, "file")?)
+        };
+        config.event_hooks_mut().plugins.push(plugin);
+        Ok(())
+    })
+}
+
+/// Appends an argument to those passed to the event hook most recently added
+/// with `selene_config_add_event_hook_plugin`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_config_add_event_hook_plugin_arg(
+    config: *mut ConfigurationBuilder,
+    arg: *const std::ffi::c_char,
+) -> VoidResult {
+    with_config(config, |config| {
+        let arg = str_arg(arg, "arg")?;
+        let plugin = config.event_hooks_mut().plugins.last_mut().ok_or_else(|| {
+            ErrorKind::InvalidArgument.error("No event hook plugin has been added")
+        })?;
+        plugin.args.push(arg.to_string());
+        Ok(())
+    })
+}
+
+/// A writer supplied by the host, to which the result stream is written in place
+/// of the configured output stream.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct OutputWriter {
+    /// Passed to each of the functions below.
+    pub user_data: *mut std::ffi::c_void,
+    /// Writes up to `length` bytes from `data`, returning the number of bytes
+    /// written, or a negative value on failure.
+    pub write: unsafe extern "C" fn(*mut std::ffi::c_void, *const u8, u64) -> i64,
+    /// Optionally flushes any data buffered by the host, returning zero on success.
+    pub flush: Option<unsafe extern "C" fn(*mut std::ffi::c_void) -> i32>,
+    /// Optionally called once, when selene no longer needs the writer.
+    pub close: Option<unsafe extern "C" fn(*mut std::ffi::c_void)>,
+}
+
+struct HostWriter(OutputWriter);
+
+impl Write for HostWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = unsafe { (self.0.write)(self.0.user_data, buf.as_ptr(), buf.len() as u64) };
+        usize::try_from(written)
+            .map_err(|_| std::io::Error::other("The host's output writer failed"))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self.0.flush {
+            Some(flush) if unsafe { flush(self.0.user_data) } != 0 => Err(std::io::Error::other(
+                "The host's output writer failed to flush",
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Drop for HostWriter {
+    fn drop(&mut self) {
+        if let Some(close) = self.0.close {
+            unsafe { close(self.0.user_data) };
+        }
+    }
+}
+
+/// Creates an instance from a configuration built with the `selene_config_*`
+/// functions. The configuration is not consumed, and may be reused.
+///
+/// If `writer` is not null, the result stream is written to it rather than to
+/// the configured output stream, and its `close` function is called once the
+/// instance is exited, or immediately if creating the instance fails.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_instance_from_config(
+    instance: *mut *mut SeleneInstance,
+    config: *const ConfigurationBuilder,
+    writer: *const OutputWriter,
+) -> VoidResult {
+    let writer = unsafe { writer.as_ref() }.map(|writer| HostWriter(*writer));
+    if instance.is_null() || config.is_null() {
+        return VoidResult::err(null_instance_error());
+    }
+    let config = unsafe { &*config }.clone();
+    let result = config.build().and_then(|config| match writer {
+        Some(writer) => SeleneInstance::with_writer(config, Box::new(writer)),
+        None => SeleneInstance::new(config),
+    });
+    match result {
+        Ok(new_instance) => {
+            unsafe { *instance = Box::into_raw(Box::new(new_instance)) };
+            VoidResult::ok()
+        }
+        Err(e) => {
+            set_last_error_message(format!("{e:#}"));
+            VoidResult::err(ErrorKind::of(&e).code())
+        }
+    }
+}
+
+/// Reads up to `capacity` bytes of output from the named memory sink into `buffer`,
+/// blocking until data is available. A value of zero indicates that the emulator has
+/// finished writing and all output has been consumed.
+///
+/// `name` must be a null terminated UTF-8 string, and `buffer` must point to at least
+/// `capacity` writable bytes. Otherwise an invalid argument error is returned.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_memory_sink_read(
+    name: *const std::ffi::c_char,
+    buffer: *mut u8,
+    capacity: u64,
+) -> U64Result {
+    if name.is_null() || (buffer.is_null() && capacity > 0) {
+        set_last_error_message("selene_memory_sink_read called with a null name or buffer");
+        return U64Result::err(ErrorKind::InvalidArgument.code());
+    }
+    let name = match unsafe { std::ffi::CStr::from_ptr(name) }.to_str() {
+        Ok(name) => name,
+        Err(e) => {
+            set_last_error_message(format!("Error parsing memory sink name: {e}"));
+            return U64Result::err(ErrorKind::InvalidArgument.code());
+        }
+    };
+    if capacity == 0 {
+        return U64Result::ok(0);
+    }
+    let buffer = unsafe { std::slice::from_raw_parts_mut(buffer, capacity as usize) };
+    match MemorySinkReader::open(name).read(buffer) {
+        Ok(n) => U64Result::ok(n as u64),
+        Err(e) => {
+            set_last_error_message(format!("Error reading from memory sink '{name}': {e}"));
+            U64Result::err(ErrorKind::Output.code())
+        }
+    }
+}
+
+/// Returns a message describing the latest error returned by a selene call in the
+/// calling thread, as a null terminated string, or null if no call has failed. The
+/// error code returned by the call identifies the category of the error. The string
+/// remains valid until the next failing call on the same thread.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_last_error_message() -> *const std::ffi::c_char {
+    last_error_message_ptr()
+}
+
+/// Reports an error raised by a call on the instance through the result stream,
+/// returning the code of its kind.
+fn report_error(instance: &mut SeleneInstance, e: anyhow::Error) -> u32 {
+    let code = instance.print_error(&e);
+    set_last_error_message(format!("{e:#}"));
+    code
+}
+
+fn null_instance_error() -> u32 {
+    set_last_error_message("selene called with a null instance");
+    ErrorKind::NullInstance.code()
+}
+
 fn with_instance_void<F>(instance: *mut SeleneInstance, f: F) -> VoidResult
 where
     F: FnOnce(&mut SeleneInstance) -> Result<()>,
 {
     if instance.is_null() {
-        return VoidResult::err(100000);
+        return VoidResult::err(null_instance_error());
     }
     let instance = unsafe { &mut *instance };
-    if let Err(e) = f(instance) {
-        let code = 100001;
-        instance.fallible_print_panic(format!("{e:#}").as_str(), code);
-        VoidResult::err(code)
-    } else {
-        VoidResult::ok()
+    match f(instance) {
+        Ok(()) => VoidResult::ok(),
+        Err(e) => VoidResult::err(report_error(instance, e)),
     }
 }
 fn with_instance_bool<F>(instance: *mut SeleneInstance, f: F) -> BoolResult
@@ -206,16 +676,12 @@ where
     F: FnOnce(&mut SeleneInstance) -> Result<bool>,
 {
     if instance.is_null() {
-        return BoolResult::err(100000);
+        return BoolResult::err(null_instance_error());
     }
     let instance = unsafe { &mut *instance };
     match f(instance) {
         Ok(value) => BoolResult::ok(value),
-        Err(e) => {
-            let code = 100001;
-            instance.fallible_print_panic(format!("{e:#}").as_str(), code);
-            BoolResult::err(code)
-        }
+        Err(e) => BoolResult::err(report_error(instance, e)),
     }
 }
 
@@ -224,16 +690,12 @@ where
     F: FnOnce(&mut SeleneInstance) -> anyhow::Result<u64>,
 {
     if instance.is_null() {
-        return U64Result::err(100000);
+        return U64Result::err(null_instance_error());
     }
     let instance = unsafe { &mut *instance };
     match f(instance) {
         Ok(value) => U64Result::ok(value),
-        Err(e) => {
-            let code = 100001;
-            instance.fallible_print_panic(format!("{e:#}").as_str(), code);
-            U64Result::err(code)
-        }
+        Err(e) => U64Result::err(report_error(instance, e)),
     }
 }
 fn with_instance_f64<F>(instance: *mut SeleneInstance, f: F) -> F64Result
@@ -241,16 +703,12 @@ where
     F: FnOnce(&mut SeleneInstance) -> anyhow::Result<f64>,
 {
     if instance.is_null() {
-        return F64Result::err(100000);
+        return F64Result::err(null_instance_error());
     }
     let instance = unsafe { &mut *instance };
     match f(instance) {
         Ok(value) => F64Result::ok(value),
-        Err(e) => {
-            let code = 100001;
-            instance.fallible_print_panic(format!("{e:#}").as_str(), code);
-            F64Result::err(code)
-        }
+        Err(e) => F64Result::err(report_error(instance, e)),
     }
 }
 
@@ -259,16 +717,12 @@ where
     F: FnOnce(&mut SeleneInstance) -> Result<u32>,
 {
     if instance.is_null() {
-        return U32Result::err(100000);
+        return U32Result::err(null_instance_error());
     }
     let instance = unsafe { &mut *instance };
     match f(instance) {
         Ok(value) => U32Result::ok(value),
-        Err(e) => {
-            let code = 100001;
-            instance.fallible_print_panic(format!("{e:#}").as_str(), code);
-            U32Result::err(code)
-        }
+        Err(e) => U32Result::err(report_error(instance, e)),
     }
 }
 
@@ -277,29 +731,25 @@ where
     F: FnOnce(&mut SeleneInstance) -> Result<u64>,
 {
     if instance.is_null() {
-        return FutureResult::err(100000);
+        return FutureResult::err(null_instance_error());
     }
     let instance = unsafe { &mut *instance };
     match f(instance) {
         Ok(value) => FutureResult::ok(value),
-        Err(e) => {
-            let code = 100001;
-            instance.fallible_print_panic(format!("{e:#}").as_str(), code);
-            FutureResult::err(code)
-        }
+        Err(e) => FutureResult::err(report_error(instance, e)),
     }
 }
 
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn selene_exit(instance: *mut SeleneInstance) -> VoidResult {
     if instance.is_null() {
-        eprintln!("Error: selene_exit called with null state");
-        return VoidResult { error_code: 1 };
+        report_startup_error("Error: selene_exit called with null state");
+        return VoidResult::err(ErrorKind::NullInstance.code());
     }
     let mut instance = unsafe { Box::from_raw(instance) };
     if let Err(e) = instance.exit() {
-        eprintln!("Error: {e}");
-        return VoidResult { error_code: 1 };
+        report_startup_error(format!("Error: {e:#}"));
+        return VoidResult::err(ErrorKind::of(&e).code());
     }
     // instance lifetime ends here
     VoidResult { error_code: 0 }
@@ -328,6 +778,65 @@ pub unsafe extern "C" fn selene_on_shot_end(instance: *mut SeleneInstance) -> Vo
     with_instance_void(instance, |instance| instance.shot_end())
 }
 
+/// A function run by each worker in [selene_run_workers], returning zero on success.
+pub type WorkerCallback =
+    unsafe extern "C" fn(instance: *mut SeleneInstance, user_data: *mut std::ffi::c_void) -> u32;
+
+// The user data pointer is shared between worker threads. It is up to the
+// caller to ensure that the callback's use of it is thread safe.
+#[derive(Clone, Copy)]
+struct WorkerUserData(*mut std::ffi::c_void);
+unsafe impl Send for WorkerUserData {}
+unsafe impl Sync for WorkerUserData {}
+impl WorkerUserData {
+    fn get(self) -> *mut std::ffi::c_void {
+        self.0
+    }
+}
+
+/// A non-zero code returned by a [WorkerCallback].
+#[derive(Debug)]
+struct WorkerErrorCode(u32);
+impl std::fmt::Display for WorkerErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Worker exited with error code {}", self.0)
+    }
+}
+impl std::error::Error for WorkerErrorCode {}
+
+/// Runs all configured shots, calling `callback` with each worker's instance from
+/// the worker's thread. The callback should run every shot of the instance it is
+/// given (see `selene_shot_count`) but must not exit it. The output of all workers
+/// is written to this instance's output stream in shot order.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_run_workers(
+    instance: *mut SeleneInstance,
+    callback: WorkerCallback,
+    user_data: *mut std::ffi::c_void,
+) -> VoidResult {
+    if instance.is_null() {
+        return VoidResult::err(null_instance_error());
+    }
+    let instance = unsafe { &mut *instance };
+    let user_data = WorkerUserData(user_data);
+    let result =
+        instance.run_workers(
+            |worker| match unsafe { callback(worker, user_data.get()) } {
+                0 => Ok(()),
+                code => Err(WorkerErrorCode(code).into()),
+            },
+        );
+    match result {
+        Ok(()) => VoidResult::ok(),
+        // Errors within a worker have already been reported through its
+        // output, so the worker's code is passed on as it is.
+        Err(e) => match e.downcast_ref::<WorkerErrorCode>() {
+            Some(WorkerErrorCode(code)) => VoidResult::err(*code),
+            None => VoidResult::err(report_error(instance, e)),
+        },
+    }
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn selene_print_bool(
     instance: *mut SeleneInstance,
@@ -408,6 +917,10 @@ pub unsafe extern "C" fn selene_print_f64_array(
     with_instance_void(instance, |instance| instance.print(tag.to_str(), value))
 }
 
+/// Write a panic to the result stream, ending the shot if `error_code` is
+/// below 1000 and the run otherwise. Codes 998 and 999 are reserved for
+/// errors raised by selene (see `SeleneErrorKind`), and must not be used by
+/// user programs.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn selene_print_panic(
     instance: *mut SeleneInstance,
@@ -570,6 +1083,24 @@ pub unsafe extern "C" fn selene_qubit_lazy_measure_leaked(
     with_instance_future_bool(instance, |instance| instance.qubit_lazy_measure_leaked(q))
 }
 
+/// Performs a lazy measurement followed by a reset, as a single fused operation
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_qubit_lazy_measure_reset(
+    instance: *mut SeleneInstance,
+    q: u64,
+) -> FutureResult {
+    with_instance_future_bool(instance, |instance| instance.qubit_lazy_measure_reset(q))
+}
+
+/// Performs a lazy fused measurement and reset, then frees the qubit
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_qubit_measure_and_free(
+    instance: *mut SeleneInstance,
+    q: u64,
+) -> FutureResult {
+    with_instance_future_bool(instance, |instance| instance.qubit_measure_and_free(q))
+}
+
 /// Decrements a refcount
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn selene_refcount_decrement(
@@ -606,6 +1137,26 @@ pub unsafe extern "C" fn selene_future_read_u64(
     with_instance_u64(instance, |instance| instance.future_read_u64(r))
 }
 
+/// Begins a block of operations conditioned on a future
+///
+/// Quantum operations issued until the matching `selene_end_condition`
+/// are only applied if the result referenced by `r` equals `value`.
+/// Unlike reading the future, this does not force the runtime to flush.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_begin_condition(
+    instance: *mut SeleneInstance,
+    r: u64,
+    value: bool,
+) -> VoidResult {
+    with_instance_void(instance, |instance| instance.begin_condition(r, value))
+}
+
+/// Ends the innermost conditional block
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn selene_end_condition(instance: *mut SeleneInstance) -> VoidResult {
+    with_instance_void(instance, |instance| instance.end_condition())
+}
+
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn selene_local_barrier(
     instance: *mut SeleneInstance,
@@ -0,0 +1,284 @@
+use super::*;
+use crate::test_utils::{register_plugins, scratch_dir};
+use selene_core::decoder::{OutputStreamReader, Value};
+use std::ffi::{CStr, CString};
+use std::path::PathBuf;
+
+fn last_error_message() -> String {
+    let message = unsafe { selene_last_error_message() };
+    assert!(!message.is_null());
+    unsafe { CStr::from_ptr(message) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[test]
+fn load_config_errors() {
+    let mut instance = std::ptr::null_mut();
+    let path = CString::new("/nonexistent/configuration.yaml").unwrap();
+
+    let result = unsafe { selene_load_config(std::ptr::null_mut(), path.as_ptr()) };
+    assert_eq!(result.error_code, ErrorKind::NullInstance.code());
+
+    let result = unsafe { selene_load_config(&mut instance, std::ptr::null()) };
+    assert_eq!(result.error_code, ErrorKind::InvalidArgument.code());
+    assert!(last_error_message().contains("config file path"));
+
+    let result = unsafe { selene_load_config(&mut instance, path.as_ptr()) };
+    assert_eq!(result.error_code, ErrorKind::InvalidArgument.code());
+    assert!(last_error_message().contains("config file"));
+
+    let dir = scratch_dir("load_config_errors");
+    let config = dir.join("configuration.yaml");
+    std::fs::write(&config, "n_qubits: [").unwrap();
+    let path = CString::new(config.to_str().unwrap()).unwrap();
+    let result = unsafe { selene_load_config(&mut instance, path.as_ptr()) };
+    assert_eq!(result.error_code, ErrorKind::InvalidArgument.code());
+    assert!(instance.is_null());
+}
+
+#[test]
+fn exit_null_instance() {
+    let result = unsafe { selene_exit(std::ptr::null_mut()) };
+    assert_eq!(result.error_code, ErrorKind::NullInstance.code());
+    assert!(last_error_message().contains("null"));
+}
+
+fn c_string(value: &str) -> CString {
+    CString::new(value).unwrap()
+}
+
+/// Builds a configuration through the C interface, checking that every call
+/// succeeds.
+fn config_from_ffi() -> *mut ConfigurationBuilder {
+    let config = selene_config_new();
+    let check = |result: VoidResult| assert_eq!(result.error_code, 0, "{}", last_error_message());
+    unsafe {
+        check(selene_config_set_n_qubits(config, 2));
+        for (role, name) in [
+            (SELENE_PLUGIN_SIMULATOR, "coinflip"),
+            (SELENE_PLUGIN_ERROR_MODEL, "ideal"),
+            (SELENE_PLUGIN_RUNTIME, "simple"),
+        ] {
+            check(selene_config_set_plugin(
+                config,
+                role,
+                c_string(name).as_ptr(),
+                std::ptr::null(),
+            ));
+            check(selene_config_set_plugin_seed(
+                config,
+                role,
+                10 + role as u64,
+            ));
+        }
+        check(selene_config_add_plugin_arg(
+            config,
+            SELENE_PLUGIN_SIMULATOR,
+            c_string("--bias=1").as_ptr(),
+        ));
+        check(selene_config_set_shots(config, 4, 100, 3));
+        check(selene_config_set_workers(config, 2));
+        check(selene_config_set_error_policy(
+            config,
+            c_string("skip_shot").as_ptr(),
+        ));
+        check(selene_config_set_output_format(
+            config,
+            c_string("json_lines").as_ptr(),
+        ));
+        check(selene_config_set_event_hooks(config, true, false));
+    }
+    config
+}
+
+#[test]
+fn config_ffi() {
+    let config = config_from_ffi();
+    let built = unsafe { &*config }.clone().build().unwrap();
+    assert_eq!(built.n_qubits, 2);
+    assert_eq!(built.simulator.path(), PathBuf::from("builtin:coinflip"));
+    assert_eq!(built.simulator.args, vec!["--bias=1"]);
+    assert_eq!(built.simulator.seed, 10);
+    assert_eq!(built.error_model.seed, 11);
+    assert_eq!(built.runtime.seed, 12);
+    assert_eq!(
+        (built.shots.count, built.shots.offset, built.shots.increment),
+        (4, 100, 3)
+    );
+    assert_eq!(built.shots.workers, 2);
+    assert_eq!(built.on_error, ErrorPolicy::SkipShot);
+    assert_eq!(built.output_options.format, OutputFormat::JsonLines);
+    assert!(built.event_hooks.provide_instruction_log);
+    assert!(!built.event_hooks.provide_metrics);
+    unsafe { selene_config_free(config) };
+}
+
+#[test]
+fn config_ffi_errors() {
+    let invalid = ErrorKind::InvalidArgument.code();
+    let config = selene_config_new();
+    unsafe {
+        let result = selene_config_set_n_qubits(std::ptr::null_mut(), 1);
+        assert_eq!(result.error_code, ErrorKind::NullInstance.code());
+        let result =
+            selene_config_set_plugin(config, 7, c_string("coinflip").as_ptr(), std::ptr::null());
+        assert_eq!(result.error_code, invalid);
+        // Arguments can only be added to a plugin that has been set.
+        let result =
+            selene_config_add_plugin_arg(config, SELENE_PLUGIN_RUNTIME, c_string("-v").as_ptr());
+        assert_eq!(result.error_code, invalid);
+        assert!(last_error_message().contains("Runtime"));
+        let result = selene_config_set_plugin(
+            config,
+            SELENE_PLUGIN_RUNTIME,
+            std::ptr::null(),
+            std::ptr::null(),
+        );
+        assert_eq!(result.error_code, invalid);
+        let result = selene_config_set_output_format(config, c_string("xml").as_ptr());
+        assert_eq!(result.error_code, invalid);
+        let result = selene_config_set_error_policy(config, c_string("retry").as_ptr());
+        assert_eq!(result.error_code, invalid);
+        let result = selene_config_set_operation_log(config, c_string("qasm2").as_ptr());
+        assert_eq!(result.error_code, invalid);
+        let result = selene_config_add_event_hook_plugin_arg(config, c_string("-v").as_ptr());
+        assert_eq!(result.error_code, invalid);
+        selene_config_free(config);
+        selene_config_free(std::ptr::null_mut());
+    }
+}
+
+/// The state behind a host's [OutputWriter].
+#[derive(Default)]
+struct HostSink {
+    data: Vec<u8>,
+    flushes: usize,
+    closes: usize,
+    fail: bool,
+}
+
+unsafe extern "C" fn sink_write(
+    user_data: *mut std::ffi::c_void,
+    data: *const u8,
+    length: u64,
+) -> i64 {
+    let sink = unsafe { &mut *(user_data as *mut HostSink) };
+    if sink.fail {
+        return -1;
+    }
+    sink.data
+        .extend_from_slice(unsafe { std::slice::from_raw_parts(data, length as usize) });
+    length as i64
+}
+
+unsafe extern "C" fn sink_flush(user_data: *mut std::ffi::c_void) -> i32 {
+    let sink = unsafe { &mut *(user_data as *mut HostSink) };
+    sink.flushes += 1;
+    sink.fail as i32
+}
+
+unsafe extern "C" fn sink_close(user_data: *mut std::ffi::c_void) {
+    let sink = unsafe { &mut *(user_data as *mut HostSink) };
+    sink.closes += 1;
+}
+
+fn output_writer(sink: &mut HostSink) -> OutputWriter {
+    OutputWriter {
+        user_data: sink as *mut HostSink as *mut std::ffi::c_void,
+        write: sink_write,
+        flush: Some(sink_flush),
+        close: Some(sink_close),
+    }
+}
+
+#[test]
+fn host_writer() {
+    let mut sink = HostSink::default();
+    let mut writer = HostWriter(output_writer(&mut sink));
+    writer.write_all(b"abc").unwrap();
+    writer.flush().unwrap();
+    drop(writer);
+    assert_eq!(sink.data, b"abc");
+    assert_eq!((sink.flushes, sink.closes), (1, 1));
+
+    let mut sink = HostSink {
+        fail: true,
+        ..Default::default()
+    };
+    let mut writer = HostWriter(output_writer(&mut sink));
+    assert!(writer.write(b"abc").is_err());
+    assert!(writer.flush().is_err());
+    drop(writer);
+    assert_eq!(sink.closes, 1);
+
+    // Without flush and close functions, flushing does nothing.
+    let mut sink = HostSink::default();
+    let mut writer = HostWriter(OutputWriter {
+        flush: None,
+        close: None,
+        ..output_writer(&mut sink)
+    });
+    writer.flush().unwrap();
+    drop(writer);
+    assert_eq!((sink.flushes, sink.closes), (0, 0));
+}
+
+#[test]
+fn instance_from_config() {
+    register_plugins();
+    let config = config_from_ffi();
+    unsafe {
+        selene_config_set_output_format(config, c_string("binary").as_ptr());
+        selene_config_set_workers(config, 1);
+    }
+    let mut sink = HostSink::default();
+    let writer = output_writer(&mut sink);
+    let mut instance = std::ptr::null_mut();
+    let result = unsafe { selene_instance_from_config(&mut instance, config, &writer) };
+    assert_eq!(result.error_code, 0, "{}", last_error_message());
+    unsafe { selene_config_free(config) };
+    unsafe { &mut *instance }
+        .run_shots(|shot| {
+            let q = shot.qalloc()?.unwrap();
+            let result = shot.measure_and_free(q)?;
+            let value = shot.read(&result)?;
+            shot.print("USER:BOOL:result", value)
+        })
+        .unwrap();
+    assert_eq!(unsafe { selene_exit(instance) }.error_code, 0);
+    assert_eq!(sink.closes, 1);
+
+    let mut reader = OutputStreamReader::new(sink.data.as_slice());
+    let mut shot_ids = Vec::new();
+    while let Some(shot) = reader.next_shot().unwrap() {
+        // The simulator is biased to always measure 1.
+        assert!(
+            shot.messages
+                .iter()
+                .any(|message| message.tag == "USER:BOOL:result"
+                    && message.values == vec![Value::Bool(true)])
+        );
+        shot_ids.push(shot.shot_id);
+    }
+    assert_eq!(shot_ids, vec![100, 103, 106, 109]);
+}
+
+#[test]
+fn instance_from_config_errors() {
+    let mut sink = HostSink::default();
+    let writer = output_writer(&mut sink);
+    let config = selene_config_new();
+    let mut instance = std::ptr::null_mut();
+    // The writer is closed as soon as creating the instance fails.
+    let result = unsafe { selene_instance_from_config(&mut instance, config, &writer) };
+    assert_eq!(result.error_code, ErrorKind::InvalidArgument.code());
+    assert!(last_error_message().contains("number of qubits"));
+    assert!(instance.is_null());
+    assert_eq!(sink.closes, 1);
+
+    let result = unsafe { selene_instance_from_config(&mut instance, std::ptr::null(), &writer) };
+    assert_eq!(result.error_code, ErrorKind::NullInstance.code());
+    assert_eq!(sink.closes, 2);
+    unsafe { selene_config_free(config) };
+}
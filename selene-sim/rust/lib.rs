@@ -1,5 +1,10 @@
 pub mod emulator;
 pub mod event_hooks;
+pub mod plugins;
+pub mod replay;
+pub mod reproduce;
 pub mod selene_instance;
+#[cfg(test)]
+mod test_utils;
 
 pub mod ffi_interface;
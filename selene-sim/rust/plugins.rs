@@ -0,0 +1,85 @@
+//! Plugins linked into selene rather than loaded from files.
+//!
+//! Each of the shipped plugins is available as a cargo feature of
+//! `selene-sim`. When enabled, the plugin is registered under its name in
+//! [selene_core::registry] on the first instance's creation, and is used by
+//! any plugin configuration with that name and no `file`. For example, with
+//! the `coinflip` feature enabled:
+//!
+//! ```yaml
+//! simulator:
+//!   name: coinflip
+//!   args: ["--bias=0.5"]
+//! ```
+//!
+//! Further plugins can be registered from Rust with the `register_*`
+//! functions of [selene_core::registry] before creating an instance.
+use std::sync::Once;
+
+static REGISTER_BUILTIN_PLUGINS: Once = Once::new();
+
+/// Register the plugins enabled by cargo features, if not already registered.
+pub fn register_builtin_plugins() {
+    REGISTER_BUILTIN_PLUGINS.call_once(|| {
+        #[allow(unused_imports)]
+        use selene_core::registry::{register_error_model, register_runtime, register_simulator};
+        #[allow(unused_imports)]
+        use std::sync::Arc;
+
+        #[cfg(feature = "coinflip")]
+        register_simulator(
+            selene_coinflip_plugin::PLUGIN_NAME,
+            Arc::new(selene_coinflip_plugin::CoinflipSimulatorFactory),
+        );
+        #[cfg(feature = "quest")]
+        register_simulator(
+            selene_quest_plugin::PLUGIN_NAME,
+            Arc::new(selene_quest_plugin::QuestSimulatorFactory),
+        );
+        #[cfg(feature = "stim")]
+        register_simulator(
+            selene_stim_plugin::PLUGIN_NAME,
+            Arc::new(selene_stim_plugin::StimSimulatorFactory),
+        );
+        #[cfg(feature = "classical-replay")]
+        register_simulator(
+            selene_classical_replay::PLUGIN_NAME,
+            Arc::new(selene_classical_replay::ClassicalReplaySimulatorFactory),
+        );
+        #[cfg(feature = "quantum-replay")]
+        register_simulator(
+            selene_quantum_replay::PLUGIN_NAME,
+            Arc::new(selene_quantum_replay::QuantumReplaySimulatorFactory),
+        );
+        #[cfg(feature = "ideal")]
+        register_error_model(
+            selene_ideal_plugin::PLUGIN_NAME,
+            Arc::new(selene_ideal_plugin::IdealErrorModelFactory),
+        );
+        #[cfg(feature = "depolarizing")]
+        register_error_model(
+            selene_depolarizing_plugin::PLUGIN_NAME,
+            Arc::new(selene_depolarizing_plugin::DepolarizingErrorModelFactory),
+        );
+        #[cfg(feature = "simple-leakage")]
+        register_error_model(
+            selene_simple_leakage_plugin::PLUGIN_NAME,
+            Arc::new(selene_simple_leakage_plugin::SimpleLeakageErrorModelFactory),
+        );
+        #[cfg(feature = "simple-runtime")]
+        register_runtime(
+            selene_simple_runtime::PLUGIN_NAME,
+            Arc::new(selene_simple_runtime::SimpleRuntimeFactory),
+        );
+        #[cfg(feature = "soft-rz-runtime")]
+        register_runtime(
+            selene_soft_rz_runtime::PLUGIN_NAME,
+            Arc::new(selene_soft_rz_runtime::SoftRZRuntimeFactory),
+        );
+        #[cfg(feature = "gate-validator-runtime")]
+        register_runtime(
+            selene_gate_validator_runtime::PLUGIN_NAME,
+            Arc::new(selene_gate_validator_runtime::GateValidatorRuntimeFactory),
+        );
+    });
+}
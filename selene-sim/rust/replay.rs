@@ -0,0 +1,153 @@
+//! Replays the operations recorded for a shot through an error model and
+//! simulator, without the user program or runtime that emitted them.
+//!
+//! The operations are read from the JSON written by the operation log (see
+//! [event_hooks::operation_log](crate::event_hooks::operation_log)), and are
+//! passed to the error model batch by batch, seeded as in the original run.
+//! Only the number of qubits, the error model and the simulator of the
+//! [Configuration] are used, so a recorded trajectory can be rerun with a
+//! different noise model or simulator by changing those entries, e.g. to
+//! regression test an error model or to debug a failing shot in isolation.
+//!
+//! See the `selene-replay` binary for a command line interface.
+use anyhow::{Context, Result, anyhow};
+use selene_core::error_model::{ErrorModel, ErrorModelInterface};
+use selene_core::event_hook::ResultValue;
+use selene_core::runtime::{BatchOperation, Operation};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::emulator::Emulator;
+use crate::selene_instance::configuration::Configuration;
+
+#[cfg(test)]
+mod tests;
+
+/// The outcome of a measurement in a replayed shot.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Measurement {
+    pub result_id: u64,
+    pub qubit_id: u64,
+    pub value: ResultValue,
+}
+
+/// The outcome of a replayed shot.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReplayedShot {
+    pub shot_id: u64,
+    /// The measurements of the shot, in the order they were recorded.
+    pub measurements: Vec<Measurement>,
+}
+
+/// Reads the batches recorded for a shot by the operation log's JSON format.
+pub fn read_operation_log(path: impl AsRef<Path>) -> Result<Vec<BatchOperation>> {
+    let path = path.as_ref();
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open the operation log '{}'", path.display()))?;
+    serde_json::from_reader(std::io::BufReader::new(file))
+        .with_context(|| format!("Failed to parse the operation log '{}'", path.display()))
+}
+
+/// The id of the shot recorded in a file named `shot_<shot_id>.json` by the
+/// operation log, if the file is named as such.
+pub fn recorded_shot_id(path: impl AsRef<Path>) -> Option<u64> {
+    path.as_ref()
+        .file_stem()?
+        .to_str()?
+        .strip_prefix("shot_")?
+        .parse()
+        .ok()
+}
+
+pub struct Replayer {
+    error_model: ErrorModel,
+    error_model_seed: u64,
+    simulator_seed: u64,
+}
+
+impl Replayer {
+    pub fn new(config: &Configuration) -> Result<Self> {
+        Ok(Self {
+            error_model: Emulator::load_error_model(config)?,
+            error_model_seed: config.error_model.seed,
+            simulator_seed: config.simulator.seed,
+        })
+    }
+
+    /// Replays the batches recorded for the given shot, seeding the error
+    /// model and simulator with the seeds of that shot.
+    pub fn replay_shot(
+        &mut self,
+        shot_id: u64,
+        batches: Vec<BatchOperation>,
+    ) -> Result<ReplayedShot> {
+        self.error_model.shot_start(
+            shot_id,
+            self.error_model_seed + shot_id,
+            self.simulator_seed + shot_id,
+        )?;
+        let measurements = self.replay_batches(batches);
+        let ended = self.error_model.shot_end();
+        let measurements = measurements?;
+        ended?;
+        Ok(ReplayedShot {
+            shot_id,
+            measurements,
+        })
+    }
+
+    fn replay_batches(&mut self, batches: Vec<BatchOperation>) -> Result<Vec<Measurement>> {
+        let mut measurements = Vec::new();
+        for batch in batches {
+            let measured: Vec<(u64, u64)> = batch
+                .iter_ops()
+                .filter_map(|op| match op {
+                    Operation::Measure {
+                        qubit_id,
+                        result_id,
+                    }
+                    | Operation::MeasureLeaked {
+                        qubit_id,
+                        result_id,
+                    }
+                    | Operation::MeasureReset {
+                        qubit_id,
+                        result_id,
+                    } => Some((*result_id, *qubit_id)),
+                    _ => None,
+                })
+                .collect();
+            let results = self.error_model.handle_operations(batch)?;
+            let mut values: HashMap<u64, ResultValue> = results
+                .bool_results
+                .into_iter()
+                .map(|result| (result.result_id, ResultValue::Bool(result.value)))
+                .chain(
+                    results
+                        .u64_results
+                        .into_iter()
+                        .map(|result| (result.result_id, ResultValue::U64(result.value))),
+                )
+                .collect();
+            for (result_id, qubit_id) in measured {
+                let value = values.remove(&result_id).ok_or_else(|| {
+                    anyhow!(
+                        "The error model set no result for the measurement of qubit {qubit_id} into result {result_id}"
+                    )
+                })?;
+                measurements.push(Measurement {
+                    result_id,
+                    qubit_id,
+                    value,
+                });
+            }
+        }
+        Ok(measurements)
+    }
+
+    /// Signals the error model that the replay is over.
+    pub fn exit(mut self) -> Result<()> {
+        self.error_model.exit()
+    }
+}
@@ -0,0 +1,130 @@
+use super::*;
+use crate::event_hooks::operation_log::OperationLogFormat;
+use crate::selene_instance::configuration::{ConfigurationBuilder, PluginConfig};
+use crate::selene_instance::program::Shot;
+use crate::test_utils::{read_shots, run_program, test_config};
+use selene_core::decoder::{self, Value};
+
+/// A configuration recording the operations of each shot as JSON, with a
+/// coinflip simulator that measures 1 half of the time.
+fn recording_config(name: &str, shots: u64) -> ConfigurationBuilder {
+    let mut config = test_config(name)
+        .simulator(
+            PluginConfig::builtin("coinflip")
+                .with_seed(1)
+                .with_args(["--bias=0.5"]),
+        )
+        .shots(shots, 0, 1);
+    config.event_hooks_mut().operation_log = Some(OperationLogFormat::Json);
+    config
+}
+
+fn program(shot: &mut Shot) -> Result<()> {
+    let q0 = shot.qalloc()?.unwrap();
+    let q1 = shot.qalloc()?.unwrap();
+    shot.rxy(&q0, std::f64::consts::FRAC_PI_2, 0.0)?;
+    shot.rzz(&q0, &q1, 0.3)?;
+    let first = shot.lazy_measure(&q0)?;
+    let second = shot.lazy_measure(&q1)?;
+    let third = shot.measure(&q0)?;
+    let first = shot.read(&first)?;
+    let second = shot.read(&second)?;
+    for value in [first, second, third] {
+        shot.print("USER:BOOL:m", value)?;
+    }
+    Ok(())
+}
+
+fn printed(shot: &decoder::Shot) -> Vec<ResultValue> {
+    shot.messages
+        .iter()
+        .filter(|message| message.tag == "USER:BOOL:m")
+        .flat_map(|message| message.values.clone())
+        .map(|value| match value {
+            Value::Bool(value) => ResultValue::Bool(value),
+            value => panic!("unexpected value {value:?}"),
+        })
+        .collect()
+}
+
+/// The qubit and result ids of the measurements recorded in the batches.
+fn recorded_measurements(batches: &[BatchOperation]) -> Vec<(u64, u64)> {
+    batches
+        .iter()
+        .flat_map(|batch| batch.iter_ops())
+        .filter_map(|op| match op {
+            Operation::Measure {
+                qubit_id,
+                result_id,
+            } => Some((*qubit_id, *result_id)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn replayed_shots_match_the_recording() {
+    let config = recording_config("replay", 8);
+    let shots = read_shots(&run_program(config.clone(), program));
+    let config = config.build().unwrap();
+    let mut replayer = Replayer::new(&config).unwrap();
+    let mut values = Vec::new();
+    for shot in &shots {
+        let path = config
+            .artifact_dir
+            .join(format!("shot_{}.json", shot.shot_id));
+        assert_eq!(recorded_shot_id(&path), Some(shot.shot_id));
+        let batches = read_operation_log(&path).unwrap();
+        let recorded = recorded_measurements(&batches);
+        assert_eq!(recorded.len(), 3);
+
+        let replayed = replayer.replay_shot(shot.shot_id, batches).unwrap();
+        assert_eq!(replayed.shot_id, shot.shot_id);
+        let measured: Vec<(u64, u64)> = replayed
+            .measurements
+            .iter()
+            .map(|measurement| (measurement.qubit_id, measurement.result_id))
+            .collect();
+        assert_eq!(measured, recorded);
+        let replayed: Vec<ResultValue> = replayed
+            .measurements
+            .into_iter()
+            .map(|measurement| measurement.value)
+            .collect();
+        assert_eq!(replayed, printed(shot));
+        values.extend(replayed);
+    }
+    replayer.exit().unwrap();
+    // The shots are seeded differently, so their outcomes are not all equal.
+    assert!(values.contains(&ResultValue::Bool(true)));
+    assert!(values.contains(&ResultValue::Bool(false)));
+}
+
+#[test]
+fn replays_are_seeded_by_shot() {
+    let config = recording_config("replay_seeds", 1);
+    run_program(config.clone(), program);
+    let config = config.build().unwrap();
+    let batches = read_operation_log(config.artifact_dir.join("shot_0.json")).unwrap();
+    let mut replayer = Replayer::new(&config).unwrap();
+    let first = replayer.replay_shot(0, batches.clone()).unwrap();
+    let again = replayer.replay_shot(0, batches).unwrap();
+    assert_eq!(first, again);
+    replayer.exit().unwrap();
+}
+
+#[test]
+fn recorded_shot_ids() {
+    assert_eq!(recorded_shot_id("out/shot_12.json"), Some(12));
+    assert_eq!(recorded_shot_id("shot_x.json"), None);
+    assert_eq!(recorded_shot_id("trace_3.json"), None);
+}
+
+#[test]
+fn missing_logs_are_reported() {
+    let error = read_operation_log("/nonexistent/shot_0.json").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Failed to open the operation log '/nonexistent/shot_0.json'"
+    );
+}
@@ -0,0 +1,334 @@
+//! Reruns a single shot of a previous run and verifies that it reproduces the
+//! original results.
+//!
+//! Each shot is seeded from its id alone (the runtime with `runtime.seed +
+//! shot_id`, and likewise for the error model and simulator), so any shot can
+//! be rerun in isolation by running the same program with a configuration
+//! that has a single shot at that id. [ShotReproducer] writes such a
+//! configuration from the one saved with the run, with the instruction log
+//! enabled, the result stream written uncompressed to a file, and artifacts
+//! such as state dumps written to a directory of their own. It then runs the
+//! program with it.
+//!
+//! [read_shot] and [compare_shots] find the messages of the shot in the
+//! original and reproduced result streams, and check that they are identical
+//! byte for byte, with three exceptions:
+//! - an instruction log is only compared if the original run provided one;
+//! - state dumps are compared by the contents of the dumped files rather
+//!   than by their paths;
+//! - the wall-clock time reported for a shot that exceeded its limits is
+//!   skipped, as it is not deterministic.
+//!
+//! See the `selene-reproduce` binary for a command line interface.
+use anyhow::{Context, Result, anyhow, bail};
+use selene_core::decoder::{Message, MessageKind, OutputStreamReader};
+use std::fmt;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+use url::Url;
+
+use crate::selene_instance::configuration::Configuration;
+use crate::selene_instance::output_format::OutputFormat;
+
+#[cfg(test)]
+mod tests;
+
+/// A message of a shot, along with its encoding in the result stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawMessage {
+    pub message: Message,
+    pub bytes: Vec<u8>,
+}
+
+/// The outcome of rerunning a shot with [ShotReproducer::run].
+#[derive(Debug)]
+pub struct ReproducedShot {
+    pub shot_id: u64,
+    /// The configuration the program was run with.
+    pub config_path: PathBuf,
+    /// The result stream written by the program.
+    pub output_path: PathBuf,
+    /// The artifact directory of the rerun, holding any state dumps.
+    pub artifact_dir: PathBuf,
+    pub status: ExitStatus,
+}
+
+impl ReproducedShot {
+    /// The messages of the shot in the reproduced result stream.
+    pub fn messages(&self) -> Result<Vec<RawMessage>> {
+        read_shot(&self.output_path, self.shot_id).with_context(|| {
+            format!(
+                "Failed to read the reproduced shot (the program exited with {})",
+                self.status
+            )
+        })
+    }
+}
+
+/// The first difference found between an original and a reproduced shot.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    /// The index of the differing message among the compared messages.
+    pub index: usize,
+    pub original: Option<Message>,
+    pub reproduced: Option<Message>,
+    pub reason: String,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let describe = |message: &Option<Message>| match message {
+            Some(message) => format!("'{}' at {}ns", message.tag, message.time_cursor),
+            None => "nothing".to_string(),
+        };
+        write!(
+            f,
+            "Message {} differs: {} (the original has {}, the reproduction has {})",
+            self.index,
+            self.reason,
+            describe(&self.original),
+            describe(&self.reproduced)
+        )
+    }
+}
+
+/// Reruns individual shots of a run, given the program and the configuration
+/// it was run with.
+pub struct ShotReproducer {
+    program: PathBuf,
+    config: serde_yml::Mapping,
+    configuration: Configuration,
+}
+
+impl ShotReproducer {
+    pub fn new(program: impl Into<PathBuf>, config_path: impl AsRef<Path>) -> Result<Self> {
+        let config_path = config_path.as_ref();
+        let configuration = Configuration::from_file(config_path)?;
+        let file = std::fs::File::open(config_path).context("Error opening config file")?;
+        let config = serde_yml::from_reader(file).context("Error parsing config file")?;
+        Ok(Self {
+            program: program.into(),
+            config,
+            configuration,
+        })
+    }
+
+    /// The result stream of the original run, if it was written to a file.
+    pub fn original_stream(&self) -> Option<PathBuf> {
+        let url = Url::parse(&self.configuration.output_stream).ok()?;
+        if url.scheme() != "file" {
+            return None;
+        }
+        url.to_file_path().ok()
+    }
+
+    /// The directory that a shot is rerun in by default, within the artifact
+    /// directory of the original run.
+    pub fn default_output_dir(&self, shot_id: u64) -> PathBuf {
+        self.configuration
+            .artifact_dir
+            .join(format!("reproduce_shot_{shot_id}"))
+    }
+
+    /// The original configuration, changed to run only the given shot and to
+    /// write its results and artifacts to `output_dir`.
+    fn shot_config(&self, shot_id: u64, output_dir: &Path) -> Result<serde_yml::Mapping> {
+        if self.configuration.output_options.format != OutputFormat::Binary {
+            bail!("Only runs with the binary output format can be reproduced");
+        }
+        let output_path = output_dir.join("results.bin");
+        let output_stream = Url::from_file_path(&output_path)
+            .map_err(|()| anyhow!("Invalid output path '{}'", output_path.display()))?;
+        let mut config = self.config.clone();
+        config.insert("output_stream".into(), output_stream.as_str().into());
+        config.remove("output_options");
+        config.remove("checkpoint");
+        config.insert(
+            "artifact_dir".into(),
+            output_dir
+                .join("artifacts")
+                .to_string_lossy()
+                .as_ref()
+                .into(),
+        );
+        let mut shots = serde_yml::Mapping::new();
+        shots.insert("count".into(), 1.into());
+        shots.insert("offset".into(), shot_id.into());
+        shots.insert("increment".into(), 1.into());
+        config.insert("shots".into(), shots.into());
+        let event_hooks = config
+            .entry("event_hooks".into())
+            .or_insert_with(|| serde_yml::Mapping::new().into())
+            .as_mapping_mut()
+            .ok_or_else(|| anyhow!("The event_hooks of the configuration are not a mapping"))?;
+        event_hooks.insert("provide_instruction_log".into(), true.into());
+        Ok(config)
+    }
+
+    /// Reruns the given shot, writing the configuration, result stream and
+    /// artifacts of the rerun to `output_dir`.
+    pub fn run(&self, shot_id: u64, output_dir: impl AsRef<Path>) -> Result<ReproducedShot> {
+        let output_dir = std::path::absolute(output_dir.as_ref())?;
+        let artifact_dir = output_dir.join("artifacts");
+        std::fs::create_dir_all(&artifact_dir).with_context(|| {
+            format!(
+                "Failed to create the directory '{}'",
+                artifact_dir.display()
+            )
+        })?;
+        let config_path = output_dir.join("configuration.yaml");
+        let config = serde_yml::to_string(&self.shot_config(shot_id, &output_dir)?)?;
+        std::fs::write(&config_path, config).with_context(|| {
+            format!(
+                "Failed to write the configuration '{}'",
+                config_path.display()
+            )
+        })?;
+        let status = Command::new(&self.program)
+            .arg("--configuration")
+            .arg(&config_path)
+            .status()
+            .with_context(|| format!("Failed to run '{}'", self.program.display()))?;
+        Ok(ReproducedShot {
+            shot_id,
+            config_path,
+            output_path: output_dir.join("results.bin"),
+            artifact_dir,
+            status,
+        })
+    }
+}
+
+/// A reader that keeps a copy of the bytes read through it.
+struct RecordingReader<R> {
+    reader: R,
+    recorded: Vec<u8>,
+}
+
+impl<R: Read> Read for RecordingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        self.recorded.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Reads the messages of the given shot from a binary result stream, which
+/// may be compressed, excluding the shot start and end markers.
+///
+/// The stream is read a message at a time, and only the messages of the
+/// shot are kept, so long runs need not fit in memory.
+pub fn read_shot(path: impl AsRef<Path>, shot_id: u64) -> Result<Vec<RawMessage>> {
+    let path = path.as_ref();
+    let read_error = || format!("Failed to read the result stream '{}'", path.display());
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open the result stream '{}'", path.display()))?;
+    let stream = OutputStreamReader::open(std::io::BufReader::new(file))
+        .with_context(read_error)?
+        .into_inner();
+    // The decoder reads no further than the end of each message, so the bytes
+    // recorded since the previous message are exactly those of the next.
+    let mut reader = OutputStreamReader::new(RecordingReader {
+        reader: stream,
+        recorded: Vec::new(),
+    });
+    let mut messages = None;
+    loop {
+        let start = reader.bytes_read();
+        let Some(message) = reader.next_message().with_context(read_error)? else {
+            break;
+        };
+        let bytes = std::mem::take(&mut reader.get_mut().recorded);
+        debug_assert_eq!(bytes.len() as u64, reader.bytes_read() - start);
+        match (message.kind()?, &mut messages) {
+            (MessageKind::ShotStart(id), None) if id == shot_id => messages = Some(Vec::new()),
+            (MessageKind::ShotEnd(id), Some(messages)) if id == shot_id => {
+                return Ok(std::mem::take(messages));
+            }
+            (_, Some(messages)) => messages.push(RawMessage { message, bytes }),
+            (_, None) => {}
+        }
+    }
+    match messages {
+        Some(_) => bail!("Shot {shot_id} did not end in '{}'", path.display()),
+        None => bail!("Shot {shot_id} was not found in '{}'", path.display()),
+    }
+}
+
+/// Whether a message is left out of the comparison of shots.
+fn is_skipped(message: &Message, compare_instruction_log: bool) -> bool {
+    match message.kind() {
+        Ok(MessageKind::InstructionLog(_)) => !compare_instruction_log,
+        Ok(MessageKind::Metric {
+            category: Some("shot_limits"),
+            name: "wall_time",
+            ..
+        }) => true,
+        _ => false,
+    }
+}
+
+/// Compares the messages of an original and a reproduced shot, returning the
+/// first difference if there is one.
+pub fn compare_shots(
+    original: &[RawMessage],
+    reproduced: &[RawMessage],
+) -> Result<Option<Mismatch>> {
+    let compare_instruction_log = original
+        .iter()
+        .any(|raw| matches!(raw.message.kind(), Ok(MessageKind::InstructionLog(_))));
+    let mut original = original
+        .iter()
+        .filter(|raw| !is_skipped(&raw.message, compare_instruction_log));
+    let mut reproduced = reproduced
+        .iter()
+        .filter(|raw| !is_skipped(&raw.message, compare_instruction_log));
+    let mut index = 0;
+    loop {
+        let (original, reproduced) = match (original.next(), reproduced.next()) {
+            (None, None) => return Ok(None),
+            (Some(original), Some(reproduced)) => (original, reproduced),
+            (original, reproduced) => {
+                return Ok(Some(Mismatch {
+                    index,
+                    original: original.map(|raw| raw.message.clone()),
+                    reproduced: reproduced.map(|raw| raw.message.clone()),
+                    reason: "the shots have different numbers of messages".to_string(),
+                }));
+            }
+        };
+        let reason = match (original.message.kind()?, reproduced.message.kind()?) {
+            (
+                MessageKind::UserState {
+                    name: original_name,
+                    path: original_path,
+                },
+                MessageKind::UserState {
+                    name: reproduced_name,
+                    path: reproduced_path,
+                },
+            ) if original_name == reproduced_name
+                && original.message.time_cursor == reproduced.message.time_cursor =>
+            {
+                let read = |path: &str| {
+                    std::fs::read(path)
+                        .with_context(|| format!("Failed to read the state dump '{path}'"))
+                };
+                (read(original_path)? != read(reproduced_path)?)
+                    .then(|| "the dumped states differ".to_string())
+            }
+            _ => (original.bytes != reproduced.bytes)
+                .then(|| "the encoded messages differ".to_string()),
+        };
+        if let Some(reason) = reason {
+            return Ok(Some(Mismatch {
+                index,
+                original: Some(original.message.clone()),
+                reproduced: Some(reproduced.message.clone()),
+                reason,
+            }));
+        }
+        index += 1;
+    }
+}
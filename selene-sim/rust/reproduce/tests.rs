@@ -0,0 +1,312 @@
+use super::*;
+use crate::test_utils::{SharedBuffer, scratch_dir};
+use selene_core::decoder::Value;
+use selene_core::encoder::OutputStream;
+use std::io::Write;
+
+fn message(time_cursor: u64, tag: &str, values: Vec<Value>) -> Message {
+    Message {
+        time_cursor,
+        tag: tag.to_string(),
+        values,
+    }
+}
+
+/// Encodes messages as a binary result stream.
+fn encode(messages: &[Message], end_of_stream: bool) -> Vec<u8> {
+    let buffer = SharedBuffer::default();
+    let mut stream = OutputStream::new(Box::new(buffer.clone()));
+    for message in messages {
+        stream.write_message(message).unwrap();
+    }
+    if end_of_stream {
+        stream.end_of_stream().unwrap();
+    }
+    buffer.contents()
+}
+
+fn raw(message: Message) -> RawMessage {
+    RawMessage {
+        bytes: encode(std::slice::from_ref(&message), false),
+        message,
+    }
+}
+
+fn result(time_cursor: u64, value: u64) -> RawMessage {
+    raw(message(
+        time_cursor,
+        "USER:INT:result",
+        vec![Value::U64(value)],
+    ))
+}
+
+/// The messages of a shot, enclosed by its start and end markers.
+fn shot(shot_id: u64, messages: &[Message]) -> Vec<Message> {
+    let marker = |tag: &str| message(0, tag, vec![Value::U64(shot_id)]);
+    let mut shot = vec![marker("SELENE:SHOT_START")];
+    shot.extend_from_slice(messages);
+    shot.push(marker("SELENE:SHOT_END"));
+    shot
+}
+
+fn shot_messages(shot_id: u64) -> Vec<Message> {
+    vec![
+        message(shot_id, "USER:INT:result", vec![Value::U64(shot_id)]),
+        message(
+            shot_id + 1,
+            "USER:STR:label",
+            vec![Value::Str(format!("shot {shot_id}"))],
+        ),
+    ]
+}
+
+/// A result stream of shots 0 to 2, written uncompressed and gzipped.
+fn write_streams(name: &str) -> [PathBuf; 2] {
+    let dir = scratch_dir(name);
+    let messages: Vec<Message> = (0..3)
+        .flat_map(|shot_id| shot(shot_id, &shot_messages(shot_id)))
+        .collect();
+    let data = encode(&messages, true);
+    let plain = dir.join("results.bin");
+    std::fs::write(&plain, &data).unwrap();
+    let gzipped = dir.join("results.bin.gz");
+    let mut encoder = flate2::write::GzEncoder::new(
+        std::fs::File::create(&gzipped).unwrap(),
+        flate2::Compression::default(),
+    );
+    encoder.write_all(&data).unwrap();
+    encoder.finish().unwrap();
+    [plain, gzipped]
+}
+
+#[test]
+fn shots_are_read_with_their_encoding() {
+    for path in write_streams("read_shot") {
+        for shot_id in 0..3 {
+            let messages = read_shot(&path, shot_id).unwrap();
+            let expected: Vec<RawMessage> = shot_messages(shot_id).into_iter().map(raw).collect();
+            assert_eq!(messages, expected);
+        }
+        let error = read_shot(&path, 3).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("Shot 3 was not found in '{}'", path.display())
+        );
+    }
+}
+
+#[test]
+fn unfinished_shots_are_reported() {
+    let dir = scratch_dir("read_unfinished_shot");
+    let path = dir.join("results.bin");
+    let mut messages = shot(0, &shot_messages(0));
+    messages.pop();
+    std::fs::write(&path, encode(&messages, true)).unwrap();
+    let error = read_shot(&path, 0).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        format!("Shot 0 did not end in '{}'", path.display())
+    );
+
+    // A stream cut off mid-message cannot be read.
+    let data = encode(&shot(0, &shot_messages(0)), true);
+    std::fs::write(&path, &data[..data.len() - 20]).unwrap();
+    let error = read_shot(&path, 0).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        format!("Failed to read the result stream '{}'", path.display())
+    );
+}
+
+#[test]
+fn matching_shots() {
+    let original = [result(1, 4), result(2, 5)];
+    assert_eq!(compare_shots(&original, &original.clone()).unwrap(), None);
+    assert_eq!(compare_shots(&[], &[]).unwrap(), None);
+}
+
+#[test]
+fn mismatching_shots() {
+    let original = [result(1, 4), result(2, 5)];
+    let mismatch = compare_shots(&original, &[result(1, 4), result(2, 6)])
+        .unwrap()
+        .unwrap();
+    assert_eq!(mismatch.index, 1);
+    assert_eq!(mismatch.reason, "the encoded messages differ");
+    assert_eq!(mismatch.original, Some(original[1].message.clone()));
+    assert_eq!(
+        mismatch.to_string(),
+        "Message 1 differs: the encoded messages differ (the original has \
+         'USER:INT:result' at 2ns, the reproduction has 'USER:INT:result' at 2ns)"
+    );
+
+    // Messages at different times differ too.
+    let mismatch = compare_shots(&original, &[result(1, 4), result(3, 5)])
+        .unwrap()
+        .unwrap();
+    assert_eq!(mismatch.index, 1);
+
+    let mismatch = compare_shots(&original, &original[..1]).unwrap().unwrap();
+    assert_eq!(mismatch.index, 1);
+    assert_eq!(
+        mismatch.reason,
+        "the shots have different numbers of messages"
+    );
+    assert_eq!(mismatch.reproduced, None);
+    assert_eq!(
+        mismatch.to_string(),
+        "Message 1 differs: the shots have different numbers of messages (the \
+         original has 'USER:INT:result' at 2ns, the reproduction has nothing)"
+    );
+}
+
+fn instruction_log(qubit: u64) -> RawMessage {
+    // A qubit allocation by the user program.
+    raw(message(
+        5,
+        "INSTRUCTIONLOG",
+        vec![Value::U64(0), Value::U64(1), Value::U64(qubit)],
+    ))
+}
+
+fn wall_time(seconds: f64) -> RawMessage {
+    raw(message(
+        9,
+        "METRICS:FLOAT:shot_limits:wall_time",
+        vec![Value::F64(seconds)],
+    ))
+}
+
+#[test]
+fn skipped_messages() {
+    // The wall-clock time is never compared.
+    let original = [result(1, 4), wall_time(0.5)];
+    let reproduced = [result(1, 4), wall_time(0.25)];
+    assert_eq!(compare_shots(&original, &reproduced).unwrap(), None);
+    assert_eq!(compare_shots(&original, &original[..1]).unwrap(), None);
+
+    // Instruction logs are only compared if the original has one.
+    let reproduced = [instruction_log(0), result(1, 4)];
+    assert_eq!(compare_shots(&[result(1, 4)], &reproduced).unwrap(), None);
+    let original = [instruction_log(1), result(1, 4)];
+    let mismatch = compare_shots(&original, &reproduced).unwrap().unwrap();
+    assert_eq!(mismatch.index, 0);
+    assert_eq!(mismatch.reason, "the encoded messages differ");
+}
+
+#[test]
+fn state_dumps_are_compared_by_contents() {
+    let dir = scratch_dir("compare_state_dumps");
+    let dump = |file: &str, contents: &[u8]| {
+        let path = dir.join(file);
+        std::fs::write(&path, contents).unwrap();
+        raw(message(
+            3,
+            "USER:STATE:psi",
+            vec![Value::Str(path.to_string_lossy().into_owned())],
+        ))
+    };
+    let original = [dump("original.state", b"state")];
+    let same = [dump("same.state", b"state")];
+    let different = [dump("different.state", b"other")];
+    assert_eq!(compare_shots(&original, &same).unwrap(), None);
+    let mismatch = compare_shots(&original, &different).unwrap().unwrap();
+    assert_eq!(mismatch.reason, "the dumped states differ");
+
+    std::fs::remove_file(dir.join("same.state")).unwrap();
+    let error = compare_shots(&original, &same).unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .starts_with("Failed to read the state dump '"),
+        "{error}"
+    );
+}
+
+fn reproducer(name: &str, extra: &str) -> (ShotReproducer, PathBuf) {
+    let dir = scratch_dir(name);
+    let config_path = dir.join("configuration.yaml");
+    let config = format!(
+        "n_qubits: 2
+output_stream: file://{dir}/results.bin
+artifact_dir: {dir}/artifacts
+simulator: {{name: coinflip, seed: 1, args: []}}
+error_model: {{name: ideal, seed: 2, args: []}}
+runtime: {{name: simple, seed: 3, args: []}}
+event_hooks: {{provide_metrics: true}}
+shots: {{count: 100, offset: 0, increment: 1, workers: 4}}
+checkpoint: {{resume: true}}
+{extra}",
+        dir = dir.display()
+    );
+    std::fs::write(&config_path, config).unwrap();
+    (ShotReproducer::new("program", &config_path).unwrap(), dir)
+}
+
+#[test]
+fn shot_configurations() {
+    let (reproducer, dir) = reproducer("shot_config", "");
+    assert_eq!(reproducer.original_stream(), Some(dir.join("results.bin")));
+    assert_eq!(
+        reproducer.default_output_dir(7),
+        dir.join("artifacts").join("reproduce_shot_7")
+    );
+
+    let output_dir = dir.join("rerun");
+    let config = reproducer.shot_config(7, &output_dir).unwrap();
+    let config: Configuration = serde_yml::from_value(config.into()).unwrap();
+    assert_eq!(
+        config.output_stream,
+        format!("file://{}", output_dir.join("results.bin").display())
+    );
+    assert_eq!(config.artifact_dir, output_dir.join("artifacts"));
+    assert_eq!(
+        (
+            config.shots.count,
+            config.shots.offset,
+            config.shots.increment
+        ),
+        (1, 7, 1)
+    );
+    assert!(config.checkpoint.is_none());
+    // The instruction log is added to the original event hooks.
+    assert!(config.event_hooks.provide_instruction_log);
+    assert!(config.event_hooks.provide_metrics);
+    // The plugins and their seeds are kept.
+    assert_eq!(config.simulator.name, "coinflip");
+    assert_eq!(
+        (
+            config.simulator.seed,
+            config.error_model.seed,
+            config.runtime.seed
+        ),
+        (1, 2, 3)
+    );
+}
+
+#[test]
+fn original_streams_are_decoded() {
+    let (mut reproducer, dir) = reproducer("original_stream_decoded", "");
+    let path = dir.join("with space").join("results.bin");
+    reproducer.configuration.output_stream = Url::from_file_path(&path).unwrap().to_string();
+    assert!(
+        reproducer
+            .configuration
+            .output_stream
+            .contains("with%20space")
+    );
+    assert_eq!(reproducer.original_stream(), Some(path));
+    reproducer.configuration.output_stream = "tcp://localhost:1234".to_string();
+    assert_eq!(reproducer.original_stream(), None);
+}
+
+#[test]
+fn only_binary_streams_are_reproduced() {
+    let (reproducer, dir) =
+        reproducer("shot_config_json", "output_options: {format: json_lines}\n");
+    let error = reproducer.shot_config(0, &dir).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Only runs with the binary output format can be reproduced"
+    );
+}
@@ -1,17 +1,31 @@
 use anyhow::Result;
+use std::io::Write;
 
+pub mod capabilities;
+pub mod checkpoint;
 pub mod configuration;
+pub mod limits;
+pub mod memory_sink;
 pub mod metadata;
+pub mod output_format;
+pub mod output_options;
 pub mod print;
+pub mod program;
 pub mod quantum;
+pub mod recovery;
 pub mod rng;
 pub mod state_dump;
+pub mod workers;
+use checkpoint::Checkpoint;
 use configuration::Configuration;
+use output_options::FlushPolicy;
+use recovery::FailedShot;
 
 use crate::emulator::Emulator;
 use crate::event_hooks::EventHook;
 use rand_pcg::Pcg32;
 use selene_core::encoder::OutputStream;
+use selene_core::error::ErrorKind;
 use selene_core::error_model::ErrorModelInterface;
 use selene_core::runtime::RuntimeInterface;
 
@@ -22,13 +36,64 @@ pub struct SeleneInstance {
     pub time_cursor: u64,
     pub shot_number: u64,
     pub prng: Option<Pcg32>,
+    pub checkpoint: Option<Checkpoint>,
+    /// The shots that have failed, when shots are skipped on error. See
+    /// [recovery].
+    pub failed_shots: Vec<FailedShot>,
+    in_shot: bool,
+    shot_failed: bool,
 }
 
 impl SeleneInstance {
     /// Create a new Selene simulator instance from the provided configuration,
     /// e.g. loaded from a config file.
-    pub fn new(config: Configuration) -> Result<Self> {
-        let mut out_encoder = OutputStream::new(config.get_output_writer());
+    pub fn new(mut config: Configuration) -> Result<Self> {
+        let (out_encoder, checkpoint) = Self::open_output(&mut config).inspect_err(|e| {
+            // There is no output stream to report the error through, so it
+            // can only be communicated via stderr.
+            eprintln!("Failed to open output stream: {e:#}");
+        })?;
+        let mut instance = Self::with_output_stream(config, out_encoder)?;
+        instance.checkpoint = checkpoint;
+        instance.report_capabilities();
+        Ok(instance)
+    }
+
+    /// Create a new Selene simulator instance that writes its results to the
+    /// provided writer, such as one supplied by an embedding host, in place of
+    /// the configured output stream. The configured output options still
+    /// apply. Checkpointing is not supported, as it requires a file output.
+    pub fn with_writer(config: Configuration, writer: Box<dyn Write>) -> Result<Self> {
+        if config.checkpoint.is_some() {
+            return Err(ErrorKind::InvalidArgument
+                .error("Checkpointing is not supported when providing an output writer"));
+        }
+        let writer = config.output_options.wrap(writer)?;
+        let out_encoder = config.output_options.format.open_stream(writer)?;
+        let instance = Self::with_output_stream(config, out_encoder)?;
+        instance.report_capabilities();
+        Ok(instance)
+    }
+
+    fn open_output(config: &mut Configuration) -> Result<(OutputStream, Option<Checkpoint>)> {
+        let (writer, checkpoint) = match config.checkpoint {
+            Some(_) => {
+                let (checkpoint, writer) = Checkpoint::open(config)?;
+                (writer, Some(checkpoint))
+            }
+            None => (config.get_output_writer()?, None),
+        };
+        let writer = config.output_options.wrap(writer)?;
+        let out_encoder = config.output_options.format.open_stream(writer)?;
+        Ok((out_encoder, checkpoint))
+    }
+
+    /// Create a new Selene simulator instance that writes to the provided
+    /// output stream rather than the one described by the configuration.
+    pub fn with_output_stream(
+        config: Configuration,
+        mut out_encoder: OutputStream,
+    ) -> Result<Self> {
         let emulator = match Emulator::from_configuration(&config) {
             Ok(emulator) => emulator,
             Err(e) => {
@@ -37,10 +102,9 @@ impl SeleneInstance {
                 // error through the OutputStream. This can lead to to frontends having to
                 // guess what went wrong during initialization.
                 //
-                // A future API design should consider allowing the output stream to be configured
-                // outside of the config file and passed in.
-                //
-                // Avoiding an API break for now, we write the error to the stream here before returning.
+                // Hosts may create the output stream themselves and pass it in through
+                // `with_writer` or `with_output_stream`, but they may not be the ones reading
+                // it, so we write the error to the stream here before returning.
                 eprintln!("Failed to initialize SeleneInstance: {:?}", e);
                 print::print_directly_to_stream(
                     &mut out_encoder,
@@ -59,12 +123,24 @@ impl SeleneInstance {
             time_cursor: 0,
             shot_number: shot_offset,
             prng: None,
+            checkpoint: None,
+            failed_shots: Vec::new(),
+            in_shot: false,
+            shot_failed: false,
         })
     }
 
-    /// Upon termination of the simulator instance, we signal to the output stream
-    /// that we are closing out gracefully, and flush all remaining data.
+    /// Upon termination of the simulator instance, we let event hooks clean
+    /// up, summarise any failed shots, signal to the output stream that we are closing out gracefully,
+    /// and flush all remaining data.
     pub fn exit(&mut self) -> Result<()> {
+        self.emulator.event_hooks.exit()?;
+        self.write_failed_shots()?;
+        self.close_output()
+    }
+
+    /// Close the output stream, without writing any summaries of the run.
+    fn close_output(&mut self) -> Result<()> {
         self.out_encoder.end_of_stream()?;
         self.out_encoder.flush()?;
         Ok(())
@@ -83,21 +159,46 @@ impl SeleneInstance {
         let shot_id = self.config.shots.offset + self.config.shots.increment * shot_index;
         self.shot_number = shot_id;
         self.print_shot_start()?;
+        self.in_shot = true;
+        self.shot_failed = false;
+        self.emulator.limits.shot_start(shot_id);
         let runtime_seed = self.config.runtime.seed + shot_id;
         let error_model_seed = self.config.error_model.seed + shot_id;
         let simulator_seed = self.config.simulator.seed + shot_id;
 
         // Now we fire off any shot start event hooks and prepare the
         // runtime and error model for the new shot.
-        self.emulator.event_hooks.on_shot_start(shot_id);
-        self.emulator.runtime.shot_start(shot_id, runtime_seed)?;
-        self.emulator
-            .error_model
-            .shot_start(shot_id, error_model_seed, simulator_seed)?;
+        let result = self.emulator.event_hooks.on_shot_start(shot_id);
+        self.recover(result)?;
+        let result = self.emulator.runtime.shot_start(shot_id, runtime_seed);
+        self.recover(result)?;
+        let result =
+            self.emulator
+                .error_model
+                .shot_start(shot_id, error_model_seed, simulator_seed);
+        self.recover(result)?;
         // If the runtime wishes to perform any startup operations
         // before the main shot processing begins, we process them now.
-        self.emulator.poke()?;
-        Ok(())
+        let result = self.poke_within_limits();
+        self.recover(result)
+    }
+
+    /// Process the operations that the runtime issues at the start or end of
+    /// a shot. A shot that exceeds its limits here is reported in the result
+    /// stream, rather than failing the call, so that the frontend carries on
+    /// with the next shot as it would had the program exceeded them. A shot
+    /// that has already exceeded them issues no further operations.
+    fn poke_within_limits(&mut self) -> Result<()> {
+        if self.emulator.limits.exceeded() || self.shot_failed {
+            return Ok(());
+        }
+        match self.emulator.poke() {
+            Err(e) if ErrorKind::of(&e) == ErrorKind::ShotLimitExceeded => {
+                self.print_error(&e);
+                Ok(())
+            }
+            result => result,
+        }
     }
 
     /// End the current shot, flushing any remaining operations
@@ -108,27 +209,49 @@ impl SeleneInstance {
         // Emit any metrics before plugins are invoked with shot_end.
         // This ensures that metrics reflect the state of the shot
         // up to this point.
-        if self.config.event_hooks.provide_metrics {
-            self.write_metrics()?;
+        //
+        // Once a shot has failed, each step is taken regardless of errors,
+        // so that the plugins are ready for the next shot (see [recovery]).
+        if self.config.event_hooks.provide_metrics && !self.shot_failed {
+            let result = self.write_metrics();
+            self.recover(result)?;
         }
         // Tell the runtime that it's time to shut down.
         // This may flush additional operations as part of
         // the shutdown process.
-        self.emulator.runtime.shot_end()?;
+        let result = self.emulator.runtime.shot_end();
+        self.recover(result)?;
         // Handle any operations that resulted from the runtime's shot
         // end process
-        self.emulator.poke()?;
+        let result = self.poke_within_limits();
+        self.recover(result)?;
+        // A shot that exceeded its limits reports its usage, whether or not
+        // metrics were requested.
+        if self.emulator.limits.exceeded() {
+            self.write_limit_metrics()?;
+        }
         // Tell the error model, having already processed anything from
         // the current runtime shot, that the shot is ending. The error model
         // must also end the shot on its internal simulator.
-        self.emulator.error_model.shot_end()?;
+        let result = self.emulator.error_model.shot_end();
+        self.recover(result)?;
         // Finally, write out any stored metadata e.g. instruction logs
         // and inform event hooks that the shot has ended.
-        self.write_metadata()?;
-        self.emulator.event_hooks.on_shot_end();
+        let result = self.write_metadata();
+        self.recover(result)?;
+        let result = self.emulator.event_hooks.on_shot_end();
+        self.recover(result)?;
         // Print shot boundary information so that the result stream
         // is properly delimited.
+        self.in_shot = false;
         self.print_shot_end()?;
+        if self.config.output_options.flush_policy == FlushPolicy::PerShot {
+            self.out_encoder.flush()?;
+        }
+        if let Some(checkpoint) = &mut self.checkpoint {
+            self.out_encoder.flush()?;
+            checkpoint.record(self.shot_number)?;
+        }
         Ok(())
     }
 }
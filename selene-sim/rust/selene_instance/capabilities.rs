@@ -0,0 +1,63 @@
+//! Reporting of the optional plugin features available to a run.
+//!
+//! Before the first shot, the capabilities of the configured runtime, error
+//! model and simulator are written to a capabilities file in the artifact
+//! directory, so that frontends can check that the features a program relies
+//! on, such as postselection or state dumps, are supported by the plugins in
+//! use.
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use selene_core::capabilities::Capabilities;
+
+use super::SeleneInstance;
+
+#[cfg(test)]
+mod tests;
+
+pub const CAPABILITIES_FILE: &str = "capabilities.yaml";
+
+/// The contents of a capabilities file, listing the capabilities of each
+/// plugin by name.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ActiveCapabilities {
+    pub runtime: Vec<String>,
+    pub error_model: Vec<String>,
+    pub simulator: Vec<String>,
+}
+
+fn names(capabilities: Capabilities) -> Vec<String> {
+    capabilities
+        .names()
+        .into_iter()
+        .map(str::to_string)
+        .collect()
+}
+
+impl SeleneInstance {
+    /// The capabilities of the plugins making up the emulator.
+    pub fn capabilities(&self) -> ActiveCapabilities {
+        ActiveCapabilities {
+            runtime: names(self.emulator.runtime.capabilities()),
+            error_model: names(self.emulator.error_model.capabilities()),
+            simulator: names(self.emulator.simulator_capabilities),
+        }
+    }
+
+    /// Write the capabilities of the plugins to the artifact directory.
+    pub fn write_capabilities(&self) -> Result<()> {
+        let path = self.config.artifact_dir.join(CAPABILITIES_FILE);
+        let contents = serde_yml::to_string(&self.capabilities())?;
+        std::fs::write(&path, contents)
+            .with_context(|| format!("Failed to write capabilities to {}", path.display()))
+    }
+
+    /// Write the capabilities file before the first shot. The file is only
+    /// informative, so failing to write it is reported on stderr rather than
+    /// preventing the run.
+    pub(crate) fn report_capabilities(&self) {
+        if let Err(e) = self.write_capabilities() {
+            eprintln!("{e:#}");
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use super::*;
+use crate::selene_instance::configuration::{ConfigurationBuilder, PluginConfig};
+use crate::test_utils::{SharedBuffer, scratch_dir, test_config};
+use anyhow::Result;
+use selene_core::error_model::{BatchResult, ErrorModelInterface, ErrorModelInterfaceFactory};
+use selene_core::registry::register_error_model;
+use selene_core::runtime::BatchOperation;
+use selene_core::utils::MetricValue;
+use selene_ideal_plugin::{IdealErrorModel, IdealErrorModelFactory};
+use std::ffi::OsStr;
+use std::sync::Arc;
+
+/// The ideal error model, reporting the given simulator capabilities in
+/// place of those of the simulator it loaded.
+struct ReportingErrorModel(Box<IdealErrorModel>, Option<Capabilities>);
+
+impl ErrorModelInterface for ReportingErrorModel {
+    fn exit(&mut self) -> Result<()> {
+        self.0.exit()
+    }
+    fn shot_start(&mut self, shot_id: u64, seed: u64, simulator_seed: u64) -> Result<()> {
+        self.0.shot_start(shot_id, seed, simulator_seed)
+    }
+    fn shot_end(&mut self) -> Result<()> {
+        self.0.shot_end()
+    }
+    fn handle_operations(&mut self, operations: BatchOperation) -> Result<BatchResult> {
+        self.0.handle_operations(operations)
+    }
+    fn get_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        self.0.get_metric(nth_metric)
+    }
+    fn get_simulator_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        self.0.get_simulator_metric(nth_metric)
+    }
+    fn simulator_capabilities(&mut self) -> Result<Option<Capabilities>> {
+        Ok(self.1)
+    }
+}
+
+struct ReportingErrorModelFactory(Option<Capabilities>);
+
+impl ErrorModelInterfaceFactory for ReportingErrorModelFactory {
+    type Interface = ReportingErrorModel;
+
+    fn init(
+        self: Arc<Self>,
+        n_qubits: u64,
+        error_model_args: &[impl AsRef<str>],
+        simulator_plugin: &impl AsRef<OsStr>,
+        simulator_args: &[impl AsRef<str>],
+    ) -> Result<Box<Self::Interface>> {
+        let error_model = Arc::new(IdealErrorModelFactory).init(
+            n_qubits,
+            error_model_args,
+            simulator_plugin,
+            simulator_args,
+        )?;
+        Ok(Box::new(ReportingErrorModel(error_model, self.0)))
+    }
+}
+
+fn new_instance(config: ConfigurationBuilder) -> SeleneInstance {
+    let config = config.build().unwrap();
+    SeleneInstance::with_writer(config, Box::new(SharedBuffer::default())).unwrap()
+}
+
+#[test]
+fn capabilities_are_written() {
+    let dir = scratch_dir("capabilities_are_written");
+    let mut instance = new_instance(test_config("capabilities_plugins").artifact_dir(&dir));
+    let expected = ActiveCapabilities {
+        runtime: vec!["batching", "metrics", "conditions", "measure_reset"]
+            .into_iter()
+            .map(str::to_string)
+            .collect(),
+        error_model: vec!["dump_state".to_string()],
+        simulator: vec!["postselect".to_string(), "metrics".to_string()],
+    };
+    assert_eq!(instance.capabilities(), expected);
+    let file = std::fs::File::open(dir.join(CAPABILITIES_FILE)).unwrap();
+    let written: ActiveCapabilities = serde_yml::from_reader(file).unwrap();
+    assert_eq!(written, expected);
+    instance.exit().unwrap();
+}
+
+#[test]
+fn simulator_capabilities_come_from_the_error_model() {
+    register_error_model(
+        "capabilities_reporting",
+        Arc::new(ReportingErrorModelFactory(Some(Capabilities::DUMP_STATE))),
+    );
+    let config = test_config("capabilities_reporting")
+        .error_model(PluginConfig::builtin("capabilities_reporting").with_seed(2));
+    let mut instance = new_instance(config);
+    assert_eq!(instance.capabilities().simulator, vec!["dump_state"]);
+    instance.exit().unwrap();
+
+    // Error models that do not report them fall back to those of the
+    // simulator plugin.
+    register_error_model(
+        "capabilities_unreported",
+        Arc::new(ReportingErrorModelFactory(None)),
+    );
+    let config = test_config("capabilities_unreported")
+        .error_model(PluginConfig::builtin("capabilities_unreported").with_seed(2));
+    let mut instance = new_instance(config);
+    assert_eq!(
+        instance.capabilities().simulator,
+        vec!["postselect", "metrics"]
+    );
+    instance.exit().unwrap();
+}
+
+#[test]
+fn write_failures_are_not_fatal() {
+    // The artifact directory is a file, so nothing can be written within it.
+    let dir = scratch_dir("capabilities_write_failures");
+    let file = dir.join("not_a_directory");
+    std::fs::write(&file, b"").unwrap();
+    let mut instance = new_instance(test_config("capabilities_plugins").artifact_dir(&file));
+    assert!(instance.write_capabilities().is_err());
+    instance
+        .run_shots(|shot| {
+            let q = shot.qalloc()?.unwrap();
+            shot.free(q)
+        })
+        .unwrap();
+    instance.exit().unwrap();
+}
@@ -0,0 +1,175 @@
+//! Checkpointing of long shot runs.
+//!
+//! When a `checkpoint` section is present in the configuration, a checkpoint
+//! file is kept in the artifact directory and updated after every completed
+//! shot, recording the number of shots completed, the id of the last one, and
+//! the length of the output written so far. The output is flushed before each
+//! update, so the recorded length always ends on a shot boundary.
+//!
+//! With `resume` set, a run that finds a checkpoint left by an interrupted run
+//! with the same shot configuration truncates the output to the recorded
+//! length, discarding any partial shot, and continues from the next shot.
+//! As per-shot seeds are derived from shot ids, the resulting output is the
+//! same as that of an uninterrupted run.
+//!
+//! The output must be a `file://` URI, and the output format must be one that
+//! can be appended to, so compressed output and Arrow IPC are not supported.
+use std::cell::Cell;
+use std::io::{Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::configuration::Configuration;
+use super::output_format::OutputFormat;
+use super::output_options::Compression;
+
+#[cfg(test)]
+mod tests;
+
+pub const CHECKPOINT_FILE: &str = "checkpoint.yaml";
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct CheckpointConfig {
+    /// Continue from the checkpoint left by a previous run, if there is one.
+    #[serde(default)]
+    pub resume: bool,
+}
+
+/// The contents of a checkpoint file.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CheckpointState {
+    /// The shot configuration of the run, which must match when resuming.
+    pub shot_count: u64,
+    pub shot_offset: u64,
+    pub shot_increment: u64,
+    pub completed_shots: u64,
+    pub last_shot_id: Option<u64>,
+    pub output_offset: u64,
+}
+
+pub struct Checkpoint {
+    path: PathBuf,
+    state: CheckpointState,
+    bytes_written: Rc<Cell<u64>>,
+}
+
+impl Checkpoint {
+    /// Open the output for a checkpointed run. When resuming, the output is
+    /// reopened at the end of the last completed shot, and the shot
+    /// configuration is advanced past the shots that have already completed.
+    pub fn open(config: &mut Configuration) -> Result<(Self, Box<dyn Write>)> {
+        let Some(checkpoint_config) = &config.checkpoint else {
+            bail!("Checkpointing is not enabled");
+        };
+        if config.output_options.compression != Compression::None {
+            bail!("Checkpointing does not support compressed output");
+        }
+        if config.output_options.format == OutputFormat::ArrowIpc {
+            bail!("Checkpointing does not support Arrow IPC output");
+        }
+        let url = Url::parse(&config.output_stream).ok();
+        let Some(output_path) = url
+            .filter(|url| url.scheme() == "file")
+            .and_then(|url| url.to_file_path().ok())
+        else {
+            bail!("Checkpointing requires a file:// output stream");
+        };
+
+        let path = config.artifact_dir.join(CHECKPOINT_FILE);
+        let previous = if checkpoint_config.resume && path.exists() {
+            let file = std::fs::File::open(&path)
+                .with_context(|| format!("Failed to open checkpoint '{}'", path.display()))?;
+            let state: CheckpointState = serde_yml::from_reader(file)
+                .with_context(|| format!("Failed to parse checkpoint '{}'", path.display()))?;
+            Some(state)
+        } else {
+            None
+        };
+
+        let shots = &mut config.shots;
+        let (state, writer): (CheckpointState, Box<dyn Write>) = match previous {
+            Some(state) => {
+                if (state.shot_count, state.shot_offset, state.shot_increment)
+                    != (shots.count, shots.offset, shots.increment)
+                {
+                    bail!(
+                        "Checkpoint '{}' was written for a different shot configuration",
+                        path.display()
+                    );
+                }
+                let mut file = std::fs::OpenOptions::new()
+                    .write(true)
+                    .open(&output_path)
+                    .with_context(|| {
+                        format!("Failed to reopen output file '{}'", output_path.display())
+                    })?;
+                file.set_len(state.output_offset)?;
+                file.seek(SeekFrom::End(0))?;
+                shots.offset += state.completed_shots * shots.increment;
+                shots.count -= state.completed_shots;
+                (state, Box::new(file))
+            }
+            None => {
+                let state = CheckpointState {
+                    shot_count: shots.count,
+                    shot_offset: shots.offset,
+                    shot_increment: shots.increment,
+                    completed_shots: 0,
+                    last_shot_id: None,
+                    output_offset: 0,
+                };
+                (state, config.get_output_writer()?)
+            }
+        };
+        let bytes_written = Rc::new(Cell::new(state.output_offset));
+        let writer = Box::new(CountingWriter {
+            inner: writer,
+            count: bytes_written.clone(),
+        });
+        let checkpoint = Self {
+            path,
+            state,
+            bytes_written,
+        };
+        Ok((checkpoint, writer))
+    }
+
+    /// Record the completion of a shot. The output stream must have been
+    /// flushed beforehand.
+    pub fn record(&mut self, shot_id: u64) -> Result<()> {
+        self.state.completed_shots += 1;
+        self.state.last_shot_id = Some(shot_id);
+        self.state.output_offset = self.bytes_written.get();
+        // Write to a temporary file and move it into place, so that an
+        // interruption cannot leave a partially written checkpoint.
+        let temp_path = self.path.with_extension("yaml.tmp");
+        let file = std::fs::File::create(&temp_path)
+            .with_context(|| format!("Failed to write checkpoint '{}'", temp_path.display()))?;
+        serde_yml::to_writer(file, &self.state)?;
+        std::fs::rename(&temp_path, &self.path)
+            .with_context(|| format!("Failed to write checkpoint '{}'", self.path.display()))?;
+        Ok(())
+    }
+}
+
+/// Counts the bytes passed on to the output, after any buffering.
+struct CountingWriter {
+    inner: Box<dyn Write>,
+    count: Rc<Cell<u64>>,
+}
+
+impl Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.count.set(self.count.get() + n as u64);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -0,0 +1,168 @@
+use super::*;
+use crate::selene_instance::SeleneInstance;
+use crate::selene_instance::configuration::ConfigurationBuilder;
+use crate::selene_instance::program::Shot;
+use crate::test_utils::{read_shots, scratch_dir, test_config};
+use std::path::Path;
+
+/// A configuration of six shots, writing its output to `out.bin` in the given
+/// directory, with checkpoints kept in the same directory.
+fn checkpointed(dir: &Path, resume: bool) -> ConfigurationBuilder {
+    test_config("checkpoint_plugins")
+        .artifact_dir(dir)
+        .output_stream(format!("file://{}", dir.join("out.bin").display()))
+        .checkpoint(CheckpointConfig { resume })
+        .shots(6, 20, 5)
+}
+
+fn program(shot: &mut Shot) -> Result<()> {
+    let q = shot.qalloc()?.unwrap();
+    shot.rxy(&q, 0.5, 0.25)?;
+    let result = shot.measure_and_free(q)?;
+    let value = shot.read(&result)?;
+    shot.print("USER:BOOL:result", value)?;
+    let id = shot.shot_id();
+    shot.print("USER:INT:shot_id", id)
+}
+
+fn run(config: ConfigurationBuilder) -> Result<()> {
+    let mut instance = SeleneInstance::new(config.build()?)?;
+    instance.run_shots(program)?;
+    instance.exit()
+}
+
+/// Runs the configuration, stopping abruptly part way through the shot with
+/// the given id, as if the process had been killed.
+fn run_interrupted(config: ConfigurationBuilder, interrupted_shot: u64) {
+    let mut instance = SeleneInstance::new(config.build().unwrap()).unwrap();
+    let interrupted = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        instance.run_shots(|shot| {
+            let id = shot.shot_id();
+            if id == interrupted_shot {
+                shot.print("USER:INT:partial", id)?;
+                panic!("interrupted");
+            }
+            program(shot)
+        })
+    }));
+    assert!(interrupted.is_err());
+    // Nothing is cleaned up after a kill.
+    std::mem::forget(instance);
+}
+
+fn read_state(dir: &Path) -> CheckpointState {
+    let file = std::fs::File::open(dir.join(CHECKPOINT_FILE)).unwrap();
+    serde_yml::from_reader(file).unwrap()
+}
+
+fn output(dir: &Path) -> Vec<u8> {
+    std::fs::read(dir.join("out.bin")).unwrap()
+}
+
+#[test]
+fn checkpoint_is_written() {
+    let dir = scratch_dir("checkpoint_is_written");
+    run(checkpointed(&dir, false)).unwrap();
+    let state = read_state(&dir);
+    assert_eq!(
+        state,
+        CheckpointState {
+            shot_count: 6,
+            shot_offset: 20,
+            shot_increment: 5,
+            completed_shots: 6,
+            last_shot_id: Some(45),
+            output_offset: state.output_offset,
+        }
+    );
+    // The recorded offset is the end of the last shot, which is followed
+    // only by the end of the stream.
+    let output = output(&dir);
+    let shots = read_shots(&output);
+    assert_eq!(shots.len(), 6);
+    assert_eq!(shots[5].shot_id, 45);
+    let end_of_stream = &output[state.output_offset as usize..];
+    assert!(!end_of_stream.is_empty());
+    assert!(end_of_stream.iter().all(|&byte| byte == 0xff));
+}
+
+#[test]
+fn resume_is_byte_identical() {
+    let reference = scratch_dir("resume_reference");
+    run(checkpointed(&reference, false)).unwrap();
+
+    let dir = scratch_dir("resume_interrupted");
+    run_interrupted(checkpointed(&dir, true), 35);
+    let state = read_state(&dir);
+    assert_eq!(state.completed_shots, 3);
+    assert_eq!(state.last_shot_id, Some(30));
+    // The interrupted shot was partially written, and must be discarded.
+    assert!((output(&dir).len() as u64) > state.output_offset);
+
+    run(checkpointed(&dir, true)).unwrap();
+    assert_eq!(output(&dir), output(&reference));
+    assert_eq!(read_state(&dir), read_state(&reference));
+}
+
+#[test]
+fn resume_with_workers() {
+    let reference = scratch_dir("workers_reference");
+    run(checkpointed(&reference, false)).unwrap();
+
+    // Shots merged from several workers are checkpointed as they are
+    // written.
+    let dir = scratch_dir("workers_full");
+    run(checkpointed(&dir, false).workers(3)).unwrap();
+    assert_eq!(output(&dir), output(&reference));
+    assert_eq!(read_state(&dir), read_state(&reference));
+
+    // A run interrupted with one worker can be resumed with several.
+    let dir = scratch_dir("workers_resumed");
+    run_interrupted(checkpointed(&dir, true), 25);
+    assert_eq!(read_state(&dir).completed_shots, 1);
+    run(checkpointed(&dir, true).workers(3)).unwrap();
+    assert_eq!(output(&dir), output(&reference));
+}
+
+#[test]
+fn resume_without_checkpoint_starts_afresh() {
+    let reference = scratch_dir("afresh_reference");
+    run(checkpointed(&reference, false)).unwrap();
+    let dir = scratch_dir("afresh");
+    run(checkpointed(&dir, true)).unwrap();
+    assert_eq!(output(&dir), output(&reference));
+}
+
+#[test]
+fn resume_rejects_different_shots() {
+    let dir = scratch_dir("resume_rejects_different_shots");
+    run_interrupted(checkpointed(&dir, true), 30);
+    let error = run(checkpointed(&dir, true).shots(7, 20, 5)).unwrap_err();
+    assert!(
+        error.to_string().contains("different shot configuration"),
+        "{error:#}"
+    );
+    // Without resuming, the checkpoint is ignored and overwritten.
+    run(checkpointed(&dir, false).shots(7, 20, 5)).unwrap();
+    assert_eq!(read_state(&dir).completed_shots, 7);
+}
+
+#[test]
+fn unsupported_outputs() {
+    let dir = scratch_dir("checkpoint_unsupported_outputs");
+    let mut config = checkpointed(&dir, false)
+        .output_format(OutputFormat::ArrowIpc)
+        .build()
+        .unwrap();
+    assert!(Checkpoint::open(&mut config).is_err());
+
+    let mut config = checkpointed(&dir, false).build().unwrap();
+    config.output_options.compression = Compression::Zstd;
+    assert!(Checkpoint::open(&mut config).is_err());
+
+    let mut config = checkpointed(&dir, false)
+        .output_stream("stdout")
+        .build()
+        .unwrap();
+    assert!(Checkpoint::open(&mut config).is_err());
+}
@@ -1,8 +1,21 @@
+use super::checkpoint::CheckpointConfig;
+use super::limits::ShotLimits;
+use super::memory_sink::{self, MemorySinkWriter};
+use super::output_format::OutputFormat;
+use super::output_options::OutputOptions;
+use super::recovery::ErrorPolicy;
+use crate::event_hooks::operation_log::OperationLogFormat;
+use anyhow::{Context, Result, anyhow, bail};
+use selene_core::error::ErrorKind;
+use selene_core::{host, registry};
 use serde::Deserialize;
 use std::io::Write;
 use std::path::PathBuf;
 use url::Url;
 
+#[cfg(test)]
+mod tests;
+
 fn disable_by_default() -> bool {
     false
 }
@@ -11,82 +24,421 @@ fn random_by_default() -> u64 {
     rand::random()
 }
 
+fn one_by_default() -> u64 {
+    1
+}
+
 /// Configuration for plugins, e.g. the simulator, error model, and runtime.
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct PluginConfig {
     pub name: String,
     #[serde(default = "random_by_default")]
     pub seed: u64,
-    pub file: PathBuf,
+    /// The plugin library to load. Without one, the plugin registered under
+    /// `name` is used (see [plugins](crate::plugins)).
+    #[serde(default)]
+    pub file: Option<PathBuf>,
     pub args: Vec<String>,
+    /// Host the plugin in a separate process, so that a crash of the plugin
+    /// is reported rather than ending the run (see [selene_core::host]).
+    /// Only simulators and error models can be hosted out of process, and an
+    /// isolated error model hosts its simulator in the same process. Event
+    /// hooks cannot be hosted out of process.
+    #[serde(default)]
+    pub isolated: bool,
+}
+
+impl PluginConfig {
+    /// A plugin loaded from the given file, with no arguments and a random seed.
+    pub fn new(name: impl Into<String>, file: impl Into<PathBuf>) -> Self {
+        Self {
+            name: name.into(),
+            seed: random_by_default(),
+            file: Some(file.into()),
+            args: Vec::new(),
+            isolated: false,
+        }
+    }
+
+    /// The plugin registered under the given name, with no arguments and a
+    /// random seed.
+    pub fn builtin(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            seed: random_by_default(),
+            file: None,
+            args: Vec::new(),
+            isolated: false,
+        }
+    }
+
+    /// The path to load the plugin from, referring to a registered plugin if
+    /// no file is given.
+    pub fn path(&self) -> PathBuf {
+        let path = match &self.file {
+            Some(file) => file.clone(),
+            None => PathBuf::from(registry::builtin_path(&self.name)),
+        };
+        if self.isolated {
+            PathBuf::from(host::isolated_path(path))
+        } else {
+            path
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_isolation(mut self, isolated: bool) -> Self {
+        self.isolated = isolated;
+        self
+    }
 }
 
-#[derive(Deserialize, Debug)]
+/// The role of a plugin within the emulator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginRole {
+    Simulator,
+    ErrorModel,
+    Runtime,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
 pub struct EventHookConfig {
     #[serde(default = "disable_by_default")]
     pub provide_instruction_log: bool,
     #[serde(default = "disable_by_default")]
     pub provide_metrics: bool,
+    /// Writes the operations emitted by the runtime in each shot to the
+    /// artifact directory, in the given format (see
+    /// [event_hooks::operation_log](crate::event_hooks::operation_log)).
+    #[serde(default)]
+    pub operation_log: Option<OperationLogFormat>,
+    /// Event hooks loaded from plugins, built with
+    /// `selene_core::export_event_hook_plugin!` or implementing its C
+    /// interface. Their metrics are written under their names (see
+    /// [event_hooks::plugin](crate::event_hooks::plugin)).
+    #[serde(default)]
+    pub plugins: Vec<PluginConfig>,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct ShotConfig {
     pub count: u64,
     pub offset: u64,
     pub increment: u64,
+    /// The number of worker threads to run the shots across. See
+    /// [workers](super::workers).
+    #[serde(default = "one_by_default")]
+    pub workers: u64,
+}
+
+impl ShotConfig {
+    /// The number of workers that will be used, which is at most one per shot.
+    pub fn n_workers(&self) -> u64 {
+        self.workers.min(self.count).max(1)
+    }
+
+    /// The shots run by one of `n_workers` workers, which takes every
+    /// `n_workers`th shot starting from the `worker`th.
+    pub fn partition(&self, worker: u64, n_workers: u64) -> ShotConfig {
+        Self {
+            count: (self.count + n_workers - 1 - worker) / n_workers,
+            offset: self.offset + worker * self.increment,
+            increment: self.increment * n_workers,
+            workers: 1,
+        }
+    }
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct Configuration {
     pub n_qubits: u64,
     pub output_stream: String,
+    #[serde(default)]
+    pub output_options: OutputOptions,
     pub artifact_dir: PathBuf,
     pub simulator: PluginConfig,
     pub error_model: PluginConfig,
     pub runtime: PluginConfig,
     pub event_hooks: EventHookConfig,
     pub shots: ShotConfig,
+    /// Enables checkpointing of progress through the shots. See
+    /// [checkpoint](super::checkpoint).
+    #[serde(default)]
+    pub checkpoint: Option<CheckpointConfig>,
+    /// Limits on the resources used by each shot. See
+    /// [limits](super::limits).
+    #[serde(default)]
+    pub limits: ShotLimits,
+    /// What to do when an error is raised during a shot. See
+    /// [recovery](super::recovery).
+    #[serde(default)]
+    pub on_error: ErrorPolicy,
 }
 
 impl Configuration {
-    /// Get writer based on output arg (can be a file or a tcp address
-    /// at the moment)
-    pub fn get_output_writer(&self) -> Box<dyn std::io::Write> {
+    /// Reads a configuration from a YAML file.
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let file = std::fs::File::open(path).context("Error opening config file")?;
+        serde_yml::from_reader(file).context("Error parsing config file")
+    }
+
+    /// Get writer based on output arg. Supported outputs are `stdout`,
+    /// `stderr`, and URIs with the schemes:
+    /// - `file://<path>`, truncating or creating the file.
+    /// - `pipe://<path>`, writing to an existing named pipe.
+    /// - `tcp://<host>:<port>`, connecting to a listening socket.
+    /// - `unix://<path>`, connecting to a listening unix domain socket.
+    /// - `memory://<name>[?capacity=<bytes>]`, writing to an in-memory
+    ///   ring buffer. See [memory_sink](super::memory_sink).
+    pub fn get_output_writer(&self) -> Result<Box<dyn Write>> {
         match self.output_stream.as_str() {
-            "stdout" => Box::new(std::io::stdout()),
-            "stderr" => Box::new(std::io::stderr()),
+            "stdout" => Ok(Box::new(std::io::stdout())),
+            "stderr" => Ok(Box::new(std::io::stderr())),
             uri => {
-                let url = Url::parse(uri).unwrap();
+                let url = Url::parse(uri)
+                    .with_context(|| format!("Invalid output stream URI '{uri}'"))?;
                 match url.scheme() {
                     "file" => {
-                        let path = url.path();
+                        let path = url
+                            .to_file_path()
+                            .map_err(|_| anyhow!("Output stream URI '{uri}' is not a file path"))?;
                         let file = std::fs::OpenOptions::new()
                             .write(true)
                             .create(true)
                             .truncate(true)
+                            .open(&path)
+                            .with_context(|| {
+                                format!("Failed to open output file '{}'", path.display())
+                            })?;
+                        Ok(Box::new(file))
+                    }
+                    "pipe" => {
+                        let path = url.path();
+                        let pipe = std::fs::OpenOptions::new()
+                            .write(true)
                             .open(path)
-                            .unwrap();
-                        Box::new(file)
+                            .with_context(|| format!("Failed to open output pipe '{path}'"))?;
+                        Ok(Box::new(pipe))
                     }
                     "tcp" => {
-                        let host = url.host_str().unwrap();
-                        let port = url.port().unwrap();
-                        let mut stream = std::net::TcpStream::connect((host, port)).unwrap();
-                        // Communicate the shot configuration to the client
-                        stream
-                            .write_all(self.shots.offset.to_le_bytes().as_slice())
-                            .unwrap();
-                        stream
-                            .write_all(self.shots.increment.to_le_bytes().as_slice())
-                            .unwrap();
-                        stream
-                            .write_all(self.shots.count.to_le_bytes().as_slice())
-                            .unwrap();
-                        Box::new(stream)
+                        let host = url
+                            .host_str()
+                            .ok_or_else(|| anyhow!("Output stream URI '{uri}' has no host"))?;
+                        let port = url
+                            .port()
+                            .ok_or_else(|| anyhow!("Output stream URI '{uri}' has no port"))?;
+                        let mut stream = std::net::TcpStream::connect((host, port))
+                            .with_context(|| format!("Failed to connect to '{uri}'"))?;
+                        self.write_shot_preamble(&mut stream)?;
+                        Ok(Box::new(stream))
                     }
-                    _ => panic!("Unsupported output scheme: {}", url.scheme()),
+                    #[cfg(unix)]
+                    "unix" => {
+                        let path = url.path();
+                        let mut stream = std::os::unix::net::UnixStream::connect(path)
+                            .with_context(|| format!("Failed to connect to '{uri}'"))?;
+                        self.write_shot_preamble(&mut stream)?;
+                        Ok(Box::new(stream))
+                    }
+                    "memory" => {
+                        let name = url.host_str().unwrap_or_default();
+                        if name.is_empty() {
+                            bail!("Output stream URI '{uri}' has no sink name");
+                        }
+                        let mut capacity = memory_sink::DEFAULT_CAPACITY;
+                        for (key, value) in url.query_pairs() {
+                            match key.as_ref() {
+                                "capacity" => {
+                                    capacity = value.parse().with_context(|| {
+                                        format!("Invalid memory sink capacity '{value}'")
+                                    })?
+                                }
+                                other => bail!("Unknown memory sink option '{other}'"),
+                            }
+                        }
+                        Ok(Box::new(MemorySinkWriter::open(name, capacity)?))
+                    }
+                    scheme => bail!("Unsupported output scheme: {scheme}"),
                 }
             }
         }
     }
+
+    /// Communicate the shot configuration to a connected client.
+    fn write_shot_preamble(&self, stream: &mut impl Write) -> Result<()> {
+        stream.write_all(self.shots.offset.to_le_bytes().as_slice())?;
+        stream.write_all(self.shots.increment.to_le_bytes().as_slice())?;
+        stream.write_all(self.shots.count.to_le_bytes().as_slice())?;
+        Ok(())
+    }
+}
+
+/// Builds a [Configuration] programmatically, as an alternative to loading
+/// one from a config file. The number of qubits and all three plugins must be
+/// provided. Otherwise, output goes to stdout in the binary format, artifacts
+/// are written to the system's temporary directory, event hooks are disabled,
+/// and a single shot is run.
+#[derive(Debug, Clone, Default)]
+pub struct ConfigurationBuilder {
+    n_qubits: Option<u64>,
+    output_stream: Option<String>,
+    output_options: OutputOptions,
+    artifact_dir: Option<PathBuf>,
+    simulator: Option<PluginConfig>,
+    error_model: Option<PluginConfig>,
+    runtime: Option<PluginConfig>,
+    event_hooks: EventHookConfig,
+    shots: Option<ShotConfig>,
+    checkpoint: Option<CheckpointConfig>,
+    limits: ShotLimits,
+    on_error: ErrorPolicy,
+}
+
+impl Configuration {
+    pub fn builder() -> ConfigurationBuilder {
+        ConfigurationBuilder::default()
+    }
+}
+
+impl ConfigurationBuilder {
+    pub fn n_qubits(mut self, n_qubits: u64) -> Self {
+        self.n_qubits = Some(n_qubits);
+        self
+    }
+
+    /// The output stream, in any of the forms accepted by
+    /// [Configuration::get_output_writer]. This is not used by instances
+    /// created with their own writer.
+    pub fn output_stream(mut self, output_stream: impl Into<String>) -> Self {
+        self.output_stream = Some(output_stream.into());
+        self
+    }
+
+    pub fn output_options(mut self, output_options: OutputOptions) -> Self {
+        self.output_options = output_options;
+        self
+    }
+
+    pub fn output_format(mut self, format: OutputFormat) -> Self {
+        self.output_options.format = format;
+        self
+    }
+
+    pub fn artifact_dir(mut self, artifact_dir: impl Into<PathBuf>) -> Self {
+        self.artifact_dir = Some(artifact_dir.into());
+        self
+    }
+
+    pub fn simulator(self, simulator: PluginConfig) -> Self {
+        self.plugin(PluginRole::Simulator, simulator)
+    }
+
+    pub fn error_model(self, error_model: PluginConfig) -> Self {
+        self.plugin(PluginRole::ErrorModel, error_model)
+    }
+
+    pub fn runtime(self, runtime: PluginConfig) -> Self {
+        self.plugin(PluginRole::Runtime, runtime)
+    }
+
+    pub fn plugin(mut self, role: PluginRole, plugin: PluginConfig) -> Self {
+        *self.plugin_mut(role) = Some(plugin);
+        self
+    }
+
+    /// The configuration of the plugin with the given role, if it has been set.
+    pub fn plugin_mut(&mut self, role: PluginRole) -> &mut Option<PluginConfig> {
+        match role {
+            PluginRole::Simulator => &mut self.simulator,
+            PluginRole::ErrorModel => &mut self.error_model,
+            PluginRole::Runtime => &mut self.runtime,
+        }
+    }
+
+    pub fn event_hooks(mut self, event_hooks: EventHookConfig) -> Self {
+        self.event_hooks = event_hooks;
+        self
+    }
+
+    pub fn event_hooks_mut(&mut self) -> &mut EventHookConfig {
+        &mut self.event_hooks
+    }
+
+    /// Load an event hook from a plugin, in addition to any already added.
+    pub fn event_hook_plugin(mut self, plugin: PluginConfig) -> Self {
+        self.event_hooks.plugins.push(plugin);
+        self
+    }
+
+    /// Run `count` shots with ids starting from `offset` and separated by
+    /// `increment`.
+    pub fn shots(mut self, count: u64, offset: u64, increment: u64) -> Self {
+        let workers = self.shots.as_ref().map_or(1, |shots| shots.workers);
+        self.shots = Some(ShotConfig {
+            count,
+            offset,
+            increment,
+            workers,
+        });
+        self
+    }
+
+    /// The number of worker threads to run the shots across.
+    pub fn workers(mut self, workers: u64) -> Self {
+        self.shots.get_or_insert_with(default_shots).workers = workers;
+        self
+    }
+
+    pub fn checkpoint(mut self, checkpoint: CheckpointConfig) -> Self {
+        self.checkpoint = Some(checkpoint);
+        self
+    }
+
+    pub fn limits(mut self, limits: ShotLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn on_error(mut self, on_error: ErrorPolicy) -> Self {
+        self.on_error = on_error;
+        self
+    }
+
+    pub fn build(self) -> Result<Configuration> {
+        let missing = |field: &str| {
+            ErrorKind::InvalidArgument.error(format!("The configuration has no {field}"))
+        };
+        Ok(Configuration {
+            n_qubits: self.n_qubits.ok_or_else(|| missing("number of qubits"))?,
+            output_stream: self.output_stream.unwrap_or_else(|| "stdout".to_string()),
+            output_options: self.output_options,
+            artifact_dir: self.artifact_dir.unwrap_or_else(std::env::temp_dir),
+            simulator: self.simulator.ok_or_else(|| missing("simulator"))?,
+            error_model: self.error_model.ok_or_else(|| missing("error model"))?,
+            runtime: self.runtime.ok_or_else(|| missing("runtime"))?,
+            event_hooks: self.event_hooks,
+            shots: self.shots.unwrap_or_else(default_shots),
+            checkpoint: self.checkpoint,
+            limits: self.limits,
+            on_error: self.on_error,
+        })
+    }
+}
+
+fn default_shots() -> ShotConfig {
+    ShotConfig {
+        count: 1,
+        offset: 0,
+        increment: 1,
+        workers: 1,
+    }
 }
@@ -0,0 +1,163 @@
+use super::*;
+use crate::test_utils::scratch_dir;
+use std::io::Read;
+
+fn configuration(output_stream: &str) -> Configuration {
+    Configuration::builder()
+        .n_qubits(1)
+        .output_stream(output_stream)
+        .simulator(PluginConfig::builtin("coinflip"))
+        .error_model(PluginConfig::builtin("ideal"))
+        .runtime(PluginConfig::builtin("simple"))
+        .shots(3, 10, 2)
+        .build()
+        .unwrap()
+}
+
+fn writer(output_stream: &str) -> Result<Box<dyn Write>> {
+    configuration(output_stream).get_output_writer()
+}
+
+#[test]
+fn memory_uri() {
+    let mut output = writer("memory://uri_sink?capacity=8").unwrap();
+    output.write_all(b"abc").unwrap();
+    drop(output);
+    let mut data = Vec::new();
+    memory_sink::MemorySinkReader::open("uri_sink")
+        .read_to_end(&mut data)
+        .unwrap();
+    assert_eq!(data, b"abc");
+
+    assert!(writer("memory://").is_err());
+    assert!(writer("memory://bad_capacity?capacity=lots").is_err());
+    assert!(writer("memory://bad_option?colour=blue").is_err());
+}
+
+#[test]
+fn file_uri() {
+    let dir = scratch_dir("file_uri");
+    let path = dir.join("with space").join("results.bin");
+    std::fs::create_dir(path.parent().unwrap()).unwrap();
+    // Percent-encoded paths are decoded.
+    let mut output = writer(Url::from_file_path(&path).unwrap().as_str()).unwrap();
+    output.write_all(b"abc").unwrap();
+    drop(output);
+    assert_eq!(std::fs::read(&path).unwrap(), b"abc");
+}
+
+#[test]
+fn pipe_uri() {
+    let dir = scratch_dir("pipe_uri");
+    let path = dir.join("pipe");
+    // Pipes must already exist, unlike files.
+    assert!(writer(&format!("pipe://{}", path.display())).is_err());
+    std::fs::write(&path, b"").unwrap();
+    let mut output = writer(&format!("pipe://{}", path.display())).unwrap();
+    output.write_all(b"abc").unwrap();
+    drop(output);
+    assert_eq!(std::fs::read(&path).unwrap(), b"abc");
+}
+
+#[cfg(unix)]
+#[test]
+fn unix_uri() {
+    let dir = scratch_dir("unix_uri");
+    let path = dir.join("socket");
+    let uri = format!("unix://{}", path.display());
+    assert!(writer(&uri).is_err());
+    let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+    let mut output = writer(&uri).unwrap();
+    output.write_all(b"abc").unwrap();
+    drop(output);
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut data = Vec::new();
+    stream.read_to_end(&mut data).unwrap();
+    // The shot configuration is sent ahead of the output.
+    let mut expected = Vec::new();
+    for value in [10u64, 2, 3] {
+        expected.extend(value.to_le_bytes());
+    }
+    expected.extend(b"abc");
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn unsupported_uri() {
+    assert!(writer("ftp://example.com/results").is_err());
+    assert!(writer("not a uri").is_err());
+}
+
+#[test]
+fn build_requires_qubits_and_plugins() {
+    let complete = || {
+        Configuration::builder()
+            .n_qubits(1)
+            .simulator(PluginConfig::builtin("coinflip"))
+            .error_model(PluginConfig::builtin("ideal"))
+            .runtime(PluginConfig::builtin("simple"))
+    };
+    let mut incomplete = vec![(ConfigurationBuilder::default(), "number of qubits")];
+    for (role, name) in [
+        (PluginRole::Simulator, "simulator"),
+        (PluginRole::ErrorModel, "error model"),
+        (PluginRole::Runtime, "runtime"),
+    ] {
+        let mut builder = complete();
+        *builder.plugin_mut(role) = None;
+        incomplete.push((builder, name));
+    }
+    for (builder, missing) in incomplete {
+        let error = builder.build().unwrap_err();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::InvalidArgument);
+        assert_eq!(
+            error.to_string(),
+            format!("The configuration has no {missing}")
+        );
+    }
+
+    let config = complete().build().unwrap();
+    assert_eq!(config.output_stream, "stdout");
+    assert_eq!(config.output_options.format, OutputFormat::Binary);
+    assert_eq!(
+        (
+            config.shots.count,
+            config.shots.offset,
+            config.shots.increment
+        ),
+        (1, 0, 1)
+    );
+    assert_eq!(config.shots.workers, 1);
+    assert!(config.checkpoint.is_none());
+}
+
+#[test]
+fn builder_keeps_workers_with_shots() {
+    let config = Configuration::builder()
+        .n_qubits(1)
+        .simulator(PluginConfig::builtin("coinflip"))
+        .error_model(PluginConfig::builtin("ideal"))
+        .runtime(
+            PluginConfig::builtin("simple")
+                .with_seed(5)
+                .with_args(["--a"]),
+        )
+        .workers(3)
+        .shots(10, 2, 4)
+        .build()
+        .unwrap();
+    assert_eq!(config.shots.workers, 3);
+    assert_eq!(config.shots.n_workers(), 3);
+    assert_eq!(config.runtime.seed, 5);
+    assert_eq!(config.runtime.args, vec!["--a"]);
+    assert_eq!(config.runtime.path(), PathBuf::from("builtin:simple"));
+    // Every shot is run by exactly one worker.
+    let mut ids: Vec<u64> = (0..3)
+        .flat_map(|worker| {
+            let shots = config.shots.partition(worker, 3);
+            (0..shots.count).map(move |i| shots.offset + i * shots.increment)
+        })
+        .collect();
+    ids.sort();
+    assert_eq!(ids, (0..10).map(|i| 2 + 4 * i).collect::<Vec<_>>());
+}
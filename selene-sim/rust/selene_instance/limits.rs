@@ -0,0 +1,148 @@
+//! Limits on the resources used by a single shot.
+//!
+//! A runtime that never stops emitting operations, or a program that issues
+//! them without end, would otherwise keep a shot running forever. Each limit
+//! configured in [ShotLimits] is checked as the shot progresses, and the
+//! first to be exceeded fails the operation that exceeded it with an error of
+//! kind [ErrorKind::ShotLimitExceeded]. Its code is below 1000, so frontends
+//! end the shot and carry on with the next one. Operations issued later in
+//! the shot fail in the same way, and the usage of the shot is written to the
+//! result stream as metrics under `shot_limits` when the shot ends.
+//!
+//! Time spent in the program between calls to selene is only noticed on its
+//! next call, so a program that never calls back is not stopped by
+//! `max_wall_time`.
+use std::time::Instant;
+
+use anyhow::Result;
+use selene_core::error::ErrorKind;
+use selene_core::utils::MetricValue;
+use serde::Deserialize;
+
+/// The limits applied to each shot. Limits that are not set do not apply.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct ShotLimits {
+    /// The maximum number of operations that the program may issue.
+    pub max_operations: Option<u64>,
+    /// The maximum number of batches of operations that the runtime may emit.
+    pub max_batches: Option<u64>,
+    /// The maximum wall-clock time of the shot, in seconds.
+    pub max_wall_time: Option<f64>,
+    /// The maximum number of qubits that may be allocated at once.
+    pub max_qubits: Option<u64>,
+}
+
+/// Tracks the usage of the current shot against its limits.
+pub struct ShotLimiter {
+    limits: ShotLimits,
+    shot_id: u64,
+    started: Instant,
+    operations: u64,
+    batches: u64,
+    allocated_qubits: u64,
+    peak_allocated_qubits: u64,
+    /// The description of the limit that the shot exceeded, if any.
+    exceeded: Option<String>,
+}
+
+impl ShotLimiter {
+    pub fn new(limits: ShotLimits) -> Self {
+        Self {
+            limits,
+            shot_id: 0,
+            started: Instant::now(),
+            operations: 0,
+            batches: 0,
+            allocated_qubits: 0,
+            peak_allocated_qubits: 0,
+            exceeded: None,
+        }
+    }
+
+    pub fn shot_start(&mut self, shot_id: u64) {
+        *self = Self {
+            shot_id,
+            ..Self::new(std::mem::take(&mut self.limits))
+        };
+    }
+
+    /// Whether the current shot has exceeded one of its limits.
+    pub fn exceeded(&self) -> bool {
+        self.exceeded.is_some()
+    }
+
+    /// Record an operation issued by the program.
+    pub fn operation(&mut self) -> Result<()> {
+        self.operations += 1;
+        self.check(
+            self.limits.max_operations,
+            self.operations,
+            "operations issued",
+        )
+    }
+
+    /// Record a batch of operations emitted by the runtime.
+    pub fn batch(&mut self) -> Result<()> {
+        self.batches += 1;
+        self.check(self.limits.max_batches, self.batches, "runtime batches")
+    }
+
+    /// Record the allocation of a qubit by the program, before it is made.
+    pub fn qalloc(&mut self) -> Result<()> {
+        self.operation()?;
+        self.check(
+            self.limits.max_qubits,
+            self.allocated_qubits + 1,
+            "qubits allocated at once",
+        )
+    }
+
+    /// Record the outcome of an allocation, given the address the runtime
+    /// returned for it.
+    pub fn allocated(&mut self, address: u64) {
+        if address != u64::MAX {
+            self.allocated_qubits += 1;
+            self.peak_allocated_qubits = self.peak_allocated_qubits.max(self.allocated_qubits);
+        }
+    }
+
+    pub fn freed(&mut self) {
+        self.allocated_qubits = self.allocated_qubits.saturating_sub(1);
+    }
+
+    fn check(&mut self, limit: Option<u64>, usage: u64, what: &str) -> Result<()> {
+        if self.exceeded.is_none() {
+            if let Some(limit) = limit.filter(|&limit| usage > limit) {
+                self.exceeded = Some(format!("the limit of {limit} {what}"));
+            } else if let Some(limit) = self
+                .limits
+                .max_wall_time
+                .filter(|&limit| self.started.elapsed().as_secs_f64() > limit)
+            {
+                self.exceeded = Some(format!("the wall-clock limit of {limit}s"));
+            }
+        }
+        match &self.exceeded {
+            Some(exceeded) => Err(ErrorKind::ShotLimitExceeded
+                .error(format!("Shot {} exceeded {exceeded}", self.shot_id))),
+            None => Ok(()),
+        }
+    }
+
+    /// The usage of the current shot, as metrics.
+    pub fn metrics(&self) -> [(&'static str, MetricValue); 4] {
+        [
+            ("operations", MetricValue::U64(self.operations)),
+            ("batches", MetricValue::U64(self.batches)),
+            (
+                "wall_time",
+                MetricValue::F64(self.started.elapsed().as_secs_f64()),
+            ),
+            (
+                "peak_allocated_qubits",
+                MetricValue::U64(self.peak_allocated_qubits),
+            ),
+        ]
+    }
+}
@@ -0,0 +1,177 @@
+//! Named in-memory output sinks, for applications that embed selene and wish
+//! to consume the result stream without going through the filesystem or a
+//! socket.
+//!
+//! A sink is selected with an output stream URI of the form
+//! `memory://<name>?capacity=<bytes>`. The emulator writes into a bounded
+//! ring buffer registered under `<name>`, and the embedding application reads
+//! from it with [MemorySinkReader] (or `selene_memory_sink_read` over FFI),
+//! typically on another thread. The writer blocks while the buffer is full,
+//! so no output is lost, but the buffer must be drained concurrently for the
+//! emulator to make progress.
+use anyhow::{Result, bail};
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Write};
+use std::sync::{Arc, Condvar, LazyLock, Mutex, MutexGuard};
+
+#[cfg(test)]
+mod tests;
+
+pub const DEFAULT_CAPACITY: usize = 1 << 20;
+
+static SINKS: LazyLock<Mutex<HashMap<String, Arc<MemorySink>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+struct RingBuffer {
+    data: VecDeque<u8>,
+    capacity: usize,
+    writer_attached: bool,
+    closed: bool,
+}
+
+struct MemorySink {
+    state: Mutex<RingBuffer>,
+    readable: Condvar,
+    writable: Condvar,
+}
+
+impl MemorySink {
+    fn lock(&self) -> MutexGuard<'_, RingBuffer> {
+        // A panic while holding the lock cannot leave the buffer in an
+        // inconsistent state, so poisoning is ignored.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn get_or_create(name: &str) -> Arc<MemorySink> {
+    let mut sinks = SINKS.lock().unwrap_or_else(|e| e.into_inner());
+    sinks
+        .entry(name.to_string())
+        .or_insert_with(|| {
+            Arc::new(MemorySink {
+                state: Mutex::new(RingBuffer {
+                    data: VecDeque::new(),
+                    capacity: DEFAULT_CAPACITY,
+                    writer_attached: false,
+                    closed: false,
+                }),
+                readable: Condvar::new(),
+                writable: Condvar::new(),
+            })
+        })
+        .clone()
+}
+
+fn unregister(name: &str, sink: &Arc<MemorySink>) {
+    let mut sinks = SINKS.lock().unwrap_or_else(|e| e.into_inner());
+    if sinks.get(name).is_some_and(|s| Arc::ptr_eq(s, sink)) {
+        sinks.remove(name);
+    }
+}
+
+/// The emulator's end of a memory sink. Dropping the writer marks the
+/// stream as complete.
+pub struct MemorySinkWriter {
+    sink: Arc<MemorySink>,
+}
+
+impl MemorySinkWriter {
+    pub fn open(name: &str, capacity: usize) -> Result<Self> {
+        if capacity == 0 {
+            bail!("memory sink '{name}' must have a non-zero capacity");
+        }
+        let sink = get_or_create(name);
+        {
+            let mut state = sink.lock();
+            if state.writer_attached {
+                bail!("memory sink '{name}' is already in use by another instance");
+            }
+            if state.closed && !state.data.is_empty() {
+                bail!("memory sink '{name}' still holds unread output from a previous run");
+            }
+            state.writer_attached = true;
+            state.closed = false;
+            state.capacity = capacity;
+        }
+        Ok(Self { sink })
+    }
+}
+
+impl Write for MemorySinkWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut state = self.sink.lock();
+        while state.data.len() >= state.capacity {
+            state = self
+                .sink
+                .writable
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        let n = buf.len().min(state.capacity - state.data.len());
+        state.data.extend(&buf[..n]);
+        self.sink.readable.notify_all();
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for MemorySinkWriter {
+    fn drop(&mut self) {
+        let mut state = self.sink.lock();
+        state.writer_attached = false;
+        state.closed = true;
+        self.sink.readable.notify_all();
+    }
+}
+
+/// The embedding application's end of a memory sink. Reads block until data
+/// is available, and return zero bytes once the writer has finished and all
+/// output has been consumed, at which point the sink is unregistered.
+pub struct MemorySinkReader {
+    name: String,
+    sink: Arc<MemorySink>,
+}
+
+impl MemorySinkReader {
+    /// Attach to the named sink, creating it if the emulator has not yet
+    /// opened it.
+    pub fn open(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            sink: get_or_create(name),
+        }
+    }
+}
+
+impl Read for MemorySinkReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let mut state = self.sink.lock();
+        while state.data.is_empty() && !state.closed {
+            state = self
+                .sink
+                .readable
+                .wait(state)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        if state.data.is_empty() {
+            drop(state);
+            unregister(&self.name, &self.sink);
+            return Ok(0);
+        }
+        let n = buf.len().min(state.data.len());
+        for (dst, src) in buf.iter_mut().zip(state.data.drain(..n)) {
+            *dst = src;
+        }
+        self.sink.writable.notify_all();
+        Ok(n)
+    }
+}
@@ -0,0 +1,103 @@
+use super::*;
+use crate::ffi_interface::selene_memory_sink_read;
+use selene_core::error::ErrorKind;
+use std::ffi::CString;
+
+fn read_all(name: &str) -> Vec<u8> {
+    let mut data = Vec::new();
+    MemorySinkReader::open(name).read_to_end(&mut data).unwrap();
+    data
+}
+
+fn is_registered(name: &str) -> bool {
+    SINKS.lock().unwrap().contains_key(name)
+}
+
+#[test]
+fn roundtrip() {
+    let mut writer = MemorySinkWriter::open("roundtrip", 16).unwrap();
+    writer.write_all(b"hello").unwrap();
+    drop(writer);
+    assert_eq!(read_all("roundtrip"), b"hello");
+    // The sink is unregistered once it has been drained.
+    assert!(!is_registered("roundtrip"));
+}
+
+#[test]
+fn writer_blocks_until_drained() {
+    let data: Vec<u8> = (0..=255).cycle().take(10_000).collect();
+    let expected = data.clone();
+    let mut writer = MemorySinkWriter::open("blocking", 7).unwrap();
+    let writing = std::thread::spawn(move || {
+        writer.write_all(&data).unwrap();
+    });
+    let mut reader = MemorySinkReader::open("blocking");
+    let mut received = Vec::new();
+    let mut buffer = [0; 64];
+    loop {
+        let n = reader.read(&mut buffer).unwrap();
+        // Never more than the capacity is buffered.
+        assert!(n <= 7);
+        if n == 0 {
+            break;
+        }
+        received.extend_from_slice(&buffer[..n]);
+    }
+    writing.join().unwrap();
+    assert_eq!(received, expected);
+}
+
+#[test]
+fn reader_may_attach_first() {
+    let reading = std::thread::spawn(|| read_all("reader_first"));
+    std::thread::sleep(std::time::Duration::from_millis(10));
+    let mut writer = MemorySinkWriter::open("reader_first", 4).unwrap();
+    writer.write_all(b"abcdefgh").unwrap();
+    drop(writer);
+    assert_eq!(reading.join().unwrap(), b"abcdefgh");
+}
+
+#[test]
+fn open_errors() {
+    assert!(MemorySinkWriter::open("zero_capacity", 0).is_err());
+
+    let writer = MemorySinkWriter::open("in_use", 4).unwrap();
+    assert!(MemorySinkWriter::open("in_use", 4).is_err());
+    drop(writer);
+    assert_eq!(read_all("in_use"), b"");
+
+    let mut writer = MemorySinkWriter::open("unread", 4).unwrap();
+    writer.write_all(b"abc").unwrap();
+    drop(writer);
+    assert!(MemorySinkWriter::open("unread", 4).is_err());
+    assert_eq!(read_all("unread"), b"abc");
+    assert!(MemorySinkWriter::open("unread", 4).is_ok());
+}
+
+#[test]
+fn ffi_read() {
+    let mut writer = MemorySinkWriter::open("ffi", 16).unwrap();
+    writer.write_all(b"abc").unwrap();
+    drop(writer);
+    let name = CString::new("ffi").unwrap();
+    let mut buffer = [0u8; 2];
+    let mut read = || unsafe { selene_memory_sink_read(name.as_ptr(), buffer.as_mut_ptr(), 2) };
+    let result = read();
+    assert_eq!((result.error_code, result.value), (0, 2));
+    let result = read();
+    assert_eq!((result.error_code, result.value), (0, 1));
+    let result = read();
+    assert_eq!((result.error_code, result.value), (0, 0));
+}
+
+#[test]
+fn ffi_read_rejects_null_arguments() {
+    let name = CString::new("ffi_null").unwrap();
+    let mut buffer = [0u8; 2];
+    let invalid = ErrorKind::InvalidArgument.code();
+    let result = unsafe { selene_memory_sink_read(std::ptr::null(), buffer.as_mut_ptr(), 2) };
+    assert_eq!(result.error_code, invalid);
+    let result = unsafe { selene_memory_sink_read(name.as_ptr(), std::ptr::null_mut(), 2) };
+    assert_eq!(result.error_code, invalid);
+    assert!(!is_registered("ffi_null"));
+}
@@ -66,6 +66,13 @@ impl SeleneInstance {
         }
         Ok(())
     }
+    /// Write the usage of a shot that exceeded its limits.
+    pub fn write_limit_metrics(&mut self) -> Result<()> {
+        for (tag, value) in self.emulator.limits.metrics() {
+            self.write_metric("shot_limits", tag, value)?;
+        }
+        Ok(())
+    }
     pub fn write_metadata(&mut self) -> Result<()> {
         self.emulator
             .event_hooks
@@ -0,0 +1,359 @@
+//! Structured alternatives to the binary result stream.
+//!
+//! Selected with the `format` output option, these are implemented as
+//! [MessageWriter]s for the [OutputStream], so results, metrics, instruction
+//! logs and state dump references are all written through the same calls as
+//! for the binary encoding. Each message is annotated with the id of the shot
+//! it belongs to, taken from the enclosing `SELENE:SHOT_START` message, or
+//! null for messages written outside of a shot.
+use std::io::Write;
+use std::sync::Arc;
+
+use arrow_array::builder::{
+    BooleanBuilder, Float64Builder, Int64Builder, ListBuilder, StringBuilder, UInt64Builder,
+};
+use arrow_array::{
+    ArrayRef, BinaryArray, BooleanArray, Float64Array, Int64Array, ListArray, RecordBatch,
+    StringArray, StructArray, UInt8Array, UInt64Array,
+};
+use arrow_buffer::OffsetBuffer;
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{ArrowError, DataType, Field, FieldRef, Fields, Schema, SchemaRef};
+use serde::Deserialize;
+use serde_json::json;
+
+use selene_core::decoder::{Message, MessageKind, Value};
+use selene_core::encoder::{MessageWriter, OutputStream, OutputStreamError};
+
+#[cfg(test)]
+mod tests;
+
+/// The format of the result stream.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// The binary encoding of [selene_core::encoder].
+    #[default]
+    Binary,
+    /// One JSON object per line for each message, with the fields
+    /// `shot_id`, `time_cursor`, `tag` and `values`, the latter holding a
+    /// `{"type": ..., "value": ...}` object for each value in the message.
+    JsonLines,
+    /// An Arrow IPC stream of record batches with one row per message. See
+    /// [ArrowIpcWriter] for the schema.
+    ArrowIpc,
+}
+
+impl OutputFormat {
+    pub fn open_stream(self, writer: Box<dyn Write>) -> Result<OutputStream, OutputStreamError> {
+        Ok(match self {
+            OutputFormat::Binary => OutputStream::new(writer),
+            OutputFormat::JsonLines => {
+                OutputStream::with_message_writer(Box::new(JsonLinesWriter::new(writer)))
+            }
+            OutputFormat::ArrowIpc => {
+                OutputStream::with_message_writer(Box::new(ArrowIpcWriter::new(writer)?))
+            }
+        })
+    }
+}
+
+/// Tracks the shot that messages belong to from the shot boundary messages.
+#[derive(Default)]
+struct ShotTracker {
+    current: Option<u64>,
+}
+
+impl ShotTracker {
+    fn shot_id(&mut self, message: &Message) -> Option<u64> {
+        match message.kind() {
+            Ok(MessageKind::ShotStart(shot_id)) => {
+                self.current = Some(shot_id);
+                Some(shot_id)
+            }
+            Ok(MessageKind::ShotEnd(_)) => self.current.take(),
+            _ => self.current,
+        }
+    }
+}
+
+pub struct JsonLinesWriter {
+    writer: Box<dyn Write>,
+    shots: ShotTracker,
+}
+
+impl JsonLinesWriter {
+    pub fn new(writer: Box<dyn Write>) -> Self {
+        Self {
+            writer,
+            shots: ShotTracker::default(),
+        }
+    }
+}
+
+/// The name of a value's type, as written by the structured formats.
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::U64(_) => "u64",
+        Value::I64(_) => "i64",
+        Value::F64(_) => "f64",
+        Value::Bool(_) => "bool",
+        Value::Byte(_) => "byte",
+        Value::Str(_) => "str",
+        Value::U64Array(_) => "u64[]",
+        Value::I64Array(_) => "i64[]",
+        Value::F64Array(_) => "f64[]",
+        Value::BoolArray(_) => "bool[]",
+        Value::Bytes(_) => "byte[]",
+    }
+}
+
+fn typed_json(value: &Value) -> serde_json::Value {
+    let json_value = match value {
+        Value::U64(v) => json!(v),
+        Value::I64(v) => json!(v),
+        Value::F64(v) => json!(v),
+        Value::Bool(v) => json!(v),
+        Value::Byte(v) => json!(v),
+        Value::Str(v) => json!(v),
+        Value::U64Array(v) => json!(v),
+        Value::I64Array(v) => json!(v),
+        Value::F64Array(v) => json!(v),
+        Value::BoolArray(v) => json!(v),
+        Value::Bytes(v) => json!(v),
+    };
+    json!({"type": type_name(value), "value": json_value})
+}
+
+impl MessageWriter for JsonLinesWriter {
+    fn write_message(&mut self, message: Message) -> Result<(), OutputStreamError> {
+        let object = json!({
+            "shot_id": self.shots.shot_id(&message),
+            "time_cursor": message.time_cursor,
+            "tag": message.tag,
+            "values": message.values.iter().map(typed_json).collect::<Vec<_>>(),
+        });
+        serde_json::to_writer(&mut self.writer, &object)
+            .map_err(|e| OutputStreamError::IoError(e.into()))?;
+        self.writer
+            .write_all(b"\n")
+            .map_err(OutputStreamError::IoError)
+    }
+
+    fn end_of_stream(&mut self) -> Result<(), OutputStreamError> {
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), OutputStreamError> {
+        self.writer.flush().map_err(OutputStreamError::IoError)
+    }
+}
+
+/// The number of messages buffered before a record batch is written. Batches
+/// are also written whenever the stream is flushed.
+const ARROW_BATCH_ROWS: usize = 1024;
+
+fn arrow_error(e: ArrowError) -> OutputStreamError {
+    OutputStreamError::OtherError(format!("Arrow error: {e}"))
+}
+
+/// The fields of the structs in the `values` column of [ArrowIpcWriter].
+fn arrow_value_fields() -> Fields {
+    let list = |name: &str, data_type: DataType| {
+        Field::new_list(name, Field::new_list_field(data_type, true), true)
+    };
+    Fields::from(vec![
+        Field::new("type", DataType::Utf8, false),
+        Field::new("u64", DataType::UInt64, true),
+        Field::new("i64", DataType::Int64, true),
+        Field::new("f64", DataType::Float64, true),
+        Field::new("bool", DataType::Boolean, true),
+        Field::new("byte", DataType::UInt8, true),
+        Field::new("str", DataType::Utf8, true),
+        list("u64_array", DataType::UInt64),
+        list("i64_array", DataType::Int64),
+        list("f64_array", DataType::Float64),
+        list("bool_array", DataType::Boolean),
+        Field::new("bytes", DataType::Binary, true),
+    ])
+}
+
+/// Builds the struct array holding the given values, with the field matching
+/// each value's type set and all others null.
+fn arrow_values(fields: Fields, values: &[Value]) -> Result<StructArray, ArrowError> {
+    macro_rules! column {
+        ($variant:ident) => {
+            values
+                .iter()
+                .map(|value| match value {
+                    Value::$variant(v) => Some(v.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+    }
+    macro_rules! list_column {
+        ($variant:ident, $builder:ty) => {{
+            let mut builder = ListBuilder::new(<$builder>::new());
+            for value in values {
+                match value {
+                    Value::$variant(v) => {
+                        builder.values().append_slice(v);
+                        builder.append(true);
+                    }
+                    _ => builder.append_null(),
+                }
+            }
+            builder.finish()
+        }};
+    }
+    let bytes: Vec<Option<&[u8]>> = values
+        .iter()
+        .map(|value| match value {
+            Value::Bytes(v) => Some(v.as_slice()),
+            _ => None,
+        })
+        .collect();
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(StringArray::from_iter_values(values.iter().map(type_name))),
+        Arc::new(UInt64Array::from(column!(U64))),
+        Arc::new(Int64Array::from(column!(I64))),
+        Arc::new(Float64Array::from(column!(F64))),
+        Arc::new(BooleanArray::from(column!(Bool))),
+        Arc::new(UInt8Array::from(column!(Byte))),
+        Arc::new(StringArray::from(column!(Str))),
+        Arc::new(list_column!(U64Array, UInt64Builder)),
+        Arc::new(list_column!(I64Array, Int64Builder)),
+        Arc::new(list_column!(F64Array, Float64Builder)),
+        Arc::new(list_column!(BoolArray, BooleanBuilder)),
+        Arc::new(BinaryArray::from(bytes)),
+    ];
+    StructArray::try_new(fields, columns, None)
+}
+
+/// Writes messages as an Arrow IPC stream, with one row per message and the
+/// columns:
+///
+/// - `shot_id`: nullable UInt64
+/// - `time_cursor`: UInt64
+/// - `tag`: Utf8
+/// - `values`: a list of the message's values, in the order they were
+///   written. Each is a struct with a `type` field naming the value's type as
+///   in the JSON lines format (`u64`, `f64[]`, ...), and a nullable field for
+///   each type, of which only the one matching `type` is set: `u64`, `i64`,
+///   `f64`, `bool`, `byte` and `str` for single values, the lists
+///   `u64_array`, `i64_array`, `f64_array` and `bool_array` for arrays, and
+///   the binary `bytes` for byte arrays.
+pub struct ArrowIpcWriter {
+    writer: Option<StreamWriter<Box<dyn Write>>>,
+    schema: SchemaRef,
+    value_fields: Fields,
+    shots: ShotTracker,
+    shot_id: UInt64Builder,
+    time_cursor: UInt64Builder,
+    tag: StringBuilder,
+    values: Vec<Value>,
+    value_counts: Vec<usize>,
+}
+
+impl ArrowIpcWriter {
+    pub fn new(writer: Box<dyn Write>) -> Result<Self, OutputStreamError> {
+        let value_fields = arrow_value_fields();
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("shot_id", DataType::UInt64, true),
+            Field::new("time_cursor", DataType::UInt64, false),
+            Field::new("tag", DataType::Utf8, false),
+            Field::new_list(
+                "values",
+                Field::new_list_field(DataType::Struct(value_fields.clone()), false),
+                false,
+            ),
+        ]));
+        let writer = StreamWriter::try_new(writer, &schema).map_err(arrow_error)?;
+        Ok(Self {
+            writer: Some(writer),
+            schema,
+            value_fields,
+            shots: ShotTracker::default(),
+            shot_id: UInt64Builder::new(),
+            time_cursor: UInt64Builder::new(),
+            tag: StringBuilder::new(),
+            values: Vec::new(),
+            value_counts: Vec::new(),
+        })
+    }
+
+    fn write_batch(&mut self) -> Result<(), OutputStreamError> {
+        if self.value_counts.is_empty() {
+            return Ok(());
+        }
+        let Some(writer) = self.writer.as_mut() else {
+            return Err(OutputStreamError::OtherError(
+                "Arrow stream has already been closed".to_string(),
+            ));
+        };
+        let values = arrow_values(self.value_fields.clone(), &self.values).map_err(arrow_error)?;
+        let values = ListArray::try_new(
+            FieldRef::new(Field::new_list_field(
+                DataType::Struct(self.value_fields.clone()),
+                false,
+            )),
+            OffsetBuffer::from_lengths(self.value_counts.drain(..)),
+            Arc::new(values),
+            None,
+        )
+        .map_err(arrow_error)?;
+        self.values.clear();
+        let batch = RecordBatch::try_new(
+            self.schema.clone(),
+            vec![
+                Arc::new(self.shot_id.finish()),
+                Arc::new(self.time_cursor.finish()),
+                Arc::new(self.tag.finish()),
+                Arc::new(values),
+            ],
+        )
+        .map_err(arrow_error)?;
+        writer.write(&batch).map_err(arrow_error)
+    }
+}
+
+impl MessageWriter for ArrowIpcWriter {
+    fn write_message(&mut self, message: Message) -> Result<(), OutputStreamError> {
+        self.shot_id.append_option(self.shots.shot_id(&message));
+        self.time_cursor.append_value(message.time_cursor);
+        self.tag.append_value(&message.tag);
+        self.value_counts.push(message.values.len());
+        self.values.extend(message.values);
+        if self.value_counts.len() >= ARROW_BATCH_ROWS {
+            self.write_batch()?;
+        }
+        Ok(())
+    }
+
+    fn end_of_stream(&mut self) -> Result<(), OutputStreamError> {
+        if self.writer.is_none() {
+            return Ok(());
+        }
+        self.write_batch()?;
+        if let Some(mut writer) = self.writer.take() {
+            writer.finish().map_err(arrow_error)?;
+            writer
+                .get_mut()
+                .flush()
+                .map_err(OutputStreamError::IoError)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), OutputStreamError> {
+        if self.writer.is_none() {
+            return Ok(());
+        }
+        self.write_batch()?;
+        if let Some(writer) = self.writer.as_mut() {
+            writer.flush().map_err(arrow_error)?;
+        }
+        Ok(())
+    }
+}
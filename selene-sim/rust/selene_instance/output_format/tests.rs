@@ -0,0 +1,196 @@
+use super::*;
+use crate::test_utils::SharedBuffer;
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float64Type, Int64Type, UInt8Type, UInt64Type};
+use arrow_array::{Array, StructArray};
+use arrow_ipc::reader::StreamReader;
+
+fn message(time_cursor: u64, tag: &str, values: Vec<Value>) -> Message {
+    Message {
+        time_cursor,
+        tag: tag.to_string(),
+        values,
+    }
+}
+
+/// Messages with values of every type, interleaving types to check that
+/// their order is kept.
+fn messages() -> Vec<Message> {
+    vec![
+        message(0, "SELENE:SHOT_START", vec![Value::U64(7)]),
+        message(
+            5,
+            "USER:MIXED",
+            vec![
+                Value::U64(1),
+                Value::F64(0.1),
+                Value::U64(2),
+                Value::Bool(true),
+                Value::I64(-3),
+                Value::Str("a".to_string()),
+                Value::Byte(4),
+                Value::U64Array(vec![5, 6]),
+                Value::U64(3),
+                Value::I64Array(vec![-1]),
+                Value::F64Array(vec![1.5, -2.5]),
+                Value::BoolArray(vec![false, true]),
+                Value::Bytes(vec![1, 2, 3]),
+                Value::Str("b".to_string()),
+            ],
+        ),
+        message(9, "USER:EMPTY", vec![]),
+        message(12, "SELENE:SHOT_END", vec![Value::U64(7)]),
+        message(12, "EXIT:INT:done", vec![Value::U64(0)]),
+    ]
+}
+
+fn write(format: OutputFormat, messages: &[Message]) -> Vec<u8> {
+    let buffer = SharedBuffer::default();
+    let mut stream = format.open_stream(Box::new(buffer.clone())).unwrap();
+    for message in messages {
+        stream.write_message(message).unwrap();
+    }
+    stream.end_of_stream().unwrap();
+    buffer.contents()
+}
+
+fn parse<T: serde::de::DeserializeOwned>(value: &serde_json::Value) -> T {
+    serde_json::from_value(value.clone()).unwrap()
+}
+
+fn value_from_json(value: &serde_json::Value) -> Value {
+    match value["type"].as_str().unwrap() {
+        "u64" => Value::U64(parse(&value["value"])),
+        "i64" => Value::I64(parse(&value["value"])),
+        "f64" => Value::F64(parse(&value["value"])),
+        "bool" => Value::Bool(parse(&value["value"])),
+        "byte" => Value::Byte(parse(&value["value"])),
+        "str" => Value::Str(parse(&value["value"])),
+        "u64[]" => Value::U64Array(parse(&value["value"])),
+        "i64[]" => Value::I64Array(parse(&value["value"])),
+        "f64[]" => Value::F64Array(parse(&value["value"])),
+        "bool[]" => Value::BoolArray(parse(&value["value"])),
+        "byte[]" => Value::Bytes(parse(&value["value"])),
+        other => panic!("unexpected type {other}"),
+    }
+}
+
+fn value_from_arrow(values: &StructArray, index: usize) -> Value {
+    let column = |name: &str| values.column_by_name(name).unwrap();
+    let list = |name: &str| column(name).as_list::<i32>().value(index);
+    match values.column(0).as_string::<i32>().value(index) {
+        "u64" => Value::U64(column("u64").as_primitive::<UInt64Type>().value(index)),
+        "i64" => Value::I64(column("i64").as_primitive::<Int64Type>().value(index)),
+        "f64" => Value::F64(column("f64").as_primitive::<Float64Type>().value(index)),
+        "bool" => Value::Bool(column("bool").as_boolean().value(index)),
+        "byte" => Value::Byte(column("byte").as_primitive::<UInt8Type>().value(index)),
+        "str" => Value::Str(column("str").as_string::<i32>().value(index).to_string()),
+        "u64[]" => Value::U64Array(
+            list("u64_array")
+                .as_primitive::<UInt64Type>()
+                .values()
+                .to_vec(),
+        ),
+        "i64[]" => Value::I64Array(
+            list("i64_array")
+                .as_primitive::<Int64Type>()
+                .values()
+                .to_vec(),
+        ),
+        "f64[]" => Value::F64Array(
+            list("f64_array")
+                .as_primitive::<Float64Type>()
+                .values()
+                .to_vec(),
+        ),
+        "bool[]" => Value::BoolArray(list("bool_array").as_boolean().iter().flatten().collect()),
+        "byte[]" => Value::Bytes(column("bytes").as_binary::<i32>().value(index).to_vec()),
+        other => panic!("unexpected type {other}"),
+    }
+}
+
+#[test]
+fn json_lines() {
+    let messages = messages();
+    let output = String::from_utf8(write(OutputFormat::JsonLines, &messages)).unwrap();
+    let mut shot_ids = Vec::new();
+    let decoded: Vec<Message> = output
+        .lines()
+        .map(|line| {
+            let object: serde_json::Value = serde_json::from_str(line).unwrap();
+            shot_ids.push(object["shot_id"].as_u64());
+            message(
+                object["time_cursor"].as_u64().unwrap(),
+                object["tag"].as_str().unwrap(),
+                object["values"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(value_from_json)
+                    .collect(),
+            )
+        })
+        .collect();
+    assert_eq!(decoded, messages);
+    assert_eq!(shot_ids, vec![Some(7), Some(7), Some(7), Some(7), None]);
+}
+
+#[test]
+fn arrow_ipc() {
+    let messages = messages();
+    let output = write(OutputFormat::ArrowIpc, &messages);
+    let reader = StreamReader::try_new(std::io::Cursor::new(output), None).unwrap();
+    let mut shot_ids = Vec::new();
+    let mut decoded = Vec::new();
+    for batch in reader {
+        let batch = batch.unwrap();
+        let shot_id = batch
+            .column_by_name("shot_id")
+            .unwrap()
+            .as_primitive::<UInt64Type>();
+        let time_cursor = batch
+            .column_by_name("time_cursor")
+            .unwrap()
+            .as_primitive::<UInt64Type>();
+        let tag = batch.column_by_name("tag").unwrap().as_string::<i32>();
+        let values = batch.column_by_name("values").unwrap().as_list::<i32>();
+        for row in 0..batch.num_rows() {
+            shot_ids.push(shot_id.is_valid(row).then(|| shot_id.value(row)));
+            let row_values = values.value(row);
+            let row_values = row_values.as_struct();
+            decoded.push(message(
+                time_cursor.value(row),
+                tag.value(row),
+                (0..row_values.len())
+                    .map(|index| value_from_arrow(row_values, index))
+                    .collect(),
+            ));
+        }
+    }
+    assert_eq!(decoded, messages);
+    assert_eq!(shot_ids, vec![Some(7), Some(7), Some(7), Some(7), None]);
+}
+
+#[test]
+fn arrow_ipc_batches() {
+    // Messages beyond a batch's worth, and those flushed early, are written
+    // in further batches.
+    let buffer = SharedBuffer::default();
+    let mut stream = OutputFormat::ArrowIpc
+        .open_stream(Box::new(buffer.clone()))
+        .unwrap();
+    let values = |i: u64| vec![Value::U64(i), Value::Str(i.to_string())];
+    for i in 0..ARROW_BATCH_ROWS as u64 + 10 {
+        stream
+            .write_message(&message(i, "USER:N", values(i)))
+            .unwrap();
+    }
+    stream.flush().unwrap();
+    stream
+        .write_message(&message(0, "USER:N", values(0)))
+        .unwrap();
+    stream.end_of_stream().unwrap();
+    let reader = StreamReader::try_new(std::io::Cursor::new(buffer.contents()), None).unwrap();
+    let rows: Vec<usize> = reader.map(|batch| batch.unwrap().num_rows()).collect();
+    assert_eq!(rows, vec![ARROW_BATCH_ROWS, 10, 1]);
+}
@@ -0,0 +1,131 @@
+//! Buffering and compression of the result stream.
+//!
+//! By default every write to the result stream is passed straight to the
+//! output writer. For long runs, particularly with instruction logs enabled,
+//! this can bottleneck on syscalls and produce very large outputs, so the
+//! stream may instead be buffered with an explicit flush policy and compressed
+//! with gzip or zstd. Compressed streams are self-describing: readers can
+//! detect them from the gzip or zstd magic bytes at the start of the stream,
+//! where an uncompressed stream begins with a time cursor of zero.
+use super::output_format::OutputFormat;
+use anyhow::{Result, bail};
+use serde::Deserialize;
+use std::io::{BufWriter, Write};
+
+#[cfg(test)]
+mod tests;
+
+fn default_buffer_size() -> usize {
+    1 << 16
+}
+
+/// When buffered output is passed on to the output writer.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FlushPolicy {
+    /// Write every message to the output writer as it is produced.
+    #[default]
+    Immediate,
+    /// Flush at the end of every shot.
+    PerShot,
+    /// Flush whenever at least this many bytes have been written since the
+    /// last flush, and at the end of the run.
+    EveryNBytes(u64),
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct OutputOptions {
+    /// The format of the result stream. See [output_format](super::output_format).
+    #[serde(default)]
+    pub format: OutputFormat,
+    #[serde(default)]
+    pub flush_policy: FlushPolicy,
+    /// The capacity of the write buffer when a buffered flush policy is used.
+    #[serde(default = "default_buffer_size")]
+    pub buffer_size: usize,
+    #[serde(default)]
+    pub compression: Compression,
+    /// The compression level, defaulting to the compressor's own default.
+    #[serde(default)]
+    pub compression_level: Option<i32>,
+}
+
+impl OutputOptions {
+    /// Wrap the output writer according to the configured options.
+    pub fn wrap(&self, writer: Box<dyn Write>) -> Result<Box<dyn Write>> {
+        let writer: Box<dyn Write> = match self.compression {
+            Compression::None => writer,
+            // Compressors only emit decodable data when flushed, so an
+            // immediate policy would hold back all output until the run ends.
+            _ if self.flush_policy == FlushPolicy::Immediate => {
+                bail!("Compressed output requires a per_shot or every_n_bytes flush policy")
+            }
+            Compression::Gzip => {
+                let level = match self.compression_level {
+                    Some(level @ 0..=9) => flate2::Compression::new(level as u32),
+                    Some(level) => bail!("Invalid gzip compression level {level}, expected 0-9"),
+                    None => flate2::Compression::default(),
+                };
+                Box::new(flate2::write::GzEncoder::new(writer, level))
+            }
+            Compression::Zstd => {
+                let level = self.compression_level.unwrap_or(0);
+                Box::new(zstd::stream::write::Encoder::new(writer, level)?.auto_finish())
+            }
+        };
+        Ok(match self.flush_policy {
+            FlushPolicy::Immediate => writer,
+            FlushPolicy::PerShot => Box::new(FlushingWriter::new(writer, self.buffer_size, None)),
+            FlushPolicy::EveryNBytes(n) => {
+                Box::new(FlushingWriter::new(writer, self.buffer_size, Some(n)))
+            }
+        })
+    }
+}
+
+/// A buffered writer that additionally flushes through to the underlying
+/// writer after a given number of bytes. Unlike a plain [BufWriter], this
+/// flushes any compressor beneath it rather than only handing it data.
+struct FlushingWriter {
+    inner: BufWriter<Box<dyn Write>>,
+    flush_every: Option<u64>,
+    unflushed: u64,
+}
+
+impl FlushingWriter {
+    fn new(inner: Box<dyn Write>, buffer_size: usize, flush_every: Option<u64>) -> Self {
+        Self {
+            inner: BufWriter::with_capacity(buffer_size, inner),
+            flush_every,
+            unflushed: 0,
+        }
+    }
+}
+
+impl Write for FlushingWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.unflushed += n as u64;
+        if self
+            .flush_every
+            .is_some_and(|limit| self.unflushed >= limit)
+        {
+            self.flush()?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.unflushed = 0;
+        self.inner.flush()
+    }
+}
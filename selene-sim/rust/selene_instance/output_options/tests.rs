@@ -0,0 +1,108 @@
+use super::*;
+use crate::test_utils::SharedBuffer;
+use selene_core::decoder::{OutputStreamReader, StreamCompression, detect_compression};
+use std::io::Read;
+
+fn options(flush_policy: FlushPolicy, compression: Compression) -> OutputOptions {
+    OutputOptions {
+        flush_policy,
+        buffer_size: 1024,
+        compression,
+        ..Default::default()
+    }
+}
+
+fn wrap(options: &OutputOptions) -> (Box<dyn Write>, SharedBuffer) {
+    let buffer = SharedBuffer::default();
+    (options.wrap(Box::new(buffer.clone())).unwrap(), buffer)
+}
+
+fn decompress(data: Vec<u8>, len: usize) -> Vec<u8> {
+    let mut reader = OutputStreamReader::open(std::io::Cursor::new(data))
+        .unwrap()
+        .into_inner();
+    let mut decompressed = vec![0; len];
+    reader.read_exact(&mut decompressed).unwrap();
+    decompressed
+}
+
+#[test]
+fn immediate() {
+    let (mut writer, buffer) = wrap(&options(FlushPolicy::Immediate, Compression::None));
+    writer.write_all(b"abc").unwrap();
+    assert_eq!(buffer.contents(), b"abc");
+}
+
+#[test]
+fn per_shot() {
+    let (mut writer, buffer) = wrap(&options(FlushPolicy::PerShot, Compression::None));
+    writer.write_all(b"abc").unwrap();
+    writer.write_all(b"def").unwrap();
+    assert_eq!(buffer.contents(), b"");
+    // Flushed by the instance at the end of each shot.
+    writer.flush().unwrap();
+    assert_eq!(buffer.contents(), b"abcdef");
+}
+
+#[test]
+fn every_n_bytes() {
+    let (mut writer, buffer) = wrap(&options(FlushPolicy::EveryNBytes(10), Compression::None));
+    for chunk in [b"abcd", b"efgh"] {
+        writer.write_all(chunk).unwrap();
+        assert_eq!(buffer.contents(), b"");
+    }
+    writer.write_all(b"ijkl").unwrap();
+    assert_eq!(buffer.contents(), b"abcdefghijkl");
+    writer.write_all(b"mnop").unwrap();
+    assert_eq!(buffer.contents(), b"abcdefghijkl");
+    writer.flush().unwrap();
+    assert_eq!(buffer.contents(), b"abcdefghijklmnop");
+}
+
+#[test]
+fn compressed_roundtrip() {
+    let data: Vec<u8> = (0..10_000u32).flat_map(|i| (i % 7).to_le_bytes()).collect();
+    for (compression, detected) in [
+        (Compression::Gzip, StreamCompression::Gzip),
+        (Compression::Zstd, StreamCompression::Zstd),
+    ] {
+        let (mut writer, buffer) = wrap(&options(FlushPolicy::PerShot, compression));
+        writer.write_all(&data[..100]).unwrap();
+        writer.flush().unwrap();
+        // Everything written up to a flush can be decoded before the stream
+        // is finished.
+        let flushed = buffer.contents();
+        assert_eq!(detect_compression(&flushed), detected);
+        assert_eq!(decompress(flushed, 100), data[..100]);
+
+        writer.write_all(&data[100..]).unwrap();
+        drop(writer);
+        let compressed = buffer.contents();
+        assert!(compressed.len() < data.len());
+        assert_eq!(decompress(compressed, data.len()), data);
+    }
+}
+
+#[test]
+fn uncompressed_is_detected() {
+    let (mut writer, buffer) = wrap(&options(FlushPolicy::PerShot, Compression::None));
+    writer.write_all(&0u64.to_le_bytes()).unwrap();
+    writer.flush().unwrap();
+    assert_eq!(
+        detect_compression(&buffer.contents()),
+        StreamCompression::None
+    );
+}
+
+#[test]
+fn invalid_options() {
+    let buffer = SharedBuffer::default();
+    assert!(
+        options(FlushPolicy::Immediate, Compression::Gzip)
+            .wrap(Box::new(buffer.clone()))
+            .is_err()
+    );
+    let mut gzip = options(FlushPolicy::PerShot, Compression::Gzip);
+    gzip.compression_level = Some(10);
+    assert!(gzip.wrap(Box::new(buffer)).is_err());
+}
@@ -0,0 +1,368 @@
+//! A safe interface for writing emulated programs in Rust.
+//!
+//! Frontends usually drive an instance through the C interface, handing raw
+//! qubit addresses and measurement references back and forth. When embedding
+//! selene in Rust, [SeleneInstance::run_shots] instead runs a closure for each
+//! shot, passing it a [Shot] through which the program allocates [Qubit]s,
+//! applies gates and takes measurements.
+//!
+//! Lazy measurements produce [Future]s, which hold a reference to the result
+//! in the runtime. The reference is released when the last clone of a future
+//! is dropped, so the runtime is free to discard the result once the program
+//! no longer needs it.
+//!
+//! ```no_run
+//! # use selene::selene_instance::SeleneInstance;
+//! # fn example(instance: &mut SeleneInstance) -> anyhow::Result<()> {
+//! instance.run_shots(|shot| {
+//!     let q = shot.qalloc()?.expect("a qubit is available");
+//!     shot.rxy(&q, std::f64::consts::FRAC_PI_2, 0.0)?;
+//!     let result = shot.measure_and_free(q)?;
+//!     let value = shot.read(&result)?;
+//!     shot.print("USER:BOOL:result", value)
+//! })
+//! # }
+//! ```
+//!
+//! Qubits and futures borrow the lifetime of the [Shot] they were created in,
+//! so they cannot be kept for use in a later shot, where the addresses and
+//! references they hold would refer to something else:
+//!
+//! ```compile_fail
+//! # use selene::selene_instance::SeleneInstance;
+//! # fn example(instance: &mut SeleneInstance) -> anyhow::Result<()> {
+//! let kept = std::sync::Mutex::new(None);
+//! instance.run_shots(|shot| {
+//!     let q = shot.qalloc()?.expect("a qubit is available");
+//!     if let Some(previous) = kept.lock().unwrap().replace(q) {
+//!         shot.rz(&previous, 0.5)?;
+//!     }
+//!     Ok(())
+//! })
+//! # }
+//! ```
+use std::cell::RefCell;
+use std::fmt;
+use std::marker::PhantomData;
+use std::rc::Rc;
+
+use anyhow::Result;
+use selene_core::encoder::StreamWritable;
+use selene_core::error::ErrorKind;
+
+use super::SeleneInstance;
+
+#[cfg(test)]
+mod tests;
+
+/// Ties a handle to the [Shot] it was created in. The lifetime is invariant,
+/// so that handles cannot be converted to those of another shot.
+type ShotBrand<'s> = PhantomData<fn(&'s ()) -> &'s ()>;
+
+/// A qubit allocated within a shot. Qubits are not freed on drop, so a qubit
+/// that is no longer needed should be passed to [Shot::free] or measured with
+/// [Shot::measure_and_free].
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct Qubit<'s>(u64, ShotBrand<'s>);
+
+impl Qubit<'_> {
+    /// The address of the qubit in the runtime.
+    pub fn id(&self) -> u64 {
+        self.0
+    }
+}
+
+type ReleaseQueue = Rc<RefCell<Vec<u64>>>;
+
+struct FutureHandle {
+    reference: u64,
+    released: ReleaseQueue,
+}
+
+impl Drop for FutureHandle {
+    fn drop(&mut self) {
+        self.released.borrow_mut().push(self.reference);
+    }
+}
+
+/// The result of a lazy measurement, read with [Shot::read].
+///
+/// Clones refer to the same result. The program's reference to the result is
+/// released once every clone has been dropped.
+pub struct Future<'s, T> {
+    handle: Rc<FutureHandle>,
+    value: PhantomData<T>,
+    shot: ShotBrand<'s>,
+}
+
+impl<T> Future<'_, T> {
+    /// The reference to the result in the runtime.
+    pub fn reference(&self) -> u64 {
+        self.handle.reference
+    }
+}
+
+impl<T> Clone for Future<'_, T> {
+    fn clone(&self) -> Self {
+        Self {
+            handle: self.handle.clone(),
+            value: PhantomData,
+            shot: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Future<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Future")
+            .field(&self.handle.reference)
+            .finish()
+    }
+}
+
+/// A value that a [Future] may resolve to.
+pub trait FutureValue: Sized {
+    fn read(instance: &mut SeleneInstance, reference: u64) -> Result<Self>;
+}
+
+impl FutureValue for bool {
+    fn read(instance: &mut SeleneInstance, reference: u64) -> Result<Self> {
+        instance.future_read_bool(reference)
+    }
+}
+
+impl FutureValue for u64 {
+    fn read(instance: &mut SeleneInstance, reference: u64) -> Result<Self> {
+        instance.future_read_u64(reference)
+    }
+}
+
+/// The error returned by [Shot::panic], which has already been written to
+/// the result stream.
+#[derive(Debug)]
+pub struct ProgramPanic {
+    pub message: String,
+    pub code: u32,
+}
+
+impl fmt::Display for ProgramPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Program panicked with code {}: {}",
+            self.code, self.message
+        )
+    }
+}
+
+impl std::error::Error for ProgramPanic {}
+
+/// The operations available to a program within a single shot.
+///
+/// Each shot is given its own lifetime `'s`, which the [Qubit]s and [Future]s
+/// it creates borrow, so that they can only be passed back to the same shot.
+pub struct Shot<'s> {
+    instance: &'s mut SeleneInstance,
+    released: ReleaseQueue,
+    brand: ShotBrand<'s>,
+}
+
+impl<'s> Shot<'s> {
+    fn new(instance: &'s mut SeleneInstance) -> Self {
+        Self {
+            instance,
+            released: Rc::default(),
+            brand: PhantomData,
+        }
+    }
+
+    /// The id of the shot, as written to the result stream.
+    pub fn shot_id(&self) -> u64 {
+        self.instance.shot_number
+    }
+
+    /// The underlying instance, for operations not covered by [Shot].
+    pub fn instance(&mut self) -> &mut SeleneInstance {
+        self.instance
+    }
+
+    /// Release the references held by dropped futures.
+    fn release_futures(&mut self) -> Result<()> {
+        let released = std::mem::take(&mut *self.released.borrow_mut());
+        for reference in released {
+            self.instance.refcount_decrement(reference)?;
+        }
+        Ok(())
+    }
+
+    fn future<T>(&self, reference: u64) -> Future<'s, T> {
+        Future {
+            handle: Rc::new(FutureHandle {
+                reference,
+                released: self.released.clone(),
+            }),
+            value: PhantomData,
+            shot: self.brand,
+        }
+    }
+
+    /// Allocate a qubit, or return `None` if the runtime has none available.
+    pub fn qalloc(&mut self) -> Result<Option<Qubit<'s>>> {
+        self.release_futures()?;
+        match self.instance.qalloc()? {
+            u64::MAX => Ok(None),
+            address => Ok(Some(Qubit(address, self.brand))),
+        }
+    }
+
+    pub fn free(&mut self, qubit: Qubit<'s>) -> Result<()> {
+        self.release_futures()?;
+        self.instance.qfree(qubit.0)
+    }
+
+    pub fn rxy(&mut self, qubit: &Qubit<'s>, theta: f64, phi: f64) -> Result<()> {
+        self.release_futures()?;
+        self.instance.rxy(qubit.0, theta, phi)
+    }
+
+    pub fn rz(&mut self, qubit: &Qubit<'s>, theta: f64) -> Result<()> {
+        self.release_futures()?;
+        self.instance.rz(qubit.0, theta)
+    }
+
+    pub fn rzz(&mut self, qubit: &Qubit<'s>, qubit2: &Qubit<'s>, theta: f64) -> Result<()> {
+        self.release_futures()?;
+        self.instance.rzz(qubit.0, qubit2.0, theta)
+    }
+
+    pub fn reset(&mut self, qubit: &Qubit<'s>) -> Result<()> {
+        self.release_futures()?;
+        self.instance.qubit_reset(qubit.0)
+    }
+
+    /// Measure a qubit, waiting for the result.
+    pub fn measure(&mut self, qubit: &Qubit<'s>) -> Result<bool> {
+        self.release_futures()?;
+        self.instance.qubit_measure(qubit.0)
+    }
+
+    /// Measure a qubit, leaving the runtime free to defer the measurement
+    /// until the result is read.
+    pub fn lazy_measure(&mut self, qubit: &Qubit<'s>) -> Result<Future<'s, bool>> {
+        self.release_futures()?;
+        let reference = self.instance.qubit_lazy_measure(qubit.0)?;
+        Ok(self.future(reference))
+    }
+
+    /// As [Shot::lazy_measure], but with a result that distinguishes leaked
+    /// qubits from the computational basis states.
+    pub fn lazy_measure_leaked(&mut self, qubit: &Qubit<'s>) -> Result<Future<'s, u64>> {
+        self.release_futures()?;
+        let reference = self.instance.qubit_lazy_measure_leaked(qubit.0)?;
+        Ok(self.future(reference))
+    }
+
+    /// Lazily measure a qubit and reset it.
+    pub fn lazy_measure_reset(&mut self, qubit: &Qubit<'s>) -> Result<Future<'s, bool>> {
+        self.release_futures()?;
+        let reference = self.instance.qubit_lazy_measure_reset(qubit.0)?;
+        Ok(self.future(reference))
+    }
+
+    /// Lazily measure a qubit and free it.
+    pub fn measure_and_free(&mut self, qubit: Qubit<'s>) -> Result<Future<'s, bool>> {
+        self.release_futures()?;
+        let reference = self.instance.qubit_measure_and_free(qubit.0)?;
+        Ok(self.future(reference))
+    }
+
+    /// Read the value of a future, waiting for it to be resolved.
+    pub fn read<T: FutureValue>(&mut self, future: &Future<'s, T>) -> Result<T> {
+        self.release_futures()?;
+        T::read(self.instance, future.reference())
+    }
+
+    pub fn global_barrier(&mut self, sleep_time: u64) -> Result<()> {
+        self.release_futures()?;
+        self.instance.global_barrier(sleep_time)
+    }
+
+    pub fn local_barrier(&mut self, qubits: &[&Qubit<'s>], sleep_time: u64) -> Result<()> {
+        self.release_futures()?;
+        let addresses: Vec<u64> = qubits.iter().map(|qubit| qubit.0).collect();
+        self.instance.local_barrier(&addresses, sleep_time)
+    }
+
+    /// Write a tagged value to the result stream.
+    pub fn print<T: StreamWritable>(&mut self, tag: &str, value: T) -> Result<()> {
+        self.instance.print(tag, value)
+    }
+
+    /// Write a panic to the result stream, returning the error for the
+    /// program to return. As in programs compiled against the C interface,
+    /// codes below 1000 end the shot only, and higher codes end the run. Codes
+    /// 998 and 999 are reserved for selene (see [ErrorKind]).
+    pub fn panic(&mut self, message: &str, code: u32) -> anyhow::Error {
+        if let Err(e) = self.instance.print_panic(message, code) {
+            return e;
+        }
+        ProgramPanic {
+            message: message.to_string(),
+            code,
+        }
+        .into()
+    }
+}
+
+/// Codes below this end the shot in which they occur, but not the run.
+const SHOT_PANIC_LIMIT: u32 = 1000;
+
+impl SeleneInstance {
+    /// Write an error to the result stream as a panic, returning its code.
+    /// When shots are skipped on error, an error raised during a shot is
+    /// instead recorded as the failure of the shot (see
+    /// [recovery](super::recovery)).
+    pub fn print_error(&mut self, error: &anyhow::Error) -> u32 {
+        if let Some(code) = self.record_failure(error) {
+            return code;
+        }
+        let code = ErrorKind::of(error).code();
+        self.fallible_print_panic(&format!("{error:#}"), code);
+        code
+    }
+
+    /// Run the configured shots, calling `program` for each of them, and
+    /// across the configured number of workers.
+    ///
+    /// An error returned by `program`, or raised by an operation it requests,
+    /// is written to the result stream as a panic. Panics with a code below
+    /// 1000, such as those raised through [Shot::panic], end only the shot in
+    /// which they occur, and all others end the run. Either way, the run
+    /// itself succeeds, as the panic is reported through the result stream.
+    /// The instance is not exited, so that further output may be written
+    /// before calling [SeleneInstance::exit].
+    pub fn run_shots<F>(&mut self, program: F) -> Result<()>
+    where
+        F: Fn(&mut Shot) -> Result<()> + Sync,
+    {
+        self.run_workers(|instance| {
+            for shot_index in 0..instance.config.shots.count {
+                instance.shot_start(shot_index)?;
+                let mut shot = Shot::new(instance);
+                let result = program(&mut shot);
+                let result = result.and_then(|_| shot.release_futures());
+                let code = result
+                    .err()
+                    .map(|e| match e.downcast_ref::<ProgramPanic>() {
+                        Some(panic) => panic.code,
+                        None => instance.print_error(&e),
+                    });
+                instance.shot_end()?;
+                if code.is_some_and(|code| code >= SHOT_PANIC_LIMIT) {
+                    break;
+                }
+            }
+            Ok(())
+        })
+    }
+}
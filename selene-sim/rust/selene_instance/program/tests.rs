@@ -0,0 +1,289 @@
+use super::*;
+use crate::selene_instance::configuration::PluginConfig;
+use crate::test_utils::{read_shots, run_program, test_config};
+use selene_core::decoder::{MessageKind, Value};
+use selene_core::registry::register_runtime;
+use selene_core::runtime::{BatchOperation, RuntimeInterface, RuntimeInterfaceFactory};
+use selene_core::utils::MetricValue;
+use selene_simple_runtime::{SimpleRuntime, SimpleRuntimeFactory};
+use std::sync::{Arc, Mutex};
+
+type Log = Arc<Mutex<Vec<String>>>;
+
+/// The simple runtime, logging the gates and reference count changes it
+/// receives.
+struct RecordingRuntime {
+    inner: Box<SimpleRuntime>,
+    log: Log,
+}
+
+impl RecordingRuntime {
+    fn record(&self, entry: String) {
+        self.log.lock().unwrap().push(entry);
+    }
+}
+
+impl RuntimeInterface for RecordingRuntime {
+    fn exit(&mut self) -> Result<()> {
+        self.inner.exit()
+    }
+    fn get_next_operations(&mut self) -> Result<Option<BatchOperation>> {
+        self.inner.get_next_operations()
+    }
+    fn shot_start(&mut self, shot_id: u64, seed: u64) -> Result<()> {
+        self.inner.shot_start(shot_id, seed)
+    }
+    fn shot_end(&mut self) -> Result<()> {
+        self.record("shot_end".to_string());
+        self.inner.shot_end()
+    }
+    fn get_metric(&mut self, nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        self.inner.get_metric(nth_metric)
+    }
+    fn qalloc(&mut self) -> Result<u64> {
+        self.inner.qalloc()
+    }
+    fn qfree(&mut self, qubit_id: u64) -> Result<()> {
+        self.inner.qfree(qubit_id)
+    }
+    fn rxy_gate(&mut self, qubit_id: u64, theta: f64, phi: f64) -> Result<()> {
+        self.inner.rxy_gate(qubit_id, theta, phi)
+    }
+    fn rzz_gate(&mut self, qubit_id_1: u64, qubit_id_2: u64, theta: f64) -> Result<()> {
+        self.inner.rzz_gate(qubit_id_1, qubit_id_2, theta)
+    }
+    fn rz_gate(&mut self, qubit_id: u64, theta: f64) -> Result<()> {
+        self.record(format!("rz {theta}"));
+        self.inner.rz_gate(qubit_id, theta)
+    }
+    fn measure(&mut self, qubit_id: u64) -> Result<u64> {
+        self.inner.measure(qubit_id)
+    }
+    fn measure_leaked(&mut self, qubit_id: u64) -> Result<u64> {
+        self.inner.measure_leaked(qubit_id)
+    }
+    fn reset(&mut self, qubit_id: u64) -> Result<()> {
+        self.inner.reset(qubit_id)
+    }
+    fn force_result(&mut self, result_id: u64) -> Result<()> {
+        self.inner.force_result(result_id)
+    }
+    fn get_bool_result(&mut self, result_id: u64) -> Result<Option<bool>> {
+        self.inner.get_bool_result(result_id)
+    }
+    fn get_u64_result(&mut self, result_id: u64) -> Result<Option<u64>> {
+        self.inner.get_u64_result(result_id)
+    }
+    fn set_bool_result(&mut self, result_id: u64, result: bool) -> Result<()> {
+        self.inner.set_bool_result(result_id, result)
+    }
+    fn set_u64_result(&mut self, result_id: u64, result: u64) -> Result<()> {
+        self.inner.set_u64_result(result_id, result)
+    }
+    fn increment_future_refcount(&mut self, future: u64) -> Result<()> {
+        self.record(format!("increment {future}"));
+        self.inner.increment_future_refcount(future)
+    }
+    fn decrement_future_refcount(&mut self, future: u64) -> Result<()> {
+        self.record(format!("decrement {future}"));
+        self.inner.decrement_future_refcount(future)
+    }
+    fn local_barrier(&mut self, qubits: &[u64], sleep_ns: u64) -> Result<()> {
+        self.inner.local_barrier(qubits, sleep_ns)
+    }
+    fn global_barrier(&mut self, sleep_ns: u64) -> Result<()> {
+        self.inner.global_barrier(sleep_ns)
+    }
+}
+
+struct RecordingRuntimeFactory(Log);
+
+impl RuntimeInterfaceFactory for RecordingRuntimeFactory {
+    type Interface = RecordingRuntime;
+
+    fn init(
+        self: Arc<Self>,
+        n_qubits: u64,
+        start: selene_core::time::Instant,
+        args: &[impl AsRef<str>],
+    ) -> Result<Box<Self::Interface>> {
+        Ok(Box::new(RecordingRuntime {
+            inner: Arc::new(SimpleRuntimeFactory).init(n_qubits, start, args)?,
+            log: self.0.clone(),
+        }))
+    }
+}
+
+fn values(shot: &selene_core::decoder::Shot, tag: &str) -> Vec<Value> {
+    shot.messages
+        .iter()
+        .filter(|message| message.tag == tag)
+        .flat_map(|message| message.values.clone())
+        .collect()
+}
+
+fn exit_codes(shot: &selene_core::decoder::Shot) -> Vec<u64> {
+    shot.messages
+        .iter()
+        .filter_map(|message| match message.kind() {
+            Ok(MessageKind::Exit { code, .. }) => Some(code),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn qalloc_measure_read() {
+    let output = run_program(test_config("qalloc_measure_read").shots(2, 5, 1), |shot| {
+        let q0 = shot.qalloc()?.unwrap();
+        let q1 = shot.qalloc()?.unwrap();
+        assert_ne!(q0, q1);
+        // Only two qubits are configured.
+        assert!(shot.qalloc()?.is_none());
+        shot.rxy(&q0, std::f64::consts::PI, 0.0)?;
+        shot.rzz(&q0, &q1, 0.5)?;
+        let measured = shot.measure(&q0)?;
+        let lazy = shot.lazy_measure(&q1)?;
+        let leaked = shot.lazy_measure_leaked(&q1)?;
+        let reset = shot.lazy_measure_reset(&q0)?;
+        shot.print("USER:BOOL:measured", measured)?;
+        let value = shot.read(&lazy)?;
+        shot.print("USER:BOOL:lazy", value)?;
+        let value = shot.read(&leaked)?;
+        shot.print("USER:INT:leaked", value)?;
+        let value = shot.read(&reset)?;
+        shot.print("USER:BOOL:reset", value)?;
+        shot.free(q1)?;
+        let freed = shot.measure_and_free(q0)?;
+        let value = shot.read(&freed)?;
+        shot.print("USER:BOOL:freed", value)?;
+        // Freed qubits can be allocated again.
+        assert!(shot.qalloc()?.is_some());
+        let id = shot.shot_id();
+        shot.print("USER:INT:shot_id", id)
+    });
+    let shots = read_shots(&output);
+    assert_eq!(shots.len(), 2);
+    for (shot, id) in shots.iter().zip([5, 6]) {
+        assert_eq!(shot.shot_id, id);
+        // The simulator is biased to always measure 1.
+        for tag in [
+            "USER:BOOL:measured",
+            "USER:BOOL:lazy",
+            "USER:BOOL:reset",
+            "USER:BOOL:freed",
+        ] {
+            assert_eq!(values(shot, tag), vec![Value::Bool(true)], "{tag}");
+        }
+        assert_eq!(values(shot, "USER:INT:leaked"), vec![Value::U64(1)]);
+        assert_eq!(values(shot, "USER:INT:shot_id"), vec![Value::U64(id)]);
+        assert!(exit_codes(shot).is_empty());
+    }
+}
+
+#[test]
+fn futures_are_released_on_drop() {
+    let log = Log::default();
+    register_runtime(
+        "refcount_recording",
+        Arc::new(RecordingRuntimeFactory(log.clone())),
+    );
+    let config = test_config("futures_are_released_on_drop")
+        .runtime(PluginConfig::builtin("refcount_recording"));
+    run_program(config, |shot| {
+        let q = shot.qalloc()?.unwrap();
+        let first = shot.lazy_measure(&q)?;
+        let copy = first.clone();
+        drop(first);
+        shot.rz(&q, 1.0)?;
+        // Dropping the last clone releases the reference before the next
+        // operation.
+        drop(copy);
+        shot.rz(&q, 2.0)?;
+        // Futures still held when the program ends are released with the
+        // shot.
+        let _held = shot.lazy_measure(&q)?;
+        shot.rz(&q, 3.0)?;
+        Ok(())
+    });
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            "rz 1",
+            "decrement 0",
+            "rz 2",
+            "rz 3",
+            "decrement 1",
+            "shot_end"
+        ]
+    );
+}
+
+#[test]
+fn panics_below_1000_end_the_shot() {
+    let output = run_program(test_config("shot_panic").shots(3, 0, 1), |shot| {
+        if shot.shot_id() == 1 {
+            return Err(shot.panic("bad shot", 5));
+        }
+        shot.print("USER:INT:done", shot.shot_id())
+    });
+    let shots = read_shots(&output);
+    assert_eq!(shots.len(), 3);
+    assert_eq!(exit_codes(&shots[0]), Vec::<u64>::new());
+    assert_eq!(exit_codes(&shots[1]), vec![5]);
+    assert!(values(&shots[1], "USER:INT:done").is_empty());
+    assert_eq!(values(&shots[2], "USER:INT:done"), vec![Value::U64(2)]);
+}
+
+#[test]
+fn panics_from_1000_end_the_run() {
+    let output = run_program(test_config("run_panic").shots(3, 0, 1), |shot| {
+        if shot.shot_id() == 1 {
+            return Err(shot.panic("bad run", 1000));
+        }
+        shot.print("USER:INT:done", shot.shot_id())
+    });
+    let shots = read_shots(&output);
+    assert_eq!(shots.len(), 2);
+    assert_eq!(exit_codes(&shots[1]), vec![1000]);
+}
+
+#[test]
+fn errors_are_reported_as_panics() {
+    let output = run_program(test_config("program_errors").shots(2, 0, 1), |shot| {
+        let q = shot.qalloc()?.unwrap();
+        let id = q.id();
+        shot.free(q)?;
+        // The qubit has already been freed.
+        shot.rz(&Qubit(id, PhantomData), 1.0)
+    });
+    let shots = read_shots(&output);
+    // Errors from the runtime end the run.
+    assert_eq!(shots.len(), 1);
+    assert_eq!(exit_codes(&shots[0]).len(), 1);
+    assert!(exit_codes(&shots[0])[0] >= 1000);
+}
+
+#[test]
+fn multiple_workers() {
+    let config = test_config("multiple_workers").shots(7, 3, 2).workers(3);
+    let output = run_program(config, |shot| {
+        let q = shot.qalloc()?.unwrap();
+        let result = shot.measure_and_free(q)?;
+        let value = shot.read(&result)?;
+        shot.print("USER:BOOL:result", value)?;
+        let id = shot.shot_id();
+        shot.print("USER:INT:shot_id", id)
+    });
+    let shots = read_shots(&output);
+    // Shots are written in order, whichever worker ran them.
+    let ids: Vec<u64> = shots.iter().map(|shot| shot.shot_id).collect();
+    assert_eq!(ids, vec![3, 5, 7, 9, 11, 13, 15]);
+    for shot in &shots {
+        assert_eq!(values(shot, "USER:BOOL:result"), vec![Value::Bool(true)]);
+        assert_eq!(
+            values(shot, "USER:INT:shot_id"),
+            vec![Value::U64(shot.shot_id)]
+        );
+    }
+}
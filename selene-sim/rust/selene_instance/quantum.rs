@@ -36,6 +36,12 @@ impl SeleneInstance {
     pub fn qubit_lazy_measure_leaked(&mut self, q: u64) -> Result<u64> {
         self.emulator.user_issued_lazy_measure_leaked(q)
     }
+    pub fn qubit_lazy_measure_reset(&mut self, q: u64) -> Result<u64> {
+        self.emulator.user_issued_lazy_measure_reset(q)
+    }
+    pub fn qubit_measure_and_free(&mut self, q: u64) -> Result<u64> {
+        self.emulator.user_issued_measure_and_free(q)
+    }
 
     pub fn global_barrier(&mut self, sleep_time: u64) -> Result<()> {
         self.emulator.user_issued_global_barrier(sleep_time)
@@ -59,6 +65,13 @@ impl SeleneInstance {
         self.emulator.user_issued_read_future_u64(r)
     }
 
+    pub fn begin_condition(&mut self, r: u64, value: bool) -> Result<()> {
+        self.emulator.user_issued_begin_condition(r, value)
+    }
+    pub fn end_condition(&mut self) -> Result<()> {
+        self.emulator.user_issued_end_condition()
+    }
+
     pub fn custom_runtime_call(&mut self, custom_tag: u64, data: &[u8]) -> Result<u64> {
         self.emulator.custom_runtime_call(custom_tag, data)
     }
@@ -0,0 +1,129 @@
+//! Recovery from errors raised within a shot.
+//!
+//! By default, an error raised by a plugin ends the run, as the frontend is
+//! given a code of 1000 or above. Statistical studies may instead tolerate
+//! the occasional failed shot, such as a postselection on an outcome too
+//! unlikely for the simulator to reach. With `on_error` set to
+//! [ErrorPolicy::SkipShot], an error raised during a shot is recorded as the
+//! failure of that shot and written to the result stream with the code of
+//! [ErrorKind::ShotFailed], which frontends treat as ending the shot alone.
+//! The shot is then brought to an end, ending it in each plugin regardless of
+//! further errors, so that the next shot starts afresh. Only the first error
+//! of a shot is reported.
+//!
+//! Errors that leave the emulator unable to continue, such as failures to
+//! write the result stream or the crash of a plugin host, still end the run.
+//! When the instance exits, the failed shots are written to a failed shots
+//! file in the artifact directory and summarised on stderr.
+use anyhow::{Context, Result};
+use selene_core::error::ErrorKind;
+use serde::{Deserialize, Serialize};
+
+use super::SeleneInstance;
+
+#[cfg(test)]
+mod tests;
+
+pub const FAILED_SHOTS_FILE: &str = "failed_shots.yaml";
+
+/// What to do when an error is raised during a shot.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorPolicy {
+    /// End the run.
+    #[default]
+    Abort,
+    /// Record the failure of the shot, and carry on with the next one.
+    SkipShot,
+}
+
+/// A shot that failed under [ErrorPolicy::SkipShot].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FailedShot {
+    pub shot_id: u64,
+    /// The description of the kind of the error.
+    pub kind: String,
+    /// The code of the kind of the error.
+    pub code: u32,
+    pub message: String,
+}
+
+/// Whether the emulator can carry on after an error of the given kind, once
+/// the shot in which it was raised has been ended.
+fn is_confined_to_shot(kind: ErrorKind) -> bool {
+    !matches!(
+        kind,
+        ErrorKind::NullInstance | ErrorKind::Output | ErrorKind::PluginCrashed
+    )
+}
+
+impl SeleneInstance {
+    /// Record an error as the failure of the current shot if the error
+    /// policy allows, writing it to the result stream unless the shot has
+    /// already failed. Returns the code to report the error with, or `None`
+    /// if the error is to end the run.
+    pub(crate) fn record_failure(&mut self, error: &anyhow::Error) -> Option<u32> {
+        let kind = ErrorKind::of(error);
+        if self.config.on_error != ErrorPolicy::SkipShot
+            || !self.in_shot
+            || !is_confined_to_shot(kind)
+        {
+            return None;
+        }
+        // Limits already end only the shot, and describe it in their message.
+        let (code, message) = match kind {
+            ErrorKind::ShotLimitExceeded => (kind.code(), format!("{error:#}")),
+            _ => (
+                ErrorKind::ShotFailed.code(),
+                format!("Shot {} failed ({kind}): {error:#}", self.shot_number),
+            ),
+        };
+        if !self.shot_failed {
+            self.shot_failed = true;
+            self.fallible_print_panic(&message, code);
+            self.failed_shots.push(FailedShot {
+                shot_id: self.shot_number,
+                kind: kind.to_string(),
+                code: kind.code(),
+                message: format!("{error:#}"),
+            });
+        }
+        Some(code)
+    }
+
+    /// Recover from the error of a step in starting or ending a shot, if the
+    /// error policy allows.
+    pub(crate) fn recover(&mut self, result: Result<()>) -> Result<()> {
+        match result {
+            Err(e) if self.record_failure(&e).is_some() => Ok(()),
+            result => result,
+        }
+    }
+
+    /// Write the failed shots to the artifact directory and summarise them on
+    /// stderr. Nothing is written unless shots are skipped on error.
+    pub fn write_failed_shots(&mut self) -> Result<()> {
+        if self.config.on_error != ErrorPolicy::SkipShot {
+            return Ok(());
+        }
+        self.failed_shots.sort_by_key(|failure| failure.shot_id);
+        let path = self.config.artifact_dir.join(FAILED_SHOTS_FILE);
+        let contents = serde_yml::to_string(&self.failed_shots)?;
+        std::fs::write(&path, contents)
+            .with_context(|| format!("Failed to write failed shots to {}", path.display()))?;
+        if !self.failed_shots.is_empty() {
+            let ids: Vec<String> = self
+                .failed_shots
+                .iter()
+                .map(|failure| failure.shot_id.to_string())
+                .collect();
+            eprintln!(
+                "{} of {} shots failed: {}",
+                self.failed_shots.len(),
+                self.config.shots.count,
+                ids.join(", ")
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,253 @@
+use super::*;
+use crate::selene_instance::configuration::{ConfigurationBuilder, PluginConfig};
+use crate::selene_instance::program::Shot;
+use crate::test_utils::{read_shots, run_program, test_config};
+use selene_core::decoder::{self, MessageKind, Value};
+use selene_core::registry::register_simulator;
+use selene_core::simulator::{SimulatorInterface, SimulatorInterfaceFactory};
+use selene_core::utils::MetricValue;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// A simulator that measures every qubit as 1, except in the given shots,
+/// where measurements fail with an error of the given kind as if asked to
+/// postselect on an impossible outcome. With `failing_shot_end`, ending those
+/// shots fails too.
+struct FailingSimulator {
+    failing_shots: Arc<BTreeSet<u64>>,
+    kind: ErrorKind,
+    failing_shot_end: bool,
+    shot_id: u64,
+}
+
+impl FailingSimulator {
+    fn failing(&self) -> bool {
+        self.failing_shots.contains(&self.shot_id)
+    }
+}
+
+impl SimulatorInterface for FailingSimulator {
+    fn exit(&mut self) -> Result<()> {
+        Ok(())
+    }
+    fn shot_start(&mut self, shot_id: u64, _seed: u64) -> Result<()> {
+        self.shot_id = shot_id;
+        Ok(())
+    }
+    fn shot_end(&mut self) -> Result<()> {
+        if self.failing() && self.failing_shot_end {
+            return Err(self.kind.error("shot_end failed"));
+        }
+        Ok(())
+    }
+    fn rz(&mut self, _qubit: u64, _theta: f64) -> Result<()> {
+        Ok(())
+    }
+    fn rxy(&mut self, _qubit: u64, _theta: f64, _phi: f64) -> Result<()> {
+        Ok(())
+    }
+    fn rzz(&mut self, _qubit1: u64, _qubit2: u64, _theta: f64) -> Result<()> {
+        Ok(())
+    }
+    fn measure(&mut self, _qubit: u64) -> Result<bool> {
+        if self.failing() {
+            return Err(self.kind.error("postselection on an impossible outcome"));
+        }
+        Ok(true)
+    }
+    fn reset(&mut self, _qubit: u64) -> Result<()> {
+        Ok(())
+    }
+    fn get_metric(&mut self, _nth_metric: u8) -> Result<Option<(String, MetricValue)>> {
+        Ok(None)
+    }
+}
+
+struct FailingSimulatorFactory {
+    failing_shots: Arc<BTreeSet<u64>>,
+    kind: ErrorKind,
+    failing_shot_end: bool,
+}
+
+impl SimulatorInterfaceFactory for FailingSimulatorFactory {
+    type Interface = FailingSimulator;
+
+    fn init(
+        self: Arc<Self>,
+        _n_qubits: u64,
+        _args: &[impl AsRef<str>],
+    ) -> Result<Box<Self::Interface>> {
+        Ok(Box::new(FailingSimulator {
+            failing_shots: self.failing_shots.clone(),
+            kind: self.kind,
+            failing_shot_end: self.failing_shot_end,
+            shot_id: 0,
+        }))
+    }
+}
+
+/// A configuration with a failing simulator registered under the test's
+/// name, skipping failed shots.
+fn failing_config(
+    name: &str,
+    failing_shots: &[u64],
+    kind: ErrorKind,
+    failing_shot_end: bool,
+) -> ConfigurationBuilder {
+    let config = test_config(name);
+    register_simulator(
+        name,
+        Arc::new(FailingSimulatorFactory {
+            failing_shots: Arc::new(failing_shots.iter().copied().collect()),
+            kind,
+            failing_shot_end,
+        }),
+    );
+    config
+        .simulator(PluginConfig::builtin(name))
+        .on_error(ErrorPolicy::SkipShot)
+}
+
+fn program(shot: &mut Shot) -> Result<()> {
+    let q = shot.qalloc()?.unwrap();
+    let value = shot.measure(&q)?;
+    shot.print("USER:BOOL:result", value)?;
+    let id = shot.shot_id();
+    shot.print("USER:INT:shot_id", id)
+}
+
+fn exits(shot: &decoder::Shot) -> Vec<(String, u64)> {
+    shot.messages
+        .iter()
+        .filter_map(|message| match message.kind() {
+            Ok(MessageKind::Exit { message, code }) => Some((message.to_string(), code)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn results(shot: &decoder::Shot) -> Vec<Value> {
+    shot.messages
+        .iter()
+        .filter(|message| message.tag == "USER:BOOL:result")
+        .flat_map(|message| message.values.clone())
+        .collect()
+}
+
+fn failed_shots_file(config: &ConfigurationBuilder) -> PathBuf {
+    config
+        .clone()
+        .build()
+        .unwrap()
+        .artifact_dir
+        .join(FAILED_SHOTS_FILE)
+}
+
+fn read_failed_shots(config: &ConfigurationBuilder) -> Vec<FailedShot> {
+    let file = std::fs::File::open(failed_shots_file(config)).unwrap();
+    serde_yml::from_reader(file).unwrap()
+}
+
+#[test]
+fn failed_shots_are_skipped() {
+    let config =
+        failing_config("skip_failed_shots", &[1, 3], ErrorKind::Unsupported, false).shots(5, 0, 1);
+    let shots = read_shots(&run_program(config.clone(), program));
+    // Later shots carry on after each failure.
+    let ids: Vec<u64> = shots.iter().map(|shot| shot.shot_id).collect();
+    assert_eq!(ids, vec![0, 1, 2, 3, 4]);
+    for shot in &shots {
+        if shot.shot_id % 2 == 1 {
+            let [(message, code)] = exits(shot).try_into().unwrap();
+            assert_eq!(code, u64::from(ErrorKind::ShotFailed.code()));
+            assert!(
+                message.starts_with(&format!("Shot {} failed (", shot.shot_id))
+                    && message.ends_with("postselection on an impossible outcome"),
+                "{message}"
+            );
+            assert!(results(shot).is_empty());
+        } else {
+            assert!(exits(shot).is_empty());
+            assert_eq!(results(shot), vec![Value::Bool(true)]);
+        }
+    }
+    let failed = read_failed_shots(&config);
+    let ids: Vec<u64> = failed.iter().map(|failure| failure.shot_id).collect();
+    assert_eq!(ids, vec![1, 3]);
+    for failure in failed {
+        assert_eq!(failure.kind, ErrorKind::Unsupported.to_string());
+        assert_eq!(failure.code, ErrorKind::Unsupported.code());
+        assert!(
+            failure
+                .message
+                .ends_with("postselection on an impossible outcome"),
+            "{}",
+            failure.message
+        );
+    }
+}
+
+#[test]
+fn only_the_first_error_of_a_shot_is_reported() {
+    // The measurement fails, and so does ending the shot.
+    let config =
+        failing_config("first_error_only", &[0], ErrorKind::Unsupported, true).shots(2, 0, 1);
+    let shots = read_shots(&run_program(config.clone(), program));
+    assert_eq!(shots.len(), 2);
+    assert_eq!(exits(&shots[0]).len(), 1);
+    assert!(exits(&shots[1]).is_empty());
+    assert_eq!(results(&shots[1]), vec![Value::Bool(true)]);
+    let failed = read_failed_shots(&config);
+    assert_eq!(failed.len(), 1);
+    assert!(
+        failed[0]
+            .message
+            .ends_with("postselection on an impossible outcome")
+    );
+}
+
+#[test]
+fn failures_in_workers_are_merged() {
+    let config = failing_config("worker_failures", &[6, 1, 4], ErrorKind::Unsupported, false)
+        .shots(8, 0, 1)
+        .workers(3);
+    let shots = read_shots(&run_program(config.clone(), program));
+    assert_eq!(shots.len(), 8);
+    let failed: Vec<u64> = shots
+        .iter()
+        .filter(|shot| !exits(shot).is_empty())
+        .map(|shot| shot.shot_id)
+        .collect();
+    assert_eq!(failed, vec![1, 4, 6]);
+    // Failures from every worker are gathered, in the order of the shots.
+    let ids: Vec<u64> = read_failed_shots(&config)
+        .iter()
+        .map(|failure| failure.shot_id)
+        .collect();
+    assert_eq!(ids, vec![1, 4, 6]);
+}
+
+#[test]
+fn unrecoverable_errors_end_the_run() {
+    let config =
+        failing_config("unrecoverable", &[1], ErrorKind::PluginCrashed, false).shots(3, 0, 1);
+    let shots = read_shots(&run_program(config.clone(), program));
+    assert_eq!(shots.len(), 2);
+    let [(_, code)] = exits(&shots[1]).try_into().unwrap();
+    assert_eq!(code, u64::from(ErrorKind::PluginCrashed.code()));
+    assert!(read_failed_shots(&config).is_empty());
+}
+
+#[test]
+fn failures_abort_by_default() {
+    let config = failing_config("abort_by_default", &[0], ErrorKind::Unsupported, false)
+        .on_error(ErrorPolicy::Abort)
+        .shots(2, 0, 1);
+    let shots = read_shots(&run_program(config.clone(), program));
+    assert_eq!(shots.len(), 1);
+    let [(_, code)] = exits(&shots[0]).try_into().unwrap();
+    assert_eq!(code, u64::from(ErrorKind::Unsupported.code()));
+    // Nothing is written unless shots are skipped.
+    assert!(!failed_shots_file(&config).exists());
+}
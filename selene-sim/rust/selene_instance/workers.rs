@@ -0,0 +1,350 @@
+//! Multi-threaded shot execution.
+//!
+//! With `shots.workers` set above one, [SeleneInstance::run_workers] runs the
+//! configured shots across that many threads, each with its own emulator
+//! components created from the configuration. The shot range is partitioned
+//! in the same way as for multiple processes: worker `k` of `n` runs every
+//! `n`th shot starting from the `k`th (see
+//! [ShotConfig::partition](super::configuration::ShotConfig::partition)), so every
+//! shot keeps the id, and therefore the seeds, it would have when run
+//! sequentially.
+//!
+//! The instance itself acts as the first worker on the calling thread. Each
+//! worker's output is captured shot by shot, and the shots are written to the
+//! instance's output stream in shot order, so the result stream is the same
+//! as that of a sequential run. When a shot panics, or a worker stops before
+//! running all of its shots, no later shots are written, as would be the case
+//! when running sequentially.
+//!
+//! The shots that fail in each worker, when shots are skipped on error, are
+//! gathered into the instance's own [failed_shots](SeleneInstance::failed_shots).
+//!
+//! Plugins must support being instantiated several times within a process,
+//! with each instance used from a single thread.
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::sync::mpsc::{Receiver, Sender, channel};
+
+use anyhow::{Result, anyhow};
+use selene_core::decoder::{Message, MessageKind};
+use selene_core::encoder::{MessageWriter, OutputStream, OutputStreamError};
+
+use crate::event_hooks::EventHook as _;
+
+use super::SeleneInstance;
+use super::checkpoint::Checkpoint;
+use super::configuration::ShotConfig;
+use super::output_options::FlushPolicy;
+use super::recovery::FailedShot;
+
+/// Worker threads run the user program, so are given a stack comparable to
+/// that of a main thread.
+const WORKER_STACK_SIZE: usize = 8 << 20;
+
+enum WorkerEvent {
+    /// The messages of a completed shot, by its index in the full shot range,
+    /// and whether the shot panicked, ending the run.
+    Shot {
+        index: u64,
+        messages: Vec<Message>,
+        panicked: bool,
+    },
+    /// A worker has closed its output stream having completed the given
+    /// number of shots, with any messages written outside of a shot.
+    Finished {
+        worker: u64,
+        completed: u64,
+        trailing: Vec<Message>,
+    },
+}
+
+/// Writes worker output to the instance's output stream in shot order.
+struct ShotMerger {
+    output: OutputStream,
+    receiver: Receiver<WorkerEvent>,
+    n_workers: u64,
+    planned: Vec<u64>,
+    // The index of the latest shot received from each worker, and whether
+    // each worker has finished.
+    received: Vec<Option<u64>>,
+    finished: Vec<bool>,
+    flush_per_shot: bool,
+    // The checkpoint of the instance, updated as each shot is written, and
+    // the full shot configuration used to identify written shots.
+    checkpoint: Option<Checkpoint>,
+    shots: ShotConfig,
+    next_index: u64,
+    // Shots at or beyond this index are not written, as a worker stopped
+    // before reaching them.
+    end_index: u64,
+    pending: BTreeMap<u64, Vec<Message>>,
+    trailing: Vec<Message>,
+}
+
+impl ShotMerger {
+    fn accept(&mut self, event: WorkerEvent) {
+        match event {
+            WorkerEvent::Shot {
+                index,
+                messages,
+                panicked,
+            } => {
+                if panicked {
+                    self.end_index = self.end_index.min(index + 1);
+                }
+                self.received[(index % self.n_workers) as usize] = Some(index);
+                self.pending.insert(index, messages);
+            }
+            WorkerEvent::Finished {
+                worker,
+                completed,
+                trailing,
+            } => {
+                if completed < self.planned[worker as usize] {
+                    // The worker stopped after its last completed shot, so
+                    // that is the last shot that may be written.
+                    let end_index = match completed {
+                        0 => worker,
+                        n => worker + (n - 1) * self.n_workers + 1,
+                    };
+                    self.end_index = self.end_index.min(end_index);
+                }
+                self.finished[worker as usize] = true;
+                self.trailing.extend(trailing);
+            }
+        }
+    }
+
+    /// Whether it is known that no worker stopped before the given shot,
+    /// which is the case once every worker has either reached it or finished.
+    fn is_reached(&self, index: u64) -> bool {
+        (0..self.n_workers as usize)
+            .all(|worker| self.finished[worker] || self.received[worker] >= Some(index))
+    }
+
+    /// Write any shots that are ready, having received everything currently
+    /// available from other workers.
+    fn poll(&mut self) -> Result<(), OutputStreamError> {
+        while let Ok(event) = self.receiver.try_recv() {
+            self.accept(event);
+        }
+        while self.next_index < self.end_index && self.is_reached(self.next_index) {
+            let Some(messages) = self.pending.remove(&self.next_index) else {
+                break;
+            };
+            for message in &messages {
+                self.output.write_message(message)?;
+            }
+            if self.flush_per_shot || self.checkpoint.is_some() {
+                self.output.flush()?;
+            }
+            if let Some(checkpoint) = &mut self.checkpoint {
+                let shot_id = self.shots.offset + self.next_index * self.shots.increment;
+                checkpoint
+                    .record(shot_id)
+                    .map_err(|e| OutputStreamError::OtherError(format!("{e:#}")))?;
+            }
+            self.next_index += 1;
+        }
+        Ok(())
+    }
+
+    /// Write all remaining output once every worker has finished, returning
+    /// the output stream and checkpoint.
+    fn finish(mut self) -> Result<(OutputStream, Option<Checkpoint>), OutputStreamError> {
+        while let Ok(event) = self.receiver.recv() {
+            self.accept(event);
+        }
+        self.poll()?;
+        for message in std::mem::take(&mut self.trailing) {
+            self.output.write_message(&message)?;
+        }
+        Ok((self.output, self.checkpoint))
+    }
+}
+
+enum CollectorTarget {
+    Channel(Sender<WorkerEvent>),
+    Merger(Rc<RefCell<ShotMerger>>),
+}
+
+/// Captures a worker's output shot by shot.
+struct ShotCollector {
+    worker: u64,
+    n_workers: u64,
+    completed: u64,
+    messages: Vec<Message>,
+    target: Option<CollectorTarget>,
+}
+
+impl ShotCollector {
+    fn new(worker: u64, n_workers: u64, target: CollectorTarget) -> Self {
+        Self {
+            worker,
+            n_workers,
+            completed: 0,
+            messages: Vec::new(),
+            target: Some(target),
+        }
+    }
+
+    fn send(&mut self, event: WorkerEvent) -> Result<(), OutputStreamError> {
+        match &self.target {
+            Some(CollectorTarget::Channel(sender)) => {
+                // The merger only goes away once all workers have finished.
+                let _ = sender.send(event);
+                Ok(())
+            }
+            Some(CollectorTarget::Merger(merger)) => {
+                let mut merger = merger.borrow_mut();
+                merger.accept(event);
+                merger.poll()
+            }
+            None => Err(OutputStreamError::OtherError(
+                "Worker output written after the end of the stream".to_string(),
+            )),
+        }
+    }
+}
+
+impl MessageWriter for ShotCollector {
+    fn write_message(&mut self, message: Message) -> Result<(), OutputStreamError> {
+        let is_shot_end = matches!(message.kind(), Ok(MessageKind::ShotEnd(_)));
+        self.messages.push(message);
+        if is_shot_end {
+            let index = self.worker + self.completed * self.n_workers;
+            self.completed += 1;
+            let messages = std::mem::take(&mut self.messages);
+            let panicked = messages
+                .iter()
+                .any(|message| message.kind().is_ok_and(|kind| kind.is_panic()));
+            self.send(WorkerEvent::Shot {
+                index,
+                messages,
+                panicked,
+            })?;
+        }
+        Ok(())
+    }
+
+    fn end_of_stream(&mut self) -> Result<(), OutputStreamError> {
+        if self.target.is_none() {
+            return Ok(());
+        }
+        let event = WorkerEvent::Finished {
+            worker: self.worker,
+            completed: self.completed,
+            trailing: std::mem::take(&mut self.messages),
+        };
+        self.send(event)?;
+        self.target = None;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), OutputStreamError> {
+        Ok(())
+    }
+}
+
+impl SeleneInstance {
+    /// Run the configured shots, calling `run` with each worker's instance
+    /// from the worker's thread. `run` should run every shot of the instance
+    /// it is given, as counted by its shot configuration, but should not
+    /// exit the instance. With a single worker, `run` is simply called with
+    /// this instance.
+    pub fn run_workers<F>(&mut self, run: F) -> Result<()>
+    where
+        F: Fn(&mut SeleneInstance) -> Result<()> + Sync,
+    {
+        let n_workers = self.config.shots.n_workers();
+        if n_workers <= 1 {
+            return run(self);
+        }
+        let full_shots = self.config.shots.clone();
+        let (sender, receiver) = channel();
+        let merger = Rc::new(RefCell::new(ShotMerger {
+            output: self.replace_output(OutputStream::new(Box::new(std::io::sink()))),
+            receiver,
+            n_workers,
+            planned: (0..n_workers)
+                .map(|worker| full_shots.partition(worker, n_workers).count)
+                .collect(),
+            received: vec![None; n_workers as usize],
+            finished: vec![false; n_workers as usize],
+            flush_per_shot: self.config.output_options.flush_policy == FlushPolicy::PerShot,
+            checkpoint: self.checkpoint.take(),
+            shots: full_shots.clone(),
+            next_index: 0,
+            end_index: u64::MAX,
+            pending: BTreeMap::new(),
+            trailing: Vec::new(),
+        }));
+        self.replace_output(OutputStream::with_message_writer(Box::new(
+            ShotCollector::new(0, n_workers, CollectorTarget::Merger(merger.clone())),
+        )));
+        self.config.shots = full_shots.partition(0, n_workers);
+
+        let run = &run;
+        let result = std::thread::scope(|scope| {
+            let handles = (1..n_workers)
+                .map(|worker| {
+                    let mut config = self.config.clone();
+                    config.shots = full_shots.partition(worker, n_workers);
+                    config.checkpoint = None;
+                    let sender = sender.clone();
+                    std::thread::Builder::new()
+                        .name(format!("selene-worker-{worker}"))
+                        .stack_size(WORKER_STACK_SIZE)
+                        .spawn_scoped(scope, move || -> Result<Vec<FailedShot>> {
+                            let collector = ShotCollector::new(
+                                worker,
+                                n_workers,
+                                CollectorTarget::Channel(sender),
+                            );
+                            let out_encoder =
+                                OutputStream::with_message_writer(Box::new(collector));
+                            let mut instance =
+                                SeleneInstance::with_output_stream(config, out_encoder)?;
+                            let result = run(&mut instance);
+                            let exited = instance.emulator.event_hooks.exit();
+                            instance.close_output()?;
+                            result.and(exited).map(|_| instance.failed_shots)
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>();
+            drop(sender);
+            let handles = handles?;
+            let mut result = run(self).and_then(|_| Ok(self.out_encoder.end_of_stream()?));
+            for (worker, handle) in (1..).zip(handles) {
+                let worker_result = handle
+                    .join()
+                    .unwrap_or_else(|_| Err(anyhow!("Worker {worker} panicked")));
+                match worker_result {
+                    Ok(failed_shots) => self.failed_shots.extend(failed_shots),
+                    Err(e) if result.is_ok() => {
+                        result = Err(e.context(format!("In worker {worker}")));
+                    }
+                    Err(_) => {}
+                }
+            }
+            result
+        });
+
+        // Restore the instance's own output and shot configuration, whether
+        // or not the workers succeeded, so that errors can be reported.
+        drop(self.replace_output(OutputStream::new(Box::new(std::io::sink()))));
+        self.config.shots = full_shots;
+        let merger = Rc::into_inner(merger)
+            .expect("worker output is no longer referenced")
+            .into_inner();
+        let (output, checkpoint) = merger.finish()?;
+        self.replace_output(output);
+        self.checkpoint = checkpoint;
+        result
+    }
+
+    fn replace_output(&mut self, out_encoder: OutputStream) -> OutputStream {
+        std::mem::replace(&mut self.out_encoder, out_encoder)
+    }
+}
@@ -0,0 +1,109 @@
+//! Helpers shared by the tests of this crate.
+use crate::selene_instance::SeleneInstance;
+use crate::selene_instance::configuration::{Configuration, ConfigurationBuilder, PluginConfig};
+use crate::selene_instance::program::Shot;
+use selene_core::decoder::{self, OutputStreamReader};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// An empty directory for a test to write to, unique to the test and the
+/// process running it.
+pub fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir()
+        .join(format!("selene-tests-{}", std::process::id()))
+        .join(name);
+    if dir.exists() {
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A writer whose contents remain readable after it is handed to an output
+/// stream or instance.
+#[derive(Clone, Default)]
+pub struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    pub fn contents(&self) -> Vec<u8> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Register the plugins used by the tests, under the names they are
+/// registered with by the cargo features of the same name.
+pub fn register_plugins() {
+    use selene_core::registry::{register_error_model, register_runtime, register_simulator};
+    use std::sync::Once;
+    static REGISTER: Once = Once::new();
+    REGISTER.call_once(|| {
+        register_simulator(
+            selene_coinflip_plugin::PLUGIN_NAME,
+            Arc::new(selene_coinflip_plugin::CoinflipSimulatorFactory),
+        );
+        register_error_model(
+            selene_ideal_plugin::PLUGIN_NAME,
+            Arc::new(selene_ideal_plugin::IdealErrorModelFactory),
+        );
+        register_error_model(
+            selene_depolarizing_plugin::PLUGIN_NAME,
+            Arc::new(selene_depolarizing_plugin::DepolarizingErrorModelFactory),
+        );
+        register_runtime(
+            selene_simple_runtime::PLUGIN_NAME,
+            Arc::new(selene_simple_runtime::SimpleRuntimeFactory),
+        );
+    });
+}
+
+/// A configuration of two qubits with a coinflip simulator that always
+/// measures 1, the ideal error model and the simple runtime, with artifacts
+/// written to a scratch directory of the given name.
+pub fn test_config(name: &str) -> ConfigurationBuilder {
+    register_plugins();
+    Configuration::builder()
+        .n_qubits(2)
+        .artifact_dir(scratch_dir(name))
+        .simulator(
+            PluginConfig::builtin("coinflip")
+                .with_seed(1)
+                .with_args(["--bias=1"]),
+        )
+        .error_model(PluginConfig::builtin("ideal").with_seed(2))
+        .runtime(PluginConfig::builtin("simple").with_seed(3))
+}
+
+/// Run a program with the given configuration, returning the result stream.
+pub fn run_program<F>(config: ConfigurationBuilder, program: F) -> Vec<u8>
+where
+    F: Fn(&mut Shot) -> anyhow::Result<()> + Sync,
+{
+    let buffer = SharedBuffer::default();
+    let mut instance =
+        SeleneInstance::with_writer(config.build().unwrap(), Box::new(buffer.clone())).unwrap();
+    instance.run_shots(program).unwrap();
+    instance.exit().unwrap();
+    buffer.contents()
+}
+
+/// The shots in a binary result stream.
+pub fn read_shots(data: &[u8]) -> Vec<decoder::Shot> {
+    let mut reader = OutputStreamReader::new(data);
+    let mut shots = Vec::new();
+    while let Some(shot) = reader.next_shot().unwrap() {
+        shots.push(shot);
+    }
+    shots
+}